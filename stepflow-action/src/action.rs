@@ -1,16 +1,35 @@
 use stepflow_base::{ObjectStoreContent, ObjectStoreFiltered, generate_id_type, IdError};
 use stepflow_data::{StateData, StateDataFiltered, value::Value, var::{Var, VarId}};
 use stepflow_step::{Step};
-use crate::ActionError;
+use crate::{ActionError, FlowEnvironment};
 
 mod action_string_template;
-pub use action_string_template::StringTemplateAction;
+pub use action_string_template::{StringTemplateAction, StringTemplateActionConfig};
 
 mod action_htmlform;
 pub use action_htmlform::{HtmlFormAction, HtmlFormConfig};
 
 mod action_set_data;
-pub use action_set_data::SetDataAction;
+pub use action_set_data::{SetDataAction, SetDataActionConfig};
+
+mod action_import;
+pub use action_import::{ImportAction, ImportActionConfig};
+
+mod action_approval;
+pub use action_approval::{ApprovalAction, ApprovalActionConfig};
+
+mod action_duplicate_check;
+pub use action_duplicate_check::{DuplicateCheckAction, DuplicateChecker};
+
+#[cfg(feature = "wasm-actions")]
+mod action_wasm;
+#[cfg(feature = "wasm-actions")]
+pub use action_wasm::{WasmAction, WasmActionConfig, DEFAULT_FUEL, DEFAULT_MEMORY_LIMIT};
+
+#[cfg(feature = "http-actions")]
+mod action_http;
+#[cfg(feature = "http-actions")]
+pub use action_http::{HttpRequestAction, HttpRequestActionConfig, HttpClient, HttpRequest, HttpResponse, UreqClient, DEFAULT_CONNECT_TIMEOUT, DEFAULT_READ_TIMEOUT};
 
 generate_id_type!(ActionId);
 
@@ -70,6 +89,44 @@ pub trait Action: std::fmt::Debug + stepflow_base::as_any::AsAny {
   /// `step_data` and `vars` only have access to input and output data declared by the Step.
   fn start(&mut self, step: &Step, step_name: Option<&str>, step_data: &StateDataFiltered, vars: &ObjectStoreFiltered<Box<dyn Var + Send + Sync>, VarId>)
     -> Result<ActionResult, ActionError>;
+
+  /// [`Var`]s the action reads besides the [`Step`]'s inputs and outputs. They're visible to [`start`](Action::start) too.
+  fn required_vars(&self) -> &[VarId] {
+    &[]
+  }
+
+  /// The vars the action sets when it finishes with a value of the type it sets them to, so a flow can be checked before it runs.
+  ///
+  /// Only the type of the value is checked against the var. Actions that don't know ahead of time return nothing.
+  fn produces(&self) -> Vec<(VarId, Box<dyn Value>)> {
+    Vec::new()
+  }
+
+  /// Called with the session's [`FlowEnvironment`] before each [`start`](Action::start) so the action can use values like API base URLs.
+  fn set_environment(&mut self, _environment: &FlowEnvironment) {
+  }
+
+  /// Key that marks the action's side effect as done for the [`Step`], i.e. an email or charge.
+  ///
+  /// Once the action finishes, the `Session` records the key and won't start the action again while it has the key.
+  fn completion_key(&self, _step: &Step, _step_data: &StateDataFiltered) -> Option<String> {
+    None
+  }
+
+  /// Box the action so it can be put in an [`ObjectStore`](stepflow_base::ObjectStore)
+  fn boxed(self) -> Box<dyn Action + Sync + Send>
+      where Self: Sized + Send + Sync + 'static
+  {
+    Box::new(self)
+  }
+}
+
+impl<T> From<T> for Box<dyn Action + Sync + Send>
+    where T: Action + Send + Sync + 'static
+{
+  fn from(action: T) -> Self {
+    Box::new(action)
+  }
 }
 
 // implement downcast helpers that have trait bounds to make it a little safer
@@ -79,6 +136,11 @@ impl dyn Action + Send + Sync {
   {
     self.as_any().downcast_ref::<T>()
   }
+  pub fn downcast_mut<T>(&mut self) -> Option<&mut T>
+    where T: Action + std::any::Any
+  {
+    self.as_any_mut().downcast_mut::<T>()
+  }
   pub fn is<T>(&self) -> bool 
     where T: Action + std::any::Any
   {
@@ -119,7 +181,7 @@ pub fn test_action_setup<'a>() -> (Step, StateData, stepflow_base::ObjectStore<B
 mod tests {
   use stepflow_test_util::test_id;
   use stepflow_data::{StateData, value::TrueValue};
-  use super::{ActionId, HtmlFormAction, SetDataAction, ActionResult};
+  use super::{Action, ActionId, HtmlFormAction, SetDataAction, ActionResult};
 
   #[test]
   fn eq() {
@@ -140,5 +202,10 @@ mod tests {
     let action = HtmlFormAction::new(test_id!(ActionId), Default::default()).boxed();
     assert!(action.is::<HtmlFormAction>());
     assert!(!action.is::<SetDataAction>());
+
+    let mut action: Box<dyn Action + Sync + Send> = SetDataAction::new(test_id!(ActionId), StateData::new(), 0).into();
+    assert!(action.is::<SetDataAction>());
+    assert!(action.downcast_mut::<SetDataAction>().is_some());
+    assert!(action.downcast_mut::<HtmlFormAction>().is_none());
   }
 }
\ No newline at end of file
@@ -0,0 +1,109 @@
+use stepflow_base::{ObjectStore, ObjectStoreFiltered, IdError};
+use stepflow_data::{StateDataFiltered, var::{Var, VarId}, value::StringValue};
+use super::{ActionResult, Action, ActionId, Step, StateData, ActionError};
+
+/// Configuration for [`ApprovalAction`]
+///
+/// `decision` is the name of the [`Var`] that holds the approver's decision.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde-support", derive(serde::Serialize, serde::Deserialize))]
+pub struct ApprovalActionConfig {
+  pub approver: String,
+  pub decision: String,
+}
+
+/// Action that waits for an approver to decide
+///
+/// Until the decision var has a value, it returns [`ActionResult::StartWith`] with the approver's role as a [`StringValue`]
+/// so the caller knows who to ask.
+#[derive(Debug)]
+pub struct ApprovalAction {
+  id: ActionId,
+  approver: String,
+  decision_var: VarId,
+}
+
+impl ApprovalAction {
+  /// Wait on `approver` to set `decision_var`
+  pub fn new(id: ActionId, approver: String, decision_var: VarId) -> Self {
+    ApprovalAction {
+      id,
+      approver,
+      decision_var,
+    }
+  }
+
+  /// Create from an [`ApprovalActionConfig`], looking up the decision [`Var`] by name in `var_store`
+  pub fn from_config(id: ActionId, config: &ApprovalActionConfig, var_store: &ObjectStore<Box<dyn Var + Send + Sync>, VarId>) -> Result<Self, ActionError> {
+    let decision_var = var_store.id_from_name(&config.decision).ok_or_else(|| ActionError::VarId(IdError::NoSuchName(config.decision.clone())))?;
+    Ok(Self::new(id, config.approver.clone(), *decision_var))
+  }
+
+  /// Role that makes the decision
+  pub fn approver(&self) -> &str {
+    &self.approver
+  }
+
+  /// Hand the decision to someone else
+  pub fn set_approver(&mut self, approver: String) {
+    self.approver = approver;
+  }
+}
+
+impl Action for ApprovalAction {
+  fn id(&self) -> &ActionId {
+    &self.id
+  }
+
+  fn start(&mut self, _step: &Step, _step_name: Option<&str>, step_data: &StateDataFiltered, _vars: &ObjectStoreFiltered<Box<dyn Var + Send + Sync>, VarId>)
+    -> Result<ActionResult, ActionError>
+  {
+    if step_data.contains(&self.decision_var) {
+      return Ok(ActionResult::Finished(StateData::new()));
+    }
+    let approver = StringValue::try_new(self.approver.clone()).map_err(|_| ActionError::InvalidConfig("approver is empty".to_owned()))?;
+    Ok(ActionResult::StartWith(approver.boxed()))
+  }
+
+  fn required_vars(&self) -> &[VarId] {
+    std::slice::from_ref(&self.decision_var)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::HashSet;
+  use stepflow_base::ObjectStoreFiltered;
+  use stepflow_data::{StateDataFiltered, value::StringValue};
+  use stepflow_test_util::test_id;
+  use crate::{ActionResult, Action, ActionId};
+  use super::{ApprovalAction, ApprovalActionConfig};
+  use super::super::test_action_setup;
+
+  #[test]
+  fn waits_for_decision() {
+    let (step, state_data, var_store, var_id, _val) = test_action_setup();
+    let vars = ObjectStoreFiltered::new(&var_store, HashSet::new());
+    let no_data = StateDataFiltered::new(&state_data, HashSet::new());
+    let decided = StateDataFiltered::new(&state_data, vec![var_id].into_iter().collect());
+
+    let mut action = ApprovalAction::new(test_id!(ActionId), "reviewer".to_owned(), var_id);
+    assert_eq!(
+      action.start(&step, None, &no_data, &vars),
+      Ok(ActionResult::StartWith(StringValue::try_new("reviewer").unwrap().boxed())));
+    assert!(matches!(action.start(&step, None, &decided, &vars), Ok(ActionResult::Finished(_))));
+  }
+
+  #[test]
+  fn from_config() {
+    let (_step, _state_data, mut var_store, _var_id, _val) = test_action_setup();
+    let decision = var_store.insert_new_named("approved", |id| Ok(stepflow_data::var::BoolVar::new(id).boxed())).unwrap();
+    let config = ApprovalActionConfig { approver: "admin".to_owned(), decision: "approved".to_owned() };
+    let action = ApprovalAction::from_config(test_id!(ActionId), &config, &var_store).unwrap();
+    assert_eq!(action.approver(), "admin");
+    assert_eq!(action.required_vars(), &[decision]);
+
+    let config = ApprovalActionConfig { approver: "admin".to_owned(), decision: "missing".to_owned() };
+    assert!(ApprovalAction::from_config(test_id!(ActionId), &config, &var_store).is_err());
+  }
+}
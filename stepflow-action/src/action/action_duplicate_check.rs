@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+use stepflow_base::{ObjectStoreFiltered, IdError};
+use stepflow_data::{StateData, StateDataFiltered, InvalidVars, Normalization, var::{Var, VarId}, value::{Value, BoolValue}};
+use super::{ActionResult, Action, ActionId, Step, ActionError};
+
+
+/// Looks up whether a value is already taken, i.e. an email that's already registered. Implement it against your own records.
+pub trait DuplicateChecker: std::fmt::Debug + Send + Sync {
+  /// Whether a record already has the normalized `value` for the var named `name`. Fails only if the check couldn't be made.
+  fn exists(&self, name: &str, value: &str) -> Result<bool, String>;
+}
+
+/// Action that checks collected values against a [`DuplicateChecker`], i.e. before creating an account
+///
+/// Each checked var is [normalized](Normalization) before the lookup so `Jane@Example.com` finds `jane@example.com`.
+/// Checked vars without a value are skipped. Values that can't be normalized fail with [`ActionError::InvalidVars`].
+/// The action finishes with the `exists` [`BoolVar`](stepflow_data::var::BoolVar) set to whether any value was found so the flow can branch on it.
+#[derive(Debug)]
+pub struct DuplicateCheckAction {
+  id: ActionId,
+  exists_var_id: VarId,
+  checks: Vec<(VarId, Normalization)>,
+  check_var_ids: Vec<VarId>,
+  checker: Box<dyn DuplicateChecker>,
+}
+
+impl DuplicateCheckAction {
+  /// Create the action. `exists_var_id` must be an output of the step.
+  pub fn new(id: ActionId, exists_var_id: VarId, checker: Box<dyn DuplicateChecker>) -> Self {
+    DuplicateCheckAction {
+      id,
+      exists_var_id,
+      checks: Vec::new(),
+      check_var_ids: Vec::new(),
+      checker,
+    }
+  }
+
+  /// Check the value of `var_id` after normalizing it
+  pub fn with_check(mut self, var_id: VarId, normalization: Normalization) -> Self {
+    self.checks.push((var_id, normalization));
+    self.check_var_ids.push(var_id);
+    self
+  }
+
+  /// Look up with `checker` instead
+  pub fn with_checker(mut self, checker: Box<dyn DuplicateChecker>) -> Self {
+    self.checker = checker;
+    self
+  }
+}
+
+impl Action for DuplicateCheckAction {
+  fn id(&self) -> &ActionId {
+    &self.id
+  }
+
+  fn start(&mut self, _step: &Step, _step_name: Option<&str>, step_data: &StateDataFiltered, vars: &ObjectStoreFiltered<Box<dyn Var + Send + Sync>, VarId>)
+    -> Result<ActionResult, ActionError>
+  {
+    // normalize everything before looking anything up
+    let mut lookups = Vec::new();
+    let mut invalid = HashMap::new();
+    for (var_id, normalization) in self.checks.iter() {
+      let val = match step_data.get(var_id) {
+        Some(val) => val,
+        None => continue,
+      };
+      let name = vars.name_from_id(var_id).ok_or(ActionError::VarId(IdError::IdHasNoName(*var_id)))?;
+      match normalization.normalize(&val.get_val().get_baseval().to_string()) {
+        Ok(normalized) => lookups.push((name, normalized)),
+        Err(e) => { invalid.insert(*var_id, e); },
+      }
+    }
+    if !invalid.is_empty() {
+      return Err(ActionError::InvalidVars(InvalidVars::new(invalid)));
+    }
+
+    let mut exists = false;
+    for (name, normalized) in lookups {
+      if self.checker.exists(name, &normalized).map_err(ActionError::PluginFailed)? {
+        exists = true;
+        break;
+      }
+    }
+
+    let exists_var = vars.get(&self.exists_var_id).ok_or(ActionError::VarId(IdError::IdMissing(self.exists_var_id)))?;
+    let mut state_data = StateData::new();
+    state_data.insert(exists_var, BoolValue::new(exists).boxed()).map_err(|e| ActionError::InvalidVars(InvalidVars::new(vec![(self.exists_var_id, e)].into_iter().collect())))?;
+    Ok(ActionResult::Finished(state_data))
+  }
+
+  fn required_vars(&self) -> &[VarId] {
+    &self.check_var_ids
+  }
+
+  fn produces(&self) -> Vec<(VarId, Box<dyn Value>)> {
+    vec![(self.exists_var_id, BoolValue::new(false).boxed())]
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::HashSet;
+  use std::sync::{Arc, Mutex};
+  use stepflow_base::{ObjectStore, ObjectStoreFiltered};
+  use stepflow_data::{StateData, StateDataFiltered, InvalidValue, InvalidVars, Normalization, var::{Var, VarId, EmailVar, StringVar, BoolVar}, value::{EmailValue, StringValue, BoolValue}};
+  use stepflow_step::{Step, StepId};
+  use stepflow_test_util::test_id;
+  use super::{DuplicateCheckAction, DuplicateChecker};
+  use crate::{Action, ActionId, ActionResult, ActionError};
+
+  #[derive(Debug)]
+  struct MockChecker {
+    existing: Vec<(String, String)>,
+    looked_up: Arc<Mutex<Vec<(String, String)>>>,
+  }
+
+  impl DuplicateChecker for MockChecker {
+    fn exists(&self, name: &str, value: &str) -> Result<bool, String> {
+      let lookup = (name.to_owned(), value.to_owned());
+      self.looked_up.lock().unwrap().push(lookup.clone());
+      Ok(self.existing.contains(&lookup))
+    }
+  }
+
+  #[derive(Debug)]
+  struct DownChecker;
+
+  impl DuplicateChecker for DownChecker {
+    fn exists(&self, _name: &str, _value: &str) -> Result<bool, String> {
+      Err("unavailable".to_owned())
+    }
+  }
+
+  #[test]
+  fn duplicate_check() {
+    let mut var_store: ObjectStore<Box<dyn Var + Send + Sync>, VarId> = ObjectStore::new();
+    let email = var_store.insert_new_named("email", |id| Ok(EmailVar::new(id).boxed())).unwrap();
+    let phone = var_store.insert_new_named("phone", |id| Ok(StringVar::new(id).boxed())).unwrap();
+    let exists = var_store.insert_new_named("exists", |id| Ok(BoolVar::new(id).boxed())).unwrap();
+    let step = Step::new(test_id!(StepId), None, vec![exists]);
+    let var_filter = vec![email, phone, exists].into_iter().collect::<HashSet<_>>();
+    let vars = ObjectStoreFiltered::new(&var_store, var_filter.clone());
+    let expected = |val: bool| {
+      let mut state_data = StateData::new();
+      state_data.insert(var_store.get(&exists).unwrap(), BoolValue::new(val).boxed()).unwrap();
+      ActionResult::Finished(state_data)
+    };
+
+    let looked_up = Arc::new(Mutex::new(Vec::new()));
+    let checker = MockChecker { existing: vec![("email".to_owned(), "jane@example.com".to_owned())], looked_up: looked_up.clone() };
+    let mut action = DuplicateCheckAction::new(test_id!(ActionId), exists, Box::new(checker))
+      .with_check(email, Normalization::Email)
+      .with_check(phone, Normalization::Phone { default_country_code: "1".to_owned() });
+    assert_eq!(action.required_vars(), &[email, phone]);
+
+    // normalized email already exists, missing phone is skipped
+    let mut state_data = StateData::new();
+    state_data.insert(var_store.get(&email).unwrap(), EmailValue::try_new("Jane@Example.com").unwrap().boxed()).unwrap();
+    let step_data = StateDataFiltered::new(&state_data, var_filter.clone());
+    assert_eq!(action.start(&step, None, &step_data, &vars), Ok(expected(true)));
+    assert_eq!(*looked_up.lock().unwrap(), vec![("email".to_owned(), "jane@example.com".to_owned())]);
+
+    // new email and phone
+    looked_up.lock().unwrap().clear();
+    let mut state_data = StateData::new();
+    state_data.insert(var_store.get(&email).unwrap(), EmailValue::try_new("jo@example.com").unwrap().boxed()).unwrap();
+    state_data.insert(var_store.get(&phone).unwrap(), StringValue::try_new("(415) 555-0123").unwrap().boxed()).unwrap();
+    let step_data = StateDataFiltered::new(&state_data, var_filter.clone());
+    assert_eq!(action.start(&step, None, &step_data, &vars), Ok(expected(false)));
+    assert_eq!(looked_up.lock().unwrap()[1], ("phone".to_owned(), "+14155550123".to_owned()));
+
+    // checker failures
+    action = action.with_checker(Box::new(DownChecker));
+    assert_eq!(action.start(&step, None, &step_data, &vars), Err(ActionError::PluginFailed("unavailable".to_owned())));
+
+    // bad phone number
+    let mut state_data = StateData::new();
+    state_data.insert(var_store.get(&phone).unwrap(), StringValue::try_new("call me").unwrap().boxed()).unwrap();
+    let step_data = StateDataFiltered::new(&state_data, var_filter);
+    assert_eq!(action.start(&step, None, &step_data, &vars),
+      Err(ActionError::InvalidVars(InvalidVars::new(vec![(phone, InvalidValue::BadFormat)].into_iter().collect()))));
+  }
+}
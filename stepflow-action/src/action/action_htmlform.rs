@@ -1,21 +1,33 @@
 use std::{collections::HashMap, fmt::Write};
 use stepflow_base::{ObjectStoreFiltered, IdError};
-use stepflow_data::{StateDataFiltered, var::{Var, VarId, StringVar, EmailVar, BoolVar}, value::StringValue};
+use stepflow_data::{BaseValue, StateDataFiltered, InvalidValue, var::{Var, VarId, VarGroup, StringVar, EmailVar, BoolVar, NumberVar, DateTimeVar}, value::{StringValue, DateTimeValue, UnansweredValue}};
 use super::{ActionResult, Action, ActionId, Step, ActionError};
 use crate::{render_template, EscapedString, HtmlEscapedString};
 
 
 /// Configuration for [`HtmlFormAction`]
 ///
-/// Customize the output of [`HtmlFormAction`] with these parameters. The templates can use `{{name}}` as a placeholder for the [`Var`] name.
+/// Customize the output of [`HtmlFormAction`] with these parameters. The templates can use these placeholders:
+/// - `{{name}}`: the [`Var`] name
+/// - `{{required}}`: `required` if the [`Step`] currently requires the var, otherwise empty
+/// - `{{placeholder}}` and `{{autocomplete}}`: from the var's [`VarMetadata`](stepflow_data::var::VarMetadata), empty if not set
+/// - `{{value}}`: the current value of the var, empty if not set
+/// - `{{checked}}`: `checked` if the var's current value is `true`, otherwise empty
+/// - `{{skipped}}`: `checked` if the var is currently [unanswered](stepflow_data::value::UnansweredValue), otherwise empty
+///
+/// The default templates fill in the current values so a step shown again keeps what the user already entered.
+///
+/// Vars with [`choices`](stepflow_data::var::Var::choices) use [`choice_html_template`](HtmlFormConfig::choice_html_template) instead.
 ///
 /// ```
 /// # use stepflow_action::HtmlFormConfig;
 /// let mut html_form_config: HtmlFormConfig = Default::default();
-/// html_form_config.stringvar_html_template = "<textarea name='{{name}}'></textarea>".to_owned();
+/// html_form_config.stringvar_html_template = "<textarea name='{{name}}' placeholder='{{placeholder}}' {{required}}>{{value}}</textarea>".to_owned();
 /// ```
 // Someday we should have a HtmlFormTag trait that any var can implement and then call that for their tag. not able until we can cast a Var trait to a HtmlFormTag trait
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde-support", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde-support", serde(default))]
 pub struct HtmlFormConfig {
   /// HTML template for [`StringVar`] 
   pub stringvar_html_template: String,
@@ -26,6 +38,36 @@ pub struct HtmlFormConfig {
   /// HTML template for [`BoolVar`] 
   pub boolvar_html_template: String,
 
+  /// HTML template for [`NumberVar`]
+  pub numbervar_html_template: String,
+
+  /// HTML template for [`DateTimeVar`]s that [accept times](DateTimeVar::accepts_time).
+  /// `{{value}}` is the local date and time without its UTC offset, i.e. `2021-03-14T15:09`.
+  pub datetimevar_html_template: String,
+
+  /// HTML template for [`DateTimeVar`]s that only take dates
+  pub datevar_html_template: String,
+
+  /// HTML template added after the field of vars that [can be skipped](stepflow_data::var::Var::allows_unanswered).
+  /// The field it posts must be named with the [skip prefix](UnansweredValue::SKIP_FIELD_PREFIX) and the var's name.
+  pub skip_html_template: String,
+
+  /// HTML template for vars with a fixed set of choices.
+  /// `{{options}}` is replaced with every choice rendered with [`option_html_template`](HtmlFormConfig::option_html_template).
+  pub choice_html_template: String,
+
+  /// HTML template for each choice.
+  /// Along with the field's placeholders, it can use `{{option}}` for the choice and `{{selected}}`/`{{checked}}`
+  /// which are `selected`/`checked` when the choice is the current value, otherwise empty.
+  /// For example, render radio buttons instead of a `<select>` with:
+  /// ```
+  /// # use stepflow_action::HtmlFormConfig;
+  /// # let mut html_form_config: HtmlFormConfig = Default::default();
+  /// html_form_config.choice_html_template = "{{options}}".to_owned();
+  /// html_form_config.option_html_template = "<input type='radio' name='{{name}}' value='{{option}}' {{checked}}/>".to_owned();
+  /// ```
+  pub option_html_template: String,
+
   /// Optional HTML template inserted before any field
   /// For example, you can output a label for every field with:
   /// ```
@@ -44,13 +86,154 @@ pub struct HtmlFormConfig {
   /// ```
 
   pub wrap_tag: Option<String>, // ie. wrap entire element in a <div></div>
+
+  /// Split the fields into pages of this size. All the fields are on one page if not set.
+  ///
+  /// The page shown is the first one with a field that doesn't have a value yet.
+  /// The [`Step`] still only completes when the fields on every page have values.
+  pub fields_per_page: Option<usize>,
+
+  /// Optional HTML template for each [`VarGroup`] of fields, i.e. `<fieldset><legend>{{group}}</legend>{{fields}}</fieldset>`.
+  /// `{{group}}` is the group name and `{{fields}}` is the rendered fields in the group. Fields without a group aren't wrapped.
+  pub group_html_template: Option<String>,
+
+  /// HTML template added after the fields when they're split into more than one page.
+  /// It can use `{{page}}` for the current page, starting at 1, and `{{page_count}}` for the number of pages.
+  pub page_html_template: String,
+
+  /// HTML template for a field's error, rendered with [`format_errors`](HtmlFormConfig::format_errors).
+  /// It can use `{{name}}` for the field and `{{error_kind}}` for the [`InvalidValue`], i.e. `BadFormat`.
+  pub error_html_template: String,
+
+  /// Error templates for specific fields by var name, used instead of [`error_html_template`](HtmlFormConfig::error_html_template)
+  pub var_error_html_templates: HashMap<String, String>,
+
+  /// Templates for specific fields by var name, used instead of the template for the var's type.
+  /// For vars with choices it's used instead of [`choice_html_template`](HtmlFormConfig::choice_html_template) and can use `{{options}}`.
+  /// ```
+  /// # use stepflow_action::HtmlFormConfig;
+  /// # let mut html_form_config: HtmlFormConfig = Default::default();
+  /// html_form_config.var_html_templates.insert("bio".to_owned(), "<textarea name='{{name}}'>{{value}}</textarea>".to_owned());
+  /// ```
+  pub var_html_templates: HashMap<String, String>,
+}
+
+// per-field values for the template placeholders
+struct FieldParams {
+  name: HtmlEscapedString,
+  required: HtmlEscapedString,
+  placeholder: HtmlEscapedString,
+  autocomplete: HtmlEscapedString,
+  value: HtmlEscapedString,
+  checked: HtmlEscapedString,
+  skipped: HtmlEscapedString,
+}
+
+impl FieldParams {
+  fn from_name(name: &str) -> Self {
+    FieldParams {
+      name: HtmlEscapedString::from_unescaped(name),
+      required: HtmlEscapedString::already_escaped(String::new()),
+      placeholder: HtmlEscapedString::already_escaped(String::new()),
+      autocomplete: HtmlEscapedString::already_escaped(String::new()),
+      value: HtmlEscapedString::already_escaped(String::new()),
+      checked: HtmlEscapedString::already_escaped(String::new()),
+      skipped: HtmlEscapedString::already_escaped(String::new()),
+    }
+  }
 }
 
 impl HtmlFormConfig {
-  fn format_html_template(tag_template: &HtmlEscapedString, name_escaped: &HtmlEscapedString) -> String {
+  fn template_params(field_params: &FieldParams) -> HashMap<&'static str, &HtmlEscapedString> {
+    let mut params = HashMap::new();
+    params.insert("name", &field_params.name);
+    params.insert("required", &field_params.required);
+    params.insert("placeholder", &field_params.placeholder);
+    params.insert("autocomplete", &field_params.autocomplete);
+    params.insert("value", &field_params.value);
+    params.insert("checked", &field_params.checked);
+    params.insert("skipped", &field_params.skipped);
+    params
+  }
+
+  fn format_html_template(tag_template: &HtmlEscapedString, field_params: &FieldParams) -> String {
+    render_template::<&HtmlEscapedString>(&tag_template, Self::template_params(field_params))
+  }
+
+  // render the options for a choice var and put them into the choice template
+  fn format_choice_template(&self, choice_template: &str, choices: &[String], field_params: &FieldParams) -> String {
+    let current = field_params.value.as_ref();
+    let option_template = HtmlEscapedString::already_escaped(self.option_html_template.clone());
+    let mut options_html = String::new();
+    for choice in choices {
+      let option = HtmlEscapedString::from_unescaped(choice);
+      let is_current = option.as_ref() == current;
+      let flag = |s: &str| HtmlEscapedString::already_escaped(if is_current { s.to_owned() } else { String::new() });
+      let mut params = Self::template_params(field_params);
+      let selected = flag("selected");
+      let checked = flag("checked");
+      params.insert("option", &option);
+      params.insert("selected", &selected);
+      params.insert("checked", &checked);
+      options_html.push_str(&render_template::<&HtmlEscapedString>(&&option_template, params));
+    }
+
+    // options are already rendered so can't go through the field params which escape
+    choice_template.replace("{{options}}", &options_html)
+  }
+
+  // wrap the already rendered fields of a group
+  fn format_group_template(&self, group: &VarGroup, fields_html: &str) -> String {
+    match &self.group_html_template {
+      Some(group_html_template) => {
+        let name = HtmlEscapedString::from_unescaped(&group.name);
+        let mut params = HashMap::new();
+        params.insert("group", &name);
+        render_template::<&HtmlEscapedString>(&&HtmlEscapedString::already_escaped(group_html_template.clone()), params)
+          .replace("{{fields}}", fields_html)
+      }
+      None => fields_html.to_owned(),
+    }
+  }
+
+  // navigation info for the page being rendered. `page` starts at 1.
+  fn format_page_template(&self, page: usize, page_count: usize) -> String {
+    let page = HtmlEscapedString::already_escaped(page.to_string());
+    let page_count = HtmlEscapedString::already_escaped(page_count.to_string());
     let mut params = HashMap::new();
-    params.insert("name", name_escaped);
-    render_template::<&HtmlEscapedString>(&tag_template, params)
+    params.insert("page", &page);
+    params.insert("page_count", &page_count);
+    render_template::<&HtmlEscapedString>(&&HtmlEscapedString::already_escaped(self.page_html_template.clone()), params)
+  }
+
+  /// Render the error of each field, keyed by field name, i.e. with the errors from a form that was posted
+  ///
+  /// ```
+  /// # use stepflow_action::HtmlFormConfig;
+  /// # use stepflow_data::InvalidValue;
+  /// let mut html_form_config: HtmlFormConfig = Default::default();
+  /// html_form_config.var_error_html_templates.insert("email".to_owned(), "<p>Check your email</p>".to_owned());
+  /// let errors = html_form_config.format_errors(vec![("email", InvalidValue::BadFormat), ("name", InvalidValue::Empty)]);
+  /// assert_eq!(errors["email"], "<p>Check your email</p>");
+  /// assert_eq!(errors["name"], "<span class='error'>Empty</span>");
+  /// ```
+  pub fn format_errors<I, S>(&self, errors: I) -> HashMap<String, String>
+      where I: IntoIterator<Item = (S, InvalidValue)>,
+            S: AsRef<str>,
+  {
+    errors.into_iter()
+      .map(|(name, error)| {
+        let name = name.as_ref();
+        let template = self.var_error_html_templates.get(name).unwrap_or(&self.error_html_template);
+        let escaped_name = HtmlEscapedString::from_unescaped(name);
+        let error_kind = HtmlEscapedString::from_unescaped(&error.to_string());
+        let mut params = HashMap::new();
+        params.insert("name", &escaped_name);
+        params.insert("error_kind", &error_kind);
+        let html = render_template::<&HtmlEscapedString>(&&HtmlEscapedString::already_escaped(template.clone()), params);
+        (name.to_owned(), html)
+      })
+      .collect()
   }
 
   fn valid_wraptag(&self) -> Option<&String> {
@@ -62,8 +245,8 @@ impl HtmlFormConfig {
     None
   }
 
-  fn format_input_template(&self, html_template: &String, name_escaped: &HtmlEscapedString) -> Result<String, std::fmt::Error> {
-    let mut html = String::with_capacity(html_template.len() + name_escaped.len()); // rough guss
+  fn format_input_template(&self, html_template: &String, field_params: &FieldParams) -> Result<String, std::fmt::Error> {
+    let mut html = String::with_capacity(html_template.len() + field_params.name.len()); // rough guss
 
     // write the head of the wrap
     if let Some(wrap_tag) = self.valid_wraptag() {
@@ -74,12 +257,12 @@ impl HtmlFormConfig {
 
     // write the prefix
     if let Some(prefix_html_template) = &self.prefix_html_template {
-      let prefix_html = Self::format_html_template(&HtmlEscapedString::already_escaped(prefix_html_template.to_owned()), name_escaped);
+      let prefix_html = Self::format_html_template(&HtmlEscapedString::already_escaped(prefix_html_template.to_owned()), field_params);
       html.write_str(&prefix_html[..])?;
     }
 
     // write the tag
-    let input_html = Self::format_html_template(&HtmlEscapedString::already_escaped(html_template.to_owned()), name_escaped);
+    let input_html = Self::format_html_template(&HtmlEscapedString::already_escaped(html_template.to_owned()), field_params);
     html.write_str(&input_html[..])?;
 
     // write the tail of the wrap
@@ -95,11 +278,23 @@ impl HtmlFormConfig {
 impl Default for HtmlFormConfig {
     fn default() -> Self {
         HtmlFormConfig {
-          stringvar_html_template: "<input name='{{name}}' type='text' />".to_owned(),
-          emailvar_html_template: "<input name='{{name}}' type='email' />".to_owned(),
-          boolvar_html_template: "<input name='{{name}}' type='checkbox' />".to_owned(),
+          stringvar_html_template: "<input name='{{name}}' type='text' value='{{value}}' />".to_owned(),
+          emailvar_html_template: "<input name='{{name}}' type='email' value='{{value}}' />".to_owned(),
+          boolvar_html_template: "<input name='{{name}}' type='checkbox' value='true' {{checked}}/>".to_owned(),
+          numbervar_html_template: "<input name='{{name}}' type='number' value='{{value}}' />".to_owned(),
+          datetimevar_html_template: "<input name='{{name}}' type='datetime-local' value='{{value}}' />".to_owned(),
+          datevar_html_template: "<input name='{{name}}' type='date' value='{{value}}' />".to_owned(),
+          skip_html_template: "<label><input name='skip:{{name}}' type='checkbox' value='true' {{skipped}}/> Skip</label>".to_owned(),
+          choice_html_template: "<select name='{{name}}' {{required}}>{{options}}</select>".to_owned(),
+          option_html_template: "<option value='{{option}}' {{selected}}>{{option}}</option>".to_owned(),
           prefix_html_template: None,
           wrap_tag: None,
+          group_html_template: None,
+          fields_per_page: None,
+          page_html_template: "<input name='page' type='hidden' value='{{page}}' data-page-count='{{page_count}}' />".to_owned(),
+          error_html_template: "<span class='error'>{{error_kind}}</span>".to_owned(),
+          var_error_html_templates: HashMap::new(),
+          var_html_templates: HashMap::new(),
         }
     }
 }
@@ -108,6 +303,7 @@ impl Default for HtmlFormConfig {
 /// Action to generate an HTML form for a [`Step`]
 ///
 /// The action looks iterates through all the outputs of the current Step and generates HTML based on the [`HtmlFormConfig`].
+/// Conditional outputs are skipped when their condition doesn't hold, unless the condition depends on another output of the Step.
 /// The HTML is returned as a string in the [`ActionResult::StartWith`] result
 #[derive(Debug)]
 pub struct HtmlFormAction {
@@ -124,8 +320,8 @@ impl HtmlFormAction {
     }
   }
 
-  pub fn boxed(self) -> Box<dyn Action + Sync + Send> {
-    Box::new(self)
+  pub fn html_config(&self) -> &HtmlFormConfig {
+    &self.html_config
   }
 }
 
@@ -134,34 +330,151 @@ impl Action for HtmlFormAction {
     &self.id
   }
 
-  fn start(&mut self, step: &Step, _step_name: Option<&str>, _step_data: &StateDataFiltered, vars: &ObjectStoreFiltered<Box<dyn Var + Send + Sync>, VarId>)
+  fn start(&mut self, step: &Step, _step_name: Option<&str>, step_data: &StateDataFiltered, vars: &ObjectStoreFiltered<Box<dyn Var + Send + Sync>, VarId>)
     -> Result<ActionResult, ActionError>
   {
+    let field_var_ids = step.get_output_vars()
+      .iter()
+      .filter(|var_id| {
+        match step.get_output_condition(var_id) {
+          Some(condition) => {
+            // can only know if it's needed if the condition isn't waiting on this form
+            let depends_on_form = condition.var_ids().iter().any(|id| step.get_output_vars().contains(id));
+            depends_on_form || condition.eval_filtered(step_data)
+          }
+          None => true,
+        }
+      })
+      .collect::<Vec<_>>();
+
+    // keep the fields of a group together. ungrouped fields come first.
+    let group_of = |var_id: &VarId| vars.get(var_id).and_then(|var| var.metadata().group.as_ref());
+    let mut field_var_ids = field_var_ids;
+    field_var_ids.sort_by_key(|var_id| group_of(var_id).map(|group| (group.order, group.name.clone())));
+
+    // show the first page that still needs a value
+    let mut page_var_ids = &field_var_ids[..];
+    let mut page_info = None;
+    if let Some(fields_per_page) = self.html_config.fields_per_page.filter(|n| *n > 0) {
+      let pages = field_var_ids.chunks(fields_per_page).collect::<Vec<_>>();
+      if pages.len() > 1 {
+        let page = pages.iter()
+          .position(|page| page.iter().any(|var_id| step_data.get(var_id).is_none()))
+          .unwrap_or(pages.len() - 1);
+        page_var_ids = pages[page];
+        page_info = Some((page + 1, pages.len()));
+      }
+    }
+
     const AVG_NAME_LEN: usize = 5;
-    let mut html = String::with_capacity(step.get_output_vars().len() * (self.html_config.stringvar_html_template.len() + AVG_NAME_LEN));
-    for var_id in step.get_output_vars().iter() {
+    let mut html = String::with_capacity(page_var_ids.len() * (self.html_config.stringvar_html_template.len() + AVG_NAME_LEN));
+    let mut group: Option<&VarGroup> = None;
+    let mut group_html = String::new();
+    for var_id in page_var_ids.iter().copied() {
+      // finish the previous group when it changes
+      let var_group = group_of(var_id);
+      if var_group != group {
+        if let Some(group) = group {
+          html.push_str(&self.html_config.format_group_template(group, &group_html));
+          group_html.clear();
+        }
+        group = var_group;
+      }
+      let field_html = if group.is_some() { &mut group_html } else { &mut html };
+
       let name = vars.name_from_id(var_id).ok_or_else(|| ActionError::VarId(IdError::IdHasNoName(var_id.clone())))?;
-      let name_escaped = HtmlEscapedString::from_unescaped(&(name.to_string())[..]);
+      let mut field_params = FieldParams::from_name(name);
 
       let var = vars.get(var_id).ok_or_else(|| ActionError::VarId(IdError::IdMissing(var_id.clone())))?;
+
+      // fill in the rest of the params
+      let required = match step.get_output_condition(var_id) {
+        Some(condition) => condition.eval_filtered(step_data),
+        None => true,
+      };
+      if required {
+        field_params.required = HtmlEscapedString::already_escaped("required".to_owned());
+      }
+      let metadata = var.metadata();
+      if let Some(placeholder) = &metadata.placeholder {
+        field_params.placeholder = HtmlEscapedString::from_unescaped(placeholder);
+      }
+      if let Some(autocomplete) = &metadata.autocomplete {
+        field_params.autocomplete = HtmlEscapedString::from_unescaped(autocomplete);
+      }
+      // never echo sensitive values, i.e. passwords, back into the page
+      if let Some(valid_val) = step_data.get(var_id).filter(|_| !metadata.sensitive) {
+        let base_val = valid_val.get_val().get_baseval();
+        if base_val == BaseValue::Boolean(true) {
+          field_params.checked = HtmlEscapedString::already_escaped("checked".to_owned());
+        }
+        if valid_val.get_val().is::<UnansweredValue>() {
+          field_params.skipped = HtmlEscapedString::already_escaped("checked".to_owned());
+        }
+        let datetime_local = var.downcast::<DateTimeVar>()
+          .filter(|datetime_var| datetime_var.accepts_time())
+          .and(valid_val.get_val().downcast::<DateTimeValue>());
+        let value = match base_val {
+          BaseValue::String(s) => s,
+          BaseValue::Boolean(b) => b.to_string(),
+          BaseValue::Int(i) => i.to_string(),
+          BaseValue::Float(f) => f.to_string(),
+          list @ BaseValue::List(_) => list.to_string(),
+          BaseValue::Null => String::new(),
+        };
+        let value = datetime_local.map(datetime_local_value).unwrap_or(value);
+        field_params.value = HtmlEscapedString::from_unescaped(&value);
+      }
+
+      let choice_template;
       let html_template;
-      if var.is::<StringVar>() {
+      let var_template = self.html_config.var_html_templates.get(name);
+      if let Some(choices) = var.choices() {
+        let template = var_template.unwrap_or(&self.html_config.choice_html_template);
+        choice_template = self.html_config.format_choice_template(template, choices, &field_params);
+        html_template = &choice_template;
+      } else if let Some(var_template) = var_template {
+        html_template = var_template;
+      } else if var.is::<StringVar>() {
         html_template = &self.html_config.stringvar_html_template;
       } else if var.is::<EmailVar>() {
         html_template = &self.html_config.emailvar_html_template;
       } else if var.is::<BoolVar>() {
         html_template = &self.html_config.boolvar_html_template;
+      } else if var.is::<NumberVar>() {
+        html_template = &self.html_config.numbervar_html_template;
+      } else if let Some(datetime_var) = var.downcast::<DateTimeVar>() {
+        html_template = if datetime_var.accepts_time() {
+          &self.html_config.datetimevar_html_template
+        } else {
+          &self.html_config.datevar_html_template
+        };
       } else {
         // perhaps panic when in debug? 
         // maybe in the future we should ask variables to support a trait that gets their HTML format
         return Err(ActionError::VarId(IdError::IdUnexpected(var_id.clone())));
       }
 
+      let skippable_template;
+      let html_template = if var.allows_unanswered() {
+        skippable_template = format!("{}{}", html_template, self.html_config.skip_html_template);
+        &skippable_template
+      } else {
+        html_template
+      };
+
       self.html_config
-        .format_input_template(html_template, &name_escaped)
-        .and_then(|input_html| html.write_str(&input_html[..]))
+        .format_input_template(html_template, &field_params)
+        .and_then(|input_html| field_html.write_str(&input_html[..]))
         .map_err(|_e| ActionError::Other)?;
     }
+    if let Some(group) = group {
+      html.push_str(&self.html_config.format_group_template(group, &group_html));
+    }
+
+    if let Some((page, page_count)) = page_info {
+      html.push_str(&self.html_config.format_page_template(page, page_count));
+    }
 
     let stringval = StringValue::try_new(html).map_err(|_e| ActionError::Other)?;
     Ok(ActionResult::StartWith(stringval.boxed()))
@@ -169,13 +482,23 @@ impl Action for HtmlFormAction {
 }
 
 
+// `datetime-local` inputs take `YYYY-MM-DDTHH:MM[:SS[.fff]]` without an offset and need a time
+fn datetime_local_value(datetime: &DateTimeValue) -> String {
+  let val = datetime.val();
+  if !datetime.has_time() {
+    return format!("{}T00:00", val);
+  }
+  let (date, time) = val.split_at(10);
+  let local_len = time.find(['Z', '+', '-']).unwrap_or(time.len());
+  format!("{}{}", date, &time[..local_len])
+}
 
 #[cfg(test)]
 mod tests {
   use std::collections::HashSet;
-  use super::{HtmlEscapedString, EscapedString, HtmlFormConfig, HtmlFormAction};
+  use super::{HtmlFormConfig, HtmlFormAction, FieldParams};
   use stepflow_base::{ObjectStore, ObjectStoreFiltered};
-  use stepflow_data::{StateData, StateDataFiltered, var::{Var, VarId, EmailVar, StringVar}, value::StringValue};
+  use stepflow_data::{StateData, StateDataFiltered, Condition, InvalidValue, var::{Var, VarId, VarMetadata, VarGroup, EmailVar, StringVar, BoolVar, NumberVar, EnumVar, DateTimeVar}, value::{Value, StringValue, BoolValue, IntValue, EnumValue, DateTimeValue, UnansweredValue}};
   use stepflow_step::{Step, StepId};
   use stepflow_test_util::test_id;
   use super::super::{ActionResult, Action, ActionId};
@@ -187,7 +510,7 @@ mod tests {
     html_config.emailvar_html_template = "e({{name}},{{name}})".to_owned();
 
     // simple case
-    let escaped_n = HtmlEscapedString::from_unescaped("n");
+    let escaped_n = FieldParams::from_name("n");
     let formatted = html_config.format_input_template(&html_config.stringvar_html_template, &escaped_n).unwrap();
     assert_eq!(formatted, "s(n,n)");
 
@@ -207,6 +530,146 @@ mod tests {
     assert_eq!(wrapped_empty, "p(n)s(n,n)");
   }
 
+  #[test]
+  fn number_form() {
+    let var = NumberVar::new_int(test_id!(VarId)).with_range(Some(0.0), None).boxed();
+    let var_id = *var.id();
+    let step = Step::new(StepId::new(4), None, vec![var_id]);
+    let mut var_store: ObjectStore<Box<dyn Var + Send + Sync>, VarId> = ObjectStore::new();
+    var_store.register_named("count", var).unwrap();
+
+    // posted forms are parsed with the var
+    let mut state_data = StateData::new();
+    let posted = var_store.get(&var_id).unwrap().value_from_str("3").unwrap();
+    assert_eq!(posted.downcast::<IntValue>(), Some(&IntValue::new(3)));
+    state_data.insert(var_store.get(&var_id).unwrap(), posted).unwrap();
+
+    let var_filter = vec![var_id].into_iter().collect::<HashSet<_>>();
+    let step_data_filtered = StateDataFiltered::new(&state_data, var_filter.clone());
+    let var_store_filtered = ObjectStoreFiltered::new(&var_store, var_filter);
+    let html_config = HtmlFormConfig {
+      numbervar_html_template: "<input name='{{name}}' type='number' value='{{value}}' />".to_owned(),
+      ..Default::default()
+    };
+    let mut exec = HtmlFormAction::new(test_id!(ActionId), html_config);
+    match exec.start(&step, None, &step_data_filtered, &var_store_filtered).unwrap() {
+      ActionResult::StartWith(html) => assert_eq!(html.downcast::<StringValue>().unwrap().val(), "<input name='count' type='number' value='3' />"),
+      _ => panic!("Did not get startwith value"),
+    }
+  }
+
+  #[test]
+  fn datetime_form() {
+    let booking = DateTimeVar::new(test_id!(VarId)).boxed();
+    let birthdate = DateTimeVar::new(test_id!(VarId)).dates_only().boxed();
+    let var_ids = vec![*booking.id(), *birthdate.id()];
+    let step = Step::new(StepId::new(4), None, var_ids.clone());
+    let mut state_data = StateData::new();
+    state_data.insert(&booking, DateTimeValue::try_new("2021-03-14T15:09:26-08:00").unwrap().boxed()).unwrap();
+    state_data.insert(&birthdate, DateTimeValue::try_new("1990-07-04").unwrap().boxed()).unwrap();
+
+    let mut var_store: ObjectStore<Box<dyn Var + Send + Sync>, VarId> = ObjectStore::new();
+    var_store.register_named("booking", booking).unwrap();
+    var_store.register_named("birthdate", birthdate).unwrap();
+    let var_filter = var_ids.into_iter().collect::<HashSet<_>>();
+    let step_data_filtered = StateDataFiltered::new(&state_data, var_filter.clone());
+    let var_store_filtered = ObjectStoreFiltered::new(&var_store, var_filter.clone());
+
+    // times get a datetime-local input filled without the offset, dates only get a date input
+    let mut exec = HtmlFormAction::new(test_id!(ActionId), Default::default());
+    match exec.start(&step, None, &step_data_filtered, &var_store_filtered).unwrap() {
+      ActionResult::StartWith(html) => assert_eq!(
+        html.downcast::<StringValue>().unwrap().val(),
+        "<input name='booking' type='datetime-local' value='2021&#x2D;03&#x2D;14T15&#x3A;09&#x3A;26' /><input name='birthdate' type='date' value='1990&#x2D;07&#x2D;04' />"),
+      _ => panic!("Did not get startwith value"),
+    }
+
+    // dates in a var that takes times still fill the input
+    let mut state_data = StateData::new();
+    state_data.insert(var_store.get(&step.output_vars[0]).unwrap(), DateTimeValue::try_new("2021-03-14").unwrap().boxed()).unwrap();
+    let step_data_filtered = StateDataFiltered::new(&state_data, var_filter);
+    match exec.start(&step, None, &step_data_filtered, &var_store_filtered).unwrap() {
+      ActionResult::StartWith(html) => assert!(html.downcast::<StringValue>().unwrap().val().starts_with(
+        "<input name='booking' type='datetime-local' value='2021&#x2D;03&#x2D;14T00&#x3A;00' />")),
+      _ => panic!("Did not get startwith value"),
+    }
+  }
+
+  #[test]
+  fn prefill() {
+    let name = StringVar::new(test_id!(VarId)).boxed();
+    let agreed = BoolVar::new(test_id!(VarId)).boxed();
+    let var_ids = vec![*name.id(), *agreed.id()];
+    let step = Step::new(StepId::new(4), None, var_ids.clone());
+    let mut state_data = StateData::new();
+    state_data.insert(&name, StringValue::try_new("O'Neil").unwrap().boxed()).unwrap();
+    state_data.insert(&agreed, BoolValue::new(true).boxed()).unwrap();
+
+    let mut var_store: ObjectStore<Box<dyn Var + Send + Sync>, VarId> = ObjectStore::new();
+    var_store.register_named("name", name).unwrap();
+    var_store.register_named("agreed", agreed).unwrap();
+    let var_filter = var_ids.into_iter().collect::<HashSet<_>>();
+    let step_data_filtered = StateDataFiltered::new(&state_data, var_filter.clone());
+    let var_store_filtered = ObjectStoreFiltered::new(&var_store, var_filter);
+
+    // the default templates keep what was already entered
+    let mut exec = HtmlFormAction::new(test_id!(ActionId), Default::default());
+    match exec.start(&step, None, &step_data_filtered, &var_store_filtered).unwrap() {
+      ActionResult::StartWith(html) => assert_eq!(
+        html.downcast::<StringValue>().unwrap().val(),
+        "<input name='name' type='text' value='O&#x27;Neil' /><input name='agreed' type='checkbox' value='true' checked/>"),
+      _ => panic!("Did not get startwith value"),
+    }
+  }
+
+  #[test]
+  fn prefill_sensitive() {
+    let metadata = VarMetadata { sensitive: true, ..Default::default() };
+    let password = StringVar::new(test_id!(VarId)).with_metadata(metadata).boxed();
+    let var_ids = vec![*password.id()];
+    let step = Step::new(StepId::new(4), None, var_ids.clone());
+    let mut state_data = StateData::new();
+    state_data.insert(&password, StringValue::try_new("hunter2").unwrap().boxed()).unwrap();
+
+    let mut var_store: ObjectStore<Box<dyn Var + Send + Sync>, VarId> = ObjectStore::new();
+    var_store.register_named("password", password).unwrap();
+    let var_filter = var_ids.into_iter().collect::<HashSet<_>>();
+    let step_data_filtered = StateDataFiltered::new(&state_data, var_filter.clone());
+    let var_store_filtered = ObjectStoreFiltered::new(&var_store, var_filter);
+
+    let mut exec = HtmlFormAction::new(test_id!(ActionId), Default::default());
+    match exec.start(&step, None, &step_data_filtered, &var_store_filtered).unwrap() {
+      ActionResult::StartWith(html) => assert_eq!(html.downcast::<StringValue>().unwrap().val(), "<input name='password' type='text' value='' />"),
+      _ => panic!("Did not get startwith value"),
+    }
+  }
+
+  #[test]
+  fn skippable() {
+    let newsletter = BoolVar::new(test_id!(VarId)).allow_unanswered().boxed();
+    let newsletter_id = *newsletter.id();
+    let var_ids = vec![newsletter_id];
+    let step = Step::new(StepId::new(4), None, var_ids.clone());
+    let mut var_store: ObjectStore<Box<dyn Var + Send + Sync>, VarId> = ObjectStore::new();
+    var_store.register_named("newsletter", newsletter).unwrap();
+    let var_filter = var_ids.into_iter().collect::<HashSet<_>>();
+    let var_store_filtered = ObjectStoreFiltered::new(&var_store, var_filter.clone());
+    let mut exec = HtmlFormAction::new(test_id!(ActionId), Default::default());
+    let mut render = |state_data: &StateData| match exec.start(&step, None, &StateDataFiltered::new(state_data, var_filter.clone()), &var_store_filtered).unwrap() {
+      ActionResult::StartWith(html) => html.downcast::<StringValue>().unwrap().val().to_owned(),
+      _ => panic!("Did not get startwith value"),
+    };
+
+    assert_eq!(render(&StateData::new()),
+      "<input name='newsletter' type='checkbox' value='true' /><label><input name='skip:newsletter' type='checkbox' value='true' /> Skip</label>");
+
+    // shown as skipped when it already was
+    let mut state_data = StateData::new();
+    state_data.insert(var_store.get(&newsletter_id).unwrap(), UnansweredValue::new().boxed()).unwrap();
+    assert_eq!(render(&state_data),
+      "<input name='newsletter' type='checkbox' value='true' /><label><input name='skip:newsletter' type='checkbox' value='true' checked/> Skip</label>");
+  }
+
   #[test]
   fn simple_form() {
     let var1 = StringVar::new(test_id!(VarId));
@@ -228,7 +691,7 @@ mod tests {
     let action_result = exec.start(&step, None, &step_data_filtered, &var_store_filtered).unwrap();
     if let ActionResult::StartWith(html) = action_result {
       let html = html.downcast::<StringValue>().unwrap().val();
-      assert_eq!(html, "<input name='var&#x20;1' type='text' /><input name='var&#x20;2' type='email' />");
+      assert_eq!(html, "<input name='var&#x20;1' type='text' value='' /><input name='var&#x20;2' type='email' value='' />");
     } else {
       panic!("Did not get startwith value");
     }
@@ -248,4 +711,275 @@ mod tests {
     }
   }
 
+  #[test]
+  fn conditional_output() {
+    let is_business = BoolVar::new(test_id!(VarId)).boxed();
+    let company_name = StringVar::new(test_id!(VarId));
+    let company_id = *company_name.id();
+    let is_business_true = Condition::Equals(*is_business.id(), BoolValue::new(true).boxed());
+
+    let name = StringVar::new(test_id!(VarId));
+    let name_id = *name.id();
+
+    let mut var_store: ObjectStore<Box<dyn Var + Send + Sync>, VarId> = ObjectStore::new();
+    var_store.register_named("name", name.boxed()).unwrap();
+    var_store.register_named("company", company_name.boxed()).unwrap();
+    let var_filter = vec![name_id, company_id, *is_business.id()].into_iter().collect::<HashSet<_>>();
+    let var_store_filtered = ObjectStoreFiltered::new(&var_store, var_filter.clone());
+
+    let mut step = Step::new(StepId::new(4), None, vec![name_id]);
+    step.set_output_required_when(company_id, is_business_true);
+
+    let render = |state_data: &StateData| {
+      let step_data_filtered = StateDataFiltered::new(state_data, var_filter.clone());
+      let mut exec = HtmlFormAction::new(test_id!(ActionId), Default::default());
+      match exec.start(&step, None, &step_data_filtered, &var_store_filtered).unwrap() {
+        ActionResult::StartWith(html) => html.downcast::<StringValue>().unwrap().val().to_owned(),
+        _ => panic!("Did not get startwith value"),
+      }
+    };
+
+    let mut state_data = StateData::new();
+    state_data.insert(&is_business, BoolValue::new(false).boxed()).unwrap();
+    assert_eq!(render(&state_data), "<input name='name' type='text' value='' />");
+
+    state_data.insert(&is_business, BoolValue::new(true).boxed()).unwrap();
+    assert_eq!(render(&state_data), "<input name='name' type='text' value='' /><input name='company' type='text' value='' />");
+  }
+
+  #[test]
+  fn field_params() {
+    let metadata = VarMetadata { placeholder: Some("Jane <Doe>".to_owned()), autocomplete: Some("name".to_owned()), ..Default::default() };
+    let name = StringVar::new(test_id!(VarId)).with_metadata(metadata).boxed();
+    let nickname = StringVar::new(test_id!(VarId)).boxed();
+    let var_filter = vec![*name.id(), *nickname.id()].into_iter().collect::<HashSet<_>>();
+
+    let mut state_data = StateData::new();
+    state_data.insert(&name, StringValue::try_new("O'Neil").unwrap().boxed()).unwrap();
+    let step_data_filtered = StateDataFiltered::new(&state_data, var_filter.clone());
+
+    let mut step = Step::new(StepId::new(4), None, vec![*name.id()]);
+    step.set_output_required_when(*nickname.id(), Condition::IsSet(test_id!(VarId)));
+
+    let mut var_store: ObjectStore<Box<dyn Var + Send + Sync>, VarId> = ObjectStore::new();
+    var_store.register_named("name", name).unwrap();
+    var_store.register_named("nickname", nickname).unwrap();
+    let var_store_filtered = ObjectStoreFiltered::new(&var_store, var_filter);
+
+    let html_config = HtmlFormConfig {
+      stringvar_html_template: "<input name='{{name}}' placeholder='{{placeholder}}' autocomplete='{{autocomplete}}' value='{{value}}' {{required}}/>".to_owned(),
+      ..Default::default()
+    };
+    let mut exec = HtmlFormAction::new(test_id!(ActionId), html_config);
+    match exec.start(&step, None, &step_data_filtered, &var_store_filtered).unwrap() {
+      ActionResult::StartWith(html) => {
+        assert_eq!(
+          html.downcast::<StringValue>().unwrap().val(),
+          "<input name='name' placeholder='Jane&#x20;&lt;Doe&gt;' autocomplete='name' value='O&#x27;Neil' required/>");
+      }
+      _ => panic!("Did not get startwith value"),
+    }
+  }
+
+  #[derive(Debug)]
+  struct ColorVar {
+    id: VarId,
+    colors: Vec<String>,
+  }
+
+  impl Var for ColorVar {
+    fn id(&self) -> &VarId { &self.id }
+    fn value_from_str(&self, s: &str) -> Result<Box<dyn Value>, InvalidValue> {
+      Ok(StringValue::try_new(s.to_owned())?.boxed())
+    }
+    fn validate_val_type(&self, _val: &Box<dyn Value>) -> Result<(), InvalidValue> {
+      Ok(())
+    }
+    fn choices(&self) -> Option<&[String]> {
+      Some(&self.colors)
+    }
+  }
+
+  #[test]
+  fn choices() {
+    let color = ColorVar { id: test_id!(VarId), colors: vec!["red".to_owned(), "blue".to_owned()] };
+    let color_id = color.id;
+    let color: Box<dyn Var + Send + Sync> = Box::new(color);
+    let var_filter = vec![color_id].into_iter().collect::<HashSet<_>>();
+
+    let mut state_data = StateData::new();
+    state_data.insert(&color, StringValue::try_new("blue").unwrap().boxed()).unwrap();
+    let step_data_filtered = StateDataFiltered::new(&state_data, var_filter.clone());
+    let step = Step::new(StepId::new(4), None, vec![color_id]);
+
+    let mut var_store: ObjectStore<Box<dyn Var + Send + Sync>, VarId> = ObjectStore::new();
+    var_store.register_named("color", color).unwrap();
+    let var_store_filtered = ObjectStoreFiltered::new(&var_store, var_filter);
+
+    let render = |html_config: HtmlFormConfig| {
+      let mut exec = HtmlFormAction::new(test_id!(ActionId), html_config);
+      match exec.start(&step, None, &step_data_filtered, &var_store_filtered).unwrap() {
+        ActionResult::StartWith(html) => html.downcast::<StringValue>().unwrap().val().to_owned(),
+        _ => panic!("Did not get startwith value"),
+      }
+    };
+
+    assert_eq!(
+      render(Default::default()),
+      "<select name='color' required><option value='red' >red</option><option value='blue' selected>blue</option></select>");
+
+    let radio_config = HtmlFormConfig {
+      choice_html_template: "{{options}}".to_owned(),
+      option_html_template: "<input type='radio' name='{{name}}' value='{{option}}' {{checked}}/>".to_owned(),
+      wrap_tag: Some("fieldset".to_owned()),
+      ..Default::default()
+    };
+    assert_eq!(
+      render(radio_config),
+      "<fieldset><input type='radio' name='color' value='red' /><input type='radio' name='color' value='blue' checked/></fieldset>");
+
+    // override just this var
+    let mut var_config = HtmlFormConfig::default();
+    var_config.var_html_templates.insert("color".to_owned(), "<div>{{options}}</div>".to_owned());
+    assert_eq!(
+      render(var_config),
+      "<div><option value='red' >red</option><option value='blue' selected>blue</option></div>");
+
+    // built-in var with options
+    let plan = EnumVar::new(test_id!(VarId), vec!["free", "pro"]).boxed();
+    let plan_id = *plan.id();
+    let mut state_data = StateData::new();
+    state_data.insert(&plan, EnumValue::try_new("pro").unwrap().boxed()).unwrap();
+    let var_filter = vec![plan_id].into_iter().collect::<HashSet<_>>();
+    let step_data_filtered = StateDataFiltered::new(&state_data, var_filter.clone());
+    let mut var_store: ObjectStore<Box<dyn Var + Send + Sync>, VarId> = ObjectStore::new();
+    var_store.register_named("plan", plan).unwrap();
+    let step = Step::new(StepId::new(4), None, vec![plan_id]);
+    let mut exec = HtmlFormAction::new(test_id!(ActionId), Default::default());
+    let html = match exec.start(&step, None, &step_data_filtered, &ObjectStoreFiltered::new(&var_store, var_filter)).unwrap() {
+      ActionResult::StartWith(html) => html.downcast::<StringValue>().unwrap().val().to_owned(),
+      _ => panic!("Did not get startwith value"),
+    };
+    assert_eq!(html, "<select name='plan' required><option value='free' >free</option><option value='pro' selected>pro</option></select>");
+  }
+
+  #[test]
+  fn var_templates() {
+    let name = StringVar::new(test_id!(VarId)).boxed();
+    let bio = StringVar::new(test_id!(VarId)).boxed();
+    let var_ids = vec![*name.id(), *bio.id()];
+    let step = Step::new(StepId::new(4), None, var_ids.clone());
+    let mut state_data = StateData::new();
+    state_data.insert(&bio, StringValue::try_new("Hi <3").unwrap().boxed()).unwrap();
+
+    let mut var_store: ObjectStore<Box<dyn Var + Send + Sync>, VarId> = ObjectStore::new();
+    var_store.register_named("name", name).unwrap();
+    var_store.register_named("bio", bio).unwrap();
+    let var_filter = var_ids.into_iter().collect::<HashSet<_>>();
+    let step_data_filtered = StateDataFiltered::new(&state_data, var_filter.clone());
+    let var_store_filtered = ObjectStoreFiltered::new(&var_store, var_filter);
+
+    let mut html_config = HtmlFormConfig::default();
+    html_config.var_html_templates.insert("bio".to_owned(), "<textarea name='{{name}}'>{{value}}</textarea>".to_owned());
+    let mut exec = HtmlFormAction::new(test_id!(ActionId), html_config);
+    match exec.start(&step, None, &step_data_filtered, &var_store_filtered).unwrap() {
+      ActionResult::StartWith(html) => assert_eq!(
+        html.downcast::<StringValue>().unwrap().val(),
+        "<input name='name' type='text' value='' /><textarea name='bio'>Hi&#x20;&lt;3</textarea>"),
+      _ => panic!("Did not get startwith value"),
+    }
+  }
+
+  #[test]
+  fn pages() {
+    let vars = (0..3).map(|_| StringVar::new(test_id!(VarId)).boxed()).collect::<Vec<_>>();
+    let var_ids = vars.iter().map(|var| *var.id()).collect::<Vec<_>>();
+    let var_filter = var_ids.iter().copied().collect::<HashSet<_>>();
+    let step = Step::new(StepId::new(4), None, var_ids.clone());
+
+    let mut state_data = StateData::new();
+    let mut var_store: ObjectStore<Box<dyn Var + Send + Sync>, VarId> = ObjectStore::new();
+    for (i, var) in vars.into_iter().enumerate() {
+      var_store.register_named(format!("v{}", i), var).unwrap();
+    }
+    let var_store_filtered = ObjectStoreFiltered::new(&var_store, var_filter.clone());
+
+    let html_config = HtmlFormConfig {
+      stringvar_html_template: "s({{name}})".to_owned(),
+      page_html_template: "p({{page}}/{{page_count}})".to_owned(),
+      fields_per_page: Some(2),
+      ..Default::default()
+    };
+    let render = |state_data: &StateData| {
+      let step_data_filtered = StateDataFiltered::new(state_data, var_filter.clone());
+      let mut exec = HtmlFormAction::new(test_id!(ActionId), html_config.clone());
+      match exec.start(&step, None, &step_data_filtered, &var_store_filtered).unwrap() {
+        ActionResult::StartWith(html) => html.downcast::<StringValue>().unwrap().val().to_owned(),
+        _ => panic!("Did not get startwith value"),
+      }
+    };
+
+    assert_eq!(render(&state_data), "s(v0)s(v1)p(1/2)");
+
+    // stays on the page until all its fields have values
+    state_data.insert(var_store.get(&var_ids[0]).unwrap(), StringValue::try_new("a").unwrap().boxed()).unwrap();
+    assert_eq!(render(&state_data), "s(v0)s(v1)p(1/2)");
+    state_data.insert(var_store.get(&var_ids[1]).unwrap(), StringValue::try_new("b").unwrap().boxed()).unwrap();
+    assert_eq!(render(&state_data), "s(v2)p(2/2)");
+  }
+
+  #[test]
+  fn groups() {
+    let in_group = |group: &VarGroup| VarMetadata { group: Some(group.clone()), ..Default::default() };
+    let contact = VarGroup::new("Contact <1>", 2);
+    let address = VarGroup::new("Address", 1);
+    let vars = vec![
+      ("email", StringVar::new(test_id!(VarId)).with_metadata(in_group(&contact)).boxed()),
+      ("street", StringVar::new(test_id!(VarId)).with_metadata(in_group(&address)).boxed()),
+      ("notes", StringVar::new(test_id!(VarId)).boxed()),
+      ("phone", StringVar::new(test_id!(VarId)).with_metadata(in_group(&contact)).boxed()),
+    ];
+    let var_ids = vars.iter().map(|(_, var)| *var.id()).collect::<Vec<_>>();
+    let var_filter = var_ids.iter().copied().collect::<HashSet<_>>();
+    let step = Step::new(StepId::new(4), None, var_ids);
+
+    let mut var_store: ObjectStore<Box<dyn Var + Send + Sync>, VarId> = ObjectStore::new();
+    for (name, var) in vars {
+      var_store.register_named(name, var).unwrap();
+    }
+    let var_store_filtered = ObjectStoreFiltered::new(&var_store, var_filter.clone());
+    let state_data = StateData::new();
+    let step_data_filtered = StateDataFiltered::new(&state_data, var_filter);
+
+    let html_config = HtmlFormConfig {
+      stringvar_html_template: "s({{name}})".to_owned(),
+      group_html_template: Some("g({{group}}:{{fields}})".to_owned()),
+      ..Default::default()
+    };
+    let mut exec = HtmlFormAction::new(test_id!(ActionId), html_config);
+    match exec.start(&step, None, &step_data_filtered, &var_store_filtered).unwrap() {
+      ActionResult::StartWith(html) => {
+        assert_eq!(
+          html.downcast::<StringValue>().unwrap().val(),
+          "s(notes)g(Address:s(street))g(Contact&#x20;&lt;1&gt;:s(email)s(phone))");
+      }
+      _ => panic!("Did not get startwith value"),
+    }
+  }
+
+  #[test]
+  fn errors() {
+    let html_form_config = HtmlFormConfig {
+      error_html_template: "<em data-for='{{name}}'>{{error_kind}}</em>".to_owned(),
+      var_error_html_templates: vec![("age".to_owned(), "<p>{{name}} must be {{error_kind}}</p>".to_owned())].into_iter().collect(),
+      ..Default::default()
+    };
+    let errors = html_form_config.format_errors(vec![
+      ("first <name>".to_owned(), InvalidValue::Empty),
+      ("age".to_owned(), InvalidValue::OutOfRange),
+    ]);
+    assert_eq!(errors.len(), 2);
+    assert_eq!(errors["first <name>"], "<em data-for='first&#x20;&lt;name&gt;'>Empty</em>");
+    assert_eq!(errors["age"], "<p>age must be OutOfRange</p>");
+  }
 }
@@ -0,0 +1,386 @@
+use std::collections::HashMap;
+use std::time::Duration;
+use stepflow_base::{ObjectStore, ObjectStoreContent, ObjectStoreFiltered, IdError};
+use stepflow_data::{StateData, StateDataFiltered, var::{Var, VarId}};
+use super::{ActionResult, Action, ActionId, Step, ActionError};
+use crate::{render_template, EscapedString, UriEscapedString, FlowEnvironment};
+
+
+/// Configuration for [`HttpRequestAction`]
+#[derive(Debug, Clone, PartialEq, Default, serde::Serialize, serde::Deserialize)]
+pub struct HttpRequestActionConfig {
+  /// URL template. Values are URI escaped.
+  pub url: String,
+
+  /// HTTP method, `GET` if not set
+  #[serde(default)]
+  pub method: Option<String>,
+
+  /// Header templates. Values are filled in as is.
+  #[serde(default)]
+  pub headers: HashMap<String, String>,
+
+  /// Headers with `${secret.<name>}` references, i.e. `Bearer ${secret.api_key}`.
+  /// The secrets come from the [`FlowEnvironment`] when the request is sent so they're never in the config.
+  #[serde(default)]
+  pub secret_headers: HashMap<String, String>,
+
+  /// Body template, i.e. JSON. Values are escaped for a JSON string.
+  #[serde(default)]
+  pub body: Option<String>,
+
+  /// Var name to the [JSON pointer](https://tools.ietf.org/html/rfc6901) of its value in the response, i.e. `/data/verified`
+  #[serde(default)]
+  pub outputs: HashMap<String, String>,
+
+  /// Milliseconds to wait for a connection, [`DEFAULT_CONNECT_TIMEOUT`] if not set
+  #[serde(default)]
+  pub connect_timeout_ms: Option<u64>,
+
+  /// Milliseconds to wait while reading the response, [`DEFAULT_READ_TIMEOUT`] if not set
+  #[serde(default)]
+  pub read_timeout_ms: Option<u64>,
+}
+
+/// How long [`UreqClient`] waits for a connection by default
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long [`UreqClient`] waits while reading a response by default
+pub const DEFAULT_READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A request for an [`HttpClient`] to send
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpRequest {
+  pub method: String,
+  pub url: String,
+  pub headers: Vec<(String, String)>,
+  pub body: Option<String>,
+}
+
+/// The response to an [`HttpRequest`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpResponse {
+  pub status: u16,
+  pub body: String,
+}
+
+/// Sends the requests for an [`HttpRequestAction`]. Replace it to add retries, proxies or to test without a network.
+pub trait HttpClient: std::fmt::Debug + Send + Sync {
+  /// Send the request. Fails only if there's no response.
+  fn send(&self, request: HttpRequest) -> Result<HttpResponse, String>;
+}
+
+/// The default [`HttpClient`], a blocking client from [`ureq`]
+///
+/// Requests time out so a slow service can't hold up the session that's advancing.
+#[derive(Debug)]
+pub struct UreqClient {
+  agent: ureq::Agent,
+}
+
+impl UreqClient {
+  pub fn new(connect_timeout: Duration, read_timeout: Duration) -> Self {
+    let agent = ureq::AgentBuilder::new()
+      .timeout_connect(connect_timeout)
+      .timeout_read(read_timeout)
+      .build();
+    UreqClient { agent }
+  }
+}
+
+impl Default for UreqClient {
+  fn default() -> Self {
+    Self::new(DEFAULT_CONNECT_TIMEOUT, DEFAULT_READ_TIMEOUT)
+  }
+}
+
+impl HttpClient for UreqClient {
+  fn send(&self, request: HttpRequest) -> Result<HttpResponse, String> {
+    let mut ureq_request = self.agent.request(&request.method, &request.url);
+    for (name, value) in request.headers.iter() {
+      ureq_request = ureq_request.set(name, value);
+    }
+    let result = match request.body {
+      Some(body) => ureq_request.send_string(&body),
+      None => ureq_request.call(),
+    };
+    let response = match result {
+      Ok(response) => response,
+      Err(ureq::Error::Status(_, response)) => response,
+      Err(e) => return Err(e.to_string()),
+    };
+    let status = response.status();
+    let body = response.into_string().map_err(|e| e.to_string())?;
+    Ok(HttpResponse { status, body })
+  }
+}
+
+// escaped to go inside a JSON string
+#[derive(Debug)]
+struct JsonEscapedString(String);
+
+impl EscapedString for JsonEscapedString {
+  fn from_unescaped(unescaped_str: &str) -> Self {
+    let quoted = serde_json::Value::from(unescaped_str).to_string();
+    JsonEscapedString(quoted[1..quoted.len() - 1].to_owned())
+  }
+  fn already_escaped(escaped_str: String) -> Self {
+    JsonEscapedString(escaped_str)
+  }
+}
+
+impl AsRef<str> for JsonEscapedString {
+  fn as_ref(&self) -> &str {
+    &self.0
+  }
+}
+
+// plain text for headers
+#[derive(Debug)]
+struct UnescapedString(String);
+
+impl EscapedString for UnescapedString {
+  fn from_unescaped(unescaped_str: &str) -> Self {
+    UnescapedString(unescaped_str.to_owned())
+  }
+  fn already_escaped(escaped_str: String) -> Self {
+    UnescapedString(escaped_str)
+  }
+}
+
+impl AsRef<str> for UnescapedString {
+  fn as_ref(&self) -> &str {
+    &self.0
+  }
+}
+
+fn plugin_error<E: std::fmt::Display>(e: E) -> ActionError {
+  ActionError::PluginFailed(e.to_string())
+}
+
+/// Action that calls an external service, i.e. to verify an email or look up a customer
+///
+/// The URL, headers and body are templates that can use `{{step}}` and `{{vars.<name>}}` for the values of the step's inputs and outputs.
+/// The response must be JSON. The action finishes with the values found at each output's JSON pointer.
+/// Responses without a 2xx status and header values with line breaks fail with [`ActionError::PluginFailed`].
+#[derive(Debug)]
+pub struct HttpRequestAction {
+  id: ActionId,
+  config: HttpRequestActionConfig,
+  outputs: Vec<(VarId, String)>,
+  client: Box<dyn HttpClient>,
+  environment: FlowEnvironment,
+}
+
+impl HttpRequestAction {
+  /// Create from a [`HttpRequestActionConfig`], looking up the output [`Var`]s by name in `var_store`
+  pub fn from_config(id: ActionId, config: &HttpRequestActionConfig, var_store: &ObjectStore<Box<dyn Var + Send + Sync>, VarId>) -> Result<Self, ActionError> {
+    let outputs = config.outputs.iter()
+      .map(|(name, pointer)| {
+        let var_id = var_store.id_from_name(name).copied().ok_or_else(|| ActionError::VarId(IdError::NoSuchName(name.clone())))?;
+        Ok((var_id, pointer.clone()))
+      })
+      .collect::<Result<Vec<_>, ActionError>>()?;
+    Ok(HttpRequestAction {
+      id,
+      config: config.clone(),
+      outputs,
+      client: Box::new(UreqClient::new(
+        config.connect_timeout_ms.map(Duration::from_millis).unwrap_or(DEFAULT_CONNECT_TIMEOUT),
+        config.read_timeout_ms.map(Duration::from_millis).unwrap_or(DEFAULT_READ_TIMEOUT))),
+      environment: FlowEnvironment::new(),
+    })
+  }
+
+  /// Send the requests with `client` instead of [`UreqClient`]
+  pub fn with_client(mut self, client: Box<dyn HttpClient>) -> Self {
+    self.client = client;
+    self
+  }
+
+  fn render<T: EscapedString>(template: &str, params: &[(String, String)]) -> String {
+    let params = params.iter()
+      .map(|(key, value)| (&key[..], T::from_unescaped(value)))
+      .collect::<HashMap<_, _>>();
+    render_template(&T::already_escaped(template.to_owned()), params)
+  }
+}
+
+impl Action for HttpRequestAction {
+  fn id(&self) -> &ActionId {
+    &self.id
+  }
+
+  fn start(&mut self, step: &Step, step_name: Option<&str>, step_data: &StateDataFiltered, vars: &ObjectStoreFiltered<Box<dyn Var + Send + Sync>, VarId>)
+    -> Result<ActionResult, ActionError>
+  {
+    let step_id = step.id().to_string();
+    let mut params = vec![("step".to_owned(), step_name.unwrap_or(&step_id[..]).to_owned())];
+    let step_var_ids = step.get_input_vars().iter().flatten().chain(step.get_output_vars().iter());
+    for var_id in step_var_ids {
+      if let (Some(name), Some(val)) = (vars.name_from_id(var_id), step_data.get(var_id)) {
+        params.push((format!("vars.{}", name), val.get_val().get_baseval().to_string()));
+      }
+    }
+
+    let mut headers = self.config.headers.iter()
+      .map(|(name, value)| (name.clone(), Self::render::<UnescapedString>(value, &params)))
+      .collect::<Vec<_>>();
+    for (name, value) in self.config.secret_headers.iter() {
+      headers.push((name.clone(), self.environment.resolve_secrets(value)?));
+    }
+    // values come from user data so make sure they can't add headers
+    if let Some((name, _)) = headers.iter().find(|(_, value)| value.contains(['\r', '\n'])) {
+      return Err(plugin_error(format!("{}: line break in header", name)));
+    }
+    let request = HttpRequest {
+      method: self.config.method.clone().unwrap_or_else(|| "GET".to_owned()),
+      url: Self::render::<UriEscapedString>(&self.config.url, &params),
+      headers,
+      body: self.config.body.as_ref().map(|body| Self::render::<JsonEscapedString>(body, &params)),
+    };
+    let response = self.client.send(request).map_err(plugin_error)?;
+    if !(200..300).contains(&response.status) {
+      return Err(plugin_error(format!("HTTP {}", response.status)));
+    }
+    let json: serde_json::Value = serde_json::from_str(&response.body).map_err(plugin_error)?;
+
+    let mut state_data = StateData::new();
+    for (var_id, pointer) in self.outputs.iter() {
+      let var = vars.get(var_id).ok_or(ActionError::VarId(IdError::IdMissing(*var_id)))?;
+      let val_str = match json.pointer(pointer) {
+        Some(serde_json::Value::String(s)) => s.clone(),
+        Some(serde_json::Value::Null) | None => return Err(plugin_error(format!("{}: not in response", pointer))),
+        Some(other) => other.to_string(),
+      };
+      let val = var.value_from_str(&val_str).map_err(|e| plugin_error(format!("{}: {:?}", pointer, e)))?;
+      state_data.insert(var, val).map_err(|e| plugin_error(format!("{}: {:?}", pointer, e)))?;
+    }
+    Ok(ActionResult::Finished(state_data))
+  }
+
+  fn set_environment(&mut self, environment: &FlowEnvironment) {
+    self.environment = environment.clone();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::HashSet;
+  use std::sync::{Arc, Mutex};
+  use stepflow_base::{ObjectStore, ObjectStoreFiltered};
+  use stepflow_data::{StateData, StateDataFiltered, var::{Var, VarId, EmailVar, BoolVar, StringVar}, value::{EmailValue, BoolValue, StringValue}};
+  use stepflow_step::{Step, StepId};
+  use stepflow_test_util::test_id;
+  use super::{HttpRequestAction, HttpRequestActionConfig, HttpClient, HttpRequest, HttpResponse};
+  use crate::{Action, ActionId, ActionResult, ActionError, FlowEnvironment};
+
+  #[derive(Debug)]
+  struct MockClient {
+    sent: Arc<Mutex<Vec<HttpRequest>>>,
+    response: HttpResponse,
+  }
+
+  impl HttpClient for MockClient {
+    fn send(&self, request: HttpRequest) -> Result<HttpResponse, String> {
+      self.sent.lock().unwrap().push(request);
+      Ok(self.response.clone())
+    }
+  }
+
+  #[test]
+  fn verify_email() {
+    let mut var_store: ObjectStore<Box<dyn Var + Send + Sync>, VarId> = ObjectStore::new();
+    let email = var_store.insert_new_named("email", |id| Ok(EmailVar::new(id).boxed())).unwrap();
+    let verified = var_store.insert_new_named("verified", |id| Ok(BoolVar::new(id).boxed())).unwrap();
+    let step = Step::new(test_id!(StepId), Some(vec![email]), vec![verified]);
+    let mut state_data = StateData::new();
+    state_data.insert(var_store.get(&email).unwrap(), EmailValue::try_new("a+b@c.com").unwrap().boxed()).unwrap();
+    let var_filter = vec![email, verified].into_iter().collect::<HashSet<_>>();
+    let step_data = StateDataFiltered::new(&state_data, var_filter.clone());
+    let vars = ObjectStoreFiltered::new(&var_store, var_filter);
+
+    let config: HttpRequestActionConfig = serde_json::from_value(serde_json::json!({
+      "url": "https://verify.example.com/check?email={{vars.email}}",
+      "method": "POST",
+      "headers": { "X-Step": "{{step}}" },
+      "body": "{\"email\": \"{{vars.email}}\"}",
+      "outputs": { "verified": "/result/ok" },
+    })).unwrap();
+    let sent = Arc::new(Mutex::new(Vec::new()));
+    let client = MockClient { sent: sent.clone(), response: HttpResponse { status: 200, body: r#"{"result": {"ok": true}}"#.to_owned() } };
+    let mut action = HttpRequestAction::from_config(test_id!(ActionId), &config, &var_store).unwrap().with_client(Box::new(client));
+
+    let result = action.start(&step, Some("verify"), &step_data, &vars).unwrap();
+    let mut expected = StateData::new();
+    expected.insert(var_store.get(&verified).unwrap(), BoolValue::new(true).boxed()).unwrap();
+    assert_eq!(result, ActionResult::Finished(expected));
+
+    let request = sent.lock().unwrap().pop().unwrap();
+    assert_eq!(request.method, "POST");
+    assert_eq!(request.url, "https://verify.example.com/check?email=a%2Bb%40c.com");
+    assert_eq!(request.headers, vec![("X-Step".to_owned(), "verify".to_owned())]);
+    assert_eq!(request.body, Some(r#"{"email": "a+b@c.com"}"#.to_owned()));
+
+    // errors and missing values fail
+    let client = MockClient { sent: sent.clone(), response: HttpResponse { status: 503, body: String::new() } };
+    action = action.with_client(Box::new(client));
+    assert_eq!(action.start(&step, None, &step_data, &vars), Err(ActionError::PluginFailed("HTTP 503".to_owned())));
+    let client = MockClient { sent, response: HttpResponse { status: 200, body: "{}".to_owned() } };
+    action = action.with_client(Box::new(client));
+    assert_eq!(action.start(&step, None, &step_data, &vars), Err(ActionError::PluginFailed("/result/ok: not in response".to_owned())));
+  }
+
+  #[test]
+  fn secret_headers() {
+    let mut var_store: ObjectStore<Box<dyn Var + Send + Sync>, VarId> = ObjectStore::new();
+    let verified = var_store.insert_new_named("verified", |id| Ok(BoolVar::new(id).boxed())).unwrap();
+    let step = Step::new(test_id!(StepId), None, vec![verified]);
+    let state_data = StateData::new();
+    let var_filter = vec![verified].into_iter().collect::<HashSet<_>>();
+    let step_data = StateDataFiltered::new(&state_data, var_filter.clone());
+    let vars = ObjectStoreFiltered::new(&var_store, var_filter);
+
+    let config: HttpRequestActionConfig = serde_json::from_value(serde_json::json!({
+      "url": "https://verify.example.com/check",
+      "secret_headers": { "Authorization": "Bearer ${secret.api_key}" },
+      "outputs": { "verified": "/ok" },
+    })).unwrap();
+    let sent = Arc::new(Mutex::new(Vec::new()));
+    let client = MockClient { sent: sent.clone(), response: HttpResponse { status: 200, body: r#"{"ok": true}"#.to_owned() } };
+    let mut action = HttpRequestAction::from_config(test_id!(ActionId), &config, &var_store).unwrap().with_client(Box::new(client));
+
+    // no secret yet
+    assert!(matches!(action.start(&step, None, &step_data, &vars), Err(ActionError::InvalidConfig(_))));
+
+    let mut environment = FlowEnvironment::new();
+    environment.set_secret("api_key", "hunter2");
+    action.set_environment(&environment);
+    action.start(&step, None, &step_data, &vars).unwrap();
+    assert_eq!(sent.lock().unwrap().pop().unwrap().headers, vec![("Authorization".to_owned(), "Bearer hunter2".to_owned())]);
+    assert!(!format!("{:?}", action).contains("hunter2"));
+  }
+
+  #[test]
+  fn header_injection() {
+    let mut var_store: ObjectStore<Box<dyn Var + Send + Sync>, VarId> = ObjectStore::new();
+    let name = var_store.insert_new_named("name", |id| Ok(StringVar::new(id).boxed())).unwrap();
+    let step = Step::new(test_id!(StepId), Some(vec![name]), vec![]);
+    let mut state_data = StateData::new();
+    state_data.insert(var_store.get(&name).unwrap(), StringValue::try_new("Jane\r\nX-Admin: true").unwrap().boxed()).unwrap();
+    let var_filter = vec![name].into_iter().collect::<HashSet<_>>();
+    let step_data = StateDataFiltered::new(&state_data, var_filter.clone());
+    let vars = ObjectStoreFiltered::new(&var_store, var_filter);
+
+    let config: HttpRequestActionConfig = serde_json::from_value(serde_json::json!({
+      "url": "https://api.example.com",
+      "headers": { "X-Name": "{{vars.name}}" },
+      "read_timeout_ms": 500,
+    })).unwrap();
+    let sent = Arc::new(Mutex::new(Vec::new()));
+    let client = MockClient { sent: sent.clone(), response: HttpResponse { status: 200, body: "{}".to_owned() } };
+    let mut action = HttpRequestAction::from_config(test_id!(ActionId), &config, &var_store).unwrap().with_client(Box::new(client));
+    assert_eq!(action.start(&step, None, &step_data, &vars), Err(ActionError::PluginFailed("X-Name: line break in header".to_owned())));
+    assert!(sent.lock().unwrap().is_empty());
+  }
+}
@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+use stepflow_base::ObjectStoreFiltered;
+use stepflow_data::{StateDataFiltered, InvalidVars, var::{Var, VarId}};
+use super::{ActionResult, Action, ActionId, Step, StateData, ActionError};
+
+/// Configuration for [`ImportAction`]
+///
+/// `fields` maps field names in the imported record to [`Var`] names. Fields that aren't mapped are matched to vars with the same name.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde-support", derive(serde::Serialize, serde::Deserialize))]
+pub struct ImportActionConfig {
+  #[cfg_attr(feature = "serde-support", serde(default))]
+  pub fields: HashMap<String, String>,
+}
+
+/// Action that fulfills a step's outputs from an imported record, i.e. a CSV row or JSON object
+///
+/// Set the record before advancing. Without one, the action [`CannotFulfill`](ActionResult::CannotFulfill) so another action can take over.
+/// Values that aren't valid for their var fail with [`ActionError::InvalidVars`] and fields without a matching var are ignored.
+#[derive(Debug)]
+pub struct ImportAction {
+  id: ActionId,
+  config: ImportActionConfig,
+  record: Option<Vec<(String, String)>>,
+}
+
+impl ImportAction {
+  pub fn new(id: ActionId, config: ImportActionConfig) -> Self {
+    ImportAction {
+      id,
+      config,
+      record: None,
+    }
+  }
+
+  /// Use the field names and values for the next [`start`](Action::start)
+  pub fn set_record(&mut self, record: Vec<(String, String)>) {
+    self.record = Some(record);
+  }
+
+  /// Use a CSV row with the field names from `header`. Fields can be quoted with `"`.
+  pub fn set_csv_row(&mut self, header: &str, row: &str) -> Result<(), ActionError> {
+    let names = parse_csv_line(header);
+    let values = parse_csv_line(row);
+    if names.len() != values.len() {
+      return Err(ActionError::InvalidConfig(format!("header has {} fields but the row has {}", names.len(), values.len())));
+    }
+    self.set_record(names.into_iter().zip(values).collect());
+    Ok(())
+  }
+
+  /// Use the fields of a JSON object. Values that aren't strings use their JSON text, i.e. `true`.
+  #[cfg(feature = "serde-support")]
+  pub fn set_json(&mut self, json: &serde_json::Value) -> Result<(), ActionError> {
+    let object = json.as_object().ok_or_else(|| ActionError::InvalidConfig("expected a JSON object".to_owned()))?;
+    let record = object.iter()
+      .filter(|(_name, value)| !value.is_null())
+      .map(|(name, value)| {
+        let value = match value {
+          serde_json::Value::String(s) => s.clone(),
+          value => value.to_string(),
+        };
+        (name.clone(), value)
+      })
+      .collect();
+    self.set_record(record);
+    Ok(())
+  }
+}
+
+// split a line on commas, allowing quoted fields with "" for a quote
+fn parse_csv_line(line: &str) -> Vec<String> {
+  let mut fields = Vec::new();
+  let mut field = String::new();
+  let mut in_quotes = false;
+  let mut chars = line.trim_end_matches(&['\r', '\n'][..]).chars().peekable();
+  while let Some(c) = chars.next() {
+    match (c, in_quotes) {
+      ('"', true) if chars.peek() == Some(&'"') => {
+        chars.next();
+        field.push('"');
+      }
+      ('"', _) => in_quotes = !in_quotes,
+      (',', false) => fields.push(std::mem::take(&mut field)),
+      (c, _) => field.push(c),
+    }
+  }
+  fields.push(field);
+  fields
+}
+
+impl Action for ImportAction {
+  fn id(&self) -> &ActionId {
+    &self.id
+  }
+
+  fn start(&mut self, _step: &Step, _step_name: Option<&str>, _step_data: &StateDataFiltered, vars: &ObjectStoreFiltered<Box<dyn Var + Send + Sync>, VarId>)
+    -> Result<ActionResult, ActionError>
+  {
+    let record = match self.record.take() {
+      Some(record) => record,
+      None => return Ok(ActionResult::CannotFulfill),
+    };
+
+    let mut state_data = StateData::new();
+    let mut invalid = HashMap::new();
+    for (name, value) in record.iter() {
+      let var_name = self.config.fields.get(name).unwrap_or(name);
+      let var = match vars.get_by_name(var_name) {
+        Some(var) => var,
+        None => continue,
+      };
+      let result = var.value_from_str(value).and_then(|val| state_data.insert(var, val));
+      if let Err(err) = result {
+        invalid.insert(*var.id(), err);
+      }
+    }
+    if !invalid.is_empty() {
+      return Err(ActionError::InvalidVars(InvalidVars::new(invalid)));
+    }
+    Ok(ActionResult::Finished(state_data))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::HashSet;
+  use stepflow_base::{ObjectStore, ObjectStoreFiltered};
+  use stepflow_data::{StateData, StateDataFiltered, InvalidValue, InvalidVars, var::{Var, VarId, StringVar, EmailVar, BoolVar}, value::{BoolValue, StringValue}};
+  use stepflow_step::{Step, StepId};
+  use stepflow_test_util::test_id;
+  use crate::{ActionResult, Action, ActionId, ActionError};
+  use super::{ImportAction, ImportActionConfig, parse_csv_line};
+
+  #[test]
+  fn csv_line() {
+    assert_eq!(parse_csv_line("a,b,c\n"), vec!["a", "b", "c"]);
+    assert_eq!(parse_csv_line(r#""Doe, Jane","say ""hi""",,x"#), vec!["Doe, Jane", r#"say "hi""#, "", "x"]);
+  }
+
+  #[test]
+  fn import() {
+    let mut var_store: ObjectStore<Box<dyn Var + Send + Sync>, VarId> = ObjectStore::new();
+    let name = var_store.insert_new_named("name", |id| Ok(StringVar::new(id).boxed())).unwrap();
+    let email = var_store.insert_new_named("email", |id| Ok(EmailVar::new(id).boxed())).unwrap();
+    let vip = var_store.insert_new_named("vip", |id| Ok(BoolVar::new(id).boxed())).unwrap();
+    let vars = ObjectStoreFiltered::new(&var_store, vec![name, email, vip].into_iter().collect());
+    let step = Step::new(test_id!(StepId), None, vec![name, email, vip]);
+    let state_data = StateData::new();
+    let step_data = StateDataFiltered::new(&state_data, HashSet::new());
+
+    let mut config = ImportActionConfig::default();
+    config.fields.insert("full_name".to_owned(), "name".to_owned());
+    let mut action = ImportAction::new(test_id!(ActionId), config);
+    assert_eq!(action.start(&step, None, &step_data, &vars), Ok(ActionResult::CannotFulfill));
+
+    // unknown columns are ignored
+    action.set_csv_row("full_name,email,vip,notes", "\"Doe, Jane\",jane@example.com,true,hi").unwrap();
+    let output = match action.start(&step, None, &step_data, &vars) {
+      Ok(ActionResult::Finished(output)) => output,
+      result => panic!("unexpected result: {:?}", result),
+    };
+    assert_eq!(output.get(&name).unwrap().get_val(), &StringValue::try_new("Doe, Jane").unwrap().boxed());
+    assert_eq!(output.get(&vip).unwrap().get_val(), &BoolValue::new(true).boxed());
+    assert_eq!(action.start(&step, None, &step_data, &vars), Ok(ActionResult::CannotFulfill)); // the record was used up
+
+    assert!(action.set_csv_row("full_name,email", "Jane").is_err());
+    action.set_record(vec![("email".to_owned(), "nope".to_owned()), ("name".to_owned(), "".to_owned())]);
+    assert_eq!(
+      action.start(&step, None, &step_data, &vars),
+      Err(ActionError::InvalidVars(InvalidVars::new(vec![(email, InvalidValue::BadFormat), (name, InvalidValue::Empty)].into_iter().collect()))));
+  }
+
+  #[cfg(feature = "serde-support")]
+  #[test]
+  fn json() {
+    let mut var_store: ObjectStore<Box<dyn Var + Send + Sync>, VarId> = ObjectStore::new();
+    let vip = var_store.insert_new_named("vip", |id| Ok(BoolVar::new(id).boxed())).unwrap();
+    let vars = ObjectStoreFiltered::new(&var_store, vec![vip].into_iter().collect());
+    let step = Step::new(test_id!(StepId), None, vec![vip]);
+    let state_data = StateData::new();
+    let step_data = StateDataFiltered::new(&state_data, HashSet::new());
+
+    let mut action = ImportAction::new(test_id!(ActionId), ImportActionConfig::default());
+    assert!(action.set_json(&serde_json::json!(["vip"])).is_err());
+    action.set_json(&serde_json::json!({ "vip": true, "notes": null })).unwrap();
+    assert!(matches!(
+      action.start(&step, None, &step_data, &vars),
+      Ok(ActionResult::Finished(output)) if output.get(&vip).unwrap().get_val() == &BoolValue::new(true).boxed()));
+  }
+}
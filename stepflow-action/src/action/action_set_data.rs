@@ -1,7 +1,18 @@
-use stepflow_base::ObjectStoreFiltered;
-use stepflow_data::{StateDataFiltered, var::{Var, VarId}};
+use std::collections::HashMap;
+use stepflow_base::{ObjectStore, ObjectStoreFiltered, IdError};
+use stepflow_data::{StateDataFiltered, value::Value, var::{Var, VarId}};
 use super::{ActionResult, Action, ActionId, Step, StateData, ActionError};
 
+/// Configuration for [`SetDataAction`]
+///
+/// `data` maps [`Var`] names to the string form of their value.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde-support", derive(serde::Serialize, serde::Deserialize))]
+pub struct SetDataActionConfig {
+  pub data: HashMap<String, String>,
+  #[cfg_attr(feature = "serde-support", serde(default))]
+  pub after_attempt: u64,
+}
 
 /// Action that sets output data after a set number of attempts
 #[derive(Debug)]
@@ -24,8 +35,15 @@ impl SetDataAction {
     }
   }
 
-  pub fn boxed(self) -> Box<dyn Action + Sync + Send> {
-    Box::new(self)
+  /// Create from a [`SetDataActionConfig`], looking up the [`Var`]s by name in `var_store`
+  pub fn from_config(id: ActionId, config: &SetDataActionConfig, var_store: &ObjectStore<Box<dyn Var + Send + Sync>, VarId>) -> Result<Self, ActionError> {
+    let mut data = StateData::new();
+    for (name, val_str) in config.data.iter() {
+      let var = var_store.get_by_name(name).ok_or_else(|| ActionError::VarId(IdError::NoSuchName(name.clone())))?;
+      let val = var.value_from_str(val_str).map_err(|e| ActionError::InvalidConfig(format!("{}: {:?}", name, e)))?;
+      data.insert(var, val).map_err(|e| ActionError::InvalidConfig(format!("{}: {:?}", name, e)))?;
+    }
+    Ok(Self::new(id, data, config.after_attempt))
   }
 }
 
@@ -44,6 +62,12 @@ impl Action for SetDataAction {
       Ok(ActionResult::CannotFulfill)
     }
   }
+
+  fn produces(&self) -> Vec<(VarId, Box<dyn Value>)> {
+    self.data.iter_val()
+      .map(|(var_id, val)| (*var_id, val.clone()))
+      .collect()
+  }
 }
 
 
@@ -52,10 +76,10 @@ impl Action for SetDataAction {
 mod tests {
   use std::collections::HashSet;
   use stepflow_base::ObjectStoreFiltered;
-  use stepflow_data::{StateData, StateDataFiltered};
+  use stepflow_data::{StateData, StateDataFiltered, value::StringValue};
   use stepflow_test_util::test_id;
   use crate::{ActionResult, Action, ActionId};
-  use super::SetDataAction;
+  use super::{SetDataAction, SetDataActionConfig};
   use super::super::test_action_setup;
 
   #[test]
@@ -91,4 +115,24 @@ mod tests {
       action_after_3.start(&step, None, &step_data_filtered, &vars),
       Ok(ActionResult::Finished(output)) if output == expected_output));
   }
+
+  #[test]
+  fn from_config() {
+    let (step, state_data, mut var_store, var_id, _val) = test_action_setup();
+    let named_id = var_store.insert_new_named("name", |id| Ok(stepflow_data::var::StringVar::new(id).boxed())).unwrap();
+    let vars = ObjectStoreFiltered::new(&var_store, vec![var_id, named_id].into_iter().collect());
+    let step_data_filtered = StateDataFiltered::new(&state_data, HashSet::new());
+
+    let mut config = SetDataActionConfig::default();
+    config.data.insert("name".to_owned(), "stepflow".to_owned());
+    let mut action = SetDataAction::from_config(test_id!(ActionId), &config, &var_store).unwrap();
+    assert_eq!(action.produces(), vec![(named_id, StringValue::try_new("stepflow").unwrap().boxed())]);
+    match action.start(&step, None, &step_data_filtered, &vars) {
+      Ok(ActionResult::Finished(output)) => assert!(output.contains(&named_id)),
+      result => panic!("unexpected result: {:?}", result),
+    }
+
+    config.data.insert("missing".to_owned(), "stepflow".to_owned());
+    assert!(SetDataAction::from_config(test_id!(ActionId), &config, &var_store).is_err());
+  }
 }
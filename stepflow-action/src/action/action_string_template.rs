@@ -1,17 +1,36 @@
 use std::collections::HashMap;
 
-use stepflow_base::{ObjectStoreFiltered, ObjectStoreContent};
+use stepflow_base::{ObjectStore, ObjectStoreFiltered, ObjectStoreContent, IdError, slugify};
 use stepflow_data::{StateDataFiltered, value::StringValue, var::{Var, VarId}};
 use super::{ActionResult, Step, Action, ActionId};
-use crate::{render_template, EscapedString};
+use crate::{render_template, EscapedString, FlowEnvironment};
 use crate::ActionError;
 
 
+/// Configuration for [`StringTemplateAction`]
+///
+/// `template` must already be escaped for the kind of string it produces.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde-support", derive(serde::Serialize, serde::Deserialize))]
+pub struct StringTemplateActionConfig {
+  pub template: String,
+
+  /// Locale for the step's display name in `{{step_display}}`
+  #[cfg_attr(feature = "serde-support", serde(default))]
+  pub locale: Option<String>,
+
+  /// Names of the vars the template can show. See [`StringTemplateAction::with_summary_vars`].
+  #[cfg_attr(feature = "serde-support", serde(default))]
+  pub summary_vars: Vec<String>,
+}
 
 #[derive(Debug)]
 pub struct StringTemplateAction<T> {
   id: ActionId,
   template_escaped: T,
+  locale: Option<String>,
+  summary_vars: Vec<VarId>,
+  environment: Vec<(String, String)>,
 }
 
 impl<T> StringTemplateAction<T> 
@@ -19,17 +38,44 @@ impl<T> StringTemplateAction<T>
 {
   /// Create a new instance.
   ///
-  /// `template_escaped` must already be escaped. Parameters accepted within is `{{step}}`.
-  /// If the [`Step`] has a name, that will be populated. If not, it will be the [`StepId`].
+  /// `template_escaped` must already be escaped. Parameters accepted within are `{{step}}`, `{{step_slug}}` and `{{step_display}}`.
+  /// `{{step}}` is the [`Step`]'s name if it has one. If not, it will be the [`StepId`].
+  /// `{{step_slug}}` is the [slug](stepflow_base::slugify) of `{{step}}` which makes for cleaner URLs.
+  /// `{{step_display}}` is the step's [display name](Step::display_name) for the locale, falling back to `{{step}}`.
+  /// `{{env.<key>}}` is a value from the session's [`FlowEnvironment`]. Secrets are never filled in.
   pub fn new(id: ActionId, template_escaped: T) -> Self {
     StringTemplateAction {
       id,
       template_escaped,
+      locale: None,
+      summary_vars: Vec::new(),
+      environment: Vec::new(),
     }
   }
 
-  pub fn boxed(self) -> Box<dyn Action + Sync + Send> {
-    Box::new(self)
+  /// Set the locale used for `{{step_display}}`
+  pub fn with_locale(mut self, locale: &str) -> Self {
+    self.locale = Some(locale.to_owned());
+    self
+  }
+
+  /// Let the template show the values of `summary_vars`, i.e. for a confirmation email. No other data is shown.
+  ///
+  /// `{{vars.<name>}}` is the value of a var and `{{summary}}` is a `name: value` line for each var with a value.
+  /// Names and values are escaped like the other parameters.
+  pub fn with_summary_vars(mut self, summary_vars: Vec<VarId>) -> Self {
+    self.summary_vars = summary_vars;
+    self
+  }
+
+  /// Create from a [`StringTemplateActionConfig`], looking up the summary [`Var`]s by name in `var_store`
+  pub fn from_config(id: ActionId, config: &StringTemplateActionConfig, var_store: &ObjectStore<Box<dyn Var + Send + Sync>, VarId>) -> Result<Self, ActionError> {
+    let summary_vars = config.summary_vars.iter()
+      .map(|name| var_store.id_from_name(name).copied().ok_or_else(|| ActionError::VarId(IdError::NoSuchName(name.clone()))))
+      .collect::<Result<Vec<_>, _>>()?;
+    let mut action = Self::new(id, T::already_escaped(config.template.clone())).with_summary_vars(summary_vars);
+    action.locale = config.locale.clone();
+    Ok(action)
   }
 }
 
@@ -40,32 +86,69 @@ impl<T> Action for StringTemplateAction<T>
     &self.id
   }
 
-  fn start(&mut self, step: &Step, step_name: Option<&str>, _step_data: &StateDataFiltered, _vars: &ObjectStoreFiltered<Box<dyn Var + Send + Sync>, VarId>)
+  fn start(&mut self, step: &Step, step_name: Option<&str>, step_data: &StateDataFiltered, vars: &ObjectStoreFiltered<Box<dyn Var + Send + Sync>, VarId>)
       -> Result<ActionResult, ActionError> 
   {
-    let escaped_step = match step_name {
-      Some(name) => T::from_unescaped(name),
-      None => T::from_unescaped(&step.id().to_string()[..]),
-    };
+    let step_id = step.id().to_string();
+    let step_name = step_name.unwrap_or(&step_id[..]);
+    let display_name = self.locale.as_ref()
+      .and_then(|locale| step.display_name(locale))
+      .unwrap_or(step_name);
 
     let mut params: HashMap<&str, T> = HashMap::new();
-    params.insert("step", escaped_step);
+    params.insert("step", T::from_unescaped(step_name));
+    params.insert("step_slug", T::from_unescaped(&slugify(step_name)));
+    params.insert("step_display", T::from_unescaped(display_name));
+
+    // only the opted-in vars
+    let mut summary = Vec::new();
+    let var_values = self.summary_vars.iter()
+      .filter_map(|var_id| {
+        let name = vars.name_from_id(var_id)?;
+        let value = step_data.get(var_id).map(|valid_val| valid_val.get_val().get_baseval().to_string());
+        if let Some(value) = &value {
+          summary.push(format!("{}: {}", T::from_unescaped(name).as_ref(), T::from_unescaped(value).as_ref()));
+        }
+        Some((format!("vars.{}", name), value.unwrap_or_default()))
+      })
+      .collect::<Vec<_>>();
+    for (key, value) in var_values.iter() {
+      params.insert(key, T::from_unescaped(value));
+    }
+    params.insert("summary", T::already_escaped(summary.join("\n")));
+
+    let env_params = self.environment.iter()
+      .map(|(key, value)| (format!("env.{}", key), value))
+      .collect::<Vec<_>>();
+    for (key, value) in env_params.iter() {
+      params.insert(key, T::from_unescaped(value));
+    }
 
     let result_str = render_template::<T>(&self.template_escaped, params);
     let string_val = StringValue::try_new(result_str).map_err(|_e| ActionError::Other)?;
     Ok(ActionResult::StartWith(string_val.boxed()))
   }
+
+  fn required_vars(&self) -> &[VarId] {
+    &self.summary_vars
+  }
+
+  fn set_environment(&mut self, environment: &FlowEnvironment) {
+    self.environment = environment.iter_plain()
+      .map(|(key, value)| (key.to_owned(), value.to_owned()))
+      .collect();
+  }
 }
 
 #[cfg(test)]
 mod tests {
   use std::collections::HashSet;
-  use super::{StringTemplateAction};
+  use super::{StringTemplateAction, StringTemplateActionConfig};
   use stepflow_base::{ObjectStoreContent, ObjectStoreFiltered};
-  use stepflow_data::{StateDataFiltered, value::{StringValue}};
+  use stepflow_data::{StateDataFiltered, value::{StringValue}, var::{StringVar, BoolVar}};
   use stepflow_test_util::test_id;
   use super::super::{ActionResult, Action, ActionId, test_action_setup};
-  use crate::{EscapedString, UriEscapedString};
+  use crate::{EscapedString, UriEscapedString, HtmlEscapedString, FlowEnvironment};
 
 
   #[test]
@@ -96,4 +179,83 @@ mod tests {
     assert_eq!(action_result, expected_result);
   }
 
+  #[test]
+  fn slug() {
+    let (step, state_data, var_store, _var_id, _val) = test_action_setup();
+    let vars = ObjectStoreFiltered::new(&var_store, HashSet::new());
+    let step_data_filtered = StateDataFiltered::new(&state_data, HashSet::new());
+
+    let mut exec = StringTemplateAction::new(test_id!(ActionId), UriEscapedString::already_escaped("/test/{{step_slug}}".to_owned()));
+    let action_result = exec.start(&step, Some("Home Address"), &step_data_filtered, &vars).unwrap();
+    let expected_val = StringValue::try_new("/test/home-address").unwrap();
+    assert_eq!(action_result, ActionResult::StartWith(expected_val.boxed()));
+  }
+
+  #[test]
+  fn display_name() {
+    let (mut step, state_data, var_store, _var_id, _val) = test_action_setup();
+    let vars = ObjectStoreFiltered::new(&var_store, HashSet::new());
+    let step_data_filtered = StateDataFiltered::new(&state_data, HashSet::new());
+    step.set_display_name("fr", "Adresse");
+
+    let template = "<a href='/{{step}}'>{{step_display}}</a>";
+    let mut exec = StringTemplateAction::new(test_id!(ActionId), HtmlEscapedString::already_escaped(template.to_owned())).with_locale("fr-CA");
+    let action_result = exec.start(&step, Some("address"), &step_data_filtered, &vars).unwrap();
+    let expected_val = StringValue::try_new("<a href='/address'>Adresse</a>").unwrap();
+    assert_eq!(action_result, ActionResult::StartWith(expected_val.boxed()));
+
+    // no display name for the locale
+    let mut exec = StringTemplateAction::new(test_id!(ActionId), HtmlEscapedString::already_escaped(template.to_owned())).with_locale("en");
+    let action_result = exec.start(&step, Some("address"), &step_data_filtered, &vars).unwrap();
+    let expected_val = StringValue::try_new("<a href='/address'>address</a>").unwrap();
+    assert_eq!(action_result, ActionResult::StartWith(expected_val.boxed()));
+  }
+
+  #[test]
+  fn summary() {
+    let (step, mut state_data, mut var_store, var_id, _val) = test_action_setup();
+    let email = var_store.insert_new_named("email", |id| Ok(StringVar::new(id).boxed())).unwrap();
+    let vip = var_store.insert_new_named("vip", |id| Ok(BoolVar::new(id).boxed())).unwrap();
+    let secret = var_store.insert_new_named("secret", |id| Ok(StringVar::new(id).boxed())).unwrap();
+    state_data.insert(var_store.get(&email).unwrap(), StringValue::try_new("<jane>").unwrap().boxed()).unwrap();
+    state_data.insert(var_store.get(&secret).unwrap(), StringValue::try_new("hunter2").unwrap().boxed()).unwrap();
+    let visible = vec![var_id, email, vip, secret].into_iter().collect::<HashSet<_>>();
+    let vars = ObjectStoreFiltered::new(&var_store, visible.clone());
+    let step_data_filtered = StateDataFiltered::new(&state_data, visible);
+
+    let config = StringTemplateActionConfig {
+      template: "Sent to {{vars.email}}{{vars.vip}}{{vars.secret}}\n{{summary}}".to_owned(),
+      locale: None,
+      summary_vars: vec!["email".to_owned(), "vip".to_owned()],
+    };
+    let mut exec = StringTemplateAction::<HtmlEscapedString>::from_config(test_id!(ActionId), &config, &var_store).unwrap();
+    assert_eq!(exec.required_vars(), &[email, vip]);
+    let action_result = exec.start(&step, None, &step_data_filtered, &vars).unwrap();
+
+    // secret isn't opted in and vip has no value
+    let expected_val = StringValue::try_new("Sent to &lt;jane&gt;{{vars.secret}}\nemail: &lt;jane&gt;").unwrap();
+    assert_eq!(action_result, ActionResult::StartWith(expected_val.boxed()));
+
+    let config = StringTemplateActionConfig { summary_vars: vec!["missing".to_owned()], ..config };
+    assert!(StringTemplateAction::<HtmlEscapedString>::from_config(test_id!(ActionId), &config, &var_store).is_err());
+  }
+
+  #[test]
+  fn environment() {
+    let (step, state_data, var_store, _var_id, _val) = test_action_setup();
+    let vars = ObjectStoreFiltered::new(&var_store, HashSet::new());
+    let step_data_filtered = StateDataFiltered::new(&state_data, HashSet::new());
+
+    let mut environment = FlowEnvironment::new();
+    environment.set("app_base", "https://staging.example.com");
+    environment.set_secret("api_key", "hunter2");
+
+    // secrets aren't parameters so they stay as written
+    let template = "<a href='{{env.app_base}}/{{step}}'>{{env.api_key}}</a>";
+    let mut exec = StringTemplateAction::new(test_id!(ActionId), HtmlEscapedString::already_escaped(template.to_owned()));
+    exec.set_environment(&environment);
+    let action_result = exec.start(&step, Some("name"), &step_data_filtered, &vars).unwrap();
+    let expected_val = StringValue::try_new("<a href='https&#x3A;&#x2F;&#x2F;staging&#x2E;example&#x2E;com/name'>{{env.api_key}}</a>").unwrap();
+    assert_eq!(action_result, ActionResult::StartWith(expected_val.boxed()));
+  }
 }
@@ -0,0 +1,353 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::path::{Path, Component};
+use stepflow_base::{ObjectStoreContent, ObjectStoreFiltered, IdError};
+use stepflow_data::{StateData, StateDataFiltered, value::StringValue, var::{Var, VarId}};
+use super::{ActionResult, Action, ActionId, Step, ActionError};
+
+
+/// Configuration for [`WasmAction`]
+#[derive(Debug, Clone, PartialEq, Default, serde::Serialize, serde::Deserialize)]
+pub struct WasmActionConfig {
+  /// Path to the `.wasm` module, relative to the module directory
+  pub path: String,
+
+  /// Maximum fuel the module can use per call, [`DEFAULT_FUEL`] if not set
+  #[serde(default)]
+  pub fuel: Option<u64>,
+
+  /// Maximum bytes the module's memory can grow to, [`DEFAULT_MEMORY_LIMIT`] if not set
+  #[serde(default)]
+  pub memory_limit: Option<usize>,
+}
+
+/// How much fuel a [`WasmAction`] can use per call by default. Roughly one unit per instruction.
+pub const DEFAULT_FUEL: u64 = 10_000_000;
+
+/// How many bytes a [`WasmAction`]'s memory can grow to by default
+pub const DEFAULT_MEMORY_LIMIT: usize = 16 * 1024 * 1024;
+
+/// What the step sends to the WASM module
+#[derive(Debug, serde::Serialize)]
+struct WasmStepContext<'a> {
+  step_id: u16,
+  step_name: Option<&'a str>,
+  data: HashMap<&'a str, serde_json::Value>,
+}
+
+/// What the WASM module returns
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum WasmActionResult {
+  StartWith(String),
+  Finished(HashMap<String, String>),
+  CannotFulfill,
+}
+
+/// Action implemented by a WASM module
+///
+/// The module runs sandboxed without any imports and must export:
+/// - `memory`
+/// - `alloc(len: i32) -> i32` which returns a pointer to `len` writable bytes
+/// - `start(ptr: i32, len: i32) -> i64` which receives the step context as JSON and returns
+///   the location of the JSON result packed as `(ptr << 32) | len`
+///
+/// The step context is `{"step_id": 1, "step_name": "email", "data": {"<var name>": <value>}}` with the step's input and output data.
+/// The result is one of `{"start_with": "<string>"}`, `{"finished": {"<var name>": "<value as string>"}}` or `"cannot_fulfill"`.
+pub struct WasmAction {
+  id: ActionId,
+  engine: wasmi::Engine,
+  module: wasmi::Module,
+  fuel: u64,
+  memory_limit: usize,
+}
+
+impl std::fmt::Debug for WasmAction {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("WasmAction")
+      .field("id", &self.id)
+      .field("fuel", &self.fuel)
+      .field("memory_limit", &self.memory_limit)
+      .finish()
+  }
+}
+
+fn plugin_error<E: std::fmt::Display>(e: E) -> ActionError {
+  ActionError::PluginFailed(e.to_string())
+}
+
+impl WasmAction {
+  /// Create a new WasmAction from the bytes of a WASM module
+  ///
+  /// Each call is limited to [`DEFAULT_FUEL`] and [`DEFAULT_MEMORY_LIMIT`] unless changed with [`with_fuel`](WasmAction::with_fuel)
+  /// and [`with_memory_limit`](WasmAction::with_memory_limit).
+  pub fn new(id: ActionId, wasm: &[u8]) -> Result<Self, ActionError> {
+    let mut config = wasmi::Config::default();
+    config.consume_fuel(true);
+    let engine = wasmi::Engine::new(&config);
+    let module = wasmi::Module::new(&engine, wasm).map_err(|e| ActionError::InvalidConfig(e.to_string()))?;
+    Ok(WasmAction {
+      id,
+      engine,
+      module,
+      fuel: DEFAULT_FUEL,
+      memory_limit: DEFAULT_MEMORY_LIMIT,
+    })
+  }
+
+  /// Create from a [`WasmActionConfig`], loading the module from `module_dir`
+  ///
+  /// Fails with [`ActionError::InvalidConfig`] if the path is absolute or leads outside `module_dir`, i.e. with `..` or a symlink.
+  pub fn from_config(id: ActionId, config: &WasmActionConfig, module_dir: &Path) -> Result<Self, ActionError> {
+    let invalid_path = |reason: &dyn std::fmt::Display| ActionError::InvalidConfig(format!("{}: {}", config.path, reason));
+    let path = Path::new(&config.path);
+    if path.components().any(|component| !matches!(component, Component::Normal(_))) {
+      return Err(invalid_path(&"outside the module directory"));
+    }
+    let module_dir = module_dir.canonicalize().map_err(|e| invalid_path(&e))?;
+    let full_path = module_dir.join(path).canonicalize().map_err(|e| invalid_path(&e))?;
+    if !full_path.starts_with(&module_dir) {
+      return Err(invalid_path(&"outside the module directory"));
+    }
+    let wasm = std::fs::read(&full_path).map_err(|e| invalid_path(&e))?;
+    let mut action = Self::new(id, &wasm)?;
+    action.fuel = config.fuel.unwrap_or(DEFAULT_FUEL);
+    action.memory_limit = config.memory_limit.unwrap_or(DEFAULT_MEMORY_LIMIT);
+    Ok(action)
+  }
+
+  /// Limit the fuel the module can use for each call to [`start`](WasmAction::start)
+  pub fn with_fuel(mut self, fuel: u64) -> Self {
+    self.fuel = fuel;
+    self
+  }
+
+  /// Limit the bytes the module's memory can grow to. Growing past it fails the call.
+  pub fn with_memory_limit(mut self, memory_limit: usize) -> Self {
+    self.memory_limit = memory_limit;
+    self
+  }
+
+  /// Swap in a new module. The current module is kept if the new one fails to load.
+  pub fn reload(&mut self, wasm: &[u8]) -> Result<(), ActionError> {
+    self.module = wasmi::Module::new(&self.engine, wasm).map_err(|e| ActionError::InvalidConfig(e.to_string()))?;
+    Ok(())
+  }
+
+  // run the module with the JSON input and return its JSON output
+  fn call(&self, input: &[u8]) -> Result<Vec<u8>, ActionError> {
+    let limits = wasmi::StoreLimitsBuilder::new()
+      .memory_size(self.memory_limit)
+      .trap_on_grow_failure(true)
+      .build();
+    let mut store = wasmi::Store::new(&self.engine, limits);
+    store.limiter(|limits| limits);
+    store.set_fuel(self.fuel).map_err(plugin_error)?;
+    let linker = <wasmi::Linker<wasmi::StoreLimits>>::new(&self.engine);
+    let instance = linker
+      .instantiate(&mut store, &self.module)
+      .and_then(|pre| pre.start(&mut store))
+      .map_err(plugin_error)?;
+
+    let memory = instance.get_memory(&store, "memory").ok_or_else(|| plugin_error("missing export: memory"))?;
+    let alloc = instance.get_typed_func::<i32, i32>(&store, "alloc").map_err(plugin_error)?;
+    let start = instance.get_typed_func::<(i32, i32), i64>(&store, "start").map_err(plugin_error)?;
+
+    // write the input
+    let input_len = i32::try_from(input.len()).map_err(|_| plugin_error("input too large"))?;
+    let input_ptr = alloc.call(&mut store, input_len).map_err(plugin_error)?;
+    memory.write(&mut store, input_ptr as usize, input).map_err(plugin_error)?;
+
+    // call and read the output
+    let packed = start.call(&mut store, (input_ptr, input_len)).map_err(plugin_error)? as u64;
+    let output_ptr = (packed >> 32) as usize;
+    let output_len = (packed & 0xFFFF_FFFF) as usize;
+    // check the bounds before copying so the module can't make us allocate more than its memory
+    let output = output_ptr.checked_add(output_len)
+      .and_then(|output_end| memory.data(&store).get(output_ptr..output_end))
+      .ok_or_else(|| plugin_error("output outside of memory"))?;
+    Ok(output.to_vec())
+  }
+}
+
+impl Action for WasmAction {
+  fn id(&self) -> &ActionId {
+    &self.id
+  }
+
+  fn start(&mut self, step: &Step, step_name: Option<&str>, step_data: &StateDataFiltered, vars: &ObjectStoreFiltered<Box<dyn Var + Send + Sync>, VarId>)
+    -> Result<ActionResult, ActionError>
+  {
+    // send the step's data keyed by var name
+    let mut data = HashMap::new();
+    let step_var_ids = step.get_input_vars().iter().flatten().chain(step.get_output_vars().iter());
+    for var_id in step_var_ids {
+      if let (Some(name), Some(val)) = (vars.name_from_id(var_id), step_data.get(var_id)) {
+        data.insert(name, serde_json::to_value(val.get_val()).map_err(plugin_error)?);
+      }
+    }
+    let context = WasmStepContext {
+      step_id: step.id().val(),
+      step_name,
+      data,
+    };
+    let input = serde_json::to_vec(&context).map_err(plugin_error)?;
+
+    let output = self.call(&input)?;
+    let result: WasmActionResult = serde_json::from_slice(&output).map_err(plugin_error)?;
+    match result {
+      WasmActionResult::StartWith(s) => {
+        let val = StringValue::try_new(s).map_err(|e| plugin_error(format!("{:?}", e)))?;
+        Ok(ActionResult::StartWith(val.boxed()))
+      }
+      WasmActionResult::Finished(outputs) => {
+        let mut state_data = StateData::new();
+        for (name, val_str) in outputs {
+          let var = vars.get_by_name(&name).ok_or_else(|| ActionError::VarId(IdError::NoSuchName(name.clone())))?;
+          let val = var.value_from_str(&val_str).map_err(|e| plugin_error(format!("{}: {:?}", name, e)))?;
+          state_data.insert(var, val).map_err(|e| plugin_error(format!("{}: {:?}", name, e)))?;
+        }
+        Ok(ActionResult::Finished(state_data))
+      }
+      WasmActionResult::CannotFulfill => Ok(ActionResult::CannotFulfill),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::HashSet;
+  use stepflow_base::{ObjectStore, ObjectStoreFiltered};
+  use stepflow_data::{StateData, StateDataFiltered, var::{Var, VarId, StringVar}, value::StringValue};
+  use stepflow_step::{Step, StepId};
+  use stepflow_test_util::test_id;
+  use super::{WasmAction, WasmActionConfig, ActionError, DEFAULT_MEMORY_LIMIT};
+  use crate::{Action, ActionId, ActionResult};
+
+  // module that always returns `result`
+  fn fixed_result_module(result: &str) -> Vec<u8> {
+    let escaped = result.replace('"', "\\\"");
+    wat::parse_str(format!(r#"
+      (module
+        (memory (export "memory") 1)
+        (data (i32.const 0) "{}")
+        (func (export "alloc") (param i32) (result i32) i32.const 1024)
+        (func (export "start") (param i32 i32) (result i64) i64.const {})
+      )"#, escaped, result.len())).unwrap()
+  }
+
+  #[test]
+  fn results() {
+    let mut var_store: ObjectStore<Box<dyn Var + Send + Sync>, VarId> = ObjectStore::new();
+    let var_id = var_store.insert_new_named("name", |id| Ok(StringVar::new(id).boxed())).unwrap();
+    let vars = ObjectStoreFiltered::new(&var_store, vec![var_id].into_iter().collect());
+    let state_data = StateData::new();
+    let step_data = StateDataFiltered::new(&state_data, HashSet::new());
+    let step = Step::new(test_id!(StepId), None, vec![var_id]);
+
+    let mut action = WasmAction::new(test_id!(ActionId), &fixed_result_module(r#"{"start_with":"/wasm"}"#)).unwrap();
+    assert_eq!(
+      action.start(&step, Some("name"), &step_data, &vars),
+      Ok(ActionResult::StartWith(StringValue::try_new("/wasm").unwrap().boxed())));
+
+    action.reload(&fixed_result_module(r#"{"finished":{"name":"wasm"}}"#)).unwrap();
+    let mut expected = StateData::new();
+    expected.insert(var_store.get(&var_id).unwrap(), StringValue::try_new("wasm").unwrap().boxed()).unwrap();
+    assert_eq!(action.start(&step, Some("name"), &step_data, &vars), Ok(ActionResult::Finished(expected)));
+
+    action.reload(&fixed_result_module(r#""cannot_fulfill""#)).unwrap();
+    assert_eq!(action.start(&step, Some("name"), &step_data, &vars), Ok(ActionResult::CannotFulfill));
+
+    // bad modules are rejected and the old one is kept
+    assert!(matches!(action.reload(b"not wasm"), Err(ActionError::InvalidConfig(_))));
+    assert_eq!(action.start(&step, Some("name"), &step_data, &vars), Ok(ActionResult::CannotFulfill));
+  }
+
+  // run a module that takes no input and expect it to fail
+  fn start_fails(action: &mut WasmAction) -> bool {
+    let var_store: ObjectStore<Box<dyn Var + Send + Sync>, VarId> = ObjectStore::new();
+    let vars = ObjectStoreFiltered::new(&var_store, HashSet::new());
+    let state_data = StateData::new();
+    let step_data = StateDataFiltered::new(&state_data, HashSet::new());
+    let step = Step::new(test_id!(StepId), None, vec![]);
+    matches!(action.start(&step, None, &step_data, &vars), Err(ActionError::PluginFailed(_)))
+  }
+
+  #[test]
+  fn fuel_limit() {
+    let looping = wat::parse_str(r#"
+      (module
+        (memory (export "memory") 1)
+        (func (export "alloc") (param i32) (result i32) i32.const 0)
+        (func (export "start") (param i32 i32) (result i64) (loop br 0) i64.const 0)
+      )"#).unwrap();
+
+    // runs out of the default fuel
+    let mut action = WasmAction::new(test_id!(ActionId), &looping).unwrap();
+    assert!(start_fails(&mut action));
+    let mut action = WasmAction::new(test_id!(ActionId), &looping).unwrap().with_fuel(10_000);
+    assert!(start_fails(&mut action));
+  }
+
+  #[test]
+  fn memory_limit() {
+    // grows its memory a page at a time forever
+    let growing = wat::parse_str(r#"
+      (module
+        (memory (export "memory") 1)
+        (func (export "alloc") (param i32) (result i32) i32.const 0)
+        (func (export "start") (param i32 i32) (result i64)
+          (loop (drop (memory.grow (i32.const 1))) br 0)
+          i64.const 0)
+      )"#).unwrap();
+
+    // stops at the default limit before running out of fuel
+    let mut action = WasmAction::new(test_id!(ActionId), &growing).unwrap().with_fuel(u64::MAX);
+    assert!(start_fails(&mut action));
+    let mut action = WasmAction::new(test_id!(ActionId), &growing).unwrap().with_memory_limit(4 * 65536);
+    assert!(start_fails(&mut action));
+
+    // a module that starts out larger than the limit can't be instantiated
+    let large = wat::parse_str(format!(r#"
+      (module
+        (memory (export "memory") {})
+        (func (export "alloc") (param i32) (result i32) i32.const 0)
+        (func (export "start") (param i32 i32) (result i64) i64.const 0)
+      )"#, DEFAULT_MEMORY_LIMIT / 65536 + 1)).unwrap();
+    let mut action = WasmAction::new(test_id!(ActionId), &large).unwrap();
+    assert!(start_fails(&mut action));
+  }
+
+  #[test]
+  fn output_bounds() {
+    // claims a 4GB result at the end of a 1 page memory
+    let oversized = wat::parse_str(r#"
+      (module
+        (memory (export "memory") 1)
+        (func (export "alloc") (param i32) (result i32) i32.const 0)
+        (func (export "start") (param i32 i32) (result i64) i64.const 0x0000FFFFFFFFFFFF)
+      )"#).unwrap();
+    let var_store: ObjectStore<Box<dyn Var + Send + Sync>, VarId> = ObjectStore::new();
+    let vars = ObjectStoreFiltered::new(&var_store, HashSet::new());
+    let state_data = StateData::new();
+    let step_data = StateDataFiltered::new(&state_data, HashSet::new());
+    let step = Step::new(test_id!(StepId), None, vec![]);
+
+    let mut action = WasmAction::new(test_id!(ActionId), &oversized).unwrap();
+    assert_eq!(action.start(&step, None, &step_data, &vars), Err(ActionError::PluginFailed("output outside of memory".to_owned())));
+  }
+
+  #[test]
+  fn module_dir() {
+    let module_dir = std::env::temp_dir().join(format!("stepflow-wasm-{}", std::process::id()));
+    std::fs::create_dir_all(&module_dir).unwrap();
+    std::fs::write(module_dir.join("result.wasm"), fixed_result_module(r#""cannot_fulfill""#)).unwrap();
+    let config = |path: &str| WasmActionConfig { path: path.to_owned(), fuel: None, memory_limit: None };
+
+    assert!(WasmAction::from_config(test_id!(ActionId), &config("result.wasm"), &module_dir).is_ok());
+    for path in ["../result.wasm", "/etc/passwd", "missing.wasm"] {
+      assert!(matches!(WasmAction::from_config(test_id!(ActionId), &config(path), &module_dir), Err(ActionError::InvalidConfig(_))));
+    }
+    std::fs::remove_dir_all(&module_dir).unwrap();
+  }
+}
@@ -0,0 +1,236 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use crate::{ActionError, SecretValue, SecretsProvider};
+
+#[derive(Clone, PartialEq)]
+enum EnvValue {
+  Plain(String),
+  Secret(String),
+}
+
+/// Values for the environment a flow runs in, i.e. API base URLs and keys
+///
+/// Actions get the [`Session`](../stepflow_session/struct.Session.html)'s environment with [`Action::set_environment`](crate::Action::set_environment).
+/// Secrets are left out of the `Debug` output and aren't shown to users by the built-in actions.
+/// They're only available with [`secret`](FlowEnvironment::secret) so they can't end up in an action's config.
+/// ```
+/// # use stepflow_action::FlowEnvironment;
+/// let mut environment = FlowEnvironment::new();
+/// environment.set("api_base", "https://api.example.com");
+/// environment.set_secret("api_key", "hunter2");
+/// assert_eq!(environment.get("api_key"), None);
+/// assert_eq!(environment.secret("api_key").unwrap().expose(), "hunter2");
+/// assert!(!format!("{:?}", environment).contains("hunter2"));
+/// ```
+#[derive(Clone, Default)]
+pub struct FlowEnvironment {
+  values: HashMap<String, EnvValue>,
+  secrets_provider: Option<Arc<dyn SecretsProvider>>,
+}
+
+impl PartialEq for FlowEnvironment {
+  fn eq(&self, other: &Self) -> bool {
+    let same_provider = match (&self.secrets_provider, &other.secrets_provider) {
+      (None, None) => true,
+      (Some(provider), Some(other_provider)) => Arc::ptr_eq(provider, other_provider),
+      _ => false,
+    };
+    self.values == other.values && same_provider
+  }
+}
+
+impl std::fmt::Debug for FlowEnvironment {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let mut values = self.values.iter()
+      .map(|(key, value)| match value {
+        EnvValue::Plain(value) => format!("{}={}", key, value),
+        EnvValue::Secret(_) => format!("{}=<secret>", key),
+      })
+      .collect::<Vec<_>>();
+    values.sort();
+    f.debug_struct("FlowEnvironment")
+      .field("values", &values)
+      .field("secrets_provider", &self.secrets_provider.is_some())
+      .finish()
+  }
+}
+
+impl FlowEnvironment {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Set a value that's fine to show
+  pub fn set<K, V>(&mut self, key: K, value: V)
+      where K: Into<String>, V: Into<String>
+  {
+    self.values.insert(key.into(), EnvValue::Plain(value.into()));
+  }
+
+  /// Set a value that should never be shown, i.e. an API key
+  pub fn set_secret<K, V>(&mut self, key: K, value: V)
+      where K: Into<String>, V: Into<String>
+  {
+    self.values.insert(key.into(), EnvValue::Secret(value.into()));
+  }
+
+  /// Get a value that isn't a secret
+  pub fn get(&self, key: &str) -> Option<&str> {
+    match self.values.get(key) {
+      Some(EnvValue::Plain(value)) => Some(&value[..]),
+      Some(EnvValue::Secret(_)) | None => None,
+    }
+  }
+
+  /// Fetch secrets that aren't set here from `secrets_provider`
+  pub fn set_secrets_provider(&mut self, secrets_provider: Arc<dyn SecretsProvider>) {
+    self.secrets_provider = Some(secrets_provider);
+  }
+
+  /// The provider secrets that aren't set here are fetched from
+  pub fn secrets_provider(&self) -> Option<&Arc<dyn SecretsProvider>> {
+    self.secrets_provider.as_ref()
+  }
+
+  /// Get a secret set with [`set_secret`](FlowEnvironment::set_secret), falling back to the [`SecretsProvider`]
+  ///
+  /// Actions should call this when they use the secret rather than keeping it.
+  pub fn secret(&self, name: &str) -> Option<SecretValue> {
+    match self.values.get(name) {
+      Some(EnvValue::Secret(value)) => Some(SecretValue::new(value.clone())),
+      _ => self.secrets_provider.as_ref().and_then(|provider| provider.get(name)),
+    }
+  }
+
+  /// Check if the value is a secret
+  pub fn is_secret(&self, key: &str) -> bool {
+    matches!(self.values.get(key), Some(EnvValue::Secret(_)))
+  }
+
+  /// Values that aren't secrets
+  pub fn iter_plain(&self) -> impl Iterator<Item = (&str, &str)> {
+    self.values.iter()
+      .filter_map(|(key, value)| match value {
+        EnvValue::Plain(value) => Some((&key[..], &value[..])),
+        EnvValue::Secret(_) => None,
+      })
+  }
+
+  /// Replace each `${env.<key>}` in `s` with its value
+  ///
+  /// Fails with [`ActionError::InvalidConfig`] for keys that aren't set or are secrets. Use `${secret.<name>}` and
+  /// [`resolve_secrets`](FlowEnvironment::resolve_secrets) for secrets so they're only filled in when the action runs.
+  pub fn resolve(&self, s: &str) -> Result<String, ActionError> {
+    Self::replace_refs(s, "${env.", |key| {
+      if self.is_secret(key) {
+        return Err(ActionError::InvalidConfig(format!("{} is a secret", key)));
+      }
+      self.get(key).map(str::to_owned).ok_or_else(|| ActionError::InvalidConfig(format!("environment has no {}", key)))
+    })
+  }
+
+  /// Replace each `${secret.<name>}` in `s` with the [`secret`](FlowEnvironment::secret), i.e. for a request header
+  ///
+  /// Call this when the action runs and don't keep the result. Fails with [`ActionError::InvalidConfig`] for secrets that can't be found.
+  pub fn resolve_secrets(&self, s: &str) -> Result<String, ActionError> {
+    Self::replace_refs(s, "${secret.", |name| {
+      self.secret(name)
+        .map(|secret| secret.expose().to_owned())
+        .ok_or_else(|| ActionError::InvalidConfig(format!("no secret {}", name)))
+    })
+  }
+
+  // replace each `<prefix><key>}` with the value from `lookup`
+  fn replace_refs<F>(s: &str, prefix: &str, lookup: F) -> Result<String, ActionError>
+      where F: Fn(&str) -> Result<String, ActionError>
+  {
+    let mut result = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find(prefix) {
+      let after_prefix = &rest[start + prefix.len()..];
+      let end = after_prefix.find('}').ok_or_else(|| ActionError::InvalidConfig(format!("unclosed environment reference in {}", s)))?;
+      result.push_str(&rest[..start]);
+      result.push_str(&lookup(&after_prefix[..end])?);
+      rest = &after_prefix[end + 1..];
+    }
+    result.push_str(rest);
+    Ok(result)
+  }
+
+  /// [`resolve`](FlowEnvironment::resolve) every string in a JSON action config so serialized definitions don't have to contain the values
+  #[cfg(feature = "serde-support")]
+  pub fn resolve_config(&self, config: &serde_json::Value) -> Result<serde_json::Value, ActionError> {
+    Ok(match config {
+      serde_json::Value::String(s) => serde_json::Value::String(self.resolve(s)?),
+      serde_json::Value::Array(values) => {
+        serde_json::Value::Array(values.iter().map(|value| self.resolve_config(value)).collect::<Result<_, _>>()?)
+      }
+      serde_json::Value::Object(object) => {
+        let mut resolved = serde_json::Map::new();
+        for (key, value) in object {
+          resolved.insert(key.clone(), self.resolve_config(value)?);
+        }
+        serde_json::Value::Object(resolved)
+      }
+      value => value.clone(),
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::HashMap;
+  use std::sync::Arc;
+  use crate::{ActionError, SecretValue};
+  use super::FlowEnvironment;
+
+  #[test]
+  fn resolve() {
+    let mut environment = FlowEnvironment::new();
+    environment.set("api_base", "https://api.example.com");
+    environment.set_secret("api_key", "hunter2");
+    assert!(environment.is_secret("api_key"));
+    assert_eq!(environment.iter_plain().collect::<Vec<_>>(), vec![("api_base", "https://api.example.com")]);
+
+    assert_eq!(environment.resolve("${env.api_base}/users"), Ok("https://api.example.com/users".to_owned()));
+    assert_eq!(environment.resolve("no refs {{step}}"), Ok("no refs {{step}}".to_owned()));
+    assert_eq!(environment.resolve("${env.api_base}/users?key=${env.api_key}"), Err(ActionError::InvalidConfig("api_key is a secret".to_owned())));
+
+    // secret references are left for when the action runs
+    assert_eq!(environment.resolve("Bearer ${secret.api_key}"), Ok("Bearer ${secret.api_key}".to_owned()));
+    assert_eq!(environment.resolve_secrets("Bearer ${secret.api_key}"), Ok("Bearer hunter2".to_owned()));
+    assert!(matches!(environment.resolve_secrets("${secret.missing}"), Err(ActionError::InvalidConfig(_))));
+    assert!(matches!(environment.resolve("${env.missing}"), Err(ActionError::InvalidConfig(_))));
+    assert!(matches!(environment.resolve("${env.api_base"), Err(ActionError::InvalidConfig(_))));
+  }
+
+  #[test]
+  fn secrets_provider() {
+    let mut vault = HashMap::new();
+    vault.insert("smtp_password".to_owned(), "from vault".to_owned());
+    vault.insert("api_key".to_owned(), "from vault".to_owned());
+
+    let mut environment = FlowEnvironment::new();
+    environment.set("api_base", "https://api.example.com");
+    environment.set_secret("api_key", "hunter2");
+    assert_eq!(environment.secret("smtp_password"), None);
+
+    environment.set_secrets_provider(Arc::new(vault));
+    assert!(environment.secrets_provider().is_some());
+    assert_eq!(environment.secret("smtp_password"), Some(SecretValue::new("from vault")));
+    assert_eq!(environment.secret("api_key").unwrap().expose(), "hunter2");
+    assert_eq!(environment.secret("api_base"), None);
+    assert!(!format!("{:?}", environment).contains("from vault"));
+  }
+
+  #[cfg(feature = "serde-support")]
+  #[test]
+  fn resolve_config() {
+    let mut environment = FlowEnvironment::new();
+    environment.set("api_base", "https://api.example.com");
+    let config = serde_json::json!({ "template": "${env.api_base}/{{step}}", "after_attempt": 1, "tags": ["${env.api_base}"] });
+    assert_eq!(
+      environment.resolve_config(&config),
+      Ok(serde_json::json!({ "template": "https://api.example.com/{{step}}", "after_attempt": 1, "tags": ["https://api.example.com"] })));
+  }
+}
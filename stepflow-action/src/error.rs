@@ -1,5 +1,5 @@
 use stepflow_base::IdError;
-use stepflow_data::var::VarId;
+use stepflow_data::{InvalidVars, var::VarId};
 use stepflow_step::StepId;
 
 #[derive(Debug, PartialEq, Clone)]
@@ -8,5 +8,17 @@ pub enum ActionError {
   // ID errors
   VarId(IdError<VarId>),
   StepId(IdError<StepId>),
+
+  // data errors
+  InvalidVars(InvalidVars),
+
+  // configuration errors
+  UnknownKind(String),
+  KindAlreadyExists(String),
+  InvalidConfig(String),
+
+  // action plugin errors
+  PluginFailed(String),
+
   Other,
 }
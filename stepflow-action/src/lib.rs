@@ -5,12 +5,37 @@
 //! Pre-built Actions include
 //! - [`HtmlFormAction`]
 //! - [`SetDataAction`]
+//! - [`ApprovalAction`]
+//! - [`ImportAction`]
+//! - [`DuplicateCheckAction`]
+//! - `HttpRequestAction` when the `http-actions` feature is enabled
+//!
+//! [`testing`] has test doubles for unit testing flows.
 
 mod error;
 pub use error::ActionError;
 
+mod secrets;
+pub use secrets::{SecretsProvider, SecretValue};
+
+mod environment;
+pub use environment::FlowEnvironment;
+
 mod string_template;
 pub use string_template::{render_template, EscapedString, HtmlEscapedString, UriEscapedString};
 
 mod action;
-pub use action::{ Action, ActionId, ActionResult, StringTemplateAction, HtmlFormAction, HtmlFormConfig, SetDataAction };
+pub use action::{ Action, ActionId, ActionResult, StringTemplateAction, HtmlFormAction, HtmlFormConfig, SetDataAction, ApprovalAction, ImportAction };
+pub use action::{ StringTemplateActionConfig, SetDataActionConfig, ApprovalActionConfig, ImportActionConfig };
+pub use action::{ DuplicateCheckAction, DuplicateChecker };
+#[cfg(feature = "wasm-actions")]
+pub use action::{ WasmAction, WasmActionConfig, DEFAULT_FUEL, DEFAULT_MEMORY_LIMIT };
+#[cfg(feature = "http-actions")]
+pub use action::{ HttpRequestAction, HttpRequestActionConfig, HttpClient, HttpRequest, HttpResponse, UreqClient, DEFAULT_CONNECT_TIMEOUT, DEFAULT_READ_TIMEOUT };
+
+#[cfg(feature = "serde-support")]
+mod registry;
+#[cfg(feature = "serde-support")]
+pub use registry::{ActionRegistry, ActionConstructor};
+
+pub mod testing;
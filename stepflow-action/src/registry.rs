@@ -0,0 +1,265 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use stepflow_base::ObjectStore;
+use stepflow_data::var::{Var, VarId};
+use crate::{Action, ActionId, ActionError, HtmlFormAction, HtmlFormConfig, SetDataAction, SetDataActionConfig, ApprovalAction, ApprovalActionConfig, ImportAction, ImportActionConfig};
+use crate::{StringTemplateAction, StringTemplateActionConfig, HtmlEscapedString, UriEscapedString, FlowEnvironment};
+
+/// Creates an [`Action`] from its JSON configuration
+pub type ActionConstructor = Box<dyn Fn(ActionId, &serde_json::Value, &ObjectStore<Box<dyn Var + Send + Sync>, VarId>)
+  -> Result<Box<dyn Action + Sync + Send>, ActionError> + Send + Sync>;
+
+/// Maps action kinds to constructors so actions can be created from flow definitions
+///
+/// Downstream crates can [`register`](ActionRegistry::register) their own kinds.
+///
+/// The built-in kinds are:
+/// - `set_data`: [`SetDataAction`] configured with [`SetDataActionConfig`]
+/// - `html_form`: [`HtmlFormAction`] configured with [`HtmlFormConfig`]
+/// - `approval`: [`ApprovalAction`] configured with [`ApprovalActionConfig`]
+/// - `import`: [`ImportAction`] configured with [`ImportActionConfig`]
+/// - `uri_template`: [`StringTemplateAction`] of [`UriEscapedString`] configured with [`StringTemplateActionConfig`]
+/// - `html_template`: [`StringTemplateAction`] of [`HtmlEscapedString`] configured with [`StringTemplateActionConfig`]
+/// - `wasm`: [`WasmAction`](crate::WasmAction) configured with [`WasmActionConfig`](crate::WasmActionConfig) when the `wasm-actions` feature is enabled.
+///   Modules are only loaded once there's a [module directory](ActionRegistry::with_wasm_module_dir).
+/// - `http`: [`HttpRequestAction`](crate::HttpRequestAction) configured with [`HttpRequestActionConfig`](crate::HttpRequestActionConfig) when the `http-actions` feature is enabled
+///
+/// ```
+/// # use stepflow_base::ObjectStore;
+/// # use stepflow_action::{ActionId, ActionRegistry, StringTemplateAction, UriEscapedString};
+/// let registry = ActionRegistry::with_builtins();
+/// let config = serde_json::json!({ "template": "/register/{{step}}" });
+/// let action = registry.create("uri_template", ActionId::new(0), &config, &ObjectStore::new()).unwrap();
+/// assert!(action.is::<StringTemplateAction<UriEscapedString>>());
+/// ```
+#[derive(Default)]
+pub struct ActionRegistry {
+  constructors: HashMap<Cow<'static, str>, ActionConstructor>,
+}
+
+impl std::fmt::Debug for ActionRegistry {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("ActionRegistry")
+      .field("kinds", &self.constructors.keys().collect::<Vec<_>>())
+      .finish()
+  }
+}
+
+fn parse_config<T>(config: &serde_json::Value) -> Result<T, ActionError>
+    where T: serde::de::DeserializeOwned
+{
+  serde_json::from_value(config.clone()).map_err(|e| ActionError::InvalidConfig(e.to_string()))
+}
+
+impl ActionRegistry {
+  /// Create an empty registry
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Create a registry with all the built-in action kinds
+  pub fn with_builtins() -> Self {
+    let mut registry = Self::new();
+    registry.insert_builtin("set_data", |id, config, var_store| {
+      let config: SetDataActionConfig = parse_config(config)?;
+      Ok(SetDataAction::from_config(id, &config, var_store)?.boxed())
+    });
+    registry.insert_builtin("html_form", |id, config, _var_store| {
+      let config: HtmlFormConfig = parse_config(config)?;
+      Ok(HtmlFormAction::new(id, config).boxed())
+    });
+    registry.insert_builtin("approval", |id, config, var_store| {
+      let config: ApprovalActionConfig = parse_config(config)?;
+      Ok(ApprovalAction::from_config(id, &config, var_store)?.boxed())
+    });
+    registry.insert_builtin("import", |id, config, _var_store| {
+      let config: ImportActionConfig = parse_config(config)?;
+      Ok(ImportAction::new(id, config).boxed())
+    });
+    registry.insert_builtin("uri_template", |id, config, var_store| {
+      let config: StringTemplateActionConfig = parse_config(config)?;
+      Ok(StringTemplateAction::<UriEscapedString>::from_config(id, &config, var_store)?.boxed())
+    });
+    registry.insert_builtin("html_template", |id, config, var_store| {
+      let config: StringTemplateActionConfig = parse_config(config)?;
+      Ok(StringTemplateAction::<HtmlEscapedString>::from_config(id, &config, var_store)?.boxed())
+    });
+    #[cfg(feature = "wasm-actions")]
+    registry.insert_builtin("wasm", |_id, _config, _var_store| {
+      Err(ActionError::InvalidConfig("no wasm module directory".to_owned()))
+    });
+    #[cfg(feature = "http-actions")]
+    registry.insert_builtin("http", |id, config, var_store| {
+      let config: crate::HttpRequestActionConfig = parse_config(config)?;
+      Ok(crate::HttpRequestAction::from_config(id, &config, var_store)?.boxed())
+    });
+    registry
+  }
+
+  /// Load `wasm` actions from modules in `module_dir`. Paths in their configs can't lead outside it.
+  #[cfg(feature = "wasm-actions")]
+  pub fn with_wasm_module_dir<P: Into<std::path::PathBuf>>(mut self, module_dir: P) -> Self {
+    let module_dir = module_dir.into();
+    self.insert_builtin("wasm", move |id, config, _var_store| {
+      let config: crate::WasmActionConfig = parse_config(config)?;
+      Ok(crate::WasmAction::from_config(id, &config, &module_dir)?.boxed())
+    });
+    self
+  }
+
+  fn insert_builtin<F>(&mut self, kind: &'static str, factory: F)
+      where F: Fn(ActionId, &serde_json::Value, &ObjectStore<Box<dyn Var + Send + Sync>, VarId>) -> Result<Box<dyn Action + Sync + Send>, ActionError> + Send + Sync + 'static
+  {
+    self.constructors.insert(kind.into(), Box::new(factory));
+  }
+
+  /// Register a factory for a new kind of action
+  ///
+  /// The factory receives the JSON config from the flow definition. Fails if the kind is already registered.
+  /// ```
+  /// # use stepflow_base::ObjectStore;
+  /// # use stepflow_data::StateData;
+  /// # use stepflow_action::{Action, ActionId, ActionRegistry, SetDataAction};
+  /// let mut registry = ActionRegistry::new();
+  /// registry.register("always_done", |id, _config, _var_store| {
+  ///   Ok(SetDataAction::new(id, StateData::new(), 0).boxed())
+  /// }).unwrap();
+  /// assert!(registry.contains("always_done"));
+  /// ```
+  pub fn register<STR, F>(&mut self, kind: STR, factory: F) -> Result<(), ActionError>
+      where STR: Into<Cow<'static, str>>,
+            F: Fn(ActionId, &serde_json::Value, &ObjectStore<Box<dyn Var + Send + Sync>, VarId>) -> Result<Box<dyn Action + Sync + Send>, ActionError> + Send + Sync + 'static
+  {
+    let kind = kind.into();
+    if self.constructors.contains_key(&kind) {
+      return Err(ActionError::KindAlreadyExists(kind.into_owned()));
+    }
+    self.constructors.insert(kind, Box::new(factory));
+    Ok(())
+  }
+
+  /// Check if the kind can be created
+  pub fn contains(&self, kind: &str) -> bool {
+    self.constructors.contains_key(kind)
+  }
+
+  /// Iterate over the registered kinds
+  pub fn kinds(&self) -> impl Iterator<Item = &str> {
+    self.constructors.keys().map(|kind| kind.as_ref())
+  }
+
+  /// Create an action of `kind` with the JSON `config`. `var_store` is used to resolve [`Var`] names in the config.
+  pub fn create(&self, kind: &str, id: ActionId, config: &serde_json::Value, var_store: &ObjectStore<Box<dyn Var + Send + Sync>, VarId>)
+      -> Result<Box<dyn Action + Sync + Send>, ActionError>
+  {
+    let constructor = self.constructors.get(kind).ok_or_else(|| ActionError::UnknownKind(kind.to_owned()))?;
+    constructor(id, config, var_store)
+  }
+
+  /// Same as [`create`](ActionRegistry::create) after replacing the `${env.<key>}` references in `config` with values from `environment`
+  pub fn create_with_environment(&self, kind: &str, id: ActionId, config: &serde_json::Value, var_store: &ObjectStore<Box<dyn Var + Send + Sync>, VarId>, environment: &FlowEnvironment)
+      -> Result<Box<dyn Action + Sync + Send>, ActionError>
+  {
+    let config = environment.resolve_config(config)?;
+    self.create(kind, id, &config, var_store)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use stepflow_base::ObjectStore;
+  use stepflow_data::var::{Var, VarId, StringVar};
+  use stepflow_test_util::test_id;
+  use stepflow_data::StateData;
+  use crate::{Action, ActionId, ActionError, HtmlFormAction, SetDataAction, ApprovalAction, ImportAction, FlowEnvironment};
+  use super::ActionRegistry;
+
+  #[test]
+  fn builtins() {
+    let mut var_store: ObjectStore<Box<dyn Var + Send + Sync>, VarId> = ObjectStore::new();
+    var_store.insert_new_named("name", |id| Ok(StringVar::new(id).boxed())).unwrap();
+
+    let registry = ActionRegistry::with_builtins();
+    let feature_kinds = cfg!(feature = "wasm-actions") as usize + cfg!(feature = "http-actions") as usize;
+    assert_eq!(registry.kinds().count(), 6 + feature_kinds);
+
+    let set_data = registry.create("set_data", test_id!(ActionId), &serde_json::json!({ "data": { "name": "stepflow" } }), &var_store).unwrap();
+    assert!(set_data.is::<SetDataAction>());
+
+    let approval = registry.create("approval", test_id!(ActionId), &serde_json::json!({ "approver": "admin", "decision": "name" }), &var_store).unwrap();
+    assert!(approval.is::<ApprovalAction>());
+    assert!(registry.create("import", test_id!(ActionId), &serde_json::json!({}), &var_store).unwrap().is::<ImportAction>());
+
+    let html_form = registry.create("html_form", test_id!(ActionId), &serde_json::json!({ "wrap_tag": "div" }), &var_store).unwrap();
+    let html_form = html_form.downcast::<HtmlFormAction>().unwrap();
+    assert_eq!(html_form.html_config().wrap_tag, Some("div".to_owned()));
+    assert_eq!(html_form.html_config().stringvar_html_template, crate::HtmlFormConfig::default().stringvar_html_template);
+
+    assert_eq!(
+      registry.create("unknown", test_id!(ActionId), &serde_json::json!({}), &var_store).unwrap_err(),
+      ActionError::UnknownKind("unknown".to_owned()));
+    assert!(matches!(
+      registry.create("uri_template", test_id!(ActionId), &serde_json::json!({}), &var_store),
+      Err(ActionError::InvalidConfig(_))));
+    assert!(matches!(
+      registry.create("set_data", test_id!(ActionId), &serde_json::json!({ "data": { "other": "x" } }), &var_store),
+      Err(ActionError::VarId(_))));
+  }
+
+  #[test]
+  fn custom() {
+    let var_store: ObjectStore<Box<dyn Var + Send + Sync>, VarId> = ObjectStore::new();
+    let mut registry = ActionRegistry::with_builtins();
+
+    // factories can capture state and read their own config
+    let default_attempts = 2;
+    registry.register("delayed".to_owned(), move |id, config, _var_store| {
+      let after_attempt = config.get("after").and_then(|after| after.as_u64()).unwrap_or(default_attempts);
+      Ok(SetDataAction::new(id, StateData::new(), after_attempt).boxed())
+    }).unwrap();
+    assert!(registry.contains("delayed"));
+    assert!(registry.create("delayed", test_id!(ActionId), &serde_json::json!({ "after": 1 }), &var_store).unwrap().is::<SetDataAction>());
+
+    // can't replace an existing kind
+    let dupe = registry.register("set_data", |id, _config, _var_store| Ok(SetDataAction::new(id, StateData::new(), 0).boxed()));
+    assert_eq!(dupe, Err(ActionError::KindAlreadyExists("set_data".to_owned())));
+  }
+
+  #[test]
+  fn environment() {
+    let mut var_store: ObjectStore<Box<dyn Var + Send + Sync>, VarId> = ObjectStore::new();
+    var_store.insert_new_named("name", |id| Ok(StringVar::new(id).boxed())).unwrap();
+    let registry = ActionRegistry::with_builtins();
+    let config = serde_json::json!({ "data": { "name": "${env.default_name}" } });
+
+    let mut environment = FlowEnvironment::new();
+    assert!(matches!(
+      registry.create_with_environment("set_data", test_id!(ActionId), &config, &var_store, &environment),
+      Err(ActionError::InvalidConfig(_))));
+
+    environment.set("default_name", "stepflow");
+    let set_data = registry.create_with_environment("set_data", test_id!(ActionId), &config, &var_store, &environment).unwrap();
+    assert!(set_data.is::<SetDataAction>());
+
+    // secrets can't be put in a config
+    environment.set_secret("api_key", "hunter2");
+    let config = serde_json::json!({ "data": { "name": "${env.api_key}" } });
+    assert_eq!(
+      registry.create_with_environment("set_data", test_id!(ActionId), &config, &var_store, &environment).unwrap_err(),
+      ActionError::InvalidConfig("api_key is a secret".to_owned()));
+  }
+
+  #[cfg(feature = "http-actions")]
+  #[test]
+  fn secret_not_in_config() {
+    let var_store: ObjectStore<Box<dyn Var + Send + Sync>, VarId> = ObjectStore::new();
+    let registry = ActionRegistry::with_builtins();
+    let mut environment = FlowEnvironment::new();
+    environment.set_secret("api_key", "hunter2");
+
+    let config = serde_json::json!({ "url": "https://api.example.com", "secret_headers": { "Authorization": "Bearer ${secret.api_key}" } });
+    let action = registry.create_with_environment("http", test_id!(ActionId), &config, &var_store, &environment).unwrap();
+    let http = action.downcast::<crate::HttpRequestAction>().unwrap();
+    assert!(!format!("{:?}", http).contains("hunter2"));
+  }
+}
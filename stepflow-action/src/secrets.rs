@@ -0,0 +1,36 @@
+use std::collections::HashMap;
+
+/// A credential from a [`SecretsProvider`]. `Debug` doesn't show the value.
+#[derive(Clone, PartialEq)]
+pub struct SecretValue(String);
+
+impl SecretValue {
+  pub fn new<S: Into<String>>(value: S) -> Self {
+    SecretValue(value.into())
+  }
+
+  /// The value, i.e. to put in a request header. Don't keep it around.
+  pub fn expose(&self) -> &str {
+    &self.0[..]
+  }
+}
+
+impl std::fmt::Debug for SecretValue {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.write_str("SecretValue(<secret>)")
+  }
+}
+
+/// Fetches credentials when an action uses them, i.e. from a vault or the process environment
+///
+/// Set it on the [`FlowEnvironment`](crate::FlowEnvironment) so secrets never end up in definitions or saved sessions.
+pub trait SecretsProvider: Send + Sync {
+  /// Get the secret called `name`
+  fn get(&self, name: &str) -> Option<SecretValue>;
+}
+
+impl SecretsProvider for HashMap<String, String> {
+  fn get(&self, name: &str) -> Option<SecretValue> {
+    HashMap::get(self, name).map(SecretValue::new)
+  }
+}
@@ -3,7 +3,7 @@ use std::collections::HashMap;
 
 // NOTE: This hack is pretty unreliable and can probably avoid the string re-allocations
 // In the future, if we don't replace every var, we should return an UnusedParam error
-pub fn render_template<ES>(escaped_template: &ES, params: HashMap<&'static str, ES>) -> String
+pub fn render_template<ES>(escaped_template: &ES, params: HashMap<&str, ES>) -> String
     where ES: AsRef<str>
 {
   let mut escaped_template: &str = escaped_template.as_ref();
@@ -0,0 +1,226 @@
+//! Test doubles for unit testing flows
+//!
+//! [`MockAction`] records how it was started and returns results you queue up.
+//! [`ScriptedAction`] plays back a fixed sequence of results.
+//!
+//! ```
+//! # use stepflow_action::{Action, ActionId, ActionResult, ActionError};
+//! # use stepflow_action::testing::MockAction;
+//! # use stepflow_base::ObjectStoreFiltered;
+//! # use stepflow_data::{StateData, StateDataFiltered, value::TrueValue};
+//! # use stepflow_step::{Step, StepId};
+//! let mut action = MockAction::new(ActionId::new(0)).with_failures(1);
+//! # let step = Step::new(StepId::new(0), None, vec![]);
+//! # let state_data = StateData::new();
+//! # let step_data = StateDataFiltered::new(&state_data, Default::default());
+//! # let var_store = stepflow_base::ObjectStore::new();
+//! # let vars = ObjectStoreFiltered::new(&var_store, Default::default());
+//! assert_eq!(action.start(&step, Some("email"), &step_data, &vars), Err(ActionError::Other));
+//! assert_eq!(action.start(&step, Some("email"), &step_data, &vars), Ok(ActionResult::StartWith(TrueValue::new().boxed())));
+//! assert_eq!(action.calls().len(), 2);
+//! assert_eq!(action.calls()[0].step_name.as_deref(), Some("email"));
+//! ```
+
+use std::collections::VecDeque;
+use stepflow_base::ObjectStoreFiltered;
+use stepflow_data::{StateData, StateDataFiltered, value::TrueValue, var::{Var, VarId}};
+use stepflow_step::{Step, StepId};
+use crate::{Action, ActionId, ActionResult, ActionError};
+
+/// How an action was started
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActionCall {
+  pub step_id: StepId,
+  pub step_name: Option<String>,
+
+  /// The data the action could see
+  pub step_data: StateData,
+}
+
+impl ActionCall {
+  fn new(step: &Step, step_name: Option<&str>, step_data: &StateDataFiltered, vars: &ObjectStoreFiltered<Box<dyn Var + Send + Sync>, VarId>) -> Self {
+    let vals = step_data.iter()
+      .filter_map(|(var_id, valid_val)| Some((vars.get(var_id)?, valid_val.get_val().clone())));
+    ActionCall {
+      step_id: step.id,
+      step_name: step_name.map(|name| name.to_owned()),
+      step_data: StateData::from_vals(vals).unwrap_or_else(|_| StateData::new()),
+    }
+  }
+}
+
+/// Action that records its calls and returns queued results
+///
+/// Once the queue is empty it returns the default result, which waits for the user like a form does.
+#[derive(Debug)]
+pub struct MockAction {
+  id: ActionId,
+  results: VecDeque<Result<ActionResult, ActionError>>,
+  default_result: ActionResult,
+  required_vars: Vec<VarId>,
+  completion_key: Option<String>,
+  calls: Vec<ActionCall>,
+}
+
+impl MockAction {
+  /// Mock that returns [`ActionResult::StartWith`] when nothing is queued
+  pub fn new(id: ActionId) -> Self {
+    MockAction {
+      id,
+      results: VecDeque::new(),
+      default_result: ActionResult::StartWith(TrueValue::new().boxed()),
+      required_vars: Vec::new(),
+      completion_key: None,
+      calls: Vec::new(),
+    }
+  }
+
+  /// Mock that finishes without setting any data when nothing is queued
+  pub fn new_finished(id: ActionId) -> Self {
+    Self::new(id).with_default(ActionResult::Finished(StateData::new()))
+  }
+
+  /// Result to return once the queue is empty
+  pub fn with_default(mut self, result: ActionResult) -> Self {
+    self.default_result = result;
+    self
+  }
+
+  /// Queue a result for a later [`start`](Action::start)
+  pub fn with_result(mut self, result: Result<ActionResult, ActionError>) -> Self {
+    self.queue(result);
+    self
+  }
+
+  /// Fail the next `failures` starts with [`ActionError::Other`]
+  pub fn with_failures(mut self, failures: usize) -> Self {
+    for _ in 0..failures {
+      self.queue(Err(ActionError::Other));
+    }
+    self
+  }
+
+  pub fn with_required_vars(mut self, required_vars: Vec<VarId>) -> Self {
+    self.required_vars = required_vars;
+    self
+  }
+
+  pub fn with_completion_key(mut self, key: &str) -> Self {
+    self.completion_key = Some(key.to_owned());
+    self
+  }
+
+  /// Queue a result after the action is already in a store
+  pub fn queue(&mut self, result: Result<ActionResult, ActionError>) {
+    self.results.push_back(result);
+  }
+
+  /// Every start of the action, oldest first
+  pub fn calls(&self) -> &[ActionCall] {
+    &self.calls
+  }
+}
+
+impl Action for MockAction {
+  fn id(&self) -> &ActionId {
+    &self.id
+  }
+
+  fn start(&mut self, step: &Step, step_name: Option<&str>, step_data: &StateDataFiltered, vars: &ObjectStoreFiltered<Box<dyn Var + Send + Sync>, VarId>)
+      -> Result<ActionResult, ActionError>
+  {
+    self.calls.push(ActionCall::new(step, step_name, step_data, vars));
+    self.results.pop_front().unwrap_or_else(|| Ok(self.default_result.clone()))
+  }
+
+  fn required_vars(&self) -> &[VarId] {
+    &self.required_vars
+  }
+
+  fn completion_key(&self, _step: &Step, _step_data: &StateDataFiltered) -> Option<String> {
+    self.completion_key.clone()
+  }
+}
+
+/// Action that returns its results in order and then keeps returning the last one
+///
+/// An empty script always returns [`ActionResult::CannotFulfill`].
+#[derive(Debug)]
+pub struct ScriptedAction {
+  id: ActionId,
+  script: Vec<Result<ActionResult, ActionError>>,
+  position: usize,
+}
+
+impl ScriptedAction {
+  pub fn new(id: ActionId, script: Vec<Result<ActionResult, ActionError>>) -> Self {
+    ScriptedAction { id, script, position: 0 }
+  }
+
+  /// Number of times the action was started
+  pub fn starts(&self) -> usize {
+    self.position
+  }
+}
+
+impl Action for ScriptedAction {
+  fn id(&self) -> &ActionId {
+    &self.id
+  }
+
+  fn start(&mut self, _step: &Step, _step_name: Option<&str>, _step_data: &StateDataFiltered, _vars: &ObjectStoreFiltered<Box<dyn Var + Send + Sync>, VarId>)
+      -> Result<ActionResult, ActionError>
+  {
+    let result = self.script.get(self.position).or_else(|| self.script.last()).cloned();
+    self.position += 1;
+    result.unwrap_or(Ok(ActionResult::CannotFulfill))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use stepflow_test_util::test_id;
+  use stepflow_base::ObjectStoreFiltered;
+  use stepflow_data::{StateData, StateDataFiltered, value::TrueValue};
+  use crate::{Action, ActionId, ActionResult, ActionError};
+  use crate::action::test_action_setup;
+  use super::{MockAction, ScriptedAction};
+
+  #[test]
+  fn mock() {
+    let (step, state_data, var_store, var_id, _val) = test_action_setup();
+    let vars = ObjectStoreFiltered::new(&var_store, vec![var_id].into_iter().collect());
+    let step_data = StateDataFiltered::new(&state_data, vec![var_id].into_iter().collect());
+
+    let mut action = MockAction::new_finished(test_id!(ActionId))
+      .with_result(Ok(ActionResult::CannotFulfill))
+      .with_failures(1);
+    assert_eq!(action.start(&step, None, &step_data, &vars), Ok(ActionResult::CannotFulfill));
+    assert_eq!(action.start(&step, None, &step_data, &vars), Err(ActionError::Other));
+    assert_eq!(action.start(&step, Some("name"), &step_data, &vars), Ok(ActionResult::Finished(StateData::new())));
+
+    action.queue(Ok(ActionResult::StartWith(TrueValue::new().boxed())));
+    assert_eq!(action.start(&step, None, &step_data, &vars), Ok(ActionResult::StartWith(TrueValue::new().boxed())));
+
+    assert_eq!(action.calls().len(), 4);
+    assert_eq!(action.calls()[2].step_id, step.id);
+    assert_eq!(action.calls()[2].step_name.as_deref(), Some("name"));
+    assert_eq!(action.calls()[2].step_data, state_data);
+  }
+
+  #[test]
+  fn scripted() {
+    let (step, state_data, var_store, _var_id, _val) = test_action_setup();
+    let vars = ObjectStoreFiltered::new(&var_store, Default::default());
+    let step_data = StateDataFiltered::new(&state_data, Default::default());
+
+    let mut action = ScriptedAction::new(test_id!(ActionId), vec![Err(ActionError::Other), Ok(ActionResult::CannotFulfill)]);
+    assert_eq!(action.start(&step, None, &step_data, &vars), Err(ActionError::Other));
+    assert_eq!(action.start(&step, None, &step_data, &vars), Ok(ActionResult::CannotFulfill));
+    assert_eq!(action.start(&step, None, &step_data, &vars), Ok(ActionResult::CannotFulfill));
+    assert_eq!(action.starts(), 3);
+
+    let mut empty = ScriptedAction::new(test_id!(ActionId), vec![]);
+    assert_eq!(empty.start(&step, None, &step_data, &vars), Ok(ActionResult::CannotFulfill));
+  }
+}
@@ -3,11 +3,15 @@ use std::any::Any;
 /// Get the `Any` trait easily from objects that support it.
 pub trait AsAny {
   fn as_any(&self) -> &dyn Any;  
+  fn as_any_mut(&mut self) -> &mut dyn Any;
 }
 
 impl<T: Any> AsAny for T {
   fn as_any(&self) -> &dyn Any {
       self
   }
+  fn as_any_mut(&mut self) -> &mut dyn Any {
+      self
+  }
 }
 
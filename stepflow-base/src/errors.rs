@@ -5,8 +5,14 @@ pub enum IdError<TID> {
   IdNotReserved(TID),
   IdAlreadyExists(TID),
   IdMissing(TID),
+  /// A [generational ID](crate::GenerationalId) whose object was removed, even if the ID was used again
+  IdStale(TID),
   IdUnexpected(TID),
   IdHasNoName(TID),
   NameAlreadyExists(String),
+  /// Another name has the same [slug](crate::slugify), i.e. `first-name` when there's a `first_name`
+  SlugAlreadyExists(String),
   NoSuchName(String),
+  /// Every ID is used by an object in the store
+  IdsExhausted,
 }
\ No newline at end of file
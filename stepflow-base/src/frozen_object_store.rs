@@ -0,0 +1,133 @@
+use std::borrow::Cow;
+use crate::ObjectStoreContent;
+
+/// A read-only [`ObjectStore`](crate::ObjectStore) for a finished definition, created with [`freeze`](crate::ObjectStore::freeze)
+///
+/// Objects, names and slugs are kept in sorted `Vec`s and found with a binary search instead of hashing.
+/// Wrap it in an `Arc` to share one definition between many sessions.
+/// ```
+/// # use std::sync::Arc;
+/// # use stepflow_base::{ObjectStore, ObjectStoreContent, IdError, generate_id_type};
+/// # generate_id_type!(ObjectId);
+/// # struct Object { id: ObjectId }
+/// # impl ObjectStoreContent for Object {
+/// #   type IdType = ObjectId;
+/// #   fn new_id(id_val: u16) -> Self::IdType { ObjectId::new(id_val) }
+/// #   fn id(&self) -> &Self::IdType { &self.id }
+/// # }
+/// let mut store = ObjectStore::new();
+/// let object_id = store.insert_new_named("test object", |id| Ok(Object { id })).unwrap();
+///
+/// let frozen = Arc::new(store.freeze());
+/// assert_eq!(frozen.id_from_name("test object"), Some(&object_id));
+/// assert!(frozen.get(&object_id).is_some());
+/// ```
+#[derive(Debug)]
+pub struct FrozenObjectStore<T, TID> {
+  // sorted by ID with the name of each object
+  objects: Vec<(TID, T, Option<Cow<'static, str>>)>,
+  // sorted by name, including aliases
+  name_to_id: Vec<(Cow<'static, str>, TID)>,
+  // sorted by slug
+  slug_to_id: Vec<(String, TID)>,
+}
+
+impl<T, TID> FrozenObjectStore<T, TID>
+    where T: ObjectStoreContent + ObjectStoreContent<IdType = TID>,
+          TID: Ord + Clone,
+{
+  pub(crate) fn new<O, N, S>(objects: O, names: N, slugs: S) -> Self
+      where O: IntoIterator<Item = (TID, T)>,
+            N: IntoIterator<Item = (Cow<'static, str>, TID, bool)>,
+            S: IntoIterator<Item = (String, TID)>,
+  {
+    let mut name_to_id = Vec::new();
+    let mut objects = objects.into_iter().map(|(id, object)| (id, object, None)).collect::<Vec<_>>();
+    objects.sort_by(|(a, _, _), (b, _, _)| a.cmp(b));
+    for (name, id, is_alias) in names {
+      if !is_alias {
+        if let Ok(index) = objects.binary_search_by(|(object_id, _, _)| object_id.cmp(&id)) {
+          objects[index].2 = Some(name.clone());
+        }
+      }
+      name_to_id.push((name, id));
+    }
+    name_to_id.sort_by(|(a, _), (b, _)| a.cmp(b));
+    let mut slug_to_id = slugs.into_iter().collect::<Vec<_>>();
+    slug_to_id.sort_by(|(a, _), (b, _)| a.cmp(b));
+    Self { objects, name_to_id, slug_to_id }
+  }
+
+  fn index_of(&self, id: &TID) -> Option<usize> {
+    self.objects.binary_search_by(|(object_id, _, _)| object_id.cmp(id)).ok()
+  }
+
+  /// Number of objects
+  pub fn len(&self) -> usize {
+    self.objects.len()
+  }
+
+  /// Check if there are no objects
+  pub fn is_empty(&self) -> bool {
+    self.objects.is_empty()
+  }
+
+  /// Get an object by its ID
+  pub fn get(&self, id: &TID) -> Option<&T> {
+    self.index_of(id).map(|index| &self.objects[index].1)
+  }
+
+  /// Get an object by its name
+  pub fn get_by_name(&self, name: &str) -> Option<&T> {
+    self.id_from_name(name).and_then(|id| self.get(id))
+  }
+
+  /// Get the Object ID from the name or an old name kept as an alias
+  pub fn id_from_name(&self, name: &str) -> Option<&TID> {
+    self.name_to_id.binary_search_by(|(iter_name, _)| iter_name.as_ref().cmp(name))
+      .ok()
+      .map(|index| &self.name_to_id[index].1)
+  }
+
+  /// Get the name from the Object ID
+  pub fn name_from_id(&self, id: &TID) -> Option<&str> {
+    self.index_of(id).and_then(|index| self.objects[index].2.as_deref())
+  }
+
+  /// Get the Object ID from the [slug](crate::slugify) of its name
+  pub fn id_from_slug(&self, slug: &str) -> Option<&TID> {
+    self.slug_to_id.binary_search_by(|(iter_slug, _)| iter_slug.as_str().cmp(slug))
+      .ok()
+      .map(|index| &self.slug_to_id[index].1)
+  }
+
+  /// Iterator for the objects and their IDs, ordered by ID
+  pub fn iter(&self) -> impl Iterator<Item = (&TID, &T)> {
+    self.objects.iter().map(|(id, object, _)| (id, object))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::ObjectStore;
+  use crate::test::{TestObject, TestObjectId};
+
+  #[test]
+  fn freeze() {
+    let mut test_store: ObjectStore<TestObject, TestObjectId> = ObjectStore::new();
+    let t1 = test_store.insert_new_named("zip", |id| Ok(TestObject::new(id, 1))).unwrap();
+    let t2 = test_store.insert_new(|id| Ok(TestObject::new(id, 2))).unwrap();
+    let t3 = test_store.insert_new_named("City Name", |id| Ok(TestObject::new(id, 3))).unwrap();
+    test_store.rename(&t1, "postal_code", true).unwrap();
+
+    let frozen = test_store.freeze();
+    assert_eq!(frozen.len(), 3);
+    assert_eq!(frozen.iter().map(|(id, object)| (*id, object.val())).collect::<Vec<_>>(), vec![(t1, 1), (t2, 2), (t3, 3)]);
+    assert_eq!(frozen.get_by_name("postal_code").unwrap().val(), 1);
+    assert_eq!(frozen.id_from_name("zip"), Some(&t1));
+    assert_eq!(frozen.name_from_id(&t1), Some("postal_code"));
+    assert_eq!(frozen.name_from_id(&t2), None);
+    assert_eq!(frozen.id_from_slug("city-name"), Some(&t3));
+    assert_eq!(frozen.id_from_name("missing"), None);
+  }
+}
@@ -2,7 +2,7 @@
 #[macro_export]
 macro_rules! generate_id_type {
   ($struct_name:ident) => {
-    #[derive(Hash, Clone, Copy, Debug, PartialEq, Eq)]
+    #[derive(Hash, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
     #[cfg_attr(feature = "serde-support", derive(serde::Serialize))]
     pub struct $struct_name(u16);
     impl $struct_name {
@@ -35,6 +35,34 @@ macro_rules! generate_id_type {
   };
 }
 
+/// An ID along with the generation of the object it was taken from, to catch IDs used after their object was removed
+///
+/// The store counts how many times each ID's object was [removed](crate::ObjectStore::remove).
+/// Looking up a generational ID taken before its object was removed fails with [`IdError::IdStale`](crate::IdError::IdStale)
+/// even if a new object got the ID. Get one with [`ObjectStore::generational_id`](crate::ObjectStore::generational_id).
+#[derive(Hash, Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde-support", derive(serde::Serialize))]
+pub struct GenerationalId<TID> {
+  id: TID,
+  generation: u32,
+}
+
+impl<TID> GenerationalId<TID> {
+  pub(crate) fn new(id: TID, generation: u32) -> Self {
+    GenerationalId { id, generation }
+  }
+
+  /// The plain ID, i.e. to pass to APIs that don't check the generation
+  pub fn id(&self) -> &TID {
+    &self.id
+  }
+
+  /// How many times an object with the ID was removed before this one was added
+  pub fn generation(&self) -> u32 {
+    self.generation
+  }
+}
+
 #[cfg(test)]
 mod tests {
   use crate::IdError;
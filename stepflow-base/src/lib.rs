@@ -1,18 +1,25 @@
 //! Base utilities for [StepFlow](https://stepflow.dev)
 //!
-//! Provides the [`ObjectStore`] which contains most StepFlow objects and [`AsAny`](as_any::AsAny) which makes it easier to downcast.
+//! Provides the [`ObjectStore`] which contains most StepFlow objects, the read-only [`FrozenObjectStore`] and [`AsAny`](as_any::AsAny) which makes it easier to downcast.
 
 mod errors;
 pub use errors::IdError;
 
 pub mod id;
+pub use id::GenerationalId;
 
 mod object_store;
 pub use object_store::{ ObjectStore, ObjectStoreContent };
 
+mod slug;
+pub use slug::slugify;
+
 mod object_store_filtered;
 pub use object_store_filtered::ObjectStoreFiltered;
 
+mod frozen_object_store;
+pub use frozen_object_store::FrozenObjectStore;
+
 // NOTE: we don't do a broad use of as_any so we can be specific which objects should support the trait.
 // i.e. if Box<T> gets it via blanket implementation, then we'll have to remember to do boxed.as_ref().as_any() as opposed to boxed.as_any()
 pub mod as_any;
@@ -1,8 +1,8 @@
 use std::hash::Hash;
 use std::borrow::{Cow, Borrow};
-use std::collections::{HashMap};
+use std::collections::{HashMap, HashSet};
 use std::sync::atomic::{AtomicU16, Ordering};
-use super::IdError;
+use super::{IdError, FrozenObjectStore, GenerationalId, slugify};
 
 pub trait ObjectStoreContent {
   type IdType;
@@ -18,7 +18,11 @@ pub trait ObjectStoreContent {
 ///
 /// To add objects with an associated name, use the corresponding
 /// [`insert_new_named`](ObjectStore::insert_new_named) and [`register_named`](ObjectStore::register_named)
-/// instead.
+/// instead. Named objects also get a [slug](slugify) of their name for URLs. No two names can have the same slug,
+/// adding one fails with [`IdError::SlugAlreadyExists`].
+///
+/// IDs of [removed](ObjectStore::remove) objects are used again once the IDs wrap around. Hold on to a
+/// [`generational_id`](ObjectStore::generational_id) instead to find out when the object it was taken from is gone.
 ///
 /// # Examples
 /// ```
@@ -44,6 +48,9 @@ pub struct ObjectStore<T, TID>
 {
   id_to_object: HashMap<TID, T>,
   name_to_id: HashMap<Cow<'static, str>, TID>,
+  slug_to_id: HashMap<String, TID>,
+  aliases: HashMap<Cow<'static, str>, TID>,
+  generations: HashMap<TID, u32>,
   next_id: AtomicU16,
 }
 
@@ -62,13 +69,26 @@ impl<'s, T, TID> ObjectStore<T, TID>
     Self {
       id_to_object: HashMap::with_capacity(capacity),
       name_to_id: HashMap::with_capacity(capacity),
+      slug_to_id: HashMap::with_capacity(capacity),
+      aliases: HashMap::new(),
+      generations: HashMap::new(),
       next_id: AtomicU16::new(0)
     }
   }
 
   /// Reserve an ID in the ObjectStore. Generally followed with a call to [`register`](ObjectStore::register) using the ID.
-  pub fn reserve_id(&mut self) -> TID {
-    T::new_id(self.next_id.fetch_add(1, Ordering::SeqCst))
+  ///
+  /// Once the IDs wrap around, the IDs of [removed](ObjectStore::remove) objects are used again.
+  /// Fails with [`IdError::IdsExhausted`] if every ID is used.
+  pub fn reserve_id(&mut self) -> Result<TID, IdError<TID>> {
+    // skip the IDs still in use. there are u16::MAX + 1 IDs so this tries each of them once
+    for _ in 0..=u16::MAX {
+      let id = T::new_id(self.next_id.fetch_add(1, Ordering::SeqCst));
+      if !self.id_to_object.contains_key(&id) {
+        return Ok(id);
+      }
+    }
+    Err(IdError::IdsExhausted)
   }
 
   /// Registers an object into the ObjectStore
@@ -91,15 +111,22 @@ impl<'s, T, TID> ObjectStore<T, TID>
   {
     let name: Cow<'static, str> = name.into();
   
-    // check if name of object being registered already exists
-    if self.name_to_id.contains_key(&name) {
+    // check if name of object being registered already exists, or a name that looks the same in a URL
+    if self.name_to_id.contains_key(&name) || self.aliases.contains_key(&name) {
       return Err(IdError::NameAlreadyExists(name.clone().into_owned()))
     }
+    let slug = slugify(&name);
+    if self.slug_to_id.contains_key(&slug) {
+      return Err(IdError::SlugAlreadyExists(name.clone().into_owned()))
+    }
 
     // register the object
     self.register(object)
       .map(|object_id| {
-        // register the object's name
+        // register the object's name and slug. names without any letters or numbers don't get a slug
+        if !slug.is_empty() {
+          self.slug_to_id.insert(slug, object_id.clone());
+        }
         self.name_to_id.insert(name, object_id.clone());
         object_id
       })    
@@ -110,7 +137,7 @@ impl<'s, T, TID> ObjectStore<T, TID>
       where CB: FnOnce(TID) -> Result<T, IdError<TID>>
   {
     // reserve an ID
-    let id: TID = self.reserve_id();
+    let id: TID = self.reserve_id()?;
     let id_clone = id.clone();
 
     // get the object and ensure they used the reserved ID
@@ -131,7 +158,7 @@ impl<'s, T, TID> ObjectStore<T, TID>
     let name: Cow<'static, str> = name.into();
 
     // reserve an ID
-    let id: TID = self.reserve_id();
+    let id: TID = self.reserve_id()?;
     let id_clone = id.clone();
 
     // get the object and ensure they used the reserved ID
@@ -144,9 +171,91 @@ impl<'s, T, TID> ObjectStore<T, TID>
     self.register_named(name, object)
   }
 
-  /// Get the Object ID from the name
+  /// Number of objects registered
+  pub fn len(&self) -> usize {
+    self.id_to_object.len()
+  }
+
+  /// Check if there are no objects registered
+  pub fn is_empty(&self) -> bool {
+    self.id_to_object.is_empty()
+  }
+
+  /// Number of objects the store can hold without reallocating
+  pub fn capacity(&self) -> usize {
+    self.id_to_object.capacity()
+  }
+
+  /// Free unused capacity, i.e. after removing a lot of objects
+  pub fn shrink_to_fit(&mut self) {
+    self.id_to_object.shrink_to_fit();
+    self.name_to_id.shrink_to_fit();
+    self.slug_to_id.shrink_to_fit();
+    self.aliases.shrink_to_fit();
+    self.generations.shrink_to_fit();
+  }
+
+  /// Rough number of bytes the store has allocated, counting unused capacity
+  ///
+  /// Memory the objects allocate themselves, like the contents of a `Box`, isn't included.
+  pub fn memory_usage_estimate(&self) -> usize {
+    fn name_bytes<TID>(names: &HashMap<Cow<'static, str>, TID>) -> usize {
+      names.capacity() * std::mem::size_of::<(Cow<'static, str>, TID)>()
+        + names.keys().map(|name| match name { Cow::Owned(name) => name.capacity(), Cow::Borrowed(_) => 0 }).sum::<usize>()
+    }
+    self.id_to_object.capacity() * std::mem::size_of::<(TID, T)>()
+      + name_bytes(&self.name_to_id)
+      + name_bytes(&self.aliases)
+      + self.slug_to_id.capacity() * std::mem::size_of::<(String, TID)>()
+      + self.slug_to_id.keys().map(|slug| slug.capacity()).sum::<usize>()
+      + self.generations.capacity() * std::mem::size_of::<(TID, u32)>()
+  }
+
+  /// Get the Object ID from the name or an old name kept as an alias by [`rename`](ObjectStore::rename)
   pub fn id_from_name(&self, name: &str) -> Option<&TID> {
-    self.name_to_id.get(name)
+    self.name_to_id.get(name).or_else(|| self.aliases.get(name))
+  }
+
+  /// Change the object's name. If `keep_alias` is true, the old name still finds the object with [`id_from_name`](ObjectStore::id_from_name).
+  ///
+  /// Objects without a name get one. Fails if another object has the name or an alias, or with [`IdError::SlugAlreadyExists`] if it has a name with the same slug.
+  pub fn rename<STR>(&mut self, id: &TID, new_name: STR, keep_alias: bool) -> Result<(), IdError<TID>>
+      where STR: Into<Cow<'static, str>>
+  {
+    let new_name: Cow<'static, str> = new_name.into();
+    if !self.id_to_object.contains_key(id) {
+      return Err(IdError::IdMissing(id.clone()));
+    }
+    let taken_by_other = |taken: Option<&TID>| taken.map(|taken_id| taken_id != id).unwrap_or(false);
+    if taken_by_other(self.name_to_id.get(&new_name).or_else(|| self.aliases.get(&new_name))) {
+      return Err(IdError::NameAlreadyExists(new_name.into_owned()));
+    }
+    let slug = slugify(&new_name);
+    if taken_by_other(self.slug_to_id.get(&slug)) {
+      return Err(IdError::SlugAlreadyExists(new_name.into_owned()));
+    }
+
+    // drop the old name and slug
+    let old_name = self.name_from_id(id).map(|name| Cow::Owned(name.to_owned()));
+    if let Some(old_name) = &old_name {
+      self.name_to_id.remove(old_name);
+    }
+    self.slug_to_id.retain(|_slug, slug_id| slug_id != id);
+    self.aliases.remove(&new_name);
+
+    if !slug.is_empty() {
+      self.slug_to_id.insert(slug, id.clone());
+    }
+    self.name_to_id.insert(new_name.clone(), id.clone());
+    if let Some(old_name) = old_name.filter(|old_name| keep_alias && *old_name != new_name) {
+      self.aliases.insert(old_name, id.clone());
+    }
+    Ok(())
+  }
+
+  /// Old names that still find objects, with the IDs they find
+  pub fn iter_aliases(&self) -> impl Iterator<Item = (&Cow<'static, str>, &TID)> {
+    self.aliases.iter()
   }
 
   /// Get the name from the Object ID
@@ -156,6 +265,18 @@ impl<'s, T, TID> ObjectStore<T, TID>
       .and_then(|(name, _)| Some(name.borrow()))
   }
 
+  /// Get the Object ID from the [slug](slugify) of its name
+  pub fn id_from_slug(&self, slug: &str) -> Option<&TID> {
+    self.slug_to_id.get(slug)
+  }
+
+  /// Get the [slug](slugify) of the object's name
+  pub fn slug_from_id(&self, id: &TID) -> Option<&str> {
+    self.slug_to_id.iter()
+      .find(|(_slug, slug_id)| *slug_id == id)
+      .map(|(slug, _)| &slug[..])
+  }
+
   /// Get an object by its name
   pub fn get_by_name(&self, name: &str) -> Option<&T> {
     self.id_from_name(name).and_then(|id| self.get(id))
@@ -171,6 +292,115 @@ impl<'s, T, TID> ObjectStore<T, TID>
     self.id_to_object.get_mut(id)
   }
 
+  /// Get the ID of the object along with its generation, to hold on to when the object could be removed
+  pub fn generational_id(&self, id: &TID) -> Option<GenerationalId<TID>> {
+    if !self.id_to_object.contains_key(id) {
+      return None;
+    }
+    Some(GenerationalId::new(id.clone(), self.generation(id)))
+  }
+
+  /// Get an object by its generational ID
+  ///
+  /// Fails with [`IdError::IdStale`] if the object it was taken from was removed, even if another object has the ID now.
+  pub fn get_generational(&self, id: &GenerationalId<TID>) -> Result<&T, IdError<TID>> {
+    self.check_generation(id)?;
+    self.id_to_object.get(id.id()).ok_or_else(|| IdError::IdMissing(id.id().clone()))
+  }
+
+  /// Get a mutable reference to the object by its generational ID. See [`get_generational`](ObjectStore::get_generational).
+  pub fn get_generational_mut(&mut self, id: &GenerationalId<TID>) -> Result<&mut T, IdError<TID>> {
+    self.check_generation(id)?;
+    self.id_to_object.get_mut(id.id()).ok_or_else(|| IdError::IdMissing(id.id().clone()))
+  }
+
+  fn generation(&self, id: &TID) -> u32 {
+    self.generations.get(id).copied().unwrap_or(0)
+  }
+
+  fn check_generation(&self, id: &GenerationalId<TID>) -> Result<(), IdError<TID>> {
+    if self.generation(id.id()) != id.generation() {
+      return Err(IdError::IdStale(id.id().clone()));
+    }
+    Ok(())
+  }
+
+  // the ID's object is gone so any generational ID taken from it is stale
+  fn next_generation(&mut self, id: &TID) {
+    let generation = self.generations.entry(id.clone()).or_insert(0);
+    *generation = generation.wrapping_add(1);
+  }
+
+  /// Remove an object along with its name, slug and aliases
+  pub fn remove(&mut self, id: &TID) -> Option<T> {
+    let object = self.id_to_object.remove(id)?;
+    self.next_generation(id);
+    self.name_to_id.retain(|_name, name_id| name_id != id);
+    self.slug_to_id.retain(|_slug, slug_id| slug_id != id);
+    self.aliases.retain(|_alias, alias_id| alias_id != id);
+    Some(object)
+  }
+
+  /// Remove an object by its name or an alias. See [`remove`](ObjectStore::remove).
+  pub fn remove_by_name(&mut self, name: &str) -> Option<T> {
+    let id = self.id_from_name(name)?.clone();
+    self.remove(&id)
+  }
+
+  /// Remove every object `keep` returns false for, along with their names, slugs and aliases, i.e. to evict finished sessions
+  pub fn retain<F>(&mut self, mut keep: F)
+      where F: FnMut(&TID, &T) -> bool
+  {
+    let mut removed = HashSet::new();
+    self.id_to_object.retain(|id, object| {
+      let keep = keep(id, object);
+      if !keep {
+        removed.insert(id.clone());
+      }
+      keep
+    });
+    if removed.is_empty() {
+      return;
+    }
+    for id in removed.iter() {
+      self.next_generation(id);
+    }
+    self.name_to_id.retain(|_name, name_id| !removed.contains(name_id));
+    self.slug_to_id.retain(|_slug, slug_id| !removed.contains(slug_id));
+    self.aliases.retain(|_alias, alias_id| !removed.contains(alias_id));
+  }
+
+  /// Turn the store into a read-only [`FrozenObjectStore`] once the definition is finished
+  pub fn freeze(self) -> FrozenObjectStore<T, TID>
+      where TID: Ord
+  {
+    let names = self.name_to_id.into_iter().map(|(name, id)| (name, id, false))
+      .chain(self.aliases.into_iter().map(|(alias, id)| (alias, id, true)));
+    FrozenObjectStore::new(self.id_to_object, names, self.slug_to_id)
+  }
+
+  /// Get the IDs of all objects whose name starts with `prefix`, ordered by ID
+  pub fn ids_with_name_prefix(&self, prefix: &str) -> Vec<TID>
+      where TID: Ord
+  {
+    let mut ids = self.name_to_id.iter()
+      .filter(|(name, _id)| name.starts_with(prefix))
+      .map(|(_name, id)| id.clone())
+      .collect::<Vec<_>>();
+    ids.sort();
+    ids
+  }
+
+  /// Iterator for the objects and their IDs in no particular order
+  pub fn iter(&self) -> impl Iterator<Item = (&TID, &T)> {
+    self.id_to_object.iter()
+  }
+
+  /// Mutable iterator for the objects and their IDs in no particular order
+  pub fn iter_mut(&mut self) -> impl Iterator<Item = (&TID, &mut T)> {
+    self.id_to_object.iter_mut()
+  }
+
   // Iterator for registered object names
   pub fn iter_names(&self) -> impl Iterator<Item = (&Cow<'static, str>, &TID)> {
     self.name_to_id.iter()
@@ -184,12 +414,163 @@ mod tests {
   use super::{ObjectStore};
   use crate::{test::TestObject, test::TestObjectId, IdError};
 
+  #[test]
+  fn rename() {
+    let mut test_store: ObjectStore<TestObject, TestObjectId> = ObjectStore::new();
+    let t1 = test_store.insert_new_named("zip", |id| Ok(TestObject::new(id, 1))).unwrap();
+    let t2 = test_store.insert_new_named("city", |id| Ok(TestObject::new(id, 2))).unwrap();
+
+    test_store.rename(&t1, "Postal Code", true).unwrap();
+    assert_eq!(test_store.name_from_id(&t1), Some("Postal Code"));
+    assert_eq!(test_store.id_from_slug("postal-code"), Some(&t1));
+    assert_eq!(test_store.id_from_slug("zip"), None);
+    assert_eq!(test_store.id_from_name("zip"), Some(&t1));
+    assert_eq!(test_store.iter_aliases().count(), 1);
+
+    // names, aliases and slugs of other objects are taken
+    assert_eq!(test_store.rename(&t2, "zip", false), Err(IdError::NameAlreadyExists("zip".to_owned())));
+    assert_eq!(test_store.rename(&t2, "postal_code", false), Err(IdError::SlugAlreadyExists("postal_code".to_owned())));
+    assert!(test_store.insert_new_named("zip", |id| Ok(TestObject::new(id, 3))).is_err());
+
+    // no alias
+    test_store.rename(&t2, "town", false).unwrap();
+    assert_eq!(test_store.id_from_name("city"), None);
+    assert_eq!(test_store.get_by_name("town").unwrap().val(), 2);
+  }
+
+  #[test]
+  fn shrink_to_fit() {
+    let mut test_store: ObjectStore<TestObject, TestObjectId> = ObjectStore::with_capacity(100);
+    assert!(test_store.capacity() >= 100);
+    let empty_usage = test_store.memory_usage_estimate();
+
+    let ids = (0..50)
+      .map(|i| test_store.insert_new_named(format!("object {}", i), |id| Ok(TestObject::new(id, i))).unwrap())
+      .collect::<Vec<_>>();
+    assert!(test_store.memory_usage_estimate() > empty_usage);
+    for id in ids[1..].iter() {
+      test_store.remove(id);
+    }
+
+    let usage = test_store.memory_usage_estimate();
+    test_store.shrink_to_fit();
+    assert!(test_store.capacity() < 100);
+    assert!(test_store.memory_usage_estimate() < usage);
+    assert_eq!(test_store.get_by_name("object 0").unwrap().val(), 0);
+  }
+
+  #[test]
+  fn remove() {
+    let mut test_store: ObjectStore<TestObject, TestObjectId> = ObjectStore::new();
+    let t1 = test_store.insert_new_named("zip", |id| Ok(TestObject::new(id, 1))).unwrap();
+    test_store.rename(&t1, "postal code", true).unwrap();
+    assert_eq!(test_store.remove(&t1).map(|object| object.val()), Some(1));
+    assert_eq!(test_store.remove(&t1).map(|object| object.val()), None);
+    assert!(test_store.is_empty());
+
+    // the names are free again
+    assert_eq!(test_store.id_from_name("zip"), None);
+    assert!(test_store.insert_new_named("postal-code", |id| Ok(TestObject::new(id, 2))).is_ok());
+  }
+
+  #[test]
+  fn remove_by_name_and_retain() {
+    let mut test_store: ObjectStore<TestObject, TestObjectId> = ObjectStore::new();
+    let t1 = test_store.insert_new_named("zip", |id| Ok(TestObject::new(id, 1))).unwrap();
+    test_store.rename(&t1, "postal code", true).unwrap();
+    assert_eq!(test_store.remove_by_name("zip").map(|object| object.val()), Some(1));
+    assert_eq!(test_store.remove_by_name("postal code").map(|object| object.val()), None);
+    assert_eq!(test_store.iter_aliases().count(), 0);
+
+    for i in 0..10 {
+      test_store.insert_new_named(format!("object {}", i), |id| Ok(TestObject::new(id, i))).unwrap();
+    }
+    test_store.retain(|_id, object| object.val() % 2 == 0);
+    assert_eq!(test_store.len(), 5);
+    assert_eq!(test_store.iter_names().count(), 5);
+    assert_eq!(test_store.id_from_name("object 3"), None);
+    assert_eq!(test_store.id_from_slug("object-3"), None);
+    assert_eq!(test_store.get_by_name("object 4").unwrap().val(), 4);
+  }
+
+  #[test]
+  fn recycle_ids() {
+    let mut test_store: ObjectStore<TestObject, TestObjectId> = ObjectStore::new();
+    let t1 = test_store.insert_new(|id| Ok(TestObject::new(id, 1))).unwrap();
+    let t2 = test_store.insert_new(|id| Ok(TestObject::new(id, 2))).unwrap();
+    test_store.remove(&t1);
+
+    // wrap around: t1 is free again but t2 is still used
+    test_store.next_id.store(u16::MAX, std::sync::atomic::Ordering::SeqCst);
+    let last = test_store.insert_new(|id| Ok(TestObject::new(id, 3))).unwrap();
+    assert_eq!(last, TestObjectId::new(u16::MAX));
+    assert_eq!(test_store.insert_new(|id| Ok(TestObject::new(id, 4))).unwrap(), t1);
+    assert_ne!(test_store.insert_new(|id| Ok(TestObject::new(id, 5))).unwrap(), t2);
+    assert_eq!(test_store.get(&t2).unwrap().val(), 2);
+  }
+
+  #[test]
+  fn generational_ids() {
+    let mut test_store: ObjectStore<TestObject, TestObjectId> = ObjectStore::new();
+    let t1 = test_store.insert_new(|id| Ok(TestObject::new(id, 1))).unwrap();
+    let t2 = test_store.insert_new(|id| Ok(TestObject::new(id, 2))).unwrap();
+    let gen1 = test_store.generational_id(&t1).unwrap();
+    let gen2 = test_store.generational_id(&t2).unwrap();
+    assert_eq!(gen1.generation(), 0);
+    assert_eq!(test_store.get_generational(&gen1).unwrap().val(), 1);
+    test_store.get_generational_mut(&gen2).unwrap().set_val(20);
+    assert_eq!(test_store.generational_id(&test_id!(TestObjectId)), None);
+
+    // the ID is used again after wrapping around but the old generational ID doesn't find the new object
+    test_store.remove(&t1);
+    assert_eq!(test_store.get_generational(&gen1), Err(IdError::IdStale(t1)));
+    test_store.next_id.store(t1.val(), std::sync::atomic::Ordering::SeqCst);
+    assert_eq!(test_store.insert_new(|id| Ok(TestObject::new(id, 3))), Ok(t1));
+    assert_eq!(test_store.get(&t1).unwrap().val(), 3);
+    assert_eq!(test_store.get_generational(&gen1), Err(IdError::IdStale(t1)));
+    assert_eq!(test_store.generational_id(&t1).unwrap().generation(), 1);
+
+    test_store.retain(|_id, object| object.val() != 20);
+    assert_eq!(test_store.get_generational_mut(&gen2), Err(IdError::IdStale(t2)));
+  }
+
+  #[test]
+  fn ids_exhausted() {
+    let mut test_store: ObjectStore<TestObject, TestObjectId> = ObjectStore::with_capacity(u16::MAX as usize + 1);
+    for i in 0..=u16::MAX {
+      test_store.insert_new(|id| Ok(TestObject::new(id, i as usize))).unwrap();
+    }
+
+    // never hands out an ID that's in use
+    assert_eq!(test_store.reserve_id(), Err(IdError::IdsExhausted));
+    assert_eq!(test_store.insert_new(|id| Ok(TestObject::new(id, 0))), Err(IdError::IdsExhausted));
+    test_store.remove(&TestObjectId::new(7));
+    assert_eq!(test_store.reserve_id(), Ok(TestObjectId::new(7)));
+  }
+
+  #[test]
+  fn slugs() {
+    let mut test_store: ObjectStore<TestObject, TestObjectId> = ObjectStore::new();
+    let t1 = test_store.insert_new_named("Email Address", |id| Ok(TestObject::new(id, 1))).unwrap();
+    let t2 = test_store.insert_new(|id| Ok(TestObject::new(id, 2))).unwrap();
+    assert_eq!(test_store.id_from_slug("email-address"), Some(&t1));
+    assert_eq!(test_store.slug_from_id(&t1), Some("email-address"));
+    assert_eq!(test_store.slug_from_id(&t2), None);
+
+    // names with the same slug aren't allowed
+    let dupe_result = test_store.insert_new_named("email_address", |id| Ok(TestObject::new(id, 3)));
+    assert_eq!(dupe_result, Err(IdError::SlugAlreadyExists("email_address".to_owned())));
+    let dupe_result = test_store.insert_new_named("Email Address", |id| Ok(TestObject::new(id, 3)));
+    assert_eq!(dupe_result, Err(IdError::NameAlreadyExists("Email Address".to_owned())));
+  }
+
   #[test]
   fn basic() {
     let mut test_store: ObjectStore<TestObject, TestObjectId> = ObjectStore::new();
     let t1 = test_store.insert_new(|id| Ok(TestObject::new(id, 100))).unwrap();
     let t2 = test_store.insert_new(|id| Ok(TestObject::new(id, 200))).unwrap();
     assert_ne!(t1, t2);
+    assert_eq!(test_store.len(), 2);
 
     // don't allow dupe
     let t1_dupe = TestObject::new(t1.clone(), 3);
@@ -244,4 +625,16 @@ mod tests {
     test_store.get_mut(&t1).unwrap().set_val(5);
     assert_eq!(test_store.get(&t1).unwrap().val(), 5);
   }
+
+  #[test]
+  fn name_prefix() {
+    let mut test_store: ObjectStore<TestObject, TestObjectId> = ObjectStore::new();
+    let a1 = test_store.insert_new_named("a.1", |id| Ok(TestObject::new(id, 100))).unwrap();
+    let _b1 = test_store.insert_new_named("b.1", |id| Ok(TestObject::new(id, 200))).unwrap();
+    let a2 = test_store.insert_new_named("a.2", |id| Ok(TestObject::new(id, 300))).unwrap();
+    let _unnamed = test_store.insert_new(|id| Ok(TestObject::new(id, 400))).unwrap();
+
+    assert_eq!(test_store.ids_with_name_prefix("a."), vec![a1, a2]);
+    assert_eq!(test_store.ids_with_name_prefix("c."), vec![]);
+  }
 }
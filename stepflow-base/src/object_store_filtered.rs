@@ -45,6 +45,21 @@ impl<'os, T, TID> ObjectStoreFiltered<'os, T, TID>
     }
     self.object_store.get(id)
   }
+
+  /// Iterator for the visible objects and their IDs in no particular order
+  pub fn iter(&self) -> impl Iterator<Item = (&TID, &T)> {
+    self.allowed_ids.iter().filter_map(move |id| Some((id, self.object_store.get(id)?)))
+  }
+
+  /// Number of visible objects. Allowed IDs that aren't in the store don't count.
+  pub fn len(&self) -> usize {
+    self.iter().count()
+  }
+
+  /// Check if no objects are visible
+  pub fn is_empty(&self) -> bool {
+    self.iter().next().is_none()
+  }
 }
 
 
@@ -79,4 +94,19 @@ mod tests {
     assert_eq!(filtered.get(&t2), None);
   }
 
+  #[test]
+  fn iter() {
+    let mut object_store: ObjectStore<TestObject, TestObjectId> = ObjectStore::new();
+    let t1 = object_store.insert_new(|id| Ok(TestObject::new(id, 100))).unwrap();
+    object_store.insert_new(|id| Ok(TestObject::new(id, 200))).unwrap();
+    let removed = object_store.insert_new(|id| Ok(TestObject::new(id, 300))).unwrap();
+    object_store.remove(&removed);
+
+    let filtered = ObjectStoreFiltered::new(&object_store, vec![t1, removed].into_iter().collect());
+    assert_eq!(filtered.iter().map(|(id, object)| (*id, object.val())).collect::<Vec<_>>(), vec![(t1, 100)]);
+    assert_eq!(filtered.len(), 1);
+    assert!(!filtered.is_empty());
+    assert!(ObjectStoreFiltered::new(&object_store, HashSet::new()).is_empty());
+  }
+
 }
\ No newline at end of file
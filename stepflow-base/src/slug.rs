@@ -0,0 +1,29 @@
+/// Turn a name into a lowercase, hyphenated string for URLs. i.e. `Email Address` becomes `email-address`
+pub fn slugify(name: &str) -> String {
+  let mut slug = String::with_capacity(name.len());
+  for c in name.chars() {
+    if c.is_alphanumeric() {
+      slug.extend(c.to_lowercase());
+    } else if !slug.is_empty() && !slug.ends_with('-') {
+      slug.push('-');
+    }
+  }
+  if slug.ends_with('-') {
+    slug.pop();
+  }
+  slug
+}
+
+#[cfg(test)]
+mod tests {
+  use super::slugify;
+
+  #[test]
+  fn basic() {
+    assert_eq!(slugify("Email Address"), "email-address");
+    assert_eq!(slugify("kyc.home_address"), "kyc-home-address");
+    assert_eq!(slugify("  What's next?  "), "what-s-next");
+    assert_eq!(slugify("Étape 2"), "étape-2");
+    assert_eq!(slugify("???"), "");
+  }
+}
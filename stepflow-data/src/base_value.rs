@@ -1,11 +1,64 @@
 use std::borrow::Cow;
+use std::sync::Arc;
 
 /// The base store for [`Value`](crate::value::Value). All values must support storing and retrieving data as one of these types.
-#[derive(PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde-support", derive(serde::Serialize, serde::Deserialize), serde(untagged))]
 pub enum BaseValue {
   String(String),
   Boolean(bool),
+  Int(i64),
   Float(f64),
+  List(Vec<BaseValue>),
+  /// No value, i.e. for an [`UnansweredValue`](crate::value::UnansweredValue)
+  Null,
+}
+
+impl BaseValue {
+  /// Which kind of base value this is
+  pub fn kind(&self) -> BaseValueKind {
+    match self {
+      BaseValue::String(_) => BaseValueKind::String,
+      BaseValue::Boolean(_) => BaseValueKind::Boolean,
+      BaseValue::Int(_) => BaseValueKind::Int,
+      BaseValue::Float(_) => BaseValueKind::Float,
+      BaseValue::List(_) => BaseValueKind::List,
+      BaseValue::Null => BaseValueKind::Null,
+    }
+  }
+}
+
+/// The kinds of [`BaseValue`], used to check that values fit a [`Var`](crate::var::Var) without having one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde-support", derive(serde::Serialize, serde::Deserialize))]
+pub enum BaseValueKind {
+  String,
+  Boolean,
+  Int,
+  Float,
+  List,
+  Null,
+}
+
+impl std::fmt::Display for BaseValue {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      BaseValue::String(s) => write!(f, "{}", s),
+      BaseValue::Boolean(b) => write!(f, "{}", b),
+      BaseValue::Int(i) => write!(f, "{}", i),
+      BaseValue::Float(float) => write!(f, "{}", float),
+      BaseValue::List(list) => {
+        for (i, item) in list.iter().enumerate() {
+          if i > 0 {
+            write!(f, ", ")?;
+          }
+          write!(f, "{}", super::value::escape_list_item(&item.to_string()))?;
+        }
+        Ok(())
+      }
+      BaseValue::Null => Ok(()),
+    }
+  }
 }
 
 impl From<String> for BaseValue {
@@ -20,12 +73,24 @@ impl From<Cow<'static, str>> for BaseValue {
   }
 }
 
+impl From<Arc<str>> for BaseValue {
+  fn from(s: Arc<str>) -> Self {
+    BaseValue::String(s.as_ref().to_owned())
+  }
+}
+
 impl From<bool> for BaseValue {
     fn from(b: bool) -> Self {
       BaseValue::Boolean(b)
     }
 }
 
+impl From<i64> for BaseValue {
+  fn from(i: i64) -> Self {
+    BaseValue::Int(i)
+  }
+}
+
 impl From<f64> for BaseValue {
     fn from(float: f64) -> Self {
       BaseValue::Float(float)
@@ -0,0 +1,146 @@
+use std::collections::HashSet;
+use super::{StateData, StateDataFiltered};
+use super::var::VarId;
+use super::value::{Value, ValidVal, UnansweredValue};
+
+/// A predicate over [`StateData`]
+///
+/// Used to decide when something applies, such as an output only being required in some branches of a flow.
+/// ```
+/// # use stepflow_data::{Condition, StateData, var::{VarId, BoolVar}, value::BoolValue};
+/// let is_business = BoolVar::new(VarId::new(0)).boxed();
+/// let condition = Condition::Equals(*is_business.id(), BoolValue::new(true).boxed());
+///
+/// let mut state_data = StateData::new();
+/// assert!(!condition.eval(&state_data));
+/// state_data.insert(&is_business, BoolValue::new(true).boxed()).unwrap();
+/// assert!(condition.eval(&state_data));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde-support", derive(serde::Serialize))]
+pub enum Condition {
+  /// The var has a value, including an [`UnansweredValue`]
+  IsSet(VarId),
+
+  /// The var has a value that isn't an [`UnansweredValue`]
+  IsAnswered(VarId),
+
+  /// The var has a value and it equals the provided value
+  Equals(VarId, Box<dyn Value>),
+
+  /// The inner condition does not hold
+  Not(Box<Condition>),
+
+  /// All the inner conditions hold. True when empty.
+  All(Vec<Condition>),
+
+  /// Any of the inner conditions hold. False when empty.
+  Any(Vec<Condition>),
+}
+
+impl Condition {
+  /// Evaluate the condition against `state_data`
+  pub fn eval(&self, state_data: &StateData) -> bool {
+    self.eval_with(&|var_id| state_data.get(var_id))
+  }
+
+  /// Evaluate the condition against a filtered view of data. Vars outside the filter are treated as unset.
+  pub fn eval_filtered(&self, state_data: &StateDataFiltered) -> bool {
+    self.eval_with(&|var_id| state_data.get(var_id))
+  }
+
+  fn eval_with<'a, F>(&self, get: &F) -> bool
+      where F: Fn(&VarId) -> Option<&'a ValidVal>
+  {
+    match self {
+      Condition::IsSet(var_id) => get(var_id).is_some(),
+      Condition::IsAnswered(var_id) => get(var_id).map(|valid_val| !valid_val.get_val().is::<UnansweredValue>()).unwrap_or(false),
+      Condition::Equals(var_id, val) => get(var_id).map(|valid_val| valid_val.get_val() == val).unwrap_or(false),
+      Condition::Not(condition) => !condition.eval_with(get),
+      Condition::All(conditions) => conditions.iter().all(|condition| condition.eval_with(get)),
+      Condition::Any(conditions) => conditions.iter().any(|condition| condition.eval_with(get)),
+    }
+  }
+
+  /// Get all the vars the condition looks at
+  pub fn var_ids(&self) -> HashSet<VarId> {
+    let mut result = HashSet::new();
+    self.collect_var_ids(&mut result);
+    result
+  }
+
+  fn collect_var_ids(&self, result: &mut HashSet<VarId>) {
+    match self {
+      Condition::IsSet(var_id) |
+      Condition::IsAnswered(var_id) |
+      Condition::Equals(var_id, _) => {
+        result.insert(*var_id);
+      }
+      Condition::Not(condition) => condition.collect_var_ids(result),
+      Condition::All(conditions) |
+      Condition::Any(conditions) => {
+        for condition in conditions {
+          condition.collect_var_ids(result);
+        }
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::HashSet;
+  use stepflow_test_util::test_id;
+  use crate::{StateData, StateDataFiltered, var::{VarId, BoolVar}, value::{BoolValue, UnansweredValue}, test_var_val};
+  use super::Condition;
+
+  #[test]
+  fn eval() {
+    let (var, val) = test_var_val();
+    let bool_var = BoolVar::new(test_id!(VarId)).boxed();
+    let unset_id = test_id!(VarId);
+
+    let mut state_data = StateData::new();
+    state_data.insert(&var, val.clone()).unwrap();
+    state_data.insert(&bool_var, BoolValue::new(false).boxed()).unwrap();
+
+    let is_set = Condition::IsSet(*var.id());
+    let is_unset = Condition::IsSet(unset_id);
+    assert!(is_set.eval(&state_data));
+    assert!(!is_unset.eval(&state_data));
+    assert!(Condition::Not(Box::new(is_unset.clone())).eval(&state_data));
+
+    assert!(Condition::Equals(*var.id(), val).eval(&state_data));
+    assert!(Condition::Equals(*bool_var.id(), BoolValue::new(false).boxed()).eval(&state_data));
+    assert!(!Condition::Equals(*bool_var.id(), BoolValue::new(true).boxed()).eval(&state_data));
+    assert!(!Condition::Equals(unset_id, BoolValue::new(true).boxed()).eval(&state_data));
+
+    assert!(Condition::All(vec![]).eval(&state_data));
+    assert!(!Condition::Any(vec![]).eval(&state_data));
+    assert!(!Condition::All(vec![is_set.clone(), is_unset.clone()]).eval(&state_data));
+    assert!(Condition::Any(vec![is_set.clone(), is_unset.clone()]).eval(&state_data));
+
+    // filtered out vars look unset
+    let filtered = StateDataFiltered::new(&state_data, HashSet::new());
+    assert!(!is_set.eval_filtered(&filtered));
+
+    let all = Condition::All(vec![is_set, Condition::Not(Box::new(is_unset))]);
+    assert_eq!(all.var_ids(), vec![*var.id(), unset_id].into_iter().collect::<HashSet<_>>());
+  }
+
+  #[test]
+  fn unanswered() {
+    let answered = BoolVar::new(test_id!(VarId)).allow_unanswered().boxed();
+    let skipped = BoolVar::new(test_id!(VarId)).allow_unanswered().boxed();
+    let never_asked = test_id!(VarId);
+    let mut state_data = StateData::new();
+    state_data.insert(&answered, BoolValue::new(false).boxed()).unwrap();
+    state_data.insert(&skipped, UnansweredValue::new().boxed()).unwrap();
+
+    assert!(Condition::IsAnswered(*answered.id()).eval(&state_data));
+    assert!(Condition::IsSet(*skipped.id()).eval(&state_data));
+    assert!(!Condition::IsAnswered(*skipped.id()).eval(&state_data));
+    assert!(!Condition::IsSet(never_asked).eval(&state_data));
+    assert!(!Condition::IsAnswered(never_asked).eval(&state_data));
+  }
+}
@@ -1,5 +1,6 @@
-use std::collections::HashMap;
-use super::var::VarId;
+use std::collections::{BTreeMap, HashMap};
+use stepflow_base::ObjectStore;
+use super::var::{Var, VarId};
 
 #[derive(Debug, PartialEq, Clone, Copy)]
 #[cfg_attr(feature = "serde-support", derive(serde::Serialize))]
@@ -8,6 +9,10 @@ pub enum InvalidValue {
   BadFormat,
   Empty,
   WrongValue,
+  /// The number is outside the range the var allows
+  OutOfRange,
+  /// The var isn't expected, i.e. it's not an output of the step
+  Unexpected,
 }
 
 impl std::error::Error for InvalidValue {}
@@ -26,4 +31,68 @@ impl InvalidVars {
   pub fn new(invalid: HashMap<VarId, InvalidValue>) -> Self {
     Self(invalid)
   }
+
+  /// Report keyed by var name, i.e. to log the errors from a form post
+  ///
+  /// `submitted` are the raw strings keyed by var name. Vars without a name are keyed by their ID.
+  /// The submitted strings of [sensitive](crate::var::VarMetadata::sensitive) vars are left out.
+  pub fn report(&self, vars: &ObjectStore<Box<dyn Var + Send + Sync>, VarId>, submitted: &HashMap<String, String>)
+      -> BTreeMap<String, InvalidVarReport>
+  {
+    self.0.iter()
+      .map(|(var_id, invalid)| {
+        let name = vars.name_from_id(var_id).map(|name| name.to_owned()).unwrap_or_else(|| var_id.to_string());
+        let sensitive = vars.get(var_id).map(|var| var.metadata().sensitive).unwrap_or(false);
+        let submitted = if sensitive { None } else { submitted.get(&name).cloned() };
+        (name, InvalidVarReport { invalid: *invalid, submitted })
+      })
+      .collect()
+  }
+}
+
+/// What's wrong with one var in an [`InvalidVars::report`]
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde-support", derive(serde::Serialize))]
+pub struct InvalidVarReport {
+  pub invalid: InvalidValue,
+
+  /// The string that was submitted. `None` if it wasn't given or the var is sensitive.
+  pub submitted: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::HashMap;
+  use stepflow_base::ObjectStore;
+  use stepflow_test_util::test_id;
+  use crate::var::{Var, VarId, StringVar, EmailVar};
+  use super::{InvalidValue, InvalidVars, InvalidVarReport};
+
+  #[test]
+  fn report() {
+    let mut vars: ObjectStore<Box<dyn Var + Send + Sync>, VarId> = ObjectStore::new();
+    let email = vars.insert_new_named("email", |id| Ok(EmailVar::new(id).boxed())).unwrap();
+    let password = vars.insert_new_named("password", |id| {
+      let mut var = StringVar::new(id);
+      var.metadata_mut().unwrap().sensitive = true;
+      Ok(var.boxed())
+    }).unwrap();
+    let unnamed = test_id!(VarId);
+
+    let invalid = InvalidVars::new(vec![
+      (email, InvalidValue::BadFormat),
+      (password, InvalidValue::Empty),
+      (unnamed, InvalidValue::Unexpected),
+    ].into_iter().collect());
+    let submitted: HashMap<String, String> = vec![
+      ("email".to_owned(), "not an email".to_owned()),
+      ("password".to_owned(), "hunter2".to_owned()),
+    ].into_iter().collect();
+
+    let report = invalid.report(&vars, &submitted);
+    assert_eq!(report.len(), 3);
+    assert_eq!(report["email"], InvalidVarReport { invalid: InvalidValue::BadFormat, submitted: Some("not an email".to_owned()) });
+    assert_eq!(report["password"], InvalidVarReport { invalid: InvalidValue::Empty, submitted: None });
+    assert_eq!(report[&unnamed.to_string()], InvalidVarReport { invalid: InvalidValue::Unexpected, submitted: None });
+  }
 }
@@ -19,8 +19,23 @@ pub use statedata::StateData;
 mod statedata_filtered;
 pub use statedata_filtered::StateDataFiltered;
 
+mod var_dependencies;
+pub use var_dependencies::VarDependencies;
+
+mod var_access;
+pub use var_access::VarAccess;
+
+mod condition;
+pub use condition::Condition;
+
 mod error;
-pub use error::{InvalidValue, InvalidVars};
+pub use error::{InvalidValue, InvalidVars, InvalidVarReport};
+
+mod value_generator;
+pub use value_generator::ValueGenerator;
+
+mod normalize;
+pub use normalize::Normalization;
 
 pub mod var;
 
@@ -28,6 +43,6 @@ pub mod var;
 use var::test_var_val;
 
 mod base_value;
-pub use base_value::BaseValue;
+pub use base_value::{BaseValue, BaseValueKind};
 
 pub mod value;
@@ -0,0 +1,95 @@
+use super::InvalidValue;
+
+/// How to normalize a collected value before comparing it, i.e. to find an account that already has the email
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde-support", derive(serde::Serialize))]
+pub enum Normalization {
+  /// Trimmed and lowercased
+  Email,
+
+  /// [E.164](https://en.wikipedia.org/wiki/E.164), i.e. `+14155550123`.
+  /// Numbers without a `+` or `00` prefix get the default country code.
+  Phone { default_country_code: String },
+
+  /// Trimmed, lowercased, with runs of whitespace collapsed to one space
+  Text,
+}
+
+impl Normalization {
+  /// Normalize `s`. Fails if it's empty or, for phone numbers, isn't a phone number.
+  pub fn normalize(&self, s: &str) -> Result<String, InvalidValue> {
+    let normalized = match self {
+      Normalization::Email => s.trim().to_lowercase(),
+      Normalization::Phone { default_country_code } => return normalize_phone(s, default_country_code),
+      Normalization::Text => s.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase(),
+    };
+    if normalized.is_empty() {
+      return Err(InvalidValue::Empty);
+    }
+    Ok(normalized)
+  }
+
+  /// Whether `a` and `b` are the same once normalized. Values that can't be normalized are never the same.
+  pub fn same(&self, a: &str, b: &str) -> bool {
+    match (self.normalize(a), self.normalize(b)) {
+      (Ok(a), Ok(b)) => a == b,
+      _ => false,
+    }
+  }
+}
+
+fn normalize_phone(s: &str, default_country_code: &str) -> Result<String, InvalidValue> {
+  let trimmed = s.trim();
+  if trimmed.is_empty() {
+    return Err(InvalidValue::Empty);
+  }
+  let (international, rest) = match trimmed.strip_prefix('+') {
+    Some(rest) => (true, rest),
+    None => (false, trimmed),
+  };
+  if rest.chars().any(|c| !(c.is_ascii_digit() || c == ' ' || c == '-' || c == '.' || c == '(' || c == ')')) {
+    return Err(InvalidValue::BadFormat);
+  }
+  let digits: String = rest.chars().filter(|c| c.is_ascii_digit()).collect();
+  let digits = match (international, digits.strip_prefix("00")) {
+    (true, _) => digits,
+    (false, Some(without_prefix)) => without_prefix.to_owned(),
+    (false, None) => {
+      let country_code = default_country_code.trim_start_matches('+');
+      format!("{}{}", country_code, digits.trim_start_matches('0'))
+    },
+  };
+  // E.164 numbers are at most 15 digits
+  if digits.len() < 8 || digits.len() > 15 || digits.starts_with('0') {
+    return Err(InvalidValue::BadFormat);
+  }
+  Ok(format!("+{}", digits))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::Normalization;
+  use crate::InvalidValue;
+
+  #[test]
+  fn normalize() {
+    assert_eq!(Normalization::Email.normalize("  Jane@Example.COM "), Ok("jane@example.com".to_owned()));
+    assert_eq!(Normalization::Email.normalize(" "), Err(InvalidValue::Empty));
+    assert_eq!(Normalization::Text.normalize(" Jane   Q\tDoe "), Ok("jane q doe".to_owned()));
+
+    let phone = Normalization::Phone { default_country_code: "1".to_owned() };
+    assert_eq!(phone.normalize("(415) 555-0123"), Ok("+14155550123".to_owned()));
+    assert_eq!(phone.normalize("+1 415.555.0123"), Ok("+14155550123".to_owned()));
+    assert_eq!(phone.normalize("0044 20 7946 0958"), Ok("+442079460958".to_owned()));
+    assert_eq!(phone.normalize("555-CALL"), Err(InvalidValue::BadFormat));
+    assert_eq!(phone.normalize("123"), Err(InvalidValue::BadFormat));
+
+    // national numbers drop their trunk prefix
+    let phone_uk = Normalization::Phone { default_country_code: "+44".to_owned() };
+    assert_eq!(phone_uk.normalize("020 7946 0958"), Ok("+442079460958".to_owned()));
+
+    assert!(Normalization::Email.same("jane@example.com", "JANE@example.com "));
+    assert!(!phone.same("415 555 0123", "415 555 0124"));
+    assert!(!phone.same("", ""));
+  }
+}
@@ -1,6 +1,9 @@
 use std::collections::{HashMap, HashSet};
-use super::{InvalidValue, InvalidVars};
-use super::value::{Value, ValidVal};
+use stepflow_base::ObjectStore;
+use super::{InvalidValue, InvalidVars, VarDependencies};
+#[cfg(feature = "serde-support")]
+use super::{BaseValue, BaseValueKind};
+use super::value::{Value, ValidVal, TaggedValue, ValueRegistry, StringValue, EmailValue, StringInterner, UnansweredValue};
 use super::var::{Var, VarId};
 
 /// Store a set of [`Var`]s and corresponding [`Value`]s.
@@ -36,6 +39,16 @@ impl StateData {
     self.data.contains_key(var_id)
   }
 
+  /// Check if the user skipped the var. A var that was never asked doesn't have a value at all.
+  pub fn is_unanswered(&self, var_id: &VarId) -> bool {
+    self.data.get(var_id).map(|valid_val| valid_val.get_val().is::<UnansweredValue>()).unwrap_or(false)
+  }
+
+  /// Remove a value, returning it if it existed
+  pub fn remove(&mut self, var_id: &VarId) -> Option<ValidVal> {
+    self.data.remove(var_id)
+  }
+
   /// Confirm that the StateData *only* contains the set of [`VarId`]s listed
   pub fn contains_only(&self, contains_only: &HashSet<&VarId>) -> bool {
     let found_excluded = self.data.iter().find(|(var_id, _)| !contains_only.contains(var_id));
@@ -49,6 +62,30 @@ impl StateData {
     }
   }
 
+  /// Merge the data from another `StateData` into this one and remove any data that depended on changed values.
+  ///
+  /// Values that are part of `src` are never removed, even if they depend on another value in `src`.
+  /// Returns the [`VarId`]s that were removed.
+  pub fn merge_with_dependencies(&mut self, src: StateData, dependencies: &VarDependencies) -> HashSet<VarId> {
+    let mut removed = HashSet::new();
+    if !dependencies.is_empty() {
+      let changed = src.data
+        .iter()
+        .filter(|(var_id, val)| self.data.get(var_id) != Some(val))
+        .map(|(var_id, _)| *var_id)
+        .collect::<Vec<_>>();
+      for var_id in changed {
+        for dependent in dependencies.dependents_of(&var_id) {
+          if !src.contains(&dependent) && self.data.remove(&dependent).is_some() {
+            removed.insert(dependent);
+          }
+        }
+      }
+    }
+    self.merge_from(src);
+    removed
+  }
+
   // Get an iterator over the values
   pub fn iter_val(&self) -> impl Iterator<Item = (&VarId, &Box<dyn Value>)>  {
     self.data.iter().map(|(var_id, valid_val)| {
@@ -96,13 +133,96 @@ impl StateData {
       .collect();
     Ok(StateData { data })
   }
+
+  /// Share the strings of any [`StringValue`]s and [`EmailValue`]s with identical ones already in `interner`
+  pub fn intern_strings(&mut self, interner: &mut StringInterner) {
+    for valid_val in self.data.values_mut() {
+      let val = valid_val.get_val_mut();
+      if let Some(string_val) = val.downcast::<StringValue>() {
+        *val = interner.intern_value(string_val).boxed();
+      } else if let Some(email_val) = val.downcast::<EmailValue>() {
+        *val = interner.intern_email(email_val).boxed();
+      }
+    }
+  }
+
+  /// Every value with its type tag, i.e. to save the data and get the same types of values back with [`from_tagged`](StateData::from_tagged)
+  pub fn to_tagged(&self) -> HashMap<VarId, TaggedValue> {
+    self.iter_val()
+      .map(|(var_id, val)| (*var_id, TaggedValue::new(val.as_ref())))
+      .collect()
+  }
+
+  /// Rebuild data saved with [`to_tagged`](StateData::to_tagged). Vars that don't exist anymore are [`InvalidValue::Unexpected`].
+  pub fn from_tagged(tagged: &HashMap<VarId, TaggedValue>, vars: &ObjectStore<Box<dyn Var + Send + Sync>, VarId>, registry: &ValueRegistry)
+      -> Result<Self, InvalidVars>
+  {
+    let mut state_data = StateData::new();
+    let mut invalid = HashMap::new();
+    for (var_id, tagged_val) in tagged {
+      let result = vars.get(var_id)
+        .ok_or(InvalidValue::Unexpected)
+        .and_then(|var| state_data.insert(var, registry.from_tagged(tagged_val)?));
+      if let Err(e) = result {
+        invalid.insert(*var_id, e);
+      }
+    }
+    if !invalid.is_empty() {
+      return Err(InvalidVars::new(invalid));
+    }
+    Ok(state_data)
+  }
+
+  /// Build from a JSON object of var names and values, i.e. answers posted to an API or saved data.
+  ///
+  /// Each value is checked by its var. Strings are parsed like form fields and `null` means [unanswered](UnansweredValue).
+  /// Names without a var are ignored.
+  #[cfg(feature = "serde-support")]
+  pub fn from_json(vars: &ObjectStore<Box<dyn Var + Send + Sync>, VarId>, json: &serde_json::Map<String, serde_json::Value>)
+      -> Result<Self, InvalidVars>
+  {
+    let mut state_data = StateData::new();
+    let mut invalid = HashMap::new();
+    for (name, json_val) in json {
+      let var = match vars.get_by_name(name) {
+        Some(var) => var,
+        None => continue,
+      };
+      let result = value_from_json(var.as_ref(), json_val).and_then(|val| state_data.insert(var, val));
+      if let Err(e) = result {
+        invalid.insert(*var.id(), e);
+      }
+    }
+    if !invalid.is_empty() {
+      return Err(InvalidVars::new(invalid));
+    }
+    Ok(state_data)
+  }
+}
+
+#[cfg(feature = "serde-support")]
+fn value_from_json(var: &(dyn Var + Send + Sync), json_val: &serde_json::Value) -> Result<Box<dyn Value>, InvalidValue> {
+  let base_val: BaseValue = serde_json::from_value(json_val.clone()).map_err(|_| InvalidValue::WrongType)?;
+  match (base_val, var.base_value_kind()) {
+    (BaseValue::Null, _) if var.allows_unanswered() => Ok(UnansweredValue::new().boxed()),
+    (BaseValue::Null, _) => Err(InvalidValue::Empty),
+    (BaseValue::String(s), _) => var.value_from_str(&s),
+    (BaseValue::List(items), Some(BaseValueKind::List)) => {
+      var.value_from_str(&items.iter().map(|item| item.to_string()).collect::<Vec<_>>().join(","))
+    }
+    (base_val, Some(kind)) if kind != base_val.kind() && !(kind == BaseValueKind::Float && base_val.kind() == BaseValueKind::Int) => {
+      Err(InvalidValue::WrongType)
+    }
+    (base_val, _) => var.value_from_str(&base_val.to_string()),
+  }
 }
 
 
 #[cfg(test)]
 mod tests {
   use std::collections::{HashMap, HashSet};
-  use crate::{var::{Var, VarId, StringVar}, value::{Value, TrueValue}, InvalidValue, test_var_val};
+  use stepflow_base::ObjectStore;
+  use crate::{var::{Var, VarId, StringVar, EmailVar}, value::{Value, StringValue, StringInterner, EmailValue, TrueValue, TaggedValue, ValueRegistry}, BaseValue, InvalidValue, VarDependencies, test_var_val};
   use stepflow_test_util::test_id;
   use super::{StateData, InvalidVars};
 
@@ -133,6 +253,75 @@ mod tests {
     assert!(data_merged.contains(var3.0.id()));
   }
 
+  #[test]
+  fn tagged() {
+    let mut vars: ObjectStore<Box<dyn Var + Send + Sync>, VarId> = ObjectStore::new();
+    let email = vars.insert_new(|id| Ok(EmailVar::new(id).boxed())).unwrap();
+    let name = vars.insert_new(|id| Ok(StringVar::new(id).boxed())).unwrap();
+    let mut state_data = StateData::new();
+    state_data.insert(vars.get(&email).unwrap(), EmailValue::try_new("a@b.com").unwrap().boxed()).unwrap();
+    state_data.insert(vars.get(&name).unwrap(), StringValue::try_new("Jane").unwrap().boxed()).unwrap();
+
+    let registry = ValueRegistry::with_builtins();
+    let tagged = state_data.to_tagged();
+    assert_eq!(tagged.get(&email).unwrap().tag, "EmailValue");
+    assert_eq!(StateData::from_tagged(&tagged, &vars, &registry), Ok(state_data));
+
+    // values have to fit their var and the var has to exist
+    let mut bad = tagged.clone();
+    bad.insert(name, TaggedValue { tag: "BoolValue".to_owned(), value: BaseValue::Boolean(true), elements: vec![] });
+    let missing = test_id!(VarId);
+    bad.insert(missing, tagged.get(&email).unwrap().clone());
+    let expected = vec![(name, InvalidValue::WrongType), (missing, InvalidValue::Unexpected)].into_iter().collect();
+    assert_eq!(StateData::from_tagged(&bad, &vars, &registry), Err(InvalidVars::new(expected)));
+  }
+
+  #[cfg(feature = "serde-support")]
+  #[test]
+  fn from_json() {
+    use crate::{var::{NumberVar, BoolVar, ListVar}, value::IntValue};
+
+    let mut vars: ObjectStore<Box<dyn Var + Send + Sync>, VarId> = ObjectStore::new();
+    let name = vars.insert_new_named("name", |id| Ok(StringVar::new(id).boxed())).unwrap();
+    let age = vars.insert_new_named("age", |id| Ok(NumberVar::new_int(id).boxed())).unwrap();
+    let agreed = vars.insert_new_named("agreed", |id| Ok(BoolVar::new(id).allow_unanswered().boxed())).unwrap();
+    let emails = vars.insert_new_named("emails", |id| Ok(ListVar::new::<EmailValue>(id).boxed())).unwrap();
+
+    let json = serde_json::json!({ "name": "Jane", "age": 21, "agreed": null, "emails": ["a@b.com", "c@d.com"], "unknown": 1 });
+    let state_data = StateData::from_json(&vars, json.as_object().unwrap()).unwrap();
+    assert_eq!(state_data.get(&name).unwrap().get_val(), &StringValue::try_new("Jane").unwrap().boxed());
+    assert_eq!(state_data.get(&age).unwrap().get_val(), &IntValue::new(21).boxed());
+    assert!(state_data.is_unanswered(&agreed));
+    assert_eq!(state_data.get(&emails).unwrap().get_val().get_baseval().to_string(), "a@b.com, c@d.com");
+
+    // numbers can come as strings but not the other way around
+    let json = serde_json::json!({ "name": 5, "age": "21", "agreed": "maybe", "emails": null });
+    let expected = vec![(name, InvalidValue::WrongType), (agreed, InvalidValue::WrongValue), (emails, InvalidValue::Empty)].into_iter().collect();
+    assert_eq!(StateData::from_json(&vars, json.as_object().unwrap()), Err(InvalidVars::new(expected)));
+  }
+
+  #[test]
+  fn intern_strings() {
+    let mut vars: ObjectStore<Box<dyn Var + Send + Sync>, VarId> = ObjectStore::new();
+    let name = vars.insert_new(|id| Ok(StringVar::new(id).boxed())).unwrap();
+    let nickname = vars.insert_new(|id| Ok(StringVar::new(id).boxed())).unwrap();
+    let email = vars.insert_new(|id| Ok(EmailVar::new(id).boxed())).unwrap();
+    let mut state_data = StateData::new();
+    state_data.insert(vars.get(&name).unwrap(), StringValue::try_new("Jane").unwrap().boxed()).unwrap();
+    state_data.insert(vars.get(&nickname).unwrap(), StringValue::try_new("Jane").unwrap().boxed()).unwrap();
+    state_data.insert(vars.get(&email).unwrap(), EmailValue::try_new("a@b.com").unwrap().boxed()).unwrap();
+    let before = state_data.clone();
+
+    let mut interner = StringInterner::new();
+    let pooled_email = interner.intern("a@b.com");
+    state_data.intern_strings(&mut interner);
+    assert_eq!(state_data, before);
+    assert_eq!(interner.len(), 2);
+    let shared = |var_id| state_data.get(var_id).unwrap().get_val().downcast::<StringValue>().unwrap().shared().clone();
+    assert!(std::sync::Arc::ptr_eq(&shared(&name), &shared(&nickname)));
+    assert!(std::sync::Arc::ptr_eq(state_data.get(&email).unwrap().get_val().downcast::<EmailValue>().unwrap().shared(), &pooled_email));
+  }
+
   #[test]
   fn from_vals_err() {
     let var1 = test_var_val();
@@ -200,4 +389,41 @@ mod tests {
     assert_eq!(hashmap.get(var1.0.id()), Some(&&var1.1));
     assert_eq!(hashmap.get(var2.0.id()), Some(&&var2.1));
   }
+
+  #[test]
+  fn merge_with_dependencies() {
+    let email = test_var_val();
+    let validated = test_var_val();
+    let unrelated = test_var_val();
+
+    let mut dependencies = VarDependencies::new();
+    dependencies.add(*validated.0.id(), *email.0.id());
+
+    let mut data = StateData::new();
+    data.insert(&email.0, email.1.clone()).unwrap();
+    data.insert(&validated.0, validated.1.clone()).unwrap();
+    data.insert(&unrelated.0, unrelated.1.clone()).unwrap();
+
+    // same value doesn't invalidate anything
+    let mut same_email = StateData::new();
+    same_email.insert(&email.0, email.1.clone()).unwrap();
+    assert!(data.merge_with_dependencies(same_email, &dependencies).is_empty());
+    assert!(data.contains(validated.0.id()));
+
+    // updating both at the same time keeps the dependent
+    let mut both = StateData::new();
+    both.insert(&email.0, StringValue::try_new("both").unwrap().boxed()).unwrap();
+    both.insert(&validated.0, validated.1.clone()).unwrap();
+    assert!(data.merge_with_dependencies(both, &dependencies).is_empty());
+    assert!(data.contains(validated.0.id()));
+
+    // changed value removes the dependent
+    let mut new_email = StateData::new();
+    new_email.insert(&email.0, StringValue::try_new("changed").unwrap().boxed()).unwrap();
+    let removed = data.merge_with_dependencies(new_email, &dependencies);
+    assert_eq!(removed.len(), 1);
+    assert!(removed.contains(validated.0.id()));
+    assert!(!data.contains(validated.0.id()));
+    assert!(data.contains(unrelated.0.id()));
+  }
 }
\ No newline at end of file
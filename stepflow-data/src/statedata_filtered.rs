@@ -28,6 +28,13 @@ impl<'sd> StateDataFiltered<'sd> {
     }
     self.state_data.contains(var_id)
   }
+
+  /// Get an iterator over the visible values
+  pub fn iter(&self) -> impl Iterator<Item = (&VarId, &ValidVal)> {
+    self.allowed_var_ids
+      .iter()
+      .filter_map(move |var_id| self.state_data.get(var_id).map(|valid_val| (var_id, valid_val)))
+  }
 }
 
 #[cfg(test)]
@@ -51,10 +58,12 @@ mod tests {
     // create filtered statedata
     let mut filter = HashSet::new();
     filter.insert(var1.0.id().clone());
+    filter.insert(*test_var_val().0.id()); // allowed but not set
     let data_filtered = StateDataFiltered::new(&data, filter);
 
     assert_eq!(data_filtered.get(var1.0.id()), Some(&val1_valid));
     assert_eq!(data_filtered.get(var2.0.id()), None);
+    assert_eq!(data_filtered.iter().collect::<Vec<_>>(), vec![(var1.0.id(), &val1_valid)]);
   }
 
 }
@@ -6,6 +6,9 @@
 //!
 //! When needed, they can be downcast to their original type via `Value::downcast` and `Value::is`.
 //!
+//! Serializing a value only keeps its BaseValue. To get the same type of value back, save it as a [`TaggedValue`]
+//! and rebuild it with a [`ValueRegistry`].
+//!
 //! # Examples
 //! ```
 //! # use stepflow_data::value::EmailValue;
@@ -18,6 +21,15 @@ use super::{BaseValue, InvalidValue};
 
 pub trait Value: Debug + Sync + Send + stepflow_base::as_any::AsAny {
   fn get_baseval(&self) -> BaseValue;
+
+  /// Name for the concrete type, unique among the values. See [`ValueRegistry`].
+  ///
+  /// Defaults to the full [type name](std::any::type_name), which changes if the type moves.
+  /// Override it with a short fixed name if tagged values are saved.
+  fn type_tag(&self) -> &'static str {
+    std::any::type_name::<Self>()
+  }
+
   fn clone_box(&self) -> Box<dyn Value>;
   fn eq_box(&self, other: &Box<dyn Value>) -> bool;
 }
@@ -56,7 +68,10 @@ impl serde::Serialize for Box<dyn Value> {
       match self.get_baseval() {
           BaseValue::String(s) => s.serialize(serializer),
           BaseValue::Boolean(b) => b.serialize(serializer),
+          BaseValue::Int(i) => i.serialize(serializer),
           BaseValue::Float(float) => float.serialize(serializer),
+          BaseValue::List(list) => list.serialize(serializer),
+          BaseValue::Null => serializer.serialize_none(),
       }
     }
 }
@@ -68,6 +83,9 @@ macro_rules! define_value_impl {
       fn get_baseval(&self) -> BaseValue {
         self.val.clone().into()
       }
+      fn type_tag(&self) -> &'static str {
+        stringify!($name)
+      }
       fn clone_box(&self) -> Box<dyn Value> {
         Box::new(self.clone())
       }
@@ -133,20 +151,64 @@ pub use valid_value::ValidVal;
 mod string_value;
 pub use string_value::StringValue;
 
+mod string_interner;
+pub use string_interner::StringInterner;
+
 mod email_value;
 pub use email_value::EmailValue;
 
+mod datetime_value;
+pub use datetime_value::DateTimeValue;
+
+mod enum_value;
+pub use enum_value::EnumValue;
+
 mod bool_value;
 pub use bool_value::BoolValue;
 
 mod true_value;
 pub use true_value::TrueValue;
 
+mod number_value;
+pub use number_value::{IntValue, FloatValue};
+
+mod unanswered_value;
+pub use unanswered_value::UnansweredValue;
+
+mod list_value;
+pub use list_value::ListValue;
+pub(crate) use list_value::{escape_list_item, split_list};
+
+mod tagged_value;
+pub use tagged_value::{TaggedValue, ValueRegistry, ValueConstructor};
+
 
 #[cfg(test)]
 mod tests {
+  use crate::BaseValue;
   use super::{EmailValue, Value, StringValue, TrueValue};
 
+  #[derive(Debug, Clone, PartialEq)]
+  struct ColorValue;
+
+  impl Value for ColorValue {
+    fn get_baseval(&self) -> BaseValue {
+      BaseValue::String("red".to_owned())
+    }
+    fn clone_box(&self) -> Box<dyn Value> {
+      Box::new(self.clone())
+    }
+    fn eq_box(&self, other: &Box<dyn Value>) -> bool {
+      other.downcast::<Self>().is_some()
+    }
+  }
+
+  #[test]
+  fn type_tag() {
+    assert_eq!(StringValue::try_new("hi").unwrap().type_tag(), "StringValue");
+    assert!(ColorValue.type_tag().ends_with("ColorValue"));
+  }
+
   #[test]
   fn val_downcast() {
     // try with reference
@@ -0,0 +1,199 @@
+use std::borrow::{Borrow, Cow};
+use std::str::FromStr;
+use super::{Value, BaseValue, InvalidValue};
+
+
+/// An ISO-8601 date, i.e. `2021-03-14`, or date and time, i.e. `2021-03-14T15:09:26Z`
+///
+/// Times need hours and minutes. Seconds, fractions of a second and a `Z` or `+HH:MM` offset are optional.
+/// The value is kept as the string it was created from.
+#[derive(Debug, PartialEq, Clone)]
+pub struct DateTimeValue {
+  val: Cow<'static, str>,
+}
+
+impl DateTimeValue {
+  pub fn try_new<STR>(val: STR) -> Result<Self, InvalidValue>
+      where STR: Into<Cow<'static, str>>
+  {
+    let val = val.into();
+    Self::validate(&val)?;
+    Ok(Self { val })
+  }
+
+  pub fn validate(val: &str) -> Result<(), InvalidValue> {
+    if val.is_empty() {
+      return Err(InvalidValue::Empty);
+    }
+    parse_date(val.as_bytes())
+      .and_then(|(_, rest)| parse_time(rest))
+      .map(|_| ())
+      .ok_or(InvalidValue::BadFormat)
+  }
+
+  pub fn val(&self) -> &str {
+    self.val.borrow()
+  }
+
+  /// The year, month and day
+  pub fn date(&self) -> (i32, u32, u32) {
+    // already validated
+    parse_date(self.val.as_bytes()).unwrap().0
+  }
+
+  /// Whether there's a time after the date
+  pub fn has_time(&self) -> bool {
+    self.val.len() > DATE_LEN
+  }
+
+  pub fn boxed(self) -> Box<dyn Value> {
+    Box::new(self)
+  }
+}
+
+const DATE_LEN: usize = 10;
+
+// year, month, day
+type Date = (i32, u32, u32);
+
+// parse `count` digits into a number
+fn digits(input: &[u8], count: usize) -> Option<u32> {
+  let digits = input.get(..count)?;
+  if !digits.iter().all(u8::is_ascii_digit) {
+    return None;
+  }
+  Some(digits.iter().fold(0, |num, digit| num * 10 + (digit - b'0') as u32))
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+  match month {
+    2 if (year % 4 == 0 && year % 100 != 0) || year % 400 == 0 => 29,
+    2 => 28,
+    4 | 6 | 9 | 11 => 30,
+    _ => 31,
+  }
+}
+
+// `YYYY-MM-DD` and whatever follows it
+fn parse_date(input: &[u8]) -> Option<(Date, &[u8])> {
+  if input.get(4) != Some(&b'-') || input.get(7) != Some(&b'-') {
+    return None;
+  }
+  let year = digits(input, 4)? as i32;
+  let month = digits(&input[5..], 2)?;
+  let day = digits(&input[8..], 2)?;
+  if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+    return None;
+  }
+  Some(((year, month, day), &input[DATE_LEN..]))
+}
+
+// nothing or `THH:MM[:SS[.fff]][Z|+HH:MM|-HH:MM]`
+fn parse_time(input: &[u8]) -> Option<()> {
+  let mut rest = match input.split_first() {
+    None => return Some(()),
+    Some((b'T', rest)) => rest,
+    Some(_) => return None,
+  };
+  rest = parse_hours_minutes(rest)?;
+  if let Some((b':', after)) = rest.split_first() {
+    if digits(after, 2)? > 60 {
+      return None;
+    }
+    rest = &after[2..];
+    if let Some((b'.', after)) = rest.split_first() {
+      let fraction_len = after.iter().take_while(|c| c.is_ascii_digit()).count();
+      if fraction_len == 0 {
+        return None;
+      }
+      rest = &after[fraction_len..];
+    }
+  }
+  match rest.split_first() {
+    None => Some(()),
+    Some((b'Z', [])) => Some(()),
+    Some((b'+', offset)) | Some((b'-', offset)) if parse_hours_minutes(offset)?.is_empty() => Some(()),
+    Some(_) => None,
+  }
+}
+
+// `HH:MM` and whatever follows it
+fn parse_hours_minutes(input: &[u8]) -> Option<&[u8]> {
+  if input.get(2) != Some(&b':') {
+    return None;
+  }
+  if digits(input, 2)? > 23 || digits(&input[3..], 2)? > 59 {
+    return None;
+  }
+  Some(&input[5..])
+}
+
+define_value_impl!(DateTimeValue);
+
+impl FromStr for DateTimeValue {
+  type Err = InvalidValue;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    DateTimeValue::try_new(s.to_owned())
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use crate::BaseValue;
+  use super::super::{InvalidValue, Value};
+  use super::DateTimeValue;
+
+  #[test]
+  fn good_datetimes() {
+    let good = vec![
+      "2021-03-14",
+      "2020-02-29",
+      "2000-02-29",
+      "2021-03-14T15:09",
+      "2021-03-14T15:09:26",
+      "2021-03-14T15:09:26.535Z",
+      "2021-03-14T23:59:59+05:30",
+      "2021-03-14T00:00-08:00",
+    ];
+    for datetime in good {
+      assert_eq!(DateTimeValue::validate(datetime), Ok(()), "{}", datetime);
+    }
+  }
+
+  #[test]
+  fn bad_datetimes() {
+    assert_eq!(DateTimeValue::try_new(""), Err(InvalidValue::Empty));
+    let bad = vec![
+      "2021-3-14",
+      "14/03/2021",
+      "2021-13-01",
+      "2021-02-29",
+      "1900-02-29",
+      "2021-04-31",
+      "2021-03-14 15:09",
+      "2021-03-14T15",
+      "2021-03-14T24:00",
+      "2021-03-14T15:60",
+      "2021-03-14T15:09:26.",
+      "2021-03-14T15:09Zx",
+      "2021-03-14T15:09+0530",
+    ];
+    for datetime in bad {
+      assert_eq!(DateTimeValue::validate(datetime), Err(InvalidValue::BadFormat), "{}", datetime);
+    }
+  }
+
+  #[test]
+  fn parts() {
+    let birthdate = "1990-07-04".parse::<DateTimeValue>().unwrap();
+    assert_eq!(birthdate.date(), (1990, 7, 4));
+    assert!(!birthdate.has_time());
+    assert_eq!(birthdate.get_baseval(), BaseValue::String("1990-07-04".to_owned()));
+
+    let booking = DateTimeValue::try_new("2021-03-14T15:09Z").unwrap();
+    assert_eq!(booking.date(), (2021, 3, 14));
+    assert!(booking.has_time());
+  }
+}
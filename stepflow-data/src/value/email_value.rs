@@ -1,14 +1,17 @@
-use std::borrow::{Borrow, Cow};
+use std::borrow::Cow;
 use std::str::FromStr;
+use std::sync::Arc;
 use super::{Value, BaseValue, InvalidValue};
 
 
 /// The implementation for an email [`value`](crate::value::Value).
 ///
 /// NOTE: this is a really basic e-mail validity check and misses several cases.
+///
+/// Like [`StringValue`](super::StringValue), the string is kept in an `Arc<str>` so it can be shared by a [`StringInterner`](super::StringInterner).
 #[derive(Debug, PartialEq, Clone)]
 pub struct EmailValue {
-  val: Cow<'static, str>,
+  val: Arc<str>,
 }
 
 impl EmailValue {
@@ -16,11 +19,17 @@ impl EmailValue {
       where STR: Into<Cow<'static, str>>
   {
     let val = val.into();
+    Self::validate(&val)?;
+    Ok(Self { val: Arc::from(val.as_ref()) })
+  }
+
+  /// Create the value from a string that's already shared
+  pub fn try_from_shared(val: Arc<str>) -> Result<Self, InvalidValue> {
     Self::validate(&val)?;
     Ok(Self { val })
   }
 
-  pub fn validate(val: &Cow<'static, str>) -> Result<(), InvalidValue> {
+  pub fn validate(val: &str) -> Result<(), InvalidValue> {
     if val.is_empty() {
       return Err(InvalidValue::Empty);
     }
@@ -33,7 +42,12 @@ impl EmailValue {
   }
 
   pub fn val(&self) -> &str {
-    self.val.borrow()
+    &self.val
+  }
+
+  /// The shared string
+  pub fn shared(&self) -> &Arc<str> {
+    &self.val
   }
 
   pub fn boxed(self) -> Box<dyn Value> {
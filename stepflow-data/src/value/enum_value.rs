@@ -0,0 +1,62 @@
+use std::str::FromStr;
+use super::{Value, BaseValue, InvalidValue};
+
+
+/// One of the options of an [`EnumVar`](crate::var::EnumVar), i.e. a country or plan tier
+///
+/// The value doesn't know the options. The var checks the value is one of them.
+#[derive(Debug, PartialEq, Clone)]
+pub struct EnumValue {
+  val: String,
+}
+
+impl EnumValue {
+  pub fn try_new<STR>(val: STR) -> Result<Self, InvalidValue>
+      where STR: Into<String>
+  {
+    let val = val.into();
+    Self::validate(&val)?;
+    Ok(Self { val })
+  }
+
+  pub fn validate(val: &str) -> Result<(), InvalidValue> {
+    if val.is_empty() {
+      return Err(InvalidValue::Empty);
+    }
+    Ok(())
+  }
+
+  pub fn val(&self) -> &str {
+    &self.val
+  }
+
+  pub fn boxed(self) -> Box<dyn Value> {
+    Box::new(self)
+  }
+}
+
+define_value_impl!(EnumValue);
+
+impl FromStr for EnumValue {
+  type Err = InvalidValue;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    EnumValue::try_new(s)
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use crate::BaseValue;
+  use super::super::{InvalidValue, Value};
+  use super::EnumValue;
+
+  #[test]
+  fn enum_value() {
+    let plan = "pro".parse::<EnumValue>().unwrap();
+    assert_eq!(plan.val(), "pro");
+    assert_eq!(plan.get_baseval(), BaseValue::String("pro".to_owned()));
+    assert_eq!(EnumValue::try_new(""), Err(InvalidValue::Empty));
+  }
+}
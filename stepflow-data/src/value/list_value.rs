@@ -0,0 +1,130 @@
+use super::{Value, BaseValue, InvalidValue};
+
+/// A list of values for one [`Var`](crate::var::Var), i.e. email addresses entered over repeated steps
+///
+/// Use a [`ListVar`](crate::var::ListVar) to only allow one type of value in the list.
+/// It's stored as a [`BaseValue::List`] of each value's base value.
+/// As a string the values are separated by commas, with a backslash before any comma or backslash in a value
+/// and before whitespace at either end of a value.
+/// ```
+/// # use stepflow_data::BaseValue;
+/// # use stepflow_data::value::{Value, ListValue, EmailValue};
+/// let mut list = ListValue::new(vec![EmailValue::try_new("a@b.com").unwrap().boxed()]);
+/// list.push(EmailValue::try_new("c@d.com").unwrap().boxed());
+/// assert_eq!(list.get_baseval().to_string(), "a@b.com, c@d.com");
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct ListValue {
+  vals: Vec<Box<dyn Value>>,
+}
+
+impl ListValue {
+  pub fn new(vals: Vec<Box<dyn Value>>) -> Self {
+    Self { vals }
+  }
+
+  pub fn vals(&self) -> &[Box<dyn Value>] {
+    &self.vals
+  }
+
+  /// Add a value to the end of the list
+  pub fn push(&mut self, val: Box<dyn Value>) {
+    self.vals.push(val);
+  }
+
+  pub fn boxed(self) -> Box<dyn Value> {
+    Box::new(self)
+  }
+}
+
+/// Escape `s` so [`split_list`] gets it back as one value
+pub(crate) fn escape_list_item(s: &str) -> String {
+  let last = s.chars().count().saturating_sub(1);
+  let mut escaped = String::with_capacity(s.len());
+  for (i, c) in s.chars().enumerate() {
+    if c == ',' || c == '\\' || (c.is_whitespace() && (i == 0 || i == last)) {
+      escaped.push('\\');
+    }
+    escaped.push(c);
+  }
+  escaped
+}
+
+/// Split a list on the commas that aren't escaped, trimming whitespace that isn't escaped and dropping empty values
+pub(crate) fn split_list(s: &str) -> Result<Vec<String>, InvalidValue> {
+  let mut items = Vec::new();
+  let mut item: Vec<(char, bool)> = Vec::new();
+  let mut chars = s.chars();
+  let mut finish = |item: &mut Vec<(char, bool)>| {
+    let start = item.iter().position(|(c, escaped)| *escaped || !c.is_whitespace()).unwrap_or(item.len());
+    let end = item.iter().rposition(|(c, escaped)| *escaped || !c.is_whitespace()).map(|i| i + 1).unwrap_or(start);
+    if start < end {
+      items.push(item[start..end].iter().map(|(c, _)| c).collect::<String>());
+    }
+    item.clear();
+  };
+  while let Some(c) = chars.next() {
+    match c {
+      '\\' => item.push((chars.next().ok_or(InvalidValue::BadFormat)?, true)),
+      ',' => finish(&mut item),
+      _ => item.push((c, false)),
+    }
+  }
+  finish(&mut item);
+  Ok(items)
+}
+
+impl Value for ListValue {
+  fn get_baseval(&self) -> BaseValue {
+    BaseValue::List(self.vals.iter().map(|val| val.get_baseval()).collect())
+  }
+  fn type_tag(&self) -> &'static str {
+    "ListValue"
+  }
+  fn clone_box(&self) -> Box<dyn Value> {
+    Box::new(self.clone())
+  }
+  fn eq_box(&self, other: &Box<dyn Value>) -> bool {
+    // compare the values too since the list's base value loses their types
+    other.downcast::<Self>().map(|other| other == self).unwrap_or(false)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::{BaseValue, InvalidValue};
+  use crate::value::{Value, StringValue, EmailValue};
+  use super::{ListValue, escape_list_item, split_list};
+
+  #[test]
+  fn base_value() {
+    let list = ListValue::new(vec![
+      StringValue::try_new("a@b.com").unwrap().boxed(),
+      StringValue::try_new("c@d.com").unwrap().boxed(),
+    ]);
+    let expected = BaseValue::List(vec![BaseValue::String("a@b.com".to_owned()), BaseValue::String("c@d.com".to_owned())]);
+    assert_eq!(list.get_baseval(), expected);
+
+    // same base value but different types inside
+    let emails = ListValue::new(vec![
+      EmailValue::try_new("a@b.com").unwrap().boxed(),
+      EmailValue::try_new("c@d.com").unwrap().boxed(),
+    ]);
+    assert_eq!(emails.get_baseval(), expected);
+    assert!(list.boxed() != emails.clone().boxed());
+    assert!(emails.clone().boxed() == emails.boxed());
+  }
+
+  #[test]
+  fn escaping() {
+    let items = vec!["a, b".to_owned(), "back\\slash".to_owned(), " padded ".to_owned(), "plain".to_owned()];
+    let joined = items.iter().map(|item| escape_list_item(item)).collect::<Vec<_>>().join(", ");
+    assert_eq!(joined, "a\\, b, back\\\\slash, \\ padded\\ , plain");
+    assert_eq!(split_list(&joined), Ok(items));
+    assert_eq!(split_list(" a ,, b,"), Ok(vec!["a".to_owned(), "b".to_owned()]));
+    assert_eq!(split_list("a\\"), Err(InvalidValue::BadFormat));
+
+    let list = ListValue::new(vec![StringValue::try_new("a, b").unwrap().boxed(), StringValue::try_new("c").unwrap().boxed()]);
+    assert_eq!(list.get_baseval().to_string(), "a\\, b, c");
+  }
+}
@@ -0,0 +1,64 @@
+use super::{Value, BaseValue, InvalidValue};
+
+define_value!(IntValue, i64);
+
+impl std::str::FromStr for IntValue {
+  type Err = InvalidValue;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let s = s.trim();
+    if s.is_empty() {
+      return Err(InvalidValue::Empty);
+    }
+    s.parse::<i64>()
+      .map(IntValue::new)
+      .map_err(|_| InvalidValue::BadFormat)
+  }
+}
+
+define_value!(FloatValue, f64, validate);
+
+impl FloatValue {
+  /// Only finite numbers are allowed
+  pub fn validate(val: &f64) -> Result<(), InvalidValue> {
+    if val.is_finite() {
+      Ok(())
+    } else {
+      Err(InvalidValue::WrongValue)
+    }
+  }
+}
+
+impl std::str::FromStr for FloatValue {
+  type Err = InvalidValue;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let s = s.trim();
+    if s.is_empty() {
+      return Err(InvalidValue::Empty);
+    }
+    s.parse::<f64>()
+      .map_err(|_| InvalidValue::BadFormat)
+      .and_then(FloatValue::try_new)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::BaseValue;
+  use crate::value::Value;
+  use super::{IntValue, FloatValue, InvalidValue};
+
+  #[test]
+  fn from_str() {
+    assert_eq!(" 42".parse::<IntValue>(), Ok(IntValue::new(42)));
+    assert_eq!("4.2".parse::<IntValue>(), Err(InvalidValue::BadFormat));
+    assert_eq!("".parse::<IntValue>(), Err(InvalidValue::Empty));
+    assert_eq!(IntValue::new(-3).get_baseval(), BaseValue::Int(-3));
+
+    assert_eq!("4.5".parse::<FloatValue>(), Ok(FloatValue::try_new(4.5).unwrap()));
+    assert_eq!("abc".parse::<FloatValue>(), Err(InvalidValue::BadFormat));
+    assert_eq!("NaN".parse::<FloatValue>(), Err(InvalidValue::WrongValue));
+    assert_eq!(FloatValue::try_new(f64::INFINITY), Err(InvalidValue::WrongValue));
+  }
+}
@@ -0,0 +1,100 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use super::{StringValue, EmailValue};
+
+/// Pool of shared strings so identical [`StringValue`]s and [`EmailValue`]s only keep one copy
+///
+/// Flows that copy the same value into many vars otherwise keep a copy per var.
+/// ```
+/// # use std::sync::Arc;
+/// # use stepflow_data::value::{StringValue, StringInterner};
+/// let mut interner = StringInterner::new();
+/// let first = interner.intern_value(&StringValue::try_new("a@b.com").unwrap());
+/// let second = interner.intern_value(&StringValue::try_new("a@b.com").unwrap());
+/// assert!(Arc::ptr_eq(first.shared(), second.shared()));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct StringInterner {
+  strings: HashSet<Arc<str>>,
+}
+
+impl StringInterner {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Number of distinct strings in the pool
+  pub fn len(&self) -> usize {
+    self.strings.len()
+  }
+
+  /// Check if the pool is empty
+  pub fn is_empty(&self) -> bool {
+    self.strings.is_empty()
+  }
+
+  /// Get the shared copy of `val`, adding it to the pool if it's new
+  pub fn intern(&mut self, val: &str) -> Arc<str> {
+    if let Some(shared) = self.strings.get(val) {
+      return shared.clone();
+    }
+    let shared: Arc<str> = Arc::from(val);
+    self.strings.insert(shared.clone());
+    shared
+  }
+
+  /// Get the same value backed by the shared copy of its string
+  pub fn intern_value(&mut self, value: &StringValue) -> StringValue {
+    StringValue::try_from_shared(self.share(value.shared())).unwrap()
+  }
+
+  /// Get the same email backed by the shared copy of its string
+  pub fn intern_email(&mut self, value: &EmailValue) -> EmailValue {
+    EmailValue::try_from_shared(self.share(value.shared())).unwrap()
+  }
+
+  // the pooled copy of `val`, pooling `val` itself if it's new
+  fn share(&mut self, val: &Arc<str>) -> Arc<str> {
+    if let Some(shared) = self.strings.get(val) {
+      return shared.clone();
+    }
+    self.strings.insert(val.clone());
+    val.clone()
+  }
+
+  /// Remove the strings that aren't used anywhere else
+  pub fn purge_unused(&mut self) {
+    self.strings.retain(|shared| Arc::strong_count(shared) > 1);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::Arc;
+  use crate::value::{StringValue, EmailValue};
+  use super::StringInterner;
+
+  #[test]
+  fn intern() {
+    let mut interner = StringInterner::new();
+    let hi = interner.intern("hi");
+    assert!(Arc::ptr_eq(&hi, &interner.intern("hi")));
+    assert!(!Arc::ptr_eq(&hi, &interner.intern("bye")));
+    assert_eq!(interner.len(), 2);
+
+    // values keep their existing string when it's the first one
+    let value = StringValue::try_new("new").unwrap();
+    assert!(Arc::ptr_eq(interner.intern_value(&value).shared(), value.shared()));
+    assert_eq!(interner.intern_value(&StringValue::try_new("hi").unwrap()), StringValue::try_new("hi").unwrap());
+
+    // emails share the same pool
+    let email = interner.intern_email(&EmailValue::try_new("a@b.com").unwrap());
+    assert!(Arc::ptr_eq(email.shared(), &interner.intern("a@b.com")));
+    assert_eq!(interner.len(), 4);
+
+    drop(value);
+    drop(email);
+    interner.purge_unused();
+    assert_eq!(interner.len(), 1);
+  }
+}
@@ -1,9 +1,13 @@
-use std::borrow::{Borrow, Cow};
+use std::borrow::Cow;
+use std::sync::Arc;
 use super::{Value, BaseValue, InvalidValue};
 
+/// A non-empty string
+///
+/// The string is kept in an `Arc<str>` so clones share it. See [`StringInterner`](super::StringInterner) to share identical strings.
 #[derive(Debug, PartialEq, Clone)]
 pub struct StringValue {
-  val: Cow<'static, str>,
+  val: Arc<str>,
 }
 
 impl StringValue {
@@ -11,11 +15,17 @@ impl StringValue {
       where STR: Into<Cow<'static, str>>
   {
     let val = val.into();
+    Self::validate(&val)?;
+    Ok(Self { val: Arc::from(val.as_ref()) })
+  }
+
+  /// Create the value from a string that's already shared
+  pub fn try_from_shared(val: Arc<str>) -> Result<Self, InvalidValue> {
     Self::validate(&val)?;
     Ok(Self { val })
   }
 
-  pub fn validate(val: &Cow<'static, str>) -> Result<(), InvalidValue> {
+  pub fn validate(val: &str) -> Result<(), InvalidValue> {
     if val.is_empty() {
       return Err(InvalidValue::Empty);
     }
@@ -23,7 +33,12 @@ impl StringValue {
   }
 
   pub fn val(&self) -> &str {
-    self.val.borrow()
+    &self.val
+  }
+
+  /// The shared string
+  pub fn shared(&self) -> &Arc<str> {
+    &self.val
   }
   pub fn boxed(self) -> Box<dyn Value> {
     Box::new(self)
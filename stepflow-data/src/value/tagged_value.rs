@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+use super::{Value, BaseValue, InvalidValue, StringValue, EmailValue, BoolValue, TrueValue, UnansweredValue, IntValue, FloatValue, DateTimeValue, EnumValue, ListValue};
+
+/// A [`Value`] saved as its [`BaseValue`] along with its [`type_tag`](Value::type_tag)
+///
+/// Plain serialization of a value only keeps the base value, so it comes back as a string, bool or float.
+/// A [`ValueRegistry`] uses the tag to rebuild the same type of value.
+/// A [`ListValue`] keeps each of its values tagged in `elements`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde-support", derive(serde::Serialize, serde::Deserialize))]
+pub struct TaggedValue {
+  pub tag: String,
+  pub value: BaseValue,
+
+  #[cfg_attr(feature = "serde-support", serde(default, skip_serializing_if = "Vec::is_empty"))]
+  pub elements: Vec<TaggedValue>,
+}
+
+impl TaggedValue {
+  pub fn new(value: &dyn Value) -> Self {
+    let elements = match value.as_any().downcast_ref::<ListValue>() {
+      Some(list) => list.vals().iter().map(|val| TaggedValue::new(val.as_ref())).collect(),
+      None => Vec::new(),
+    };
+    Self {
+      tag: value.type_tag().to_owned(),
+      value: value.get_baseval(),
+      elements,
+    }
+  }
+}
+
+const LIST_TAG: &str = "ListValue";
+
+/// Rebuilds a [`Value`] from its [`BaseValue`]
+pub type ValueConstructor = fn(BaseValue) -> Result<Box<dyn Value>, InvalidValue>;
+
+/// Rebuilds [`TaggedValue`]s as the type of value they were saved from
+///
+/// Every value type has to be able to rebuild itself from the [`BaseValue`] it returns in [`get_baseval`](Value::get_baseval).
+/// Register a constructor for each custom value under its [`type_tag`](Value::type_tag).
+/// ```
+/// # use stepflow_data::value::{EmailValue, TaggedValue, ValueRegistry};
+/// let registry = ValueRegistry::with_builtins();
+/// let tagged = TaggedValue::new(&EmailValue::try_new("a@b.com").unwrap());
+/// assert!(registry.from_tagged(&tagged).unwrap().is::<EmailValue>());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ValueRegistry {
+  constructors: HashMap<String, ValueConstructor>,
+}
+
+impl ValueRegistry {
+  /// Create a registry without any values
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Create a registry with the built-in values
+  pub fn with_builtins() -> Self {
+    let mut registry = Self::new();
+    registry.register("StringValue", |base| match base {
+      BaseValue::String(s) => Ok(StringValue::try_new(s)?.boxed()),
+      _ => Err(InvalidValue::WrongType),
+    });
+    registry.register("EmailValue", |base| match base {
+      BaseValue::String(s) => Ok(EmailValue::try_new(s)?.boxed()),
+      _ => Err(InvalidValue::WrongType),
+    });
+    registry.register("DateTimeValue", |base| match base {
+      BaseValue::String(s) => Ok(DateTimeValue::try_new(s)?.boxed()),
+      _ => Err(InvalidValue::WrongType),
+    });
+    registry.register("EnumValue", |base| match base {
+      BaseValue::String(s) => Ok(EnumValue::try_new(s)?.boxed()),
+      _ => Err(InvalidValue::WrongType),
+    });
+    registry.register("BoolValue", |base| match base {
+      BaseValue::Boolean(b) => Ok(BoolValue::new(b).boxed()),
+      _ => Err(InvalidValue::WrongType),
+    });
+    registry.register("TrueValue", |base| match base {
+      BaseValue::Boolean(true) => Ok(TrueValue::new().boxed()),
+      BaseValue::Boolean(false) => Err(InvalidValue::WrongValue),
+      _ => Err(InvalidValue::WrongType),
+    });
+    registry.register("IntValue", |base| match base {
+      BaseValue::Int(i) => Ok(IntValue::new(i).boxed()),
+      _ => Err(InvalidValue::WrongType),
+    });
+    registry.register("FloatValue", |base| match base {
+      BaseValue::Float(float) => Ok(FloatValue::try_new(float)?.boxed()),
+      BaseValue::Int(i) => Ok(FloatValue::try_new(i as f64)?.boxed()),
+      _ => Err(InvalidValue::WrongType),
+    });
+    registry.register("UnansweredValue", |base| match base {
+      BaseValue::Null => Ok(UnansweredValue::new().boxed()),
+      _ => Err(InvalidValue::WrongType),
+    });
+    // the values are rebuilt from their tags in `from_tagged`, this only sees lists saved without them
+    registry.register(LIST_TAG, |base| match base {
+      BaseValue::List(vals) if vals.is_empty() => Ok(ListValue::new(Vec::new()).boxed()),
+      _ => Err(InvalidValue::WrongType),
+    });
+    registry
+  }
+
+  /// Add a constructor for the values with `tag`. Replaces any constructor with the same tag.
+  pub fn register<STR>(&mut self, tag: STR, constructor: ValueConstructor)
+      where STR: Into<String>
+  {
+    self.constructors.insert(tag.into(), constructor);
+  }
+
+  /// Rebuild the value. Fails with [`InvalidValue::WrongType`] if the tag, or the tag of a value in a list, isn't registered.
+  pub fn from_tagged(&self, tagged: &TaggedValue) -> Result<Box<dyn Value>, InvalidValue> {
+    let constructor = self.constructors.get(&tagged.tag).ok_or(InvalidValue::WrongType)?;
+    if tagged.tag == LIST_TAG && !tagged.elements.is_empty() {
+      let vals = tagged.elements.iter()
+        .map(|element| self.from_tagged(element))
+        .collect::<Result<Vec<_>, _>>()?;
+      return Ok(ListValue::new(vals).boxed());
+    }
+    constructor(tagged.value.clone())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::{BaseValue, InvalidValue};
+  use crate::value::{Value, StringValue, EmailValue, BoolValue, TrueValue, UnansweredValue, IntValue, FloatValue, DateTimeValue, EnumValue, ListValue};
+  use super::{TaggedValue, ValueRegistry};
+
+  #[test]
+  fn round_trip() {
+    let registry = ValueRegistry::with_builtins();
+    let values: Vec<Box<dyn Value>> = vec![
+      StringValue::try_new("hi").unwrap().boxed(),
+      EmailValue::try_new("a@b.com").unwrap().boxed(),
+      BoolValue::new(false).boxed(),
+      TrueValue::new().boxed(),
+      UnansweredValue::new().boxed(),
+      IntValue::new(7).boxed(),
+      FloatValue::try_new(7.5).unwrap().boxed(),
+      DateTimeValue::try_new("2021-03-14T15:09Z").unwrap().boxed(),
+      EnumValue::try_new("pro").unwrap().boxed(),
+      ListValue::new(vec![EmailValue::try_new("a@b.com").unwrap().boxed(), IntValue::new(3).boxed()]).boxed(),
+      ListValue::new(vec![]).boxed(),
+    ];
+    for value in values {
+      assert_eq!(registry.from_tagged(&TaggedValue::new(value.as_ref())), Ok(value));
+    }
+
+    let unknown = TaggedValue { tag: "ColorValue".to_owned(), value: BaseValue::String("red".to_owned()), elements: vec![] };
+    assert_eq!(registry.from_tagged(&unknown), Err(InvalidValue::WrongType));
+    let mut unknown_element = TaggedValue::new(&ListValue::new(vec![StringValue::try_new("red").unwrap().boxed()]));
+    unknown_element.elements[0].tag = "ColorValue".to_owned();
+    assert_eq!(registry.from_tagged(&unknown_element), Err(InvalidValue::WrongType));
+    let bad_email = TaggedValue { tag: "EmailValue".to_owned(), value: BaseValue::String("bad".to_owned()), elements: vec![] };
+    assert_eq!(registry.from_tagged(&bad_email), Err(InvalidValue::BadFormat));
+  }
+}
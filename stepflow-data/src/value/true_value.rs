@@ -17,6 +17,10 @@ impl Value for TrueValue {
     BaseValue::Boolean(true)
   }
 
+  fn type_tag(&self) -> &'static str {
+    "TrueValue"
+  }
+
   fn clone_box(&self) -> Box<dyn Value> {
     Box::new(self.clone())
   }
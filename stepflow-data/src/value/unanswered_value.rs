@@ -0,0 +1,57 @@
+use super::{Value, BaseValue};
+
+/// The question was asked and the user skipped it
+///
+/// Only vars that [allow it](crate::var::Var::allows_unanswered) take this value. A var without a value was never answered at all,
+/// so a resumed flow asks it again while a skipped var stays skipped.
+/// ```
+/// # use stepflow_data::{StateData, var::{VarId, BoolVar}, value::UnansweredValue};
+/// let newsletter = BoolVar::new(VarId::new(0)).allow_unanswered().boxed();
+/// let mut state_data = StateData::new();
+/// state_data.insert(&newsletter, UnansweredValue::new().boxed()).unwrap();
+/// assert!(state_data.is_unanswered(newsletter.id()));
+/// ```
+#[derive(Debug, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "serde-support", derive(serde::Serialize))]
+pub struct UnansweredValue;
+
+impl UnansweredValue {
+  /// Prefix of the form field that skips a var, i.e. `skip:newsletter` skips the `newsletter` var
+  pub const SKIP_FIELD_PREFIX: &'static str = "skip:";
+
+  pub fn new() -> Self { Self {} }
+  pub fn boxed(self) -> Box<dyn Value> { Box::new(self) }
+}
+
+impl Value for UnansweredValue {
+  fn get_baseval(&self) -> BaseValue {
+    BaseValue::Null
+  }
+
+  fn type_tag(&self) -> &'static str {
+    "UnansweredValue"
+  }
+
+  fn clone_box(&self) -> Box<dyn Value> {
+    Box::new(self.clone())
+  }
+
+  fn eq_box(&self, other: &Box<dyn Value>) -> bool {
+    other.is::<Self>()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::BaseValue;
+  use crate::value::BoolValue;
+  use super::UnansweredValue;
+
+  #[test]
+  fn unanswered() {
+    let unanswered = UnansweredValue::new().boxed();
+    assert_eq!(unanswered.get_baseval(), BaseValue::Null);
+    assert!(unanswered == UnansweredValue::new().boxed());
+    assert!(unanswered != BoolValue::new(false).boxed());
+  }
+}
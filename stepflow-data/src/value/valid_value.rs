@@ -1,5 +1,5 @@
 use super::InvalidValue;
-use super::Value;
+use super::{Value, UnansweredValue};
 use crate::var::{Var, VarId};
 
 #[derive(Debug, Clone)]
@@ -11,7 +11,12 @@ pub struct ValidVal {
 
 impl ValidVal {
   pub fn try_new(val: Box<dyn Value>, validate_with: &Box<dyn Var + Send + Sync>) -> Result<Self, InvalidValue> {
-    match validate_with.validate_val_type(&val) {
+    let validated = if val.is::<UnansweredValue>() && validate_with.allows_unanswered() {
+      Ok(())
+    } else {
+      validate_with.validate_val_type(&val)
+    };
+    match validated {
       Ok(_) => Ok(Self { 
         val: val, 
         validated_by: validate_with.id().clone() 
@@ -23,6 +28,11 @@ impl ValidVal {
   pub fn get_val(&self) -> &Box<dyn Value> {
     &self.val
   }
+
+  // only for swapping in an equal value, i.e. when interning
+  pub(crate) fn get_val_mut(&mut self) -> &mut Box<dyn Value> {
+    &mut self.val
+  }
 }
 
 impl PartialEq for ValidVal {
@@ -34,7 +44,7 @@ impl PartialEq for ValidVal {
 #[cfg(test)]
 mod tests {
   use stepflow_test_util::test_id;
-  use crate::{var::{Var, VarId, EmailVar, StringVar}, value::{EmailValue, StringValue}};
+  use crate::{InvalidValue, var::{Var, VarId, EmailVar, StringVar}, value::{EmailValue, StringValue, UnansweredValue}};
   use super::ValidVal;
 
   #[test]
@@ -61,4 +71,13 @@ mod tests {
     assert_ne!(valid_email, valid_email_different);
     assert_ne!(valid_email, valid_string);
   }
-}
\ No newline at end of file
+
+  #[test]
+  fn unanswered() {
+    let required = EmailVar::new(test_id!(VarId)).boxed();
+    let skippable = EmailVar::new(test_id!(VarId)).allow_unanswered().boxed();
+    assert_eq!(ValidVal::try_new(UnansweredValue::new().boxed(), &required), Err(InvalidValue::WrongType));
+    assert!(ValidVal::try_new(UnansweredValue::new().boxed(), &skippable).is_ok());
+    assert!(ValidVal::try_new(EmailValue::try_new("a@b.com").unwrap().boxed(), &skippable).is_ok());
+  }
+}
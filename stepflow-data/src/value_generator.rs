@@ -0,0 +1,147 @@
+use super::var::{Var, NumberVar};
+
+// strings of every kind of value. `for_var` sorts them by what the var accepts.
+const SAMPLES: &[&str] = &[
+  "Jane", "Jane Doe", "jane@example.com", "a@b.com, c@d.com", "2021-03-14", "2021-03-14T15:09:26Z",
+  "true", "false", "0", "1", "42", "3.5", "-7",
+  "", "not-an-email", "maybe", "2021-13-40", "a@b.com, nope",
+];
+
+/// Weighted candidate values for a [`Var`], valid and invalid, i.e. to fill in forms while load testing
+///
+/// Values are strings, the same as a posted form, so invalid ones exercise [`Var::value_from_str`].
+/// ```
+/// # use stepflow_data::{ValueGenerator, var::{Var, VarId, EmailVar}};
+/// let email = EmailVar::new(VarId::new(0)).boxed();
+/// let generator = ValueGenerator::for_var(email.as_ref()).with_valid("vip@example.com", 10);
+/// let (val, valid) = generator.sample(7, 0).unwrap();
+/// assert!(valid);
+/// assert!(email.value_from_str(val).is_ok());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ValueGenerator {
+  valid: Vec<(String, u32)>,
+  invalid: Vec<(String, u32)>,
+}
+
+impl ValueGenerator {
+  /// A generator without any values
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Sample values sorted by whether `var` accepts them, each with a weight of 1.
+  ///
+  /// [`NumberVar`]s also get the edges of their range and values just outside it, and vars with [`choices`](Var::choices) get each choice.
+  pub fn for_var(var: &(dyn Var + Send + Sync + 'static)) -> Self {
+    let mut samples = SAMPLES.iter().map(|s| (*s).to_owned()).collect::<Vec<_>>();
+    if let Some(number_var) = var.downcast::<NumberVar>() {
+      let step = if number_var.is_int() { 1.0 } else { 0.5 };
+      for edge in number_var.min().iter().chain(number_var.max().iter()) {
+        samples.extend(vec![edge - step, *edge, edge + step].into_iter().map(|num| num.to_string()));
+      }
+    }
+    samples.extend(var.choices().into_iter().flatten().cloned());
+    samples.into_iter().fold(Self::new(), |generator, sample| {
+      if var.value_from_str(&sample).is_ok() {
+        generator.with_valid(&sample, 1)
+      } else {
+        generator.with_invalid(&sample, 1)
+      }
+    })
+  }
+
+  /// Add a value the var accepts. Higher weights are picked more often.
+  pub fn with_valid(mut self, val: &str, weight: u32) -> Self {
+    self.valid.push((val.to_owned(), weight));
+    self
+  }
+
+  /// Add a value the var rejects. Higher weights are picked more often.
+  pub fn with_invalid(mut self, val: &str, weight: u32) -> Self {
+    self.invalid.push((val.to_owned(), weight));
+    self
+  }
+
+  pub fn valid(&self) -> &[(String, u32)] {
+    &self.valid
+  }
+
+  pub fn invalid(&self) -> &[(String, u32)] {
+    &self.invalid
+  }
+
+  /// Pick a value and whether it's valid. `random` is any random number, i.e. from your own generator.
+  ///
+  /// An invalid value is picked `invalid_percent` of the time, or whenever there are no valid values.
+  pub fn sample(&self, random: u64, invalid_percent: u8) -> Option<(&str, bool)> {
+    let pick_invalid = self.valid.is_empty() || (random % 100) < invalid_percent as u64;
+    let (candidates, valid) = if pick_invalid && !self.invalid.is_empty() {
+      (&self.invalid, false)
+    } else {
+      (&self.valid, true)
+    };
+
+    let total_weight = candidates.iter().map(|(_, weight)| *weight as u64).sum::<u64>();
+    if total_weight == 0 {
+      return None;
+    }
+    let mut roll = (random / 100) % total_weight;
+    for (val, weight) in candidates {
+      if roll < *weight as u64 {
+        return Some((val, valid));
+      }
+      roll -= *weight as u64;
+    }
+    None
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use stepflow_test_util::test_id;
+  use crate::var::{VarId, BoolVar, NumberVar, EnumVar};
+  use super::ValueGenerator;
+
+  #[test]
+  fn for_var() {
+    let agreed = BoolVar::new(test_id!(VarId)).boxed();
+    let generator = ValueGenerator::for_var(agreed.as_ref());
+    let valid = generator.valid().iter().map(|(val, _)| val.as_str()).collect::<Vec<_>>();
+    assert_eq!(valid, vec!["true", "false"]);
+    assert!(generator.invalid().iter().all(|(val, _)| agreed.value_from_str(val).is_err()));
+
+    // edges of the range
+    let age = NumberVar::new_int(test_id!(VarId)).with_range(Some(18.0), Some(99.0)).boxed();
+    let generator = ValueGenerator::for_var(age.as_ref());
+    let valid = generator.valid().iter().map(|(val, _)| val.as_str()).collect::<Vec<_>>();
+    assert_eq!(valid, vec!["42", "18", "19", "98", "99"]);
+    assert!(generator.invalid().iter().any(|(val, _)| val == "17"));
+    assert!(generator.invalid().iter().any(|(val, _)| val == "100"));
+
+    let plan = EnumVar::new(test_id!(VarId), vec!["free", "pro"]).boxed();
+    let valid = ValueGenerator::for_var(plan.as_ref()).valid().iter().map(|(val, _)| val.clone()).collect::<Vec<_>>();
+    assert_eq!(valid, vec!["free", "pro"]);
+  }
+
+  #[test]
+  fn sample() {
+    let generator = ValueGenerator::new()
+      .with_valid("rare", 1)
+      .with_valid("common", 3)
+      .with_invalid("bad", 1);
+
+    // hundreds pick the candidate and the rest picks valid or invalid
+    assert_eq!(generator.sample(50, 10), Some(("rare", true)));
+    assert_eq!(generator.sample(150, 10), Some(("common", true)));
+    assert_eq!(generator.sample(350, 10), Some(("common", true)));
+    assert_eq!(generator.sample(405, 10), Some(("bad", false)));
+    assert_eq!(generator.sample(405, 0), Some(("rare", true)));
+
+    let picks = (0..400).map(|random| generator.sample(random * 100 + 50, 0).unwrap().0).filter(|val| *val == "common").count();
+    assert_eq!(picks, 300);
+
+    assert_eq!(ValueGenerator::new().sample(0, 0), None);
+    assert_eq!(ValueGenerator::new().with_invalid("bad", 1).sample(99, 0), Some(("bad", false)));
+  }
+}
@@ -3,15 +3,88 @@
 //!
 //! When needed, they can be downcast to their original type via `Var::downcast` and `Var::is`.
 use stepflow_base::{ObjectStoreContent, IdError, generate_id_type};
-use super::InvalidValue;
+use super::{InvalidValue, BaseValueKind};
 use super::value::Value;
 
 generate_id_type!(VarId);
 
+/// Presentation hints for a [`Var`], such as those used when generating a form
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde-support", derive(serde::Serialize))]
+pub struct VarMetadata {
+  /// Example text shown before a value is entered
+  pub placeholder: Option<String>,
+
+  /// Hint for auto-filling the value, i.e. `email` or `given-name`
+  pub autocomplete: Option<String>,
+
+  /// Section the var belongs to when rendered with other vars
+  pub group: Option<VarGroup>,
+
+  /// Keep the value out of logs and error reports, i.e. a password
+  pub sensitive: bool,
+}
+
+impl VarMetadata {
+  const EMPTY: VarMetadata = VarMetadata {
+    placeholder: None,
+    autocomplete: None,
+    group: None,
+    sensitive: false,
+  };
+}
+
+/// Named section of vars, such as a `<fieldset>` in a form
+///
+/// Sections are shown by `order`, lowest first. Vars in the same section keep their relative order.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde-support", derive(serde::Serialize))]
+pub struct VarGroup {
+  pub name: String,
+  pub order: i32,
+}
+
+impl VarGroup {
+  /// Create a new group
+  pub fn new<STR: Into<String>>(name: STR, order: i32) -> Self {
+    VarGroup { name: name.into(), order }
+  }
+}
+
 pub trait Var: std::fmt::Debug + stepflow_base::as_any::AsAny {
   fn id(&self) -> &VarId;
   fn value_from_str(&self, s: &str) -> Result<Box<dyn Value>, InvalidValue>;
   fn validate_val_type(&self, val: &Box<dyn Value>) -> Result<(), InvalidValue>;
+
+  /// Get the presentation hints. Vars without any have empty metadata.
+  fn metadata(&self) -> &VarMetadata {
+    &VarMetadata::EMPTY
+  }
+
+  /// Get the presentation hints to change them. `None` if the var doesn't support metadata.
+  fn metadata_mut(&mut self) -> Option<&mut VarMetadata> {
+    None
+  }
+
+  /// Get the only values allowed, in their string form. `None` if any value of the type is allowed.
+  fn choices(&self) -> Option<&[String]> {
+    None
+  }
+
+  /// Get the kind of [`BaseValue`](crate::BaseValue) the var's values are stored as. `None` if it isn't known.
+  fn base_value_kind(&self) -> Option<BaseValueKind> {
+    None
+  }
+
+  /// Get the type of [`Value`] the var takes. `None` if it isn't known.
+  fn value_type(&self) -> Option<std::any::TypeId> {
+    None
+  }
+
+  /// Whether the var takes an [`UnansweredValue`](crate::value::UnansweredValue) when the user skips it
+  fn allows_unanswered(&self) -> bool {
+    false
+  }
 }
 
 // implement downcast helpers that have trait bounds to make it a little safer
@@ -41,16 +114,30 @@ impl ObjectStoreContent for Box<dyn Var + Sync + Send> {
 }
 
 macro_rules! define_var {
-  ($name:ident, $valuetype:ident) => {
+  ($name:ident, $valuetype:ident, $kind:ident) => {
 
     #[derive(Debug)]
     pub struct $name {
       id: VarId,
+      metadata: VarMetadata,
+      allows_unanswered: bool,
     }
     impl $name {
       /// Create a new var
       pub fn new(id: VarId) -> Self {
-        Self { id }
+        Self { id, metadata: VarMetadata::default(), allows_unanswered: false }
+      }
+
+      /// Set the presentation hints
+      pub fn with_metadata(mut self, metadata: VarMetadata) -> Self {
+        self.metadata = metadata;
+        self
+      }
+
+      /// Let the user skip the var, leaving an [`UnansweredValue`](crate::value::UnansweredValue)
+      pub fn allow_unanswered(mut self) -> Self {
+        self.allows_unanswered = true;
+        self
       }
 
       /// Box the value
@@ -75,21 +162,398 @@ macro_rules! define_var {
           Err(InvalidValue::WrongType)
         }
       }
+
+      fn metadata(&self) -> &VarMetadata { &self.metadata }
+
+      fn metadata_mut(&mut self) -> Option<&mut VarMetadata> { Some(&mut self.metadata) }
+
+      fn base_value_kind(&self) -> Option<BaseValueKind> { Some(BaseValueKind::$kind) }
+
+      fn value_type(&self) -> Option<std::any::TypeId> { Some(std::any::TypeId::of::<$valuetype>()) }
+
+      fn allows_unanswered(&self) -> bool { self.allows_unanswered }
     }
   };
 }
 
 use super::value::EmailValue;
-define_var!(EmailVar, EmailValue);
+define_var!(EmailVar, EmailValue, String);
 
 use super::value::StringValue;
-define_var!(StringVar, StringValue);
+define_var!(StringVar, StringValue, String);
 
 use super::value::TrueValue;
-define_var!(TrueVar, TrueValue);
+define_var!(TrueVar, TrueValue, Boolean);
 
 use super::value::BoolValue;
-define_var!(BoolVar, BoolValue);
+define_var!(BoolVar, BoolValue, Boolean);
+
+use super::value::{IntValue, FloatValue};
+
+/// A var for numbers with an optional range, either [`IntValue`]s or [`FloatValue`]s
+/// ```
+/// # use stepflow_data::{InvalidValue, var::{Var, VarId, NumberVar}};
+/// let age = NumberVar::new_int(VarId::new(0)).with_range(Some(18.0), None);
+/// assert!(age.value_from_str("21").is_ok());
+/// assert_eq!(age.value_from_str("16"), Err(InvalidValue::OutOfRange));
+/// ```
+#[derive(Debug)]
+pub struct NumberVar {
+  id: VarId,
+  metadata: VarMetadata,
+  allows_unanswered: bool,
+  is_int: bool,
+  min: Option<f64>,
+  max: Option<f64>,
+}
+
+impl NumberVar {
+  /// Create a new var for [`FloatValue`]s
+  pub fn new(id: VarId) -> Self {
+    Self { id, metadata: VarMetadata::default(), allows_unanswered: false, is_int: false, min: None, max: None }
+  }
+
+  /// Create a new var for [`IntValue`]s
+  pub fn new_int(id: VarId) -> Self {
+    Self { is_int: true, ..Self::new(id) }
+  }
+
+  /// Only allow numbers from `min` to `max`, inclusive
+  pub fn with_range(mut self, min: Option<f64>, max: Option<f64>) -> Self {
+    self.min = min;
+    self.max = max;
+    self
+  }
+
+  /// Set the presentation hints
+  pub fn with_metadata(mut self, metadata: VarMetadata) -> Self {
+    self.metadata = metadata;
+    self
+  }
+
+  /// Let the user skip the var, leaving an [`UnansweredValue`](crate::value::UnansweredValue)
+  pub fn allow_unanswered(mut self) -> Self {
+    self.allows_unanswered = true;
+    self
+  }
+
+  /// Whether the var takes [`IntValue`]s instead of [`FloatValue`]s
+  pub fn is_int(&self) -> bool {
+    self.is_int
+  }
+
+  /// The smallest number allowed
+  pub fn min(&self) -> Option<f64> {
+    self.min
+  }
+
+  /// The largest number allowed
+  pub fn max(&self) -> Option<f64> {
+    self.max
+  }
+
+  /// Box the value
+  pub fn boxed(self) -> Box<dyn Var + Send + Sync> {
+    Box::new(self)
+  }
+
+  fn check_range(&self, num: f64) -> Result<(), InvalidValue> {
+    if self.min.map(|min| num < min).unwrap_or(false) || self.max.map(|max| num > max).unwrap_or(false) {
+      return Err(InvalidValue::OutOfRange);
+    }
+    Ok(())
+  }
+}
+
+impl Var for NumberVar {
+  fn id(&self) -> &VarId { &self.id }
+
+  fn value_from_str(&self, s: &str) -> Result<Box<dyn Value>, InvalidValue> {
+    let val = if self.is_int {
+      s.parse::<IntValue>()?.boxed()
+    } else {
+      s.parse::<FloatValue>()?.boxed()
+    };
+    self.validate_val_type(&val)?;
+    Ok(val)
+  }
+
+  fn validate_val_type(&self, val: &Box<dyn Value>) -> Result<(), InvalidValue> {
+    let num = if self.is_int {
+      val.downcast::<IntValue>().map(|int_val| *int_val.val() as f64)
+    } else {
+      val.downcast::<FloatValue>().map(|float_val| *float_val.val())
+    };
+    self.check_range(num.ok_or(InvalidValue::WrongType)?)
+  }
+
+  fn metadata(&self) -> &VarMetadata { &self.metadata }
+
+  fn metadata_mut(&mut self) -> Option<&mut VarMetadata> { Some(&mut self.metadata) }
+
+  fn base_value_kind(&self) -> Option<BaseValueKind> {
+    Some(if self.is_int { BaseValueKind::Int } else { BaseValueKind::Float })
+  }
+
+  fn value_type(&self) -> Option<std::any::TypeId> {
+    Some(if self.is_int { std::any::TypeId::of::<IntValue>() } else { std::any::TypeId::of::<FloatValue>() })
+  }
+
+  fn allows_unanswered(&self) -> bool { self.allows_unanswered }
+}
+
+use super::value::DateTimeValue;
+
+/// A var for [`DateTimeValue`]s, either dates or dates with times
+/// ```
+/// # use stepflow_data::{InvalidValue, var::{Var, VarId, DateTimeVar}};
+/// let birthdate = DateTimeVar::new(VarId::new(0)).dates_only();
+/// assert!(birthdate.value_from_str("1990-07-04").is_ok());
+/// assert_eq!(birthdate.value_from_str("1990-07-04T08:30"), Err(InvalidValue::WrongValue));
+/// ```
+#[derive(Debug)]
+pub struct DateTimeVar {
+  id: VarId,
+  metadata: VarMetadata,
+  allows_unanswered: bool,
+  dates_only: bool,
+}
+
+impl DateTimeVar {
+  /// Create a new var that takes dates with or without a time
+  pub fn new(id: VarId) -> Self {
+    Self { id, metadata: VarMetadata::default(), allows_unanswered: false, dates_only: false }
+  }
+
+  /// Only allow dates without a time, i.e. a birthdate
+  pub fn dates_only(mut self) -> Self {
+    self.dates_only = true;
+    self
+  }
+
+  /// Set the presentation hints
+  pub fn with_metadata(mut self, metadata: VarMetadata) -> Self {
+    self.metadata = metadata;
+    self
+  }
+
+  /// Let the user skip the var, leaving an [`UnansweredValue`](crate::value::UnansweredValue)
+  pub fn allow_unanswered(mut self) -> Self {
+    self.allows_unanswered = true;
+    self
+  }
+
+  /// Whether the var takes times as well as dates
+  pub fn accepts_time(&self) -> bool {
+    !self.dates_only
+  }
+
+  /// Box the value
+  pub fn boxed(self) -> Box<dyn Var + Send + Sync> {
+    Box::new(self)
+  }
+}
+
+impl Var for DateTimeVar {
+  fn id(&self) -> &VarId { &self.id }
+
+  fn value_from_str(&self, s: &str) -> Result<Box<dyn Value>, InvalidValue> {
+    let val = s.parse::<DateTimeValue>()?.boxed();
+    self.validate_val_type(&val)?;
+    Ok(val)
+  }
+
+  fn validate_val_type(&self, val: &Box<dyn Value>) -> Result<(), InvalidValue> {
+    let datetime = val.downcast::<DateTimeValue>().ok_or(InvalidValue::WrongType)?;
+    if self.dates_only && datetime.has_time() {
+      return Err(InvalidValue::WrongValue);
+    }
+    Ok(())
+  }
+
+  fn metadata(&self) -> &VarMetadata { &self.metadata }
+
+  fn metadata_mut(&mut self) -> Option<&mut VarMetadata> { Some(&mut self.metadata) }
+
+  fn base_value_kind(&self) -> Option<BaseValueKind> { Some(BaseValueKind::String) }
+
+  fn value_type(&self) -> Option<std::any::TypeId> { Some(std::any::TypeId::of::<DateTimeValue>()) }
+
+  fn allows_unanswered(&self) -> bool { self.allows_unanswered }
+}
+
+use super::value::EnumValue;
+
+/// A var for an [`EnumValue`] that's one of a fixed set of options, i.e. a dropdown of countries
+/// ```
+/// # use stepflow_data::{InvalidValue, var::{Var, VarId, EnumVar}};
+/// let plan = EnumVar::new(VarId::new(0), vec!["free", "pro"]);
+/// assert!(plan.value_from_str("pro").is_ok());
+/// assert_eq!(plan.value_from_str("gold"), Err(InvalidValue::WrongValue));
+/// ```
+#[derive(Debug)]
+pub struct EnumVar {
+  id: VarId,
+  metadata: VarMetadata,
+  allows_unanswered: bool,
+  options: Vec<String>,
+}
+
+impl EnumVar {
+  /// Create a new var that allows only `options`, shown in the same order
+  pub fn new<STR: Into<String>>(id: VarId, options: Vec<STR>) -> Self {
+    Self {
+      id,
+      metadata: VarMetadata::default(),
+      allows_unanswered: false,
+      options: options.into_iter().map(Into::into).collect(),
+    }
+  }
+
+  /// Set the presentation hints
+  pub fn with_metadata(mut self, metadata: VarMetadata) -> Self {
+    self.metadata = metadata;
+    self
+  }
+
+  /// Let the user skip the var, leaving an [`UnansweredValue`](crate::value::UnansweredValue)
+  pub fn allow_unanswered(mut self) -> Self {
+    self.allows_unanswered = true;
+    self
+  }
+
+  /// The options allowed
+  pub fn options(&self) -> &[String] {
+    &self.options
+  }
+
+  /// Box the value
+  pub fn boxed(self) -> Box<dyn Var + Send + Sync> {
+    Box::new(self)
+  }
+}
+
+impl Var for EnumVar {
+  fn id(&self) -> &VarId { &self.id }
+
+  fn value_from_str(&self, s: &str) -> Result<Box<dyn Value>, InvalidValue> {
+    let val = s.parse::<EnumValue>()?.boxed();
+    self.validate_val_type(&val)?;
+    Ok(val)
+  }
+
+  fn validate_val_type(&self, val: &Box<dyn Value>) -> Result<(), InvalidValue> {
+    let enum_val = val.downcast::<EnumValue>().ok_or(InvalidValue::WrongType)?;
+    if self.options.iter().any(|option| option == enum_val.val()) {
+      Ok(())
+    } else {
+      Err(InvalidValue::WrongValue)
+    }
+  }
+
+  fn metadata(&self) -> &VarMetadata { &self.metadata }
+
+  fn metadata_mut(&mut self) -> Option<&mut VarMetadata> { Some(&mut self.metadata) }
+
+  fn choices(&self) -> Option<&[String]> { Some(&self.options) }
+
+  fn base_value_kind(&self) -> Option<BaseValueKind> { Some(BaseValueKind::String) }
+
+  fn value_type(&self) -> Option<std::any::TypeId> { Some(std::any::TypeId::of::<EnumValue>()) }
+
+  fn allows_unanswered(&self) -> bool { self.allows_unanswered }
+}
+
+use super::value::ListValue;
+
+/// A var for a [`ListValue`] where every value in the list is the same type
+///
+/// From a string, the values are separated by commas. Escape a comma in a value with a backslash, like the list's string form does.
+/// ```
+/// # use stepflow_data::value::EmailValue;
+/// # use stepflow_data::var::{Var, VarId, ListVar};
+/// let var = ListVar::new::<EmailValue>(VarId::new(0));
+/// assert!(var.value_from_str("a@b.com, c@d.com").is_ok());
+/// assert!(var.value_from_str("a@b.com, bad").is_err());
+/// ```
+#[derive(Debug)]
+pub struct ListVar {
+  id: VarId,
+  metadata: VarMetadata,
+  allows_unanswered: bool,
+  element_type: std::any::TypeId,
+  is_element: fn(&Box<dyn Value>) -> bool,
+  element_from_str: fn(&str) -> Result<Box<dyn Value>, InvalidValue>,
+}
+
+impl ListVar {
+  /// Create a new var for lists of `V`
+  pub fn new<V>(id: VarId) -> Self
+      where V: Value + std::str::FromStr<Err = InvalidValue> + 'static
+  {
+    Self {
+      id,
+      metadata: VarMetadata::default(),
+      allows_unanswered: false,
+      element_type: std::any::TypeId::of::<V>(),
+      is_element: |val| val.is::<V>(),
+      element_from_str: |s| Ok(Box::new(s.parse::<V>()?) as Box<dyn Value>),
+    }
+  }
+
+  /// Set the presentation hints
+  pub fn with_metadata(mut self, metadata: VarMetadata) -> Self {
+    self.metadata = metadata;
+    self
+  }
+
+  /// Let the user skip the var, leaving an [`UnansweredValue`](crate::value::UnansweredValue)
+  pub fn allow_unanswered(mut self) -> Self {
+    self.allows_unanswered = true;
+    self
+  }
+
+  /// Whether the values in the list are `V`s
+  pub fn is_list_of<V: Value + 'static>(&self) -> bool {
+    self.element_type == std::any::TypeId::of::<V>()
+  }
+
+  /// Box the value
+  pub fn boxed(self) -> Box<dyn Var + Send + Sync> {
+    Box::new(self)
+  }
+}
+
+impl Var for ListVar {
+  fn id(&self) -> &VarId { &self.id }
+
+  fn value_from_str(&self, s: &str) -> Result<Box<dyn Value>, InvalidValue> {
+    let vals = super::value::split_list(s)?
+      .iter()
+      .map(|item| (self.element_from_str)(item))
+      .collect::<Result<Vec<_>, _>>()?;
+    Ok(ListValue::new(vals).boxed())
+  }
+
+  fn validate_val_type(&self, val: &Box<dyn Value>) -> Result<(), InvalidValue> {
+    let list = val.downcast::<ListValue>().ok_or(InvalidValue::WrongType)?;
+    if list.vals().iter().all(|item| (self.is_element)(item)) {
+      Ok(())
+    } else {
+      Err(InvalidValue::WrongType)
+    }
+  }
+
+  fn metadata(&self) -> &VarMetadata { &self.metadata }
+
+  fn metadata_mut(&mut self) -> Option<&mut VarMetadata> { Some(&mut self.metadata) }
+
+  fn base_value_kind(&self) -> Option<BaseValueKind> { Some(BaseValueKind::List) }
+
+  fn value_type(&self) -> Option<std::any::TypeId> { Some(std::any::TypeId::of::<ListValue>()) }
+
+  fn allows_unanswered(&self) -> bool { self.allows_unanswered }
+}
 
 
 #[cfg(test)]
@@ -102,8 +566,8 @@ pub fn test_var_val() -> (Box<dyn Var + Send + Sync>, Box<dyn Value>) {
 #[cfg(test)]
 mod tests {
   use stepflow_test_util::test_id;
-  use crate::value::{Value, StringValue, EmailValue};
-  use super::{Var, VarId, EmailVar, StringVar, InvalidValue};
+  use crate::value::{Value, StringValue, EmailValue, EnumValue, ListValue, IntValue, FloatValue, DateTimeValue};
+  use super::{Var, VarId, VarMetadata, VarGroup, EmailVar, StringVar, EnumVar, ListVar, NumberVar, DateTimeVar, InvalidValue, BaseValueKind};
 
   #[test]
   fn validate_val_type() {
@@ -114,6 +578,74 @@ mod tests {
     let email_emailval: Box<dyn Value> = EmailValue::try_new(email_addr).unwrap().boxed();
     assert!(matches!(email_var.validate_val_type(&email_strval), Err(InvalidValue::WrongType)));
     assert!(matches!(email_var.validate_val_type(&email_emailval), Ok(())));
+
+    // stored like the values it takes
+    assert_eq!(email_var.base_value_kind(), Some(email_emailval.get_baseval().kind()));
+    assert_eq!(email_var.base_value_kind(), Some(BaseValueKind::String));
+    assert_eq!(email_var.value_type(), Some(std::any::TypeId::of::<EmailValue>()));
+  }
+
+  #[test]
+  fn datetime_var() {
+    let booking = DateTimeVar::new(test_id!(VarId));
+    assert!(booking.accepts_time());
+    assert!(booking.value_from_str("2021-03-14T15:09Z").is_ok());
+    assert!(booking.value_from_str("2021-03-14").is_ok());
+
+    let birthdate = DateTimeVar::new(test_id!(VarId)).dates_only();
+    assert!(!birthdate.accepts_time());
+    assert_eq!(birthdate.value_from_str("1990-07-04"), Ok(DateTimeValue::try_new("1990-07-04").unwrap().boxed()));
+    assert_eq!(birthdate.value_from_str("1990-07-04T08:30"), Err(InvalidValue::WrongValue));
+    assert_eq!(birthdate.validate_val_type(&StringValue::try_new("1990-07-04").unwrap().boxed()), Err(InvalidValue::WrongType));
+  }
+
+  #[test]
+  fn number_var() {
+    let count = NumberVar::new_int(test_id!(VarId)).with_range(Some(1.0), Some(10.0));
+    assert_eq!(count.value_from_str("10"), Ok(IntValue::new(10).boxed()));
+    assert_eq!(count.value_from_str("11"), Err(InvalidValue::OutOfRange));
+    assert_eq!(count.value_from_str("1.5"), Err(InvalidValue::BadFormat));
+    assert_eq!(count.validate_val_type(&FloatValue::try_new(2.0).unwrap().boxed()), Err(InvalidValue::WrongType));
+    assert_eq!(count.base_value_kind(), Some(BaseValueKind::Int));
+
+    let price = NumberVar::new(test_id!(VarId)).with_range(Some(0.0), None);
+    assert_eq!(price.value_from_str("9.99"), Ok(FloatValue::try_new(9.99).unwrap().boxed()));
+    assert_eq!(price.validate_val_type(&FloatValue::try_new(-0.01).unwrap().boxed()), Err(InvalidValue::OutOfRange));
+    assert_eq!(price.validate_val_type(&IntValue::new(5).boxed()), Err(InvalidValue::WrongType));
+    assert_eq!(price.base_value_kind(), Some(FloatValue::try_new(1.0).unwrap().get_baseval().kind()));
+  }
+
+  #[test]
+  fn enum_var() {
+    let plan = EnumVar::new(test_id!(VarId), vec!["free", "pro"]);
+    assert_eq!(plan.value_from_str("pro"), Ok(EnumValue::try_new("pro").unwrap().boxed()));
+    assert_eq!(plan.value_from_str("gold"), Err(InvalidValue::WrongValue));
+    assert_eq!(plan.value_from_str(""), Err(InvalidValue::Empty));
+    assert_eq!(plan.validate_val_type(&StringValue::try_new("pro").unwrap().boxed()), Err(InvalidValue::WrongType));
+    assert_eq!(plan.choices(), Some(&["free".to_owned(), "pro".to_owned()][..]));
+    assert_eq!(plan.base_value_kind(), Some(BaseValueKind::String));
+  }
+
+  #[test]
+  fn list_var() {
+    let list_var = ListVar::new::<EmailValue>(test_id!(VarId));
+    let emails = ListValue::new(vec![EmailValue::try_new("a@b.com").unwrap().boxed()]).boxed();
+    let strings = ListValue::new(vec![StringValue::try_new("a@b.com").unwrap().boxed()]).boxed();
+    assert_eq!(list_var.validate_val_type(&emails), Ok(()));
+    assert_eq!(list_var.validate_val_type(&strings), Err(InvalidValue::WrongType));
+    assert_eq!(list_var.validate_val_type(&EmailValue::try_new("a@b.com").unwrap().boxed()), Err(InvalidValue::WrongType));
+    assert_eq!(list_var.base_value_kind(), Some(emails.get_baseval().kind()));
+    assert!(list_var.is_list_of::<EmailValue>());
+    assert!(!list_var.is_list_of::<StringValue>());
+
+    // commas in values survive a round trip
+    let strings_var = ListVar::new::<StringValue>(test_id!(VarId));
+    let with_comma = ListValue::new(vec![StringValue::try_new("Doe, Jane").unwrap().boxed(), StringValue::try_new("Roe").unwrap().boxed()]).boxed();
+    assert_eq!(strings_var.value_from_str(&with_comma.get_baseval().to_string()), Ok(with_comma));
+
+    assert_eq!(list_var.value_from_str(" a@b.com,"), Ok(emails));
+    assert_eq!(list_var.value_from_str("a@b.com,bad"), Err(InvalidValue::BadFormat));
+    assert_eq!(list_var.value_from_str(""), Ok(ListValue::new(vec![]).boxed()));
   }
 
   #[test]
@@ -126,4 +658,20 @@ mod tests {
     assert!(matches!(stringvar_boxed.downcast::<StringVar>(), Some(_)));
     assert_eq!(stringvar_boxed.is::<StringVar>(), true);
   }
-}
\ No newline at end of file
+
+  #[test]
+  fn metadata() {
+    let mut var = StringVar::new(test_id!(VarId)).boxed();
+    assert_eq!(var.metadata(), &VarMetadata::default());
+
+    var.metadata_mut().unwrap().placeholder = Some("Jane".to_owned());
+    assert_eq!(var.metadata().placeholder, Some("Jane".to_owned()));
+
+    let metadata = VarMetadata { autocomplete: Some("email".to_owned()), ..Default::default() };
+    let email_var = EmailVar::new(test_id!(VarId)).with_metadata(metadata.clone());
+    assert_eq!(email_var.metadata(), &metadata);
+
+    var.metadata_mut().unwrap().group = Some(VarGroup::new("Contact", 1));
+    assert_eq!(var.metadata().group.as_ref().map(|group| &group.name[..]), Some("Contact"));
+  }
+}
@@ -0,0 +1,89 @@
+use std::collections::{HashMap, HashSet};
+use super::var::VarId;
+
+/// Which roles can read and write each [`Var`](crate::var::Var)
+///
+/// Vars without any roles are open to everyone. Once a role is allowed, only the allowed roles have access.
+/// ```
+/// # use stepflow_data::{VarAccess, var::VarId};
+/// let ssn = VarId::new(0);
+///
+/// let mut access = VarAccess::new();
+/// access.allow_read(ssn, "applicant");
+/// assert!(access.can_read(&ssn, "applicant"));
+/// assert!(!access.can_read(&ssn, "reviewer"));
+/// ```
+#[derive(Debug, Default)]
+pub struct VarAccess {
+  read_roles: HashMap<VarId, HashSet<String>>,
+  write_roles: HashMap<VarId, HashSet<String>>,
+}
+
+impl VarAccess {
+  /// Create a new `VarAccess` where everyone can read and write every var
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Allow `role` to read `var_id`
+  pub fn allow_read(&mut self, var_id: VarId, role: &str) {
+    self.read_roles.entry(var_id).or_default().insert(role.to_owned());
+  }
+
+  /// Allow `role` to write `var_id`
+  pub fn allow_write(&mut self, var_id: VarId, role: &str) {
+    self.write_roles.entry(var_id).or_default().insert(role.to_owned());
+  }
+
+  /// Check if `role` can read `var_id`
+  pub fn can_read(&self, var_id: &VarId, role: &str) -> bool {
+    Self::allowed(&self.read_roles, var_id, role)
+  }
+
+  /// Check if `role` can write `var_id`
+  pub fn can_write(&self, var_id: &VarId, role: &str) -> bool {
+    Self::allowed(&self.write_roles, var_id, role)
+  }
+
+  /// The vars in `var_ids` that `role` can read. Handy for creating a [`StateDataFiltered`](crate::StateDataFiltered).
+  pub fn readable<'a, I>(&self, var_ids: I, role: &str) -> HashSet<VarId>
+      where I: IntoIterator<Item = &'a VarId>
+  {
+    var_ids.into_iter().filter(|var_id| self.can_read(var_id, role)).copied().collect()
+  }
+
+  fn allowed(roles: &HashMap<VarId, HashSet<String>>, var_id: &VarId, role: &str) -> bool {
+    roles.get(var_id).map(|roles| roles.contains(role)).unwrap_or(true)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use stepflow_test_util::test_id;
+  use crate::var::VarId;
+  use super::VarAccess;
+
+  #[test]
+  fn roles() {
+    let email = test_id!(VarId);
+    let ssn = test_id!(VarId);
+    let approved = test_id!(VarId);
+
+    let mut access = VarAccess::new();
+    access.allow_read(ssn, "applicant");
+    access.allow_read(ssn, "admin");
+    access.allow_write(approved, "admin");
+
+    assert!(access.can_read(&email, "reviewer"));
+    assert!(!access.can_read(&ssn, "reviewer"));
+    assert!(access.can_read(&ssn, "admin"));
+    assert!(access.can_read(&approved, "reviewer"));
+    assert!(!access.can_write(&approved, "reviewer"));
+    assert!(access.can_write(&approved, "admin"));
+    assert!(access.can_write(&ssn, "reviewer"));
+
+    let readable = access.readable(&[email, ssn, approved], "reviewer");
+    assert_eq!(readable.len(), 2);
+    assert!(!readable.contains(&ssn));
+  }
+}
@@ -0,0 +1,94 @@
+use std::collections::{HashMap, HashSet};
+use super::var::VarId;
+
+/// Declares which [`Var`](crate::var::Var)s are derived from other vars.
+///
+/// When an upstream var changes, everything that depends on it (directly or indirectly) is stale and should be removed.
+/// ```
+/// # use stepflow_data::{VarDependencies, var::VarId};
+/// let email = VarId::new(0);
+/// let email_validated = VarId::new(1);
+///
+/// let mut dependencies = VarDependencies::new();
+/// dependencies.add(email_validated, email);
+/// assert!(dependencies.dependents_of(&email).contains(&email_validated));
+/// ```
+#[derive(Debug, Default)]
+pub struct VarDependencies {
+  dependents: HashMap<VarId, HashSet<VarId>>,
+}
+
+impl VarDependencies {
+  /// Create a new empty set of dependencies
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Declare that `var_id` is derived from `depends_on`
+  pub fn add(&mut self, var_id: VarId, depends_on: VarId) {
+    self.dependents
+      .entry(depends_on)
+      .or_default()
+      .insert(var_id);
+  }
+
+  /// Get all vars that depend on `var_id`, including indirect dependents
+  pub fn dependents_of(&self, var_id: &VarId) -> HashSet<VarId> {
+    let mut result = HashSet::new();
+    let mut to_visit = vec![*var_id];
+    while let Some(visit_id) = to_visit.pop() {
+      if let Some(dependents) = self.dependents.get(&visit_id) {
+        for dependent in dependents {
+          // insert returns false when we've seen it which also protects against cycles
+          if result.insert(*dependent) {
+            to_visit.push(*dependent);
+          }
+        }
+      }
+    }
+    result
+  }
+
+  /// Check if any dependencies have been declared
+  pub fn is_empty(&self) -> bool {
+    self.dependents.is_empty()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use stepflow_test_util::test_id;
+  use crate::var::VarId;
+  use super::VarDependencies;
+
+  #[test]
+  fn transitive() {
+    let a = test_id!(VarId);
+    let b = test_id!(VarId);
+    let c = test_id!(VarId);
+    let unrelated = test_id!(VarId);
+
+    let mut dependencies = VarDependencies::new();
+    dependencies.add(b, a);
+    dependencies.add(c, b);
+
+    let dependents_a = dependencies.dependents_of(&a);
+    assert_eq!(dependents_a.len(), 2);
+    assert!(dependents_a.contains(&b));
+    assert!(dependents_a.contains(&c));
+
+    assert_eq!(dependencies.dependents_of(&c).len(), 0);
+    assert_eq!(dependencies.dependents_of(&unrelated).len(), 0);
+  }
+
+  #[test]
+  fn cycle() {
+    let a = test_id!(VarId);
+    let b = test_id!(VarId);
+
+    let mut dependencies = VarDependencies::new();
+    dependencies.add(b, a);
+    dependencies.add(a, b);
+    assert_eq!(dependencies.dependents_of(&a).len(), 2);
+  }
+}
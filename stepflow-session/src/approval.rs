@@ -0,0 +1,160 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+use stepflow_base::IdError;
+use stepflow_data::{StateData, Condition, var::{VarId, BoolVar, StringVar}, value::{BoolValue, StringValue}};
+use stepflow_step::{Step, StepId};
+use stepflow_action::{Action, ApprovalAction};
+use crate::{Session, AdvanceBlockedOn, Error};
+
+/// The step and vars created by [`Session::insert_approval_step`]
+///
+/// Branch on the decision by [skipping](Step::set_skip_when) steps with [`ApprovalStep::approved_when`] or [`ApprovalStep::rejected_when`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApprovalStep {
+  pub step_id: StepId,
+
+  /// `true` if approved, `false` if rejected
+  pub decision: VarId,
+
+  /// Optional comment from the approver
+  pub comment: VarId,
+
+  /// Who made the decision
+  pub decided_by: VarId,
+
+  /// When the decision was made, in seconds since the Unix epoch
+  pub decided_at: VarId,
+}
+
+impl ApprovalStep {
+  /// Holds once the step is approved
+  pub fn approved_when(&self) -> Condition {
+    Condition::Equals(self.decision, BoolValue::new(true).boxed())
+  }
+
+  /// Holds once the step is rejected
+  pub fn rejected_when(&self) -> Condition {
+    Condition::Equals(self.decision, BoolValue::new(false).boxed())
+  }
+}
+
+impl Session {
+  /// Create a step that waits for `approver` to approve or reject. The vars are named after the step, i.e. `review_decision`.
+  ///
+  /// The step is [assigned](Session::set_step_assignee) to `approver` and runs an [`ApprovalAction`].
+  /// Like other steps, it still needs to be added to the flow.
+  pub fn insert_approval_step(&mut self, name: &str, approver: &str) -> Result<ApprovalStep, Error> {
+    let decision = self.var_store_mut().insert_new_named(format!("{}_decision", name), |id| Ok(BoolVar::new(id).boxed()))?;
+    let comment = self.var_store_mut().insert_new_named(format!("{}_comment", name), |id| Ok(StringVar::new(id).boxed()))?;
+    let decided_by = self.var_store_mut().insert_new_named(format!("{}_decided_by", name), |id| Ok(StringVar::new(id).boxed()))?;
+    let decided_at = self.var_store_mut().insert_new_named(format!("{}_decided_at", name), |id| Ok(StringVar::new(id).boxed()))?;
+
+    let step_id = self.step_store_mut().insert_new_named(name.to_owned(), |id| {
+      let mut step = Step::new(id, None, vec![decision, decided_by, decided_at]);
+      step.set_output_required_when(comment, Condition::Any(vec![]));
+      Ok(step)
+    })?;
+    let action_id = self.action_store_mut().insert_new(|id| Ok(ApprovalAction::new(id, approver.to_owned(), decision).boxed()))?;
+    self.set_action_for_step(action_id, Some(&step_id))?;
+    self.set_step_assignee(step_id, approver.to_owned())?;
+
+    Ok(ApprovalStep { step_id, decision, comment, decided_by, decided_at })
+  }
+
+  /// Record `actor`'s decision with the time it was made and [advance](Session::advance_as)
+  pub fn submit_approval(&mut self, actor: &str, approval: &ApprovalStep, approved: bool, comment: Option<&str>)
+      -> Result<AdvanceBlockedOn, Error>
+  {
+    let state_data = self.approval_data(actor, approval, approved, comment)?;
+    self.advance_as(actor, Some((&approval.step_id, state_data)))
+  }
+
+  // the decision with who made it and when
+  pub(crate) fn approval_data(&self, actor: &str, approval: &ApprovalStep, approved: bool, comment: Option<&str>)
+      -> Result<StateData, Error>
+  {
+    let decided_at = SystemTime::now().duration_since(UNIX_EPOCH).map_err(|_| Error::Other)?.as_secs();
+
+    let mut state_data = StateData::new();
+    let mut insert = |var_id: &VarId, val| {
+      let var = self.var_store().get(var_id).ok_or(Error::VarId(IdError::IdMissing(*var_id)))?;
+      state_data.insert(var, val).map_err(Error::from)
+    };
+    insert(&approval.decision, BoolValue::new(approved).boxed())?;
+    insert(&approval.decided_by, StringValue::try_new(actor.to_owned())?.boxed())?;
+    insert(&approval.decided_at, StringValue::try_new(decided_at.to_string())?.boxed())?;
+    if let Some(comment) = comment {
+      insert(&approval.comment, StringValue::try_new(comment.to_owned())?.boxed())?;
+    }
+    Ok(state_data)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use stepflow_base::IdError;
+  use stepflow_data::value::{BoolValue, StringValue};
+  use stepflow_step::Step;
+  use stepflow_action::Action;
+  use stepflow_action::testing::MockAction;
+  use std::time::{Duration, SystemTime};
+  use crate::{Session, AdvanceBlockedOn, Error, DeadlinePolicy, Escalation, ESCALATION_ACTOR};
+
+  #[test]
+  fn approve_and_reject() {
+    for approved in [true, false] {
+      let (mut session, root_step_id) = Session::test_new();
+      let approval = session.insert_approval_step("review", "reviewer").unwrap();
+      let approved_step = session.step_store_mut().insert_new_named("approved", |id| Ok(Step::new(id, None, vec![]))).unwrap();
+      let rejected_step = session.step_store_mut().insert_new_named("rejected", |id| Ok(Step::new(id, None, vec![]))).unwrap();
+      session.step_store_mut().get_mut(&approved_step).unwrap().set_skip_when(approval.rejected_when());
+      session.step_store_mut().get_mut(&rejected_step).unwrap().set_skip_when(approval.approved_when());
+      let root = session.step_store_mut().get_mut(&root_step_id).unwrap();
+      root.push_substep(approval.step_id);
+      root.push_substep(approved_step);
+      root.push_substep(rejected_step);
+      let test_action_id = session.action_store_mut().insert_new(|id| Ok(MockAction::new(id).boxed())).unwrap();
+      session.set_action_for_step(test_action_id, None).unwrap();
+
+      // waits on the reviewer
+      let waiting = session.advance(None).unwrap();
+      assert!(matches!(&waiting, AdvanceBlockedOn::ActionStartWith(_, val, _) if val == &StringValue::try_new("reviewer").unwrap().boxed()));
+      assert_eq!(session.steps_waiting_on("reviewer"), vec![approval.step_id]);
+      assert_eq!(session.submit_approval("applicant", &approval, true, None), Err(Error::NotAssignee(approval.step_id)));
+
+      // the manager takes over
+      assert_eq!(session.reassign_step(&approved_step, "manager".to_owned()), Err(Error::StepId(IdError::IdUnexpected(approved_step))));
+      assert_eq!(session.reassign_step(&approval.step_id, "manager".to_owned()), Ok(Some("reviewer".to_owned())));
+      assert!(session.steps_waiting_on("reviewer").is_empty());
+      let waiting = session.advance(None).unwrap();
+      assert!(matches!(&waiting, AdvanceBlockedOn::ActionStartWith(_, val, _) if val == &StringValue::try_new("manager").unwrap().boxed()));
+      assert_eq!(session.submit_approval("reviewer", &approval, true, None), Err(Error::NotAssignee(approval.step_id)));
+
+      session.submit_approval("manager", &approval, approved, Some("looks good")).unwrap();
+      let expected_step = if approved { approved_step } else { rejected_step };
+      assert_eq!(*session.current_step().unwrap(), expected_step);
+
+      let state_data = session.state_data();
+      assert_eq!(state_data.get(&approval.decision).unwrap().get_val(), &BoolValue::new(approved).boxed());
+      assert_eq!(state_data.get(&approval.decided_by).unwrap().get_val(), &StringValue::try_new("manager").unwrap().boxed());
+      assert!(state_data.contains(&approval.decided_at));
+      assert!(state_data.contains(&approval.comment));
+      assert_eq!(session.advance(None), Ok(AdvanceBlockedOn::FinishedAdvancing));
+    }
+  }
+
+  #[test]
+  fn auto_approve() {
+    let (mut session, root_step_id) = Session::test_new();
+    let approval = session.insert_approval_step("review", "reviewer").unwrap();
+    session.step_store_mut().get_mut(&root_step_id).unwrap().push_substep(approval.step_id);
+    let policy = DeadlinePolicy { within: Duration::from_secs(60), escalation: Escalation::AutoApprove(approval.clone()) };
+    session.set_deadline_policy(approval.step_id, policy).unwrap();
+
+    session.advance(None).unwrap();
+    assert_eq!(session.enforce_deadline(SystemTime::now() + Duration::from_secs(120)), Ok(Some(approval.step_id)));
+    assert!(session.current_step().is_err()); // finished
+    let state_data = session.state_data();
+    assert_eq!(state_data.get(&approval.decision).unwrap().get_val(), &BoolValue::new(true).boxed());
+    assert_eq!(state_data.get(&approval.decided_by).unwrap().get_val(), &StringValue::try_new(ESCALATION_ACTOR).unwrap().boxed());
+  }
+}
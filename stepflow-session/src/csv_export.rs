@@ -0,0 +1,165 @@
+use stepflow_base::IdError;
+use stepflow_data::BaseValue;
+use crate::{Session, Error};
+
+/// Export the data of many sessions as CSV, i.e. the finished sessions of a flow for a spreadsheet
+///
+/// Each session is a row and each var is a column with the var's name. Values are written as their
+/// [`BaseValue`](stepflow_data::BaseValue) so lists are separated by commas.
+/// Vars without a value and [sensitive](stepflow_data::var::VarMetadata::sensitive) vars are left empty
+/// while [unanswered](stepflow_data::value::UnansweredValue) vars are written as `(unanswered)` so they can be told apart.
+///
+/// Text starting with `=`, `+`, `-`, `@`, a tab or a carriage return gets a `'` in front so spreadsheets don't run it as a formula.
+/// ```
+/// # use stepflow_data::var::StringVar;
+/// # use stepflow_session::{Session, SessionId, CsvExport};
+/// let mut session = Session::new(SessionId::new(0));
+/// session.var_store_mut().insert_new_named("name", |id| Ok(StringVar::new(id).boxed())).unwrap();
+/// let csv = CsvExport::new(vec!["name"]).with_session_id().export(vec![&session]).unwrap();
+/// assert_eq!(csv, "session_id,name\n0,\n");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsvExport {
+  var_names: Vec<String>,
+  include_session_id: bool,
+  guard_formulas: bool,
+  unanswered: String,
+}
+
+impl CsvExport {
+  /// Export the vars with these names, in the same order
+  pub fn new<STR: Into<String>>(var_names: Vec<STR>) -> Self {
+    CsvExport {
+      var_names: var_names.into_iter().map(Into::into).collect(),
+      include_session_id: false,
+      guard_formulas: true,
+      unanswered: "(unanswered)".to_owned(),
+    }
+  }
+
+  /// Start each row with the session's ID in a `session_id` column
+  pub fn with_session_id(mut self) -> Self {
+    self.include_session_id = true;
+    self
+  }
+
+  /// Write unanswered vars as `unanswered` instead
+  pub fn with_unanswered<STR: Into<String>>(mut self, unanswered: STR) -> Self {
+    self.unanswered = unanswered.into();
+    self
+  }
+
+  /// Write text that looks like a formula as is. Only use it when the CSV isn't opened in a spreadsheet.
+  pub fn without_formula_guard(mut self) -> Self {
+    self.guard_formulas = false;
+    self
+  }
+
+  /// Get the CSV with a header row
+  ///
+  /// Fails with [`Error::VarId`] if a session doesn't have a var with one of the names.
+  pub fn export<'a, I>(&self, sessions: I) -> Result<String, Error>
+      where I: IntoIterator<Item = &'a Session>
+  {
+    let mut csv = String::new();
+    let header = self.include_session_id.then(|| "session_id".to_owned()).into_iter().chain(self.var_names.iter().cloned());
+    push_row(&mut csv, header);
+    for session in sessions {
+      let mut row = Vec::with_capacity(self.var_names.len() + 1);
+      if self.include_session_id {
+        row.push(session.id().to_string());
+      }
+      for name in self.var_names.iter() {
+        let var_id = session.var_store().id_from_name(name).ok_or_else(|| Error::VarId(IdError::NoSuchName(name.clone())))?;
+        let sensitive = session.var_store().get(var_id).map(|var| var.metadata().sensitive).unwrap_or(false);
+        let field = match session.state_data().get(var_id) {
+          Some(_) if !sensitive && session.state_data().is_unanswered(var_id) => self.unanswered.clone(),
+          Some(val) if !sensitive => {
+            let baseval = val.get_val().get_baseval();
+            let is_text = matches!(baseval, BaseValue::String(_) | BaseValue::List(_));
+            let field = baseval.to_string();
+            if self.guard_formulas && is_text && field.starts_with(&['=', '+', '-', '@', '\t', '\r'][..]) {
+              format!("'{}", field)
+            } else {
+              field
+            }
+          },
+          _ => String::new(),
+        };
+        row.push(field);
+      }
+      push_row(&mut csv, row.into_iter());
+    }
+    Ok(csv)
+  }
+}
+
+// fields with a separator, quote or line break are quoted with their quotes doubled
+fn push_row<I: Iterator<Item = String>>(csv: &mut String, fields: I) {
+  for (i, field) in fields.enumerate() {
+    if i > 0 {
+      csv.push(',');
+    }
+    if field.contains(&[',', '"', '\n', '\r'][..]) {
+      csv.push('"');
+      csv.push_str(&field.replace('"', "\"\""));
+      csv.push('"');
+    } else {
+      csv.push_str(&field);
+    }
+  }
+  csv.push('\n');
+}
+
+#[cfg(test)]
+mod tests {
+  use stepflow_base::IdError;
+  use stepflow_data::{StateData, var::{StringVar, BoolVar, NumberVar, VarMetadata}, value::{StringValue, BoolValue, IntValue, UnansweredValue}};
+  use crate::{Session, SessionId, Error};
+  use super::CsvExport;
+
+  fn new_session(session_id: u16, name: &'static str, age: Option<i64>) -> Session {
+    let mut session = Session::new(SessionId::new(session_id));
+    let name_id = session.var_store_mut().insert_new_named("name", |id| Ok(StringVar::new(id).boxed())).unwrap();
+    let age_id = session.var_store_mut().insert_new_named("age", |id| Ok(NumberVar::new_int(id).boxed())).unwrap();
+    let agreed_id = session.var_store_mut().insert_new_named("agreed", |id| Ok(BoolVar::new(id).allow_unanswered().boxed())).unwrap();
+    let metadata = VarMetadata { sensitive: true, ..Default::default() };
+    let password_id = session.var_store_mut().insert_new_named("password", |id| Ok(StringVar::new(id).with_metadata(metadata).boxed())).unwrap();
+
+    let mut data = StateData::new();
+    let var = |var_id| session.var_store().get(var_id).unwrap();
+    data.insert(var(&name_id), StringValue::try_new(name).unwrap().boxed()).unwrap();
+    if let Some(age) = age {
+      data.insert(var(&age_id), IntValue::new(age).boxed()).unwrap();
+    }
+    data.insert(var(&agreed_id), BoolValue::new(true).boxed()).unwrap();
+    data.insert(var(&password_id), StringValue::try_new("hunter2").unwrap().boxed()).unwrap();
+    session.seed_data(data).unwrap();
+    session
+  }
+
+  #[test]
+  fn export() {
+    let sessions = [new_session(1, "Jane", Some(30)), new_session(2, "Doe, \"JD\"", None)];
+    let csv = CsvExport::new(vec!["name", "age", "agreed", "password"]).with_session_id().export(sessions.iter()).unwrap();
+    assert_eq!(csv, "session_id,name,age,agreed,password\n1,Jane,30,true,\n2,\"Doe, \"\"JD\"\"\",,true,\n");
+
+    assert_eq!(CsvExport::new(vec!["age"]).export(Vec::new()), Ok("age\n".to_owned()));
+
+    // text that looks like a formula is guarded but numbers aren't
+    let sessions = [new_session(3, "=HYPERLINK(\"x\")", Some(-1)), new_session(4, "@SUM", None)];
+    let export = CsvExport::new(vec!["name", "age"]);
+    assert_eq!(export.export(sessions.iter()), Ok("name,age\n\"'=HYPERLINK(\"\"x\"\")\",-1\n'@SUM,\n".to_owned()));
+    assert_eq!(export.without_formula_guard().export(sessions.iter()), Ok("name,age\n\"=HYPERLINK(\"\"x\"\")\",-1\n@SUM,\n".to_owned()));
+
+    // unanswered vars aren't the same as ones without a value
+    let mut session = new_session(5, "Jo", None);
+    let mut data = StateData::new();
+    data.insert(session.var_store().get_by_name("agreed").unwrap(), UnansweredValue::new().boxed()).unwrap();
+    session.seed_data(data).unwrap();
+    let export = CsvExport::new(vec!["age", "agreed"]);
+    assert_eq!(export.export(vec![&session]), Ok("age,agreed\n,(unanswered)\n".to_owned()));
+    assert_eq!(export.with_unanswered("skipped").export(vec![&session]), Ok("age,agreed\n,skipped\n".to_owned()));
+    assert_eq!(CsvExport::new(vec!["email"]).export(sessions.iter()), Err(Error::VarId(IdError::NoSuchName("email".to_owned()))));
+  }
+}
@@ -0,0 +1,132 @@
+/// Generate a typed accessor for the data of a [`Session`](crate::Session)
+///
+/// Each field becomes a method that returns the value of the [`Var`](stepflow_data::var::Var) with the same name,
+/// or `None` if it's not set or was left [unanswered](stepflow_data::value::UnansweredValue).
+/// `new` checks every var is registered and takes the field's type of value, see [`Session::var_id_for_type`](crate::Session::var_id_for_type).
+/// The fields can be generated from a [`FlowDefinition`](crate::FlowDefinition) with `data_accessor_source`, i.e. in a build script.
+///
+/// ```
+/// # use stepflow_data::{StateData, var::StringVar, value::{Value, StringValue, EmailValue}};
+/// # use stepflow_session::{Session, SessionId, session_data_accessor};
+/// session_data_accessor! {
+///   pub struct RegistrationData {
+///     first_name: StringValue,
+///     email: EmailValue,
+///   }
+/// }
+///
+/// let mut session = Session::new(SessionId::new(0));
+/// let first_name_id = session.var_store_mut().insert_new_named("first_name", |id| Ok(StringVar::new(id).boxed())).unwrap();
+/// # session.var_store_mut().insert_new_named("email", |id| Ok(stepflow_data::var::EmailVar::new(id).boxed())).unwrap();
+/// // ... advance the session
+/// # let mut data = StateData::new();
+/// # data.insert(session.var_store().get(&first_name_id).unwrap(), StringValue::try_new("Jane").unwrap().boxed()).unwrap();
+/// # let step_id = session.step_store().id_from_name("SESSION_ROOT").unwrap().clone();
+/// # session.advance(Some((&step_id, data))).unwrap();
+///
+/// let data = RegistrationData::new(&session).unwrap();
+/// assert_eq!(data.first_name().unwrap().val(), "Jane");
+/// assert_eq!(data.email(), None);
+/// ```
+#[macro_export]
+macro_rules! session_data_accessor {
+  ($(#[$meta:meta])* $vis:vis struct $name:ident { $($field:ident : $val_type:ty),* $(,)? }) => {
+    $(#[$meta])*
+    $vis struct $name<'s> {
+      session: &'s $crate::Session,
+    }
+
+    impl<'s> $name<'s> {
+      /// Wrap the session's data. Fails if any of the vars aren't registered or take another type of value.
+      pub fn new(session: &'s $crate::Session) -> Result<Self, $crate::Error> {
+        $( session.var_id_for_type::<$val_type>(stringify!($field))?; )*
+        Ok(Self { session })
+      }
+
+      $(
+        pub fn $field(&self) -> Option<&'s $val_type> {
+          // the vars can't change while the session is borrowed so any other value is unanswered
+          let var_id = self.session.var_id_from_name(stringify!($field)).ok()?;
+          self.session.state_data().get(&var_id)?.get_val().downcast::<$val_type>()
+        }
+      )*
+    }
+  };
+}
+
+#[cfg(test)]
+mod tests {
+  use stepflow_base::IdError;
+  use stepflow_data::{StateData, InvalidValue, InvalidVars, var::{StringVar, TrueVar, BoolVar}, value::{StringValue, TrueValue, BoolValue, UnansweredValue}};
+  use stepflow_step::Step;
+  use stepflow_action::Action;
+  use stepflow_action::testing::MockAction;
+  use stepflow_test_util::test_id;
+  use crate::{Session, SessionId, Error};
+
+  session_data_accessor! {
+    struct TestData {
+      name: StringValue,
+      agreed: TrueValue,
+    }
+  }
+
+  #[test]
+  fn accessor() {
+    let (mut session, root_step_id) = Session::test_new();
+    assert!(matches!(TestData::new(&session), Err(Error::VarId(IdError::NoSuchName(name))) if name == "name"));
+
+    let name_id = session.var_store_mut().insert_new_named("name", |id| Ok(StringVar::new(id).boxed())).unwrap();
+    let agreed_id = session.var_store_mut().insert_new_named("agreed", |id| Ok(TrueVar::new(id).boxed())).unwrap();
+    let step_id = session.step_store_mut().insert_new(|id| Ok(Step::new(id, None, vec![name_id, agreed_id]))).unwrap();
+    session.step_store_mut().get_mut(&root_step_id).unwrap().push_substep(step_id);
+
+    let data = TestData::new(&session).unwrap();
+    assert_eq!(data.name(), None);
+    assert_eq!(data.agreed(), None);
+
+    let mut output = StateData::new();
+    output.insert(session.var_store().get(&name_id).unwrap(), StringValue::try_new("stepflow").unwrap().boxed()).unwrap();
+    let test_action_id = session.action_store_mut().insert_new(|id| Ok(MockAction::new(id).boxed())).unwrap();
+    session.set_action_for_step(test_action_id, None).unwrap();
+    session.advance(None).unwrap();
+    session.advance(Some((&step_id, output))).unwrap(); // still waiting on agreed
+
+    let data = TestData::new(&session).unwrap();
+    assert_eq!(data.name().unwrap().val(), "stepflow");
+    assert_eq!(data.agreed(), None);
+  }
+
+  session_data_accessor! {
+    struct MistypedData {
+      name: BoolValue,
+    }
+  }
+
+  session_data_accessor! {
+    struct NicknameData {
+      nickname: StringValue,
+    }
+  }
+
+  #[test]
+  fn mistyped() {
+    let mut session = Session::new(test_id!(SessionId));
+    let name_id = session.var_store_mut().insert_new_named("name", |id| Ok(StringVar::new(id).boxed())).unwrap();
+    let nickname_id = session.var_store_mut().insert_new_named("nickname", |id| Ok(StringVar::new(id).allow_unanswered().boxed())).unwrap();
+    let mut data = StateData::new();
+    data.insert(session.var_store().get(&name_id).unwrap(), StringValue::try_new("stepflow").unwrap().boxed()).unwrap();
+    data.insert(session.var_store().get(&nickname_id).unwrap(), UnansweredValue::new().boxed()).unwrap();
+    session.seed_data(data).unwrap();
+
+    // caught when wrapping the session, before any value is read
+    assert_eq!(
+      MistypedData::new(&session).err(),
+      Some(Error::InvalidVars(InvalidVars::new(vec![(name_id, InvalidValue::WrongType)].into_iter().collect()))));
+    assert_eq!(NicknameData::new(&session).unwrap().nickname(), None);
+
+    let mut session = Session::new(test_id!(SessionId));
+    session.var_store_mut().insert_new_named("name", |id| Ok(BoolVar::new(id).boxed())).unwrap();
+    assert_eq!(MistypedData::new(&session).unwrap().name(), None);
+  }
+}
@@ -0,0 +1,625 @@
+use stepflow_base::IdError;
+use stepflow_data::var::{Var, VarId, StringVar, EmailVar, BoolVar, TrueVar, DateTimeVar, NumberVar, EnumVar, ListVar};
+use stepflow_data::value::{StringValue, EmailValue, BoolValue, DateTimeValue, IntValue, FloatValue};
+use stepflow_step::{Step, StepId};
+use stepflow_action::{ActionId, ActionRegistry, FlowEnvironment};
+use crate::{Session, SessionId, Error};
+
+/// A [`Var`] in a [`FlowDefinition`]
+///
+/// `kind` is one of `string`, `email`, `bool`, `true`, `datetime`, `date`, `int`, `float`, `enum` or `list`.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct VarDefinition {
+  pub name: String,
+  pub kind: String,
+
+  /// The options of an `enum`
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub choices: Option<Vec<String>>,
+
+  /// Smallest number allowed for an `int` or `float`
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub min: Option<f64>,
+
+  /// Largest number allowed for an `int` or `float`
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub max: Option<f64>,
+
+  /// Kind of the values in a `list`: `string`, `email`, `bool`, `datetime`, `int` or `float`
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub element: Option<String>,
+
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub description: Option<String>,
+}
+
+impl VarDefinition {
+  /// Define a var of a kind without any options
+  pub fn new<STR: Into<String>>(name: STR, kind: STR) -> Self {
+    VarDefinition { name: name.into(), kind: kind.into(), choices: None, min: None, max: None, element: None, description: None }
+  }
+}
+
+/// A [`Step`] in a [`FlowDefinition`]. Vars and substeps are referred to by name.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct StepDefinition {
+  pub name: String,
+
+  #[serde(default)]
+  pub inputs: Option<Vec<String>>,
+
+  #[serde(default)]
+  pub outputs: Vec<String>,
+
+  #[serde(default)]
+  pub substeps: Vec<String>,
+
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub description: Option<String>,
+}
+
+/// An action in a [`FlowDefinition`], created with an [`ActionRegistry`]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ActionDefinition {
+  pub kind: String,
+
+  #[serde(default)]
+  pub config: serde_json::Value,
+
+  /// Name of the step the action is for. `None` for the general action for all steps.
+  #[serde(default)]
+  pub step: Option<String>,
+
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub description: Option<String>,
+}
+
+/// A whole flow that can be stored in a database or config file instead of being built in code
+///
+/// Create a [`Session`] with [`Session::from_definition`] and get one back with [`Session::to_definition`].
+/// Parse one with [`from_json`](FlowDefinition::from_json), or `from_toml` with the `toml-support` feature.
+/// ```
+/// # use stepflow_session::{Session, SessionId, FlowDefinition};
+/// # use stepflow_action::ActionRegistry;
+/// let definition: FlowDefinition = serde_json::from_value(serde_json::json!({
+///   "vars": [{ "name": "email", "kind": "email" }],
+///   "steps": [{ "name": "signup", "outputs": ["email"] }],
+///   "flow": ["signup"],
+///   "actions": [{ "kind": "uri_template", "config": { "template": "/{{step}}" } }],
+/// })).unwrap();
+/// let session = Session::from_definition(SessionId::new(0), &definition, &ActionRegistry::with_builtins()).unwrap();
+/// assert_eq!(session.to_definition().unwrap(), definition);
+/// ```
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct FlowDefinition {
+  pub vars: Vec<VarDefinition>,
+  pub steps: Vec<StepDefinition>,
+
+  /// Names of the top-level steps in order
+  pub flow: Vec<String>,
+
+  #[serde(default)]
+  pub actions: Vec<ActionDefinition>,
+}
+
+impl FlowDefinition {
+  /// Parse a definition from JSON
+  pub fn from_json(json: &str) -> Result<Self, Error> {
+    serde_json::from_str(json).map_err(|err| Error::InvalidDefinition(err.to_string()))
+  }
+
+  /// Parse a definition from TOML
+  /// ```
+  /// # use stepflow_session::{Session, SessionId, FlowDefinition};
+  /// # use stepflow_action::ActionRegistry;
+  /// let definition = FlowDefinition::from_toml(r#"
+  ///   flow = ["signup"]
+  ///
+  ///   [[vars]]
+  ///   name = "email"
+  ///   kind = "email"
+  ///
+  ///   [[steps]]
+  ///   name = "signup"
+  ///   outputs = ["email"]
+  ///
+  ///   [[actions]]
+  ///   kind = "uri_template"
+  ///   config = { template = "/{{step}}" }
+  /// "#).unwrap();
+  /// let session = Session::from_definition(SessionId::new(0), &definition, &ActionRegistry::with_builtins()).unwrap();
+  /// ```
+  #[cfg(feature = "toml-support")]
+  pub fn from_toml(toml: &str) -> Result<Self, Error> {
+    toml::from_str(toml).map_err(|err| Error::InvalidDefinition(err.to_string()))
+  }
+
+  /// Generate a [`session_data_accessor!`](crate::session_data_accessor) named `name` with a field for each var
+  ///
+  /// Meant for a build script that writes it to `OUT_DIR` to `include!` in the crate using the flow.
+  /// That crate needs `stepflow_session` and `stepflow_data` as dependencies since the value types are spelled out in full.
+  pub fn data_accessor_source(&self, name: &str) -> Result<String, Error> {
+    let invalid_name = |name: &str| Error::InvalidDefinition(format!("{} isn't a valid Rust name", name));
+    if !is_identifier(name) {
+      return Err(invalid_name(name));
+    }
+    let mut source = format!("stepflow_session::session_data_accessor! {{\n  pub struct {} {{\n", name);
+    for var in self.vars.iter() {
+      if !is_identifier(&var.name) {
+        return Err(invalid_name(&var.name));
+      }
+      let val_type = match var.kind.as_str() {
+        "string" => "StringValue",
+        "email" => "EmailValue",
+        "bool" => "BoolValue",
+        "true" => "TrueValue",
+        "datetime" | "date" => "DateTimeValue",
+        "int" => "IntValue",
+        "float" => "FloatValue",
+        "enum" => "EnumValue",
+        "list" => "ListValue",
+        _ => return Err(Error::UnknownVarKind(var.kind.clone())),
+      };
+      source.push_str(&format!("    {}: stepflow_data::value::{},\n", var.name, val_type));
+    }
+    source.push_str("  }\n}\n");
+    Ok(source)
+  }
+}
+
+fn is_identifier(name: &str) -> bool {
+  let mut chars = name.chars();
+  chars.next().map(|first| first.is_ascii_alphabetic() || first == '_').unwrap_or(false)
+    && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn new_var(var: &VarDefinition, id: VarId) -> Result<Box<dyn Var + Send + Sync>, Error> {
+  match var.kind.as_str() {
+    "string" => Ok(StringVar::new(id).boxed()),
+    "email" => Ok(EmailVar::new(id).boxed()),
+    "bool" => Ok(BoolVar::new(id).boxed()),
+    "true" => Ok(TrueVar::new(id).boxed()),
+    "datetime" => Ok(DateTimeVar::new(id).boxed()),
+    "date" => Ok(DateTimeVar::new(id).dates_only().boxed()),
+    "int" => Ok(NumberVar::new_int(id).with_range(var.min, var.max).boxed()),
+    "float" => Ok(NumberVar::new(id).with_range(var.min, var.max).boxed()),
+    "enum" => {
+      let choices = var.choices.clone().ok_or_else(|| Error::InvalidDefinition(format!("{} needs choices", var.name)))?;
+      Ok(EnumVar::new(id, choices).boxed())
+    },
+    "list" => {
+      let list_var = match var.element.as_deref() {
+        Some("string") => ListVar::new::<StringValue>(id),
+        Some("email") => ListVar::new::<EmailValue>(id),
+        Some("bool") => ListVar::new::<BoolValue>(id),
+        Some("datetime") => ListVar::new::<DateTimeValue>(id),
+        Some("int") => ListVar::new::<IntValue>(id),
+        Some("float") => ListVar::new::<FloatValue>(id),
+        Some(element) => return Err(Error::UnknownVarKind(element.to_owned())),
+        None => return Err(Error::InvalidDefinition(format!("{} needs an element kind", var.name))),
+      };
+      Ok(list_var.boxed())
+    },
+    _ => Err(Error::UnknownVarKind(var.kind.clone())),
+  }
+}
+
+fn list_element_kind(list_var: &ListVar) -> Option<&'static str> {
+  if list_var.is_list_of::<StringValue>() {
+    Some("string")
+  } else if list_var.is_list_of::<EmailValue>() {
+    Some("email")
+  } else if list_var.is_list_of::<BoolValue>() {
+    Some("bool")
+  } else if list_var.is_list_of::<DateTimeValue>() {
+    Some("datetime")
+  } else if list_var.is_list_of::<IntValue>() {
+    Some("int")
+  } else if list_var.is_list_of::<FloatValue>() {
+    Some("float")
+  } else {
+    None
+  }
+}
+
+fn var_definition(name: String, var: &(dyn Var + Send + Sync + 'static)) -> Option<VarDefinition> {
+  let kind = if var.is::<StringVar>() {
+    "string"
+  } else if var.is::<EmailVar>() {
+    "email"
+  } else if var.is::<BoolVar>() {
+    "bool"
+  } else if var.is::<TrueVar>() {
+    "true"
+  } else if let Some(datetime_var) = var.downcast::<DateTimeVar>() {
+    if datetime_var.accepts_time() { "datetime" } else { "date" }
+  } else if let Some(number_var) = var.downcast::<NumberVar>() {
+    let kind = if number_var.is_int() { "int" } else { "float" };
+    return Some(VarDefinition { min: number_var.min(), max: number_var.max(), ..VarDefinition::new(name, kind.to_owned()) });
+  } else if let Some(enum_var) = var.downcast::<EnumVar>() {
+    return Some(VarDefinition { choices: Some(enum_var.options().to_vec()), ..VarDefinition::new(name, "enum".to_owned()) });
+  } else if let Some(list_var) = var.downcast::<ListVar>() {
+    let element = list_element_kind(list_var)?;
+    return Some(VarDefinition { element: Some(element.to_owned()), ..VarDefinition::new(name, "list".to_owned()) });
+  } else {
+    return None;
+  };
+  Some(VarDefinition::new(name, kind.to_owned()))
+}
+
+impl Session {
+  /// Create a session from a [`FlowDefinition`], creating its actions with `registry`
+  pub fn from_definition(id: SessionId, definition: &FlowDefinition, registry: &ActionRegistry) -> Result<Self, Error> {
+    Self::from_definition_with_environment(id, definition, registry, FlowEnvironment::new())
+  }
+
+  /// Same as [`from_definition`](Session::from_definition) with the `${env.<key>}` references in action configs
+  /// [resolved](FlowEnvironment::resolve_config) from `environment`, which becomes the session's [environment](Session::environment)
+  pub fn from_definition_with_environment(id: SessionId, definition: &FlowDefinition, registry: &ActionRegistry, environment: FlowEnvironment)
+      -> Result<Self, Error>
+  {
+    let mut session = Session::new(id);
+    session.set_environment(environment);
+    for var in definition.vars.iter() {
+      let var_id = session.var_store_mut().reserve_id()?;
+      let new_var = new_var(var, var_id)?;
+      session.var_store_mut().register_named(var.name.clone(), new_var)?;
+      if let Some(description) = &var.description {
+        session.set_var_description(&var_id, description.clone())?;
+      }
+    }
+
+    let var_ids = |names: &[String], session: &Session| -> Result<Vec<VarId>, Error> {
+      names.iter().map(|name| session.var_id_from_name(name)).collect()
+    };
+    for step in definition.steps.iter() {
+      let inputs = step.inputs.as_ref().map(|inputs| var_ids(inputs, &session)).transpose()?;
+      let outputs = var_ids(&step.outputs, &session)?;
+      session.step_store_mut().insert_new_named(step.name.clone(), |id| {
+        let mut new_step = Step::new(id, inputs, outputs);
+        if let Some(description) = &step.description {
+          new_step.set_description(description.clone());
+        }
+        Ok(new_step)
+      })?;
+    }
+
+    // wire up the substeps once all the steps exist
+    let step_id = |name: &str, session: &Session| -> Result<StepId, Error> {
+      session.step_store().id_from_name(name).copied().ok_or_else(|| Error::StepId(IdError::NoSuchName(name.to_owned())))
+    };
+    for step in definition.steps.iter() {
+      let parent_id = step_id(&step.name, &session)?;
+      for substep in step.substeps.iter() {
+        let substep_id = step_id(substep, &session)?;
+        session.step_store_mut().get_mut(&parent_id).unwrap().push_substep(substep_id);
+      }
+    }
+    for name in definition.flow.iter() {
+      let step_id = step_id(name, &session)?;
+      session.push_root_substep(step_id);
+    }
+
+    for action in definition.actions.iter() {
+      let step_id = action.step.as_ref().map(|name| step_id(name, &session)).transpose()?;
+      let action_id = session.action_store_mut().reserve_id()?;
+      let created = registry.create_with_environment(&action.kind, action_id, &action.config, session.var_store(), session.environment())?;
+      session.action_store_mut().register(created)?;
+      session.set_action_for_step(action_id, step_id.as_ref())?;
+      if let Some(description) = &action.description {
+        session.set_action_description(&action_id, description.clone())?;
+      }
+      session.action_definitions.insert(action_id, ActionDefinition { description: None, ..action.clone() });
+    }
+    Ok(session)
+  }
+
+  /// Get the [`FlowDefinition`] for the session
+  ///
+  /// Vars and steps need names. Only the built-in vars and actions created by [`from_definition`](Session::from_definition) can be included.
+  /// Fails with [`Error::NotInDefinition`] if the session uses action bindings, action candidates, fallback or action chains,
+  /// repeating steps or step validators since a definition can't hold them.
+  /// Step tags, metadata, display names, visit policies, skip and output conditions are left out.
+  pub fn to_definition(&self) -> Result<FlowDefinition, Error> {
+    self.check_definable()?;
+
+    let mut var_ids = self.var_store().iter().map(|(var_id, _)| *var_id).collect::<Vec<_>>();
+    var_ids.sort();
+    let var_name = |var_id: &VarId| -> Result<String, Error> {
+      self.var_store().name_from_id(var_id).map(|name| name.to_owned()).ok_or(Error::VarId(IdError::IdHasNoName(*var_id)))
+    };
+    let vars = var_ids.iter()
+      .map(|var_id| {
+        let var = self.var_store().get(var_id).ok_or(Error::VarId(IdError::IdMissing(*var_id)))?;
+        let name = var_name(var_id)?;
+        let var_definition = var_definition(name.clone(), var.as_ref()).ok_or(Error::UnknownVarKind(name))?;
+        Ok(VarDefinition { description: self.var_description(var_id).map(|description| description.to_owned()), ..var_definition })
+      })
+      .collect::<Result<Vec<_>, Error>>()?;
+
+    let step_name = |step_id: &StepId| -> Result<String, Error> {
+      self.step_store().name_from_id(step_id).map(|name| name.to_owned()).ok_or(Error::StepId(IdError::IdHasNoName(*step_id)))
+    };
+    let mut step_ids = self.step_store().iter()
+      .map(|(step_id, _)| *step_id)
+      .filter(|step_id| *step_id != self.step_id_all && *step_id != self.step_id_root)
+      .collect::<Vec<_>>();
+    step_ids.sort();
+    let steps = step_ids.iter()
+      .map(|step_id| {
+        let step = self.step_store().get(step_id).ok_or(Error::StepId(IdError::IdMissing(*step_id)))?;
+        Ok(StepDefinition {
+          name: step_name(step_id)?,
+          inputs: step.get_input_vars().as_ref().map(|inputs| inputs.iter().map(var_name).collect()).transpose()?,
+          outputs: step.get_output_vars().iter().map(var_name).collect::<Result<_, _>>()?,
+          substeps: step.get_substeps().into_iter().flatten().map(step_name).collect::<Result<_, _>>()?,
+          description: step.description().map(|description| description.to_owned()),
+        })
+      })
+      .collect::<Result<Vec<_>, Error>>()?;
+
+    let root = self.step_store().get(&self.step_id_root).ok_or(Error::StepId(IdError::IdMissing(self.step_id_root)))?;
+    let flow = root.get_substeps().into_iter().flatten().map(step_name).collect::<Result<_, _>>()?;
+
+    let mut actions = self.actions.iter().collect::<Vec<(&StepId, &ActionId)>>();
+    actions.sort();
+    let actions = actions.into_iter()
+      .map(|(step_id, action_id)| {
+        let action = self.action_definitions.get(action_id).ok_or(Error::ActionId(IdError::IdUnexpected(*action_id)))?;
+        let step = if *step_id == self.step_id_all { None } else { Some(step_name(step_id)?) };
+        let description = self.action_description(action_id).map(|description| description.to_owned());
+        Ok(ActionDefinition { step, description, ..action.clone() })
+      })
+      .collect::<Result<Vec<_>, Error>>()?;
+
+    Ok(FlowDefinition { vars, steps, flow, actions })
+  }
+
+  fn check_definable(&self) -> Result<(), Error> {
+    let not_in_definition = |what: &str| Err(Error::NotInDefinition(what.to_owned()));
+    if !self.action_bindings.is_empty() {
+      return not_in_definition("action bindings");
+    }
+    if !self.action_candidates.is_empty() {
+      return not_in_definition("action candidates");
+    }
+    if !self.fallback_chains.is_empty() {
+      return not_in_definition("fallback chains");
+    }
+    for (step_id, step) in self.step_store().iter() {
+      let step_name = || self.step_store().name_from_id(step_id).map(|name| name.to_owned()).unwrap_or_else(|| step_id.to_string());
+      if step.repeat().is_some() {
+        return not_in_definition(&format!("repeat on {}", step_name()));
+      }
+      if step.has_validators() {
+        return not_in_definition(&format!("validators on {}", step_name()));
+      }
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use stepflow_base::IdError;
+  use stepflow_action::{Action, ActionRegistry, FlowEnvironment};
+  use stepflow_data::{Condition, InvalidValue, StateDataFiltered, var::{EmailVar, ListVar}, value::EmailValue};
+  use stepflow_step::{Repeat, StepMatcher};
+  use crate::{Session, SessionId, AdvanceBlockedOn, Error};
+  use super::{FlowDefinition, VarDefinition};
+
+  fn definition() -> FlowDefinition {
+    serde_json::from_value(serde_json::json!({
+      "vars": [{ "name": "name", "kind": "string" }, { "name": "email", "kind": "email" }],
+      "steps": [
+        { "name": "signup", "substeps": ["name", "email"] },
+        { "name": "name", "outputs": ["name"] },
+        { "name": "email", "inputs": ["name"], "outputs": ["email"] },
+      ],
+      "flow": ["signup"],
+      "actions": [
+        { "kind": "uri_template", "config": { "template": "/flow/{{step}}" } },
+        { "kind": "set_data", "config": { "data": { "email": "jane@example.com" } }, "step": "email" },
+      ],
+    })).unwrap()
+  }
+
+  #[test]
+  fn round_trip() {
+    let definition = definition();
+    let mut session = Session::from_definition(SessionId::new(0), &definition, &ActionRegistry::with_builtins()).unwrap();
+    assert_eq!(session.to_definition().unwrap(), definition);
+
+    let email_step = *session.step_store().id_from_name("email").unwrap();
+    let email_var = session.var_id_from_name("email").unwrap();
+    assert!(session.var_store().get(&email_var).unwrap().is::<EmailVar>());
+    assert_eq!(session.step_store().get(&email_step).unwrap().get_input_vars(), &Some(vec![session.var_id_from_name("name").unwrap()]));
+
+    // general action asks for the name
+    assert!(matches!(session.advance(None), Ok(AdvanceBlockedOn::ActionStartWith(_, _, _))));
+  }
+
+  #[test]
+  fn var_kinds() {
+    let vars = serde_json::json!([
+      { "name": "when", "kind": "datetime" },
+      { "name": "age", "kind": "int", "min": 18.0 },
+      { "name": "score", "kind": "float", "min": 0.0, "max": 1.0 },
+      { "name": "plan", "kind": "enum", "choices": ["free", "pro"] },
+      { "name": "emails", "kind": "list", "element": "email" },
+      { "name": "birthdate", "kind": "date" },
+    ]);
+    let mut definition = definition();
+    definition.vars.extend(serde_json::from_value::<Vec<VarDefinition>>(vars).unwrap());
+    let registry = ActionRegistry::with_builtins();
+    let session = Session::from_definition(SessionId::new(0), &definition, &registry).unwrap();
+    assert_eq!(session.to_definition().unwrap(), definition);
+
+    let age = session.var_store().get(&session.var_id_from_name("age").unwrap()).unwrap();
+    assert_eq!(age.value_from_str("16"), Err(InvalidValue::OutOfRange));
+    let emails = session.var_store().get(&session.var_id_from_name("emails").unwrap()).unwrap();
+    assert!(emails.downcast::<ListVar>().unwrap().is_list_of::<EmailValue>());
+    let birthdate = session.var_store().get(&session.var_id_from_name("birthdate").unwrap()).unwrap();
+    assert_eq!(birthdate.value_from_str("1990-07-04T08:30"), Err(InvalidValue::WrongValue));
+
+    let mut no_choices = definition.clone();
+    no_choices.vars[5].choices = None;
+    assert!(matches!(Session::from_definition(SessionId::new(0), &no_choices, &registry), Err(Error::InvalidDefinition(_))));
+    let mut bad_element = definition;
+    bad_element.vars[6].element = Some("color".to_owned());
+    assert_eq!(Session::from_definition(SessionId::new(0), &bad_element, &registry).unwrap_err(), Error::UnknownVarKind("color".to_owned()));
+  }
+
+  #[test]
+  fn descriptions() {
+    let mut definition = definition();
+    definition.vars[1].description = Some("Where we send your receipt".to_owned());
+    definition.steps[0].description = Some("Create an account".to_owned());
+    definition.actions[1].description = Some("Fills in a test email".to_owned());
+    let session = Session::from_definition(SessionId::new(0), &definition, &ActionRegistry::with_builtins()).unwrap();
+    assert_eq!(session.var_description(&session.var_id_from_name("email").unwrap()), Some("Where we send your receipt"));
+    assert_eq!(session.to_definition().unwrap(), definition);
+  }
+
+  #[test]
+  fn not_in_definition() {
+    let registry = ActionRegistry::with_builtins();
+    let new_session = || Session::from_definition(SessionId::new(0), &definition(), &registry).unwrap();
+    let not_in_definition = |what: &str| Err(Error::NotInDefinition(what.to_owned()));
+
+    let mut session = new_session();
+    let name_step = *session.step_store().id_from_name("name").unwrap();
+    let action_id = *session.actions.get(&session.step_id_all).unwrap();
+    session.bind_action(action_id, StepMatcher::Tag("contact".to_owned())).unwrap();
+    assert_eq!(session.to_definition(), not_in_definition("action bindings"));
+
+    let mut session = new_session();
+    session.add_action_candidate(&name_step, action_id, Condition::All(vec![])).unwrap();
+    assert_eq!(session.to_definition(), not_in_definition("action candidates"));
+
+    let mut session = new_session();
+    session.set_fallback_chain(name_step, vec![(action_id, 2)]).unwrap();
+    assert_eq!(session.to_definition(), not_in_definition("fallback chains"));
+
+    let mut session = new_session();
+    session.push_action_for_step(&name_step, action_id).unwrap();
+    assert_eq!(session.to_definition(), not_in_definition("fallback chains"));
+
+    let mut session = new_session();
+    let signup_step = *session.step_store().id_from_name("signup").unwrap();
+    session.step_store_mut().get_mut(&signup_step).unwrap().set_repeat(Repeat::Times(2));
+    assert_eq!(session.to_definition(), not_in_definition("repeat on signup"));
+
+    let mut session = new_session();
+    session.step_store_mut().get_mut(&name_step).unwrap().add_validator(|_: &StateDataFiltered| Ok(()));
+    assert_eq!(session.to_definition(), not_in_definition("validators on name"));
+  }
+
+  #[test]
+  fn environment() {
+    let mut definition = definition();
+    definition.actions[0].config = serde_json::json!({ "template": "${env.base}/{{step}}" });
+    let registry = ActionRegistry::with_builtins();
+    assert!(Session::from_definition(SessionId::new(0), &definition, &registry).is_err());
+
+    let mut environment = FlowEnvironment::new();
+    environment.set("base", "/staging");
+    let mut session = Session::from_definition_with_environment(SessionId::new(0), &definition, &registry, environment.clone()).unwrap();
+    assert_eq!(session.environment(), &environment);
+    match session.advance(None) {
+      Ok(AdvanceBlockedOn::ActionStartWith(_, val, _)) => assert_eq!(val.get_baseval().to_string(), "/staging/name"),
+      other => panic!("unexpected {:?}", other),
+    }
+
+    // the definition keeps the reference
+    assert_eq!(session.to_definition().unwrap(), definition);
+  }
+
+  #[test]
+  fn parse() {
+    let json = serde_json::to_string(&definition()).unwrap();
+    assert_eq!(FlowDefinition::from_json(&json), Ok(definition()));
+    assert!(matches!(FlowDefinition::from_json("{ \"vars\": [] }"), Err(Error::InvalidDefinition(_))));
+  }
+
+  #[cfg(feature = "toml-support")]
+  #[test]
+  fn parse_toml() {
+    let toml = r#"
+      flow = ["signup"]
+      vars = [{ name = "name", kind = "string" }, { name = "email", kind = "email" }]
+
+      [[steps]]
+      name = "signup"
+      substeps = ["name", "email"]
+
+      [[steps]]
+      name = "name"
+      outputs = ["name"]
+
+      [[steps]]
+      name = "email"
+      inputs = ["name"]
+      outputs = ["email"]
+
+      [[actions]]
+      kind = "uri_template"
+      config = { template = "/flow/{{step}}" }
+
+      [[actions]]
+      kind = "set_data"
+      config = { data = { email = "jane@example.com" } }
+      step = "email"
+    "#;
+    assert_eq!(FlowDefinition::from_toml(toml), Ok(definition()));
+    assert!(matches!(FlowDefinition::from_toml("flow = "), Err(Error::InvalidDefinition(_))));
+  }
+
+  crate::session_data_accessor! {
+    struct DefinitionData {
+      name: stepflow_data::value::StringValue,
+      email: stepflow_data::value::EmailValue,
+    }
+  }
+
+  #[test]
+  fn data_accessor_source() {
+    let definition = definition();
+    assert_eq!(definition.data_accessor_source("DefinitionData").unwrap(), "stepflow_session::session_data_accessor! {
+  pub struct DefinitionData {
+    name: stepflow_data::value::StringValue,
+    email: stepflow_data::value::EmailValue,
+  }
+}
+");
+    // the types line up with the vars created from the definition
+    let session = Session::from_definition(SessionId::new(0), &definition, &ActionRegistry::with_builtins()).unwrap();
+    let data = DefinitionData::new(&session).unwrap();
+    assert_eq!((data.name(), data.email()), (None, None));
+
+    let mut bad_name = definition.clone();
+    bad_name.vars[0].name = "first-name".to_owned();
+    assert!(matches!(bad_name.data_accessor_source("DefinitionData"), Err(Error::InvalidDefinition(_))));
+    assert!(matches!(definition.data_accessor_source("1Data"), Err(Error::InvalidDefinition(_))));
+  }
+
+  #[test]
+  fn bad_definitions() {
+    let registry = ActionRegistry::with_builtins();
+
+    let mut bad_kind = definition();
+    bad_kind.vars[0].kind = "color".to_owned();
+    assert_eq!(Session::from_definition(SessionId::new(0), &bad_kind, &registry).unwrap_err(), Error::UnknownVarKind("color".to_owned()));
+
+    let mut missing_step = definition();
+    missing_step.flow.push("missing".to_owned());
+    assert!(Session::from_definition(SessionId::new(0), &missing_step, &registry).is_err());
+
+    // actions not created from a definition can't be included
+    let mut session = Session::from_definition(SessionId::new(0), &definition(), &registry).unwrap();
+    let name_step = *session.step_store().id_from_name("name").unwrap();
+    let action_id = session.action_store_mut().insert_new(|id| Ok(stepflow_action::testing::MockAction::new(id).boxed())).unwrap();
+    session.set_action_for_step(action_id, Some(&name_step)).unwrap();
+    assert_eq!(session.to_definition(), Err(Error::ActionId(IdError::IdUnexpected(action_id))));
+  }
+}
@@ -1,4 +1,4 @@
-use stepflow_base::ObjectStore;
+use stepflow_base::{ObjectStore, IdError};
 use stepflow_step::{Step, StepId};
 use super::{Error};
 
@@ -20,8 +20,23 @@ enum DFSStep {
   PoppedUp,
 }
 
-#[derive(Debug)]
+/// All steps reachable from `root` in depth first order
+pub fn preorder(root: &StepId, step_store: &ObjectStore<Step, StepId>) -> Vec<StepId> {
+  let mut result = Vec::new();
+  let mut to_visit = vec![*root];
+  while let Some(step_id) = to_visit.pop() {
+    if let Some(substeps) = step_store.get(&step_id).and_then(|step| step.get_substeps()) {
+      to_visit.extend(substeps.iter().rev().cloned());
+    }
+    result.push(step_id);
+  }
+  result
+}
+
+/// Visits the steps depth first in the order they were added
+#[derive(Debug, Clone)]
 pub struct DepthFirstSearch {
+  root: StepId,
   stack: Vec<StepId>,
   next_direction: DFSDirection,
 }
@@ -29,6 +44,7 @@ pub struct DepthFirstSearch {
 impl DepthFirstSearch {
   pub fn new(root: StepId) -> Self {
     DepthFirstSearch {
+      root,
       stack: vec![root],
       next_direction: DFSDirection::Down,
     }
@@ -38,6 +54,71 @@ impl DepthFirstSearch {
     self.stack.last()
   }
 
+  /// Go back to the root
+  pub fn restart(&mut self) {
+    *self = DepthFirstSearch::new(self.root);
+  }
+
+  /// Check if the step is the current step or one of its ancestors
+  pub fn is_entered(&self, step_id: &StepId) -> bool {
+    self.stack.contains(step_id)
+  }
+
+  /// The current step and its ancestors, starting with the root
+  pub fn entered(&self) -> &[StepId] {
+    &self.stack
+  }
+
+  /// All steps reachable from the root in the order they're visited
+  pub fn preorder(&self, step_store: &ObjectStore<Step, StepId>) -> Vec<StepId> {
+    preorder(&self.root, step_store)
+  }
+
+  /// Steps that have been visited and exited, in the order they were visited
+  pub fn completed(&self, step_store: &ObjectStore<Step, StepId>) -> Vec<StepId> {
+    let current = self.stack.last();
+    self.preorder(step_store)
+      .into_iter()
+      .take_while(|step_id| Some(step_id) != current)
+      .filter(|step_id| !self.stack.contains(step_id))
+      .collect()
+  }
+
+  /// Move the search back to `step_id` as if it was just entered
+  pub fn rewind_to(&mut self, step_id: &StepId, step_store: &ObjectStore<Step, StepId>) -> Result<(), Error> {
+    fn path_to(from: &StepId, to: &StepId, step_store: &ObjectStore<Step, StepId>, path: &mut Vec<StepId>) -> bool {
+      path.push(*from);
+      if from == to {
+        return true;
+      }
+      if let Some(substeps) = step_store.get(from).and_then(|step| step.get_substeps()) {
+        for substep in substeps {
+          if path_to(substep, to, step_store, path) {
+            return true;
+          }
+        }
+      }
+      path.pop();
+      false
+    }
+
+    let mut path = Vec::new();
+    if !path_to(&self.root, step_id, step_store, &mut path) {
+      return Err(Error::StepId(IdError::IdMissing(*step_id)));
+    }
+
+    // leaves wait on being exited, otherwise continue down into the substeps
+    let has_substeps = step_store.get(step_id).and_then(|step| step.first_substep()).is_some();
+    self.next_direction = if has_substeps { DFSDirection::Down } else { DFSDirection::SiblingOrUp };
+    self.stack = path;
+    Ok(())
+  }
+
+  /// The step [`next`](DepthFirstSearch::next) would enter if every step could be entered and exited
+  pub fn peek_next(&self, step_store: &ObjectStore<Step, StepId>) -> Option<StepId> {
+    self.clone().next(|_| Ok(()), |_| Ok(()), step_store).ok().flatten()
+  }
+
   fn next_sibling_of_current<'store>(&self, step_store: &'store ObjectStore<Step, StepId>) -> Option<&'store StepId> {
     let stack_len = self.stack.len();
     if stack_len < 2 {
@@ -314,4 +395,39 @@ mod tests {
 
     assert_dfs_order_with_failures(root, &step_store, &expected_children);
   }
+
+  #[test]
+  fn rewind() {
+    let mut step_store: ObjectStore<Step, StepId> = ObjectStore::new();
+    let root = step_store.insert_new(|id| Ok(Step::new(id, None, vec![]))).unwrap();
+    let root_children = add_substeps(2, &root, &mut step_store);
+    let children1 = add_substeps(2, &root_children[0], &mut step_store);
+
+    let mut dfs = DepthFirstSearch::new(root);
+    assert_eq!(dfs.preorder(&step_store), vec![root, root_children[0], children1[0], children1[1], root_children[1]]);
+
+    // advance to the last step
+    for _ in 0..3 {
+      dfs.next(|_| Ok(()), |_| Ok(()), &step_store).unwrap();
+    }
+    assert_eq!(dfs.current(), Some(&root_children[1]));
+    assert_eq!(dfs.completed(&step_store), vec![root_children[0], children1[0], children1[1]]);
+
+    // go back to a leaf, it's re-checked for exit before moving on
+    dfs.rewind_to(&children1[1], &step_store).unwrap();
+    assert_eq!(dfs.current(), Some(&children1[1]));
+    assert_eq!(dfs.completed(&step_store), vec![children1[0]]);
+    assert_eq!(dfs.next(|_| Ok(()), |_| Err(Error::NoStateToEval), &step_store), Err(Error::NoStateToEval));
+    assert_eq!(dfs.next(|_| Ok(()), |_| Ok(()), &step_store), Ok(Some(root_children[1])));
+
+    // finish, everything is completed, then go back to a parent which goes into its children
+    assert_eq!(dfs.next(|_| Ok(()), |_| Ok(()), &step_store), Ok(None));
+    assert_eq!(dfs.completed(&step_store).len(), 5);
+    dfs.rewind_to(&root_children[0], &step_store).unwrap();
+    assert_eq!(dfs.next(|_| Ok(()), |_| Ok(()), &step_store), Ok(Some(children1[0])));
+
+    // unknown step
+    let unknown = step_store.insert_new(|id| Ok(Step::new(id, None, vec![]))).unwrap();
+    assert!(dfs.rewind_to(&unknown, &step_store).is_err());
+  }
 }
\ No newline at end of file
@@ -2,7 +2,7 @@ use stepflow_base::IdError;
 use stepflow_data::var::VarId;
 use stepflow_step::StepId;
 use stepflow_action::{ActionError, ActionId};
-use crate::SessionId;
+use crate::{SessionId, AttemptId, QuotaExceeded, DefinitionDrift, TransitionCheck};
 
 #[derive(Debug, PartialEq, Clone)]
 #[cfg_attr(feature = "serde-support", derive(serde::Serialize))]
@@ -12,19 +12,76 @@ pub enum Error {
   StepId(IdError<StepId>),
   ActionId(IdError<ActionId>),
   SessionId(IdError<SessionId>),
+  /// Submitting for an attempt the session isn't blocked on, i.e. it was already submitted
+  AttemptId(IdError<AttemptId>),
 
   // data errors
   InvalidValue(stepflow_data::InvalidValue),
   InvalidVars(stepflow_data::InvalidVars),
   InvalidStateDataError,
+  /// Every var a step is missing to be entered or exited
+  MissingVars(AdvanceError),
+  /// Vars the actor isn't allowed to write
+  WriteDenied(Vec<VarId>),
 
   // action + step execution errors
   NoStateToEval,
+  /// The current step is assigned to someone else
+  NotAssignee(StepId),
+  /// The step's [`Repeat::Until`](stepflow_step::Repeat::Until) ran `max_times` without its condition holding
+  RepeatLimit(StepId),
+  /// No [follow-up](crate::Session::pending_follow_ups) at the index
+  FollowUpIndex(usize),
+  /// The steps left can't be entered in any order that keeps the [ordering constraints](crate::PriorityTraversal::with_order),
+  /// i.e. one has to come after another that has to come after it. Has one of the steps.
+  StepOrder(StepId),
+
+  // action configuration errors
+  UnknownActionKind(String),
+  ActionKindAlreadyExists(String),
+  InvalidActionConfig(String),
+  ActionPluginFailed(String),
+
+  // definition errors
+  /// A var kind that isn't built in, or the name of a var of a custom kind
+  UnknownVarKind(String),
+  /// A flow definition that couldn't be parsed, with the parser's message
+  InvalidDefinition(String),
+  /// The session uses something a flow definition can't hold, i.e. `"validators on review"`
+  NotInDefinition(String),
+  /// The definition changed under the session, i.e. it still uses removed steps
+  DefinitionDrift(DefinitionDrift),
+
+  // resource limits
+  QuotaExceeded(QuotaExceeded),
+  /// The session was a [preview](crate::Session::set_preview) and its time is up
+  PreviewExpired,
 
   // something we try to not use
   Other,
 }
 
+/// The step an advance is blocked on and the vars it's missing, with their names so they can be shown as is
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde-support", derive(serde::Serialize))]
+pub struct AdvanceError {
+  pub step_id: StepId,
+  pub step_name: Option<String>,
+
+  /// Whether the vars are needed to enter or exit the step
+  pub check: TransitionCheck,
+
+  /// Each var with its name. `None` if the var doesn't have a name.
+  pub missing_vars: Vec<(VarId, Option<String>)>,
+}
+
+impl AdvanceError {
+  /// IDs of the missing vars
+  pub fn var_ids(&self) -> Vec<VarId> {
+    self.missing_vars.iter().map(|(var_id, _name)| *var_id).collect()
+  }
+}
+
 impl From<stepflow_data::InvalidValue> for Error {
   fn from(err: stepflow_data::InvalidValue) -> Self {
     Error::InvalidValue(err)
@@ -36,6 +93,11 @@ impl From<ActionError> for Error {
       match err {
           ActionError::VarId(id_error) => Error::VarId(id_error),
           ActionError::StepId(id_error) => Error::StepId(id_error),
+          ActionError::InvalidVars(invalid) => Error::InvalidVars(invalid),
+          ActionError::UnknownKind(kind) => Error::UnknownActionKind(kind),
+          ActionError::KindAlreadyExists(kind) => Error::ActionKindAlreadyExists(kind),
+          ActionError::InvalidConfig(msg) => Error::InvalidActionConfig(msg),
+          ActionError::PluginFailed(msg) => Error::ActionPluginFailed(msg),
           ActionError::Other => Error::Other,
       }
     }
@@ -62,4 +124,5 @@ macro_rules! from_id_error {
 from_id_error!(VarId);
 from_id_error!(StepId);
 from_id_error!(ActionId);
-from_id_error!(SessionId);
\ No newline at end of file
+from_id_error!(SessionId);
+from_id_error!(AttemptId);
\ No newline at end of file
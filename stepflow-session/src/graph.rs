@@ -0,0 +1,107 @@
+use std::fmt::Write;
+use stepflow_base::ObjectStore;
+use stepflow_step::{Step, StepId};
+
+/// Where a [`Step`] is in a running [`Session`](crate::Session)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde-support", derive(serde::Serialize))]
+pub enum StepStatus {
+  /// Exited with all of its outputs set
+  Completed,
+
+  /// Exited without some of its outputs, i.e. when they weren't required
+  Skipped,
+
+  /// Contains the current step
+  Entered,
+
+  /// The current step and it has everything it needs to exit
+  Current,
+
+  /// The current step and it's waiting on inputs or outputs
+  Blocked,
+
+  /// Not visited yet
+  Pending,
+}
+
+impl StepStatus {
+  /// Lowercase name of the status
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      StepStatus::Completed => "completed",
+      StepStatus::Skipped => "skipped",
+      StepStatus::Entered => "entered",
+      StepStatus::Current => "current",
+      StepStatus::Blocked => "blocked",
+      StepStatus::Pending => "pending",
+    }
+  }
+
+  fn fill_color(&self) -> &'static str {
+    match self {
+      StepStatus::Completed => "palegreen",
+      StepStatus::Skipped => "lightgray",
+      StepStatus::Entered => "lightblue",
+      StepStatus::Current => "gold",
+      StepStatus::Blocked => "tomato",
+      StepStatus::Pending => "white",
+    }
+  }
+}
+
+fn escape_label(label: &str) -> String {
+  label.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+// render the steps as a DOT digraph with edges from each step to its substeps and the description of each step's action next to it
+pub(crate) fn to_dot<'a>(step_statuses: &[(StepId, StepStatus)], step_store: &ObjectStore<Step, StepId>, action_description: impl Fn(&StepId) -> Option<&'a str>) -> String {
+  let mut dot = String::from("digraph session {\n  node [shape=box style=filled];\n");
+  for (step_id, status) in step_statuses {
+    let label = step_store.name_from_id(step_id).map(|name| name.to_owned()).unwrap_or_else(|| step_id.to_string());
+    let tooltip = step_store.get(step_id)
+      .and_then(|step| step.description())
+      .map(|description| format!(" tooltip=\"{}\"", escape_label(description)))
+      .unwrap_or_default();
+    let xlabel = action_description(step_id)
+      .map(|description| format!(" xlabel=\"{}\"", escape_label(description)))
+      .unwrap_or_default();
+    writeln!(dot, "  \"{}\" [label=\"{}\" status=\"{}\" fillcolor=\"{}\"{}{}];",
+      step_id, escape_label(&label), status.as_str(), status.fill_color(), tooltip, xlabel).unwrap();
+  }
+  for (step_id, _) in step_statuses {
+    if let Some(substeps) = step_store.get(step_id).and_then(|step| step.get_substeps()) {
+      for substep_id in substeps {
+        writeln!(dot, "  \"{}\" -> \"{}\";", step_id, substep_id).unwrap();
+      }
+    }
+  }
+  dot.push_str("}\n");
+  dot
+}
+
+#[cfg(test)]
+mod tests {
+  use stepflow_base::ObjectStore;
+  use stepflow_step::{Step, StepId};
+  use super::{to_dot, StepStatus};
+
+  #[test]
+  fn dot() {
+    let mut step_store: ObjectStore<Step, StepId> = ObjectStore::new();
+    let child_id = step_store.insert_new_named("say \"hi\"", |id| Ok(Step::new(id, None, vec![]))).unwrap();
+    let parent_id = step_store.insert_new(|id| Ok(Step::new(id, None, vec![]))).unwrap();
+    step_store.get_mut(&parent_id).unwrap().push_substep(child_id);
+    step_store.get_mut(&child_id).unwrap().set_description("Greet the user");
+
+    let action_description = |step_id: &StepId| if *step_id == child_id { Some("Sends a \"hello\"") } else { None };
+    let dot = to_dot(&[(parent_id, StepStatus::Entered), (child_id, StepStatus::Blocked)], &step_store, action_description);
+    assert_eq!(dot, format!(concat!(
+      "digraph session {{\n",
+      "  node [shape=box style=filled];\n",
+      "  \"{parent}\" [label=\"{parent}\" status=\"entered\" fillcolor=\"lightblue\"];\n",
+      "  \"{child}\" [label=\"say \\\"hi\\\"\" status=\"blocked\" fillcolor=\"tomato\" tooltip=\"Greet the user\" xlabel=\"Sends a \\\"hello\\\"\"];\n",
+      "  \"{parent}\" -> \"{child}\";\n",
+      "}}\n"), parent = parent_id, child = child_id));
+  }
+}
@@ -0,0 +1,121 @@
+use std::sync::Arc;
+use std::time::SystemTime;
+use stepflow_base::IdError;
+use stepflow_data::StateData;
+use stepflow_step::StepId;
+use stepflow_action::{ActionId, ActionResult};
+use super::Error;
+
+/// An entry in the [history](crate::Session::history) of a session, i.e. for an audit log
+///
+/// Order events by `seq` rather than `at` since the clock can be adjusted between them.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde-support", derive(serde::Serialize))]
+pub struct SessionEvent {
+  /// Position in the session's history, starting at 0. It keeps counting after [`take_history`](crate::Session::take_history).
+  pub seq: u64,
+
+  /// When it happened by the wall clock
+  pub at: SystemTime,
+
+  pub kind: SessionEventKind,
+}
+
+impl SessionEvent {
+  pub(crate) fn now(seq: u64, kind: SessionEventKind) -> Self {
+    SessionEvent { seq, at: SystemTime::now(), kind }
+  }
+}
+
+// the session as it was when an event was recorded, for `Session::state_at`
+#[derive(Debug, Clone)]
+pub(crate) struct Checkpoint {
+  pub(crate) seq: u64,
+  // shared with the checkpoints before it while the data doesn't change
+  pub(crate) state_data: Arc<StateData>,
+  pub(crate) current_step: Option<StepId>,
+}
+
+/// One time a session blocked on a step's action, i.e. to correlate logs or only take a form that's posted twice once
+///
+/// It's the [`seq`](SessionEvent::seq) of the [`ActionStarted`](SessionEventKind::ActionStarted) event for the action so it's unique within the session.
+/// Showing the same action again when nothing else happened in between, i.e. with `advance(None)`, keeps the attempt and isn't recorded again.
+/// See [`Session::advance_attempt`](crate::Session::advance_attempt).
+#[derive(Hash, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde-support", derive(serde::Serialize, serde::Deserialize))]
+pub struct AttemptId(u64);
+
+impl AttemptId {
+  pub fn new(seq: u64) -> Self {
+    AttemptId(seq)
+  }
+  pub fn val(&self) -> u64 {
+    self.0
+  }
+}
+
+impl std::fmt::Display for AttemptId {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}", self.0)
+  }
+}
+
+impl std::str::FromStr for AttemptId {
+  type Err = IdError<AttemptId>;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let seq = s.parse::<u64>().map_err(|_e| IdError::CannotParse(s.to_owned()))?;
+    Ok(Self::new(seq))
+  }
+}
+
+/// What happened in a [`SessionEvent`]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde-support", derive(serde::Serialize))]
+pub enum SessionEventKind {
+  /// The session moved to the step
+  StepEntered(StepId),
+
+  /// The session moved past the step
+  StepExited(StepId),
+
+  /// An action was started for the step
+  ActionStarted {
+    step_id: StepId,
+    action_id: ActionId,
+    outcome: ActionOutcome,
+  },
+
+  /// A [finish action](crate::Session::add_finish_action) was run
+  FinishActionRan {
+    action_id: ActionId,
+    outcome: ActionOutcome,
+  },
+}
+
+/// What an action returned in a [`SessionEventKind::ActionStarted`]. Values are left out since they may be sensitive.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde-support", derive(serde::Serialize))]
+pub enum ActionOutcome {
+  /// The action fulfilled the step's outputs
+  Finished,
+
+  /// The action is waiting on something, i.e. the user filling in a form
+  StartWith,
+
+  CannotFulfill,
+
+  /// The action failed with the error's description
+  Failed(String),
+}
+
+impl From<&Result<ActionResult, Error>> for ActionOutcome {
+  fn from(result: &Result<ActionResult, Error>) -> Self {
+    match result {
+      Ok(ActionResult::Finished(_)) => ActionOutcome::Finished,
+      Ok(ActionResult::StartWith(_)) => ActionOutcome::StartWith,
+      Ok(ActionResult::CannotFulfill) => ActionOutcome::CannotFulfill,
+      Err(err) => ActionOutcome::Failed(err.to_string()),
+    }
+  }
+}
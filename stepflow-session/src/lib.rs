@@ -3,12 +3,53 @@
 //! [`Session`] is the primary interface for creating and managing a flow.
 
 mod session;
-pub use session::{ Session, SessionId, AdvanceBlockedOn };
+pub use session::{ Session, SessionId, AdvanceBlockedOn, CannotFulfillDetails, UndeclaredActionVar, FinishAction, FinishActionStatus, FallbackAction, FollowUp, FollowUpStart, Compensation, BlockedNotice, BlockedObserver, Transition, TransitionObserver, DeadlinePolicy, Escalation, ESCALATION_ACTOR, NextStepPreview, BlockingRequirement, TransitionCheck };
+
+mod approval;
+pub use approval::ApprovalStep;
+
+mod subflow;
+pub use subflow::SubflowAction;
+
+mod migration;
+pub use migration::{VarMigration, DefinitionDrift};
+
+#[cfg(feature = "serde-support")]
+mod definition;
+#[cfg(feature = "serde-support")]
+pub use definition::{FlowDefinition, VarDefinition, StepDefinition, ActionDefinition};
 
 mod errors;
-pub use errors::Error;
+pub use errors::{Error, AdvanceError};
+
+mod data_accessor;
+
+mod limits;
+pub use limits::{ResourceLimits, QuotaExceeded, TenantQuotas};
+
+mod shedding;
+pub use shedding::LoadShedding;
+
+mod history;
+pub use history::{SessionEvent, SessionEventKind, ActionOutcome, AttemptId};
+
+mod trace;
+pub use trace::TraceEntry;
+
+mod graph;
+pub use graph::StepStatus;
 
 mod dfs;
+pub use dfs::DepthFirstSearch;
+
+mod priority;
+pub use priority::{PriorityTraversal, StepScore, StepScoreFn};
+
+mod scheduler;
+pub use scheduler::{FlowScheduler, StepCheck};
+
+mod load_test;
+pub use load_test::{LoadTest, LoadTestReport};
 
-#[cfg(test)]
-mod test;
\ No newline at end of file
+mod csv_export;
+pub use csv_export::CsvExport;
@@ -0,0 +1,219 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::{Duration, Instant};
+use super::{Error, SessionId};
+
+/// Limits on the resources a [`Session`](crate::Session) or tenant can use. `None` is unlimited.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ResourceLimits {
+  /// Maximum sessions a tenant can have at once
+  pub max_sessions: Option<usize>,
+
+  /// Maximum [`Step`](stepflow_step::Step)s in a session's definition
+  pub max_steps: Option<usize>,
+
+  /// Maximum [`Var`](stepflow_data::var::Var)s in a session's definition
+  pub max_vars: Option<usize>,
+
+  /// Maximum nesting of substeps. The root steps are at depth 1.
+  pub max_depth: Option<usize>,
+
+  /// Maximum substeps of any one step
+  pub max_substeps: Option<usize>,
+
+  /// Maximum calls to [`Session::advance`](crate::Session::advance) in any minute.
+  /// Counted per session for the session's own [limits](crate::Session::set_limits)
+  /// and across all of a tenant's sessions by [`TenantQuotas::record_advance`].
+  pub max_advances_per_minute: Option<u32>,
+}
+
+/// Which limit in [`ResourceLimits`] was hit, with the value of the limit
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde-support", derive(serde::Serialize))]
+pub enum QuotaExceeded {
+  Sessions(usize),
+  Steps(usize),
+  Vars(usize),
+  Depth(usize),
+  Substeps(usize),
+  AdvancesPerMinute(u32),
+}
+
+// tracks the advance calls within the last minute
+#[derive(Debug, Default)]
+pub(crate) struct AdvanceRate {
+  calls: VecDeque<Instant>,
+}
+
+impl AdvanceRate {
+  const WINDOW: Duration = Duration::from_secs(60);
+
+  // record a call if it's under the limit
+  pub(crate) fn try_record(&mut self, max_per_minute: Option<u32>, now: Instant) -> Result<(), Error> {
+    self.check(max_per_minute, now)?;
+    if max_per_minute.is_some() {
+      self.calls.push_back(now);
+    }
+    Ok(())
+  }
+
+  // fail if another call would be over the limit, without recording one
+  pub(crate) fn check(&mut self, max_per_minute: Option<u32>, now: Instant) -> Result<(), Error> {
+    let max_per_minute = match max_per_minute {
+      Some(max) => max,
+      None => return Ok(()),
+    };
+    // the window is (now - 1 minute, now] so a call exactly a minute old no longer counts
+    if let Some(window_start) = now.checked_sub(Self::WINDOW) {
+      while self.calls.front().map(|oldest| *oldest <= window_start).unwrap_or(false) {
+        self.calls.pop_front();
+      }
+    }
+    if self.calls.len() >= max_per_minute as usize {
+      return Err(Error::QuotaExceeded(QuotaExceeded::AdvancesPerMinute(max_per_minute)));
+    }
+    Ok(())
+  }
+}
+
+/// Tracks the sessions owned by each tenant, their calls to advance and the limits that apply to them
+///
+/// ```
+/// # use stepflow_session::{TenantQuotas, ResourceLimits, SessionId, Error, QuotaExceeded};
+/// let mut quotas = TenantQuotas::new(ResourceLimits { max_sessions: Some(1), ..Default::default() });
+/// quotas.add_session("acme", SessionId::new(0)).unwrap();
+/// assert_eq!(quotas.add_session("acme", SessionId::new(1)), Err(Error::QuotaExceeded(QuotaExceeded::Sessions(1))));
+/// ```
+#[derive(Debug, Default)]
+pub struct TenantQuotas {
+  default_limits: ResourceLimits,
+  tenant_limits: HashMap<String, ResourceLimits>,
+  tenant_sessions: HashMap<String, HashSet<SessionId>>,
+  advance_rates: HashMap<String, AdvanceRate>,
+}
+
+impl TenantQuotas {
+  /// Create a tracker where every tenant gets `default_limits`
+  pub fn new(default_limits: ResourceLimits) -> Self {
+    TenantQuotas {
+      default_limits,
+      ..Default::default()
+    }
+  }
+
+  /// Override the limits for a single tenant
+  pub fn set_tenant_limits<STR>(&mut self, tenant: STR, limits: ResourceLimits)
+      where STR: Into<String>
+  {
+    self.tenant_limits.insert(tenant.into(), limits);
+  }
+
+  /// Get the limits that apply to a tenant
+  pub fn limits_for(&self, tenant: &str) -> &ResourceLimits {
+    self.tenant_limits.get(tenant).unwrap_or(&self.default_limits)
+  }
+
+  /// Count a session against the tenant's quota
+  pub fn add_session(&mut self, tenant: &str, session_id: SessionId) -> Result<(), Error> {
+    let max_sessions = self.limits_for(tenant).max_sessions;
+    let sessions = self.tenant_sessions.entry(tenant.to_owned()).or_default();
+    if let Some(max_sessions) = max_sessions {
+      if sessions.len() >= max_sessions && !sessions.contains(&session_id) {
+        return Err(Error::QuotaExceeded(QuotaExceeded::Sessions(max_sessions)));
+      }
+    }
+    sessions.insert(session_id);
+    Ok(())
+  }
+
+  /// Stop counting a session against the tenant's quota
+  pub fn remove_session(&mut self, tenant: &str, session_id: &SessionId) -> bool {
+    self.tenant_sessions
+      .get_mut(tenant)
+      .map(|sessions| sessions.remove(session_id))
+      .unwrap_or(false)
+  }
+
+  /// Number of sessions the tenant has
+  pub fn session_count(&self, tenant: &str) -> usize {
+    self.tenant_sessions.get(tenant).map(|sessions| sessions.len()).unwrap_or(0)
+  }
+
+  /// Count a call to [`Session::advance`](crate::Session::advance) by any of the tenant's sessions at `now`.
+  /// Fails without counting it if the tenant already made [`max_advances_per_minute`](ResourceLimits::max_advances_per_minute).
+  pub fn record_advance(&mut self, tenant: &str, now: Instant) -> Result<(), Error> {
+    let max_per_minute = self.limits_for(tenant).max_advances_per_minute;
+    if max_per_minute.is_none() {
+      return Ok(());
+    }
+    self.advance_rates.entry(tenant.to_owned()).or_default().try_record(max_per_minute, now)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::time::{Duration, Instant};
+  use stepflow_test_util::test_id;
+  use super::{AdvanceRate, TenantQuotas, ResourceLimits, QuotaExceeded, SessionId, Error};
+
+  #[test]
+  fn advance_rate() {
+    let mut rate = AdvanceRate::default();
+    let start = Instant::now();
+    assert_eq!(rate.try_record(None, start), Ok(()));
+    assert_eq!(rate.try_record(Some(2), start), Ok(()));
+    assert_eq!(rate.try_record(Some(2), start + Duration::from_secs(30)), Ok(()));
+    assert_eq!(rate.try_record(Some(2), start + Duration::from_secs(59)), Err(Error::QuotaExceeded(QuotaExceeded::AdvancesPerMinute(2))));
+
+    // first call falls out of the window
+    assert_eq!(rate.try_record(Some(2), start + Duration::from_secs(60)), Ok(()));
+  }
+
+  #[test]
+  fn advance_rate_boundary() {
+    let mut rate = AdvanceRate::default();
+    let start = Instant::now() + Duration::from_secs(120);
+    assert_eq!(rate.try_record(Some(1), start), Ok(()));
+
+    // still in the window until it's a full minute old
+    let almost = start + Duration::from_secs(60) - Duration::from_nanos(1);
+    assert_eq!(rate.try_record(Some(1), almost), Err(Error::QuotaExceeded(QuotaExceeded::AdvancesPerMinute(1))));
+    assert_eq!(rate.try_record(Some(1), start + Duration::from_secs(60)), Ok(()));
+    assert_eq!(rate.calls.len(), 1);
+  }
+
+  #[test]
+  fn tenant_advances() {
+    let mut quotas = TenantQuotas::new(ResourceLimits { max_advances_per_minute: Some(2), ..Default::default() });
+    quotas.set_tenant_limits("unlimited", ResourceLimits::default());
+    let start = Instant::now();
+
+    // every session of the tenant counts against the same limit
+    assert_eq!(quotas.record_advance("acme", start), Ok(()));
+    assert_eq!(quotas.record_advance("acme", start), Ok(()));
+    assert_eq!(quotas.record_advance("acme", start), Err(Error::QuotaExceeded(QuotaExceeded::AdvancesPerMinute(2))));
+    assert_eq!(quotas.record_advance("other", start), Ok(()));
+    for _ in 0..3 {
+      assert_eq!(quotas.record_advance("unlimited", start), Ok(()));
+    }
+    assert_eq!(quotas.record_advance("acme", start + Duration::from_secs(60)), Ok(()));
+  }
+
+  #[test]
+  fn tenant_sessions() {
+    let mut quotas = TenantQuotas::new(ResourceLimits { max_sessions: Some(1), ..Default::default() });
+    quotas.set_tenant_limits("big", ResourceLimits::default());
+
+    let session1 = test_id!(SessionId);
+    let session2 = test_id!(SessionId);
+    assert_eq!(quotas.add_session("small", session1), Ok(()));
+    assert_eq!(quotas.add_session("small", session1), Ok(())); // same session doesn't count twice
+    assert_eq!(quotas.add_session("small", session2), Err(Error::QuotaExceeded(QuotaExceeded::Sessions(1))));
+    assert_eq!(quotas.add_session("big", session1), Ok(()));
+    assert_eq!(quotas.add_session("big", session2), Ok(()));
+    assert_eq!(quotas.session_count("big"), 2);
+
+    assert!(quotas.remove_session("small", &session1));
+    assert!(!quotas.remove_session("small", &session1));
+    assert_eq!(quotas.add_session("small", session2), Ok(()));
+  }
+}
@@ -0,0 +1,273 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use stepflow_data::{StateData, ValueGenerator, var::VarId};
+use crate::{Session, SessionId, AdvanceBlockedOn, Error};
+
+/// Drive many sessions with generated data and time each advance, i.e. for capacity planning
+///
+/// Sessions are advanced in turns until they finish. Each step's outputs are filled with values from a [`ValueGenerator`],
+/// either one set with [`with_generator`](LoadTest::with_generator) or [`ValueGenerator::for_var`].
+/// A submission with an invalid value is counted but never reaches the session, the same as a form that fails to parse.
+/// ```
+/// # use stepflow_data::var::{Var, StringVar};
+/// # use stepflow_session::{Session, LoadTest};
+/// # use stepflow_step::Step;
+/// let report = LoadTest::new(10)
+///   .with_invalid_percent(20)
+///   .run(|session_id| {
+///     let mut session = Session::new(session_id);
+///     let name = session.var_store_mut().insert_new(|id| Ok(StringVar::new(id).boxed()))?;
+///     let step = session.step_store_mut().insert_new(|id| Ok(Step::new(id, None, vec![name])))?;
+///     session.push_root_substep(step);
+///     Ok(session)
+///   })
+///   .unwrap();
+/// assert_eq!(report.finished, 10);
+/// assert!(report.percentile(99.0).is_some());
+/// ```
+#[derive(Debug, Clone)]
+pub struct LoadTest {
+  sessions: usize,
+  advances_per_sec: Option<f64>,
+  invalid_percent: u8,
+  max_attempts: usize,
+  seed: u64,
+  generators: HashMap<VarId, ValueGenerator>,
+}
+
+/// The results of [`LoadTest::run`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoadTestReport {
+  pub sessions: usize,
+
+  /// Sessions that reached the end of their flow
+  pub finished: usize,
+
+  /// Submissions with an invalid value, which weren't sent to the session
+  pub invalid_submissions: usize,
+
+  /// Sessions dropped because an advance failed or they reached the maximum attempts
+  pub failed: usize,
+
+  /// How long each advance took, shortest first
+  pub latencies: Vec<Duration>,
+
+  /// Time for the whole run
+  pub elapsed: Duration,
+}
+
+impl LoadTestReport {
+  /// Latency that `percent` of the advances were at or under, i.e. `99.0` for p99
+  pub fn percentile(&self, percent: f64) -> Option<Duration> {
+    if self.latencies.is_empty() {
+      return None;
+    }
+    let rank = ((percent / 100.0) * self.latencies.len() as f64).ceil() as usize;
+    Some(self.latencies[rank.clamp(1, self.latencies.len()) - 1])
+  }
+}
+
+// xorshift so runs are repeatable for the same seed
+fn next_random(state: &mut u64) -> u64 {
+  *state ^= *state << 13;
+  *state ^= *state >> 7;
+  *state ^= *state << 17;
+  *state
+}
+
+impl LoadTest {
+  /// Run `sessions` sessions with valid values only and no rate limit
+  pub fn new(sessions: usize) -> Self {
+    LoadTest {
+      sessions,
+      advances_per_sec: None,
+      invalid_percent: 0,
+      max_attempts: 100,
+      seed: 0x5eed,
+      generators: HashMap::new(),
+    }
+  }
+
+  /// Spread the advances out so there are at most `advances_per_sec` each second
+  pub fn with_rate(mut self, advances_per_sec: f64) -> Self {
+    self.advances_per_sec = Some(advances_per_sec);
+    self
+  }
+
+  /// Percent of the generated values that are invalid
+  pub fn with_invalid_percent(mut self, invalid_percent: u8) -> Self {
+    self.invalid_percent = invalid_percent.min(100);
+    self
+  }
+
+  /// Give up on a session after `max_attempts` advances and invalid submissions, i.e. when an action never lets it finish
+  pub fn with_max_attempts(mut self, max_attempts: usize) -> Self {
+    self.max_attempts = max_attempts;
+    self
+  }
+
+  /// Seed for the generated values. The same seed generates the same values.
+  pub fn with_seed(mut self, seed: u64) -> Self {
+    self.seed = seed.max(1);
+    self
+  }
+
+  /// Values to use for the var instead of [`ValueGenerator::for_var`]
+  pub fn with_generator(mut self, var_id: VarId, generator: ValueGenerator) -> Self {
+    self.generators.insert(var_id, generator);
+    self
+  }
+
+  /// Create the sessions with `new_session` and advance them until they all finish or fail
+  pub fn run<F>(&self, mut new_session: F) -> Result<LoadTestReport, Error>
+      where F: FnMut(SessionId) -> Result<Session, Error>
+  {
+    let started = Instant::now();
+    let mut report = LoadTestReport {
+      sessions: self.sessions,
+      finished: 0,
+      invalid_submissions: 0,
+      failed: 0,
+      latencies: Vec::new(),
+      elapsed: Duration::default(),
+    };
+    let mut generators = self.generators.clone();
+    let mut random_state = self.seed;
+
+    // each session with how many times it was tried
+    let mut active = (0..self.sessions)
+      .map(|session_num| Ok((new_session(SessionId::new(session_num as u16))?, 0)))
+      .collect::<Result<Vec<(Session, usize)>, Error>>()?;
+    let mut first_advance = true;
+    while !active.is_empty() {
+      let mut still_active = Vec::with_capacity(active.len());
+      for (mut session, attempts) in active {
+        let out_of_attempts = attempts + 1 >= self.max_attempts;
+        let step_output = if first_advance {
+          None
+        } else {
+          match self.generate_output(&session, &mut generators, &mut random_state) {
+            Some(step_output) => step_output,
+            None => {
+              report.invalid_submissions += 1;
+              if out_of_attempts {
+                report.failed += 1;
+              } else {
+                still_active.push((session, attempts + 1));
+              }
+              continue;
+            }
+          }
+        };
+
+        if let Some(advances_per_sec) = self.advances_per_sec {
+          let due = started + Duration::from_secs_f64(report.latencies.len() as f64 / advances_per_sec);
+          let now = Instant::now();
+          if due > now {
+            std::thread::sleep(due - now);
+          }
+        }
+        let advance_started = Instant::now();
+        let result = match step_output {
+          Some((step_id, state_data)) => session.advance(Some((&step_id, state_data))),
+          None => session.advance(None),
+        };
+        report.latencies.push(advance_started.elapsed());
+
+        match result {
+          Ok(AdvanceBlockedOn::FinishedAdvancing) => report.finished += 1,
+          _ if out_of_attempts => report.failed += 1,
+          Ok(_) | Err(Error::MissingVars(_)) | Err(Error::InvalidVars(_)) => still_active.push((session, attempts + 1)),
+          Err(_) => report.failed += 1,
+        }
+      }
+      active = still_active;
+      first_advance = false;
+    }
+
+    report.latencies.sort();
+    report.elapsed = started.elapsed();
+    Ok(report)
+  }
+
+  // outputs for the current step or `None` if one of the generated values was invalid
+  fn generate_output(&self, session: &Session, generators: &mut HashMap<VarId, ValueGenerator>, random_state: &mut u64)
+      -> Option<Option<(stepflow_step::StepId, StateData)>>
+  {
+    let step_id = match session.current_step() {
+      Ok(step_id) => *step_id,
+      Err(_) => return Some(None),
+    };
+    let step = session.step_store().get(&step_id)?;
+    let mut state_data = StateData::new();
+    for var_id in step.get_output_vars() {
+      let var = session.var_store().get(var_id)?;
+      let generator = generators.entry(*var_id).or_insert_with(|| ValueGenerator::for_var(var.as_ref()));
+      let (val, _valid) = generator.sample(next_random(random_state), self.invalid_percent)?;
+      let val = var.value_from_str(val).ok()?;
+      state_data.insert(var, val).ok()?;
+    }
+    Some(Some((step_id, state_data)))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::time::Duration;
+  use stepflow_data::{ValueGenerator, var::{EmailVar, NumberVar}};
+  use stepflow_step::Step;
+  use crate::{Session, SessionId, Error};
+  use super::{LoadTest, LoadTestReport};
+
+  fn new_session(session_id: SessionId) -> Result<Session, Error> {
+    let mut session = Session::new(session_id);
+    let email = session.var_store_mut().insert_new_named("email", |id| Ok(EmailVar::new(id).boxed()))?;
+    let age = session.var_store_mut().insert_new_named("age", |id| Ok(NumberVar::new_int(id).with_range(Some(18.0), None).boxed()))?;
+    let email_step = session.step_store_mut().insert_new(|id| Ok(Step::new(id, None, vec![email])))?;
+    let age_step = session.step_store_mut().insert_new(|id| Ok(Step::new(id, None, vec![age])))?;
+    session.push_root_substep(email_step);
+    session.push_root_substep(age_step);
+    Ok(session)
+  }
+
+  #[test]
+  fn run() {
+    let report = LoadTest::new(5).run(new_session).unwrap();
+    assert_eq!((report.sessions, report.finished, report.invalid_submissions, report.failed), (5, 5, 0, 0));
+    // create, email, age
+    assert_eq!(report.latencies.len(), 15);
+
+    // invalid values are retried and the same seed does the same thing
+    let load_test = LoadTest::new(5).with_invalid_percent(50).with_seed(7);
+    let report = load_test.run(new_session).unwrap();
+    assert_eq!(report.finished, 5);
+    assert!(report.invalid_submissions > 0);
+    assert_eq!(load_test.run(new_session).unwrap().invalid_submissions, report.invalid_submissions);
+
+    // generators without a valid value never finish
+    let email = *new_session(SessionId::new(0)).unwrap().var_store().id_from_name("email").unwrap();
+    let no_email = ValueGenerator::new().with_invalid("nope", 1);
+    let report = LoadTest::new(2).with_max_attempts(3).with_generator(email, no_email).run(new_session).unwrap();
+    assert_eq!((report.finished, report.invalid_submissions, report.failed), (0, 4, 2));
+
+    // limited rate
+    let report = LoadTest::new(2).with_rate(200.0).run(new_session).unwrap();
+    assert!(report.elapsed >= Duration::from_millis(25));
+  }
+
+  #[test]
+  fn percentile() {
+    let report = LoadTestReport {
+      sessions: 1,
+      finished: 1,
+      invalid_submissions: 0,
+      failed: 0,
+      latencies: (1..=100).map(Duration::from_millis).collect(),
+      elapsed: Duration::from_secs(1),
+    };
+    assert_eq!(report.percentile(50.0), Some(Duration::from_millis(50)));
+    assert_eq!(report.percentile(99.0), Some(Duration::from_millis(99)));
+    assert_eq!(report.percentile(0.0), Some(Duration::from_millis(1)));
+    assert_eq!(LoadTestReport { latencies: Vec::new(), ..report }.percentile(50.0), None);
+  }
+}
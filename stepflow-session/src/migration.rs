@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+use stepflow_base::IdError;
+use stepflow_data::{StateData, InvalidVars, var::VarId};
+use stepflow_step::StepId;
+use stepflow_action::ActionId;
+use crate::{Session, Error};
+
+/// Steps, vars and actions the session still uses that were removed from the definition. See [`Session::check_definition_drift`].
+///
+/// Each list is sorted. Migrate the session to the current definition before advancing it again.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde-support", derive(serde::Serialize))]
+pub struct DefinitionDrift {
+  pub steps: Vec<StepId>,
+  pub vars: Vec<VarId>,
+  pub actions: Vec<ActionId>,
+}
+
+impl DefinitionDrift {
+  /// Check if nothing is missing
+  pub fn is_empty(&self) -> bool {
+    self.steps.is_empty() && self.vars.is_empty() && self.actions.is_empty()
+  }
+}
+
+/// How the [`VarId`]s of an older definition map to the current one. See [`Session::migrate_data`].
+///
+/// Vars that aren't mapped keep their ID.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VarMigration {
+  ids: HashMap<VarId, VarId>,
+}
+
+impl VarMigration {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Data saved for `old` belongs to `new` now
+  pub fn map_id(mut self, old: VarId, new: VarId) -> Self {
+    self.ids.insert(old, new);
+    self
+  }
+
+  /// The current ID for `old`
+  pub fn current_id(&self, old: &VarId) -> VarId {
+    self.ids.get(old).copied().unwrap_or(*old)
+  }
+}
+
+impl Session {
+  /// Rename a var, i.e. when upgrading a definition. If `keep_alias` is true, the old name still finds the var.
+  pub fn rename_var<STR>(&mut self, var_id: &VarId, new_name: STR, keep_alias: bool) -> Result<(), Error>
+      where STR: Into<std::borrow::Cow<'static, str>>
+  {
+    self.var_store_mut().rename(var_id, new_name, keep_alias)?;
+    Ok(())
+  }
+
+  /// Move data saved with an older definition over to the current vars
+  ///
+  /// Fails if a var no longer exists so data isn't dropped without anyone noticing.
+  pub fn migrate_data(&self, saved: &StateData, migration: &VarMigration) -> Result<StateData, Error> {
+    let mut migrated = StateData::new();
+    let mut invalid = HashMap::new();
+    for (old_id, val) in saved.iter_val() {
+      let var_id = migration.current_id(old_id);
+      let var = self.var_store().get(&var_id).ok_or(Error::VarId(IdError::IdMissing(var_id)))?;
+      if let Err(invalid_val) = migrated.insert(var, val.clone()) {
+        invalid.insert(var_id, invalid_val);
+      }
+    }
+    if !invalid.is_empty() {
+      return Err(Error::InvalidVars(InvalidVars::new(invalid)));
+    }
+    Ok(migrated)
+  }
+
+  /// Parse data saved by var name, i.e. from a form or database row. Old names kept by [`rename_var`](Session::rename_var) are found too.
+  pub fn migrate_named_data<'a, I>(&self, saved: I) -> Result<StateData, Error>
+      where I: IntoIterator<Item = (&'a str, &'a str)>
+  {
+    let mut migrated = StateData::new();
+    let mut invalid = HashMap::new();
+    for (name, val_str) in saved {
+      let var_id = *self.var_store().id_from_name(name).ok_or_else(|| Error::VarId(IdError::NoSuchName(name.to_owned())))?;
+      let var = self.var_store().get(&var_id).ok_or(Error::VarId(IdError::IdMissing(var_id)))?;
+      let result = var.value_from_str(val_str).and_then(|val| migrated.insert(var, val));
+      if let Err(invalid_val) = result {
+        invalid.insert(var_id, invalid_val);
+      }
+    }
+    if !invalid.is_empty() {
+      return Err(Error::InvalidVars(InvalidVars::new(invalid)));
+    }
+    Ok(migrated)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use stepflow_base::IdError;
+  use stepflow_data::{StateData, InvalidValue, var::{StringVar, BoolVar, VarId}, value::StringValue};
+  use stepflow_step::Step;
+  use stepflow_action::Action;
+  use stepflow_test_util::test_id;
+  use crate::{Session, Error};
+  use stepflow_action::testing::MockAction;
+  use super::{VarMigration, DefinitionDrift};
+
+  #[test]
+  fn rename_var() {
+    let (mut session, _root_step_id) = Session::test_new();
+    let zip = session.var_store_mut().insert_new_named("zip", |id| Ok(StringVar::new(id).boxed())).unwrap();
+    session.rename_var(&zip, "postal_code", true).unwrap();
+    assert_eq!(session.var_store().name_from_id(&zip), Some("postal_code"));
+
+    let migrated = session.migrate_named_data(vec![("zip", "90210")]).unwrap();
+    assert_eq!(migrated.get(&zip).unwrap().get_val(), &StringValue::try_new("90210").unwrap().boxed());
+    assert_eq!(session.migrate_named_data(vec![("city", "Springfield")]), Err(Error::VarId(IdError::NoSuchName("city".to_owned()))));
+  }
+
+  #[test]
+  fn migrate_data() {
+    let (mut session, _root_step_id) = Session::test_new();
+    let name = session.test_new_stringvar();
+    let agreed = session.var_store_mut().insert_new(|id| Ok(BoolVar::new(id).boxed())).unwrap();
+
+    // the old definition's name var had a different ID
+    let old_name = test_id!(VarId);
+    let mut saved = StateData::new();
+    saved.insert(&StringVar::new(old_name).boxed(), StringValue::try_new("Jane").unwrap().boxed()).unwrap();
+    assert_eq!(session.migrate_data(&saved, &VarMigration::new()), Err(Error::VarId(IdError::IdMissing(old_name))));
+
+    let migrated = session.migrate_data(&saved, &VarMigration::new().map_id(old_name, name)).unwrap();
+    assert_eq!(migrated.get(&name).unwrap().get_val(), &StringValue::try_new("Jane").unwrap().boxed());
+
+    // values have to fit the current var
+    let migration = VarMigration::new().map_id(old_name, agreed);
+    let expected = vec![(agreed, InvalidValue::WrongType)].into_iter().collect();
+    assert_eq!(session.migrate_data(&saved, &migration), Err(Error::InvalidVars(stepflow_data::InvalidVars::new(expected))));
+  }
+
+  #[test]
+  fn definition_drift() {
+    let (mut session, _root_step_id) = Session::test_new();
+    let name = session.test_new_stringvar();
+    let step_id = session.step_store_mut().insert_new(|id| Ok(Step::new(id, None, vec![name]))).unwrap();
+    session.push_root_substep(step_id);
+    let action_id = session.action_store_mut().insert_new(|id| Ok(MockAction::new(id).boxed())).unwrap();
+    session.set_action_for_step(action_id, None).unwrap();
+    assert!(session.advance(None).is_ok());
+    assert_eq!(session.check_definition_drift(), Ok(()));
+
+    // the definition changed under the session
+    session.var_store_mut().remove(&name);
+    session.action_store_mut().remove(&action_id);
+    let expected = DefinitionDrift { steps: vec![], vars: vec![name], actions: vec![action_id] };
+    assert_eq!(session.advance(None), Err(Error::DefinitionDrift(expected)));
+
+    session.step_store_mut().remove(&step_id);
+    let expected = DefinitionDrift { steps: vec![step_id], vars: vec![], actions: vec![action_id] };
+    assert_eq!(session.check_definition_drift(), Err(Error::DefinitionDrift(expected)));
+  }
+}
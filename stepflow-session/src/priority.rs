@@ -0,0 +1,369 @@
+use stepflow_base::{ObjectStore, ObjectStoreContent, IdError};
+use stepflow_data::StateData;
+use stepflow_step::{Step, StepId};
+use super::Error;
+
+/// Custom scoring function for [`StepScore::Custom`]
+pub type StepScoreFn = Box<dyn Fn(&Step, &StateData) -> i64 + Send + Sync>;
+
+/// How to score the steps that can be entered. The highest score goes next, ties go to the earliest step.
+pub enum StepScore {
+  /// The number in the step's metadata for the key. Steps without a number score 0.
+  Metadata(String),
+
+  /// How many of the other unfinished steps need one of the step's outputs as an input
+  UnblocksMost,
+
+  /// Score with a custom function
+  Custom(StepScoreFn),
+}
+
+impl std::fmt::Debug for StepScore {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      StepScore::Metadata(key) => f.debug_tuple("Metadata").field(key).finish(),
+      StepScore::UnblocksMost => f.write_str("UnblocksMost"),
+      StepScore::Custom(_) => f.write_str("Custom"),
+    }
+  }
+}
+
+impl StepScore {
+  fn score(&self, step: &Step, pending: &[&Step], state_data: &StateData) -> i64 {
+    match self {
+      StepScore::Metadata(key) => {
+        step.get_metadata(key).and_then(|val| val.parse().ok()).unwrap_or(0)
+      }
+      StepScore::UnblocksMost => {
+        let outputs = step.get_output_vars();
+        pending.iter()
+          .filter(|other| other.id() != step.id())
+          .filter(|other| other.get_input_vars().iter().flatten().any(|var_id| outputs.contains(var_id)))
+          .count() as i64
+      }
+      StepScore::Custom(f) => f(step, state_data),
+    }
+  }
+}
+
+// paths from the root to each leaf step, in the order they'd be visited depth first
+fn leaf_paths(root: &StepId, step_store: &ObjectStore<Step, StepId>) -> Vec<Vec<StepId>> {
+  fn visit(path: &mut Vec<StepId>, step_store: &ObjectStore<Step, StepId>, result: &mut Vec<Vec<StepId>>) {
+    let step_id = path.last().unwrap();
+    match step_store.get(step_id).and_then(|step| step.get_substeps()) {
+      Some(substeps) if !substeps.is_empty() => {
+        for substep in substeps {
+          path.push(*substep);
+          visit(path, step_store, result);
+          path.pop();
+        }
+      }
+      _ => result.push(path.clone()),
+    }
+  }
+
+  let mut result = Vec::new();
+  let has_substeps = step_store.get(root).and_then(|step| step.first_substep()).is_some();
+  if has_substeps {
+    visit(&mut vec![*root], step_store, &mut result);
+  }
+  result
+}
+
+/// Visits the leaf steps in order of their [`StepScore`] instead of depth first
+///
+/// Steps that have to come after others regardless of their score are set with [`with_order`](PriorityTraversal::with_order).
+#[derive(Debug)]
+pub struct PriorityTraversal {
+  root: StepId,
+  score: StepScore,
+  order: Vec<(StepId, StepId)>,
+  path: Vec<StepId>,
+  completed: Vec<StepId>,
+  leaf_entered: bool,
+}
+
+impl PriorityTraversal {
+  pub fn new(root: StepId, score: StepScore) -> Self {
+    PriorityTraversal {
+      root,
+      score,
+      order: Vec::new(),
+      path: vec![root],
+      completed: Vec::new(),
+      leaf_entered: false,
+    }
+  }
+
+  /// Only enter `after` once `before` is completed. Either can be a group of steps.
+  pub fn with_order(mut self, before: StepId, after: StepId) -> Self {
+    self.order.push((before, after));
+    self
+  }
+
+  /// Go back to the start, keeping the score and order
+  pub fn restart(&mut self) {
+    self.path = vec![self.root];
+    self.completed.clear();
+    self.leaf_entered = false;
+  }
+
+  pub fn current(&self) -> Option<&StepId> {
+    self.path.last()
+  }
+
+  pub fn is_entered(&self, step_id: &StepId) -> bool {
+    self.path.contains(step_id)
+  }
+
+  /// The current step and its ancestors, starting with the root
+  pub fn entered(&self) -> &[StepId] {
+    &self.path
+  }
+
+  /// Leaf steps in the order they were exited, then the parents of only exited steps
+  pub fn completed(&self, step_store: &ObjectStore<Step, StepId>) -> Vec<StepId> {
+    let leaf_paths = leaf_paths(&self.root, step_store);
+    let mut result = self.completed.clone();
+    let parents = super::dfs::preorder(&self.root, step_store)
+      .into_iter()
+      .filter(|step_id| !self.is_entered(step_id))
+      .filter(|step_id| step_store.get(step_id).and_then(|step| step.first_substep()).is_some())
+      .filter(|step_id| {
+        leaf_paths.iter()
+          .filter(|path| path.contains(step_id))
+          .all(|path| self.completed.contains(path.last().unwrap()))
+      });
+    result.extend(parents);
+    result
+  }
+
+  /// Move back to `step_id` as if it was just entered. It and its substeps are no longer completed.
+  pub fn rewind_to(&mut self, step_id: &StepId, step_store: &ObjectStore<Step, StepId>) -> Result<(), Error> {
+    let leaf_paths = leaf_paths(&self.root, step_store);
+    let path = leaf_paths.iter()
+      .find_map(|path| path.iter().position(|id| id == step_id).map(|pos| path[..=pos].to_vec()))
+      .ok_or(Error::StepId(IdError::IdMissing(*step_id)))?;
+
+    self.completed.retain(|completed_id| {
+      !leaf_paths.iter().any(|path| path.last() == Some(completed_id) && path.contains(step_id))
+    });
+    self.leaf_entered = step_store.get(step_id).and_then(|step| step.first_substep()).is_none();
+    self.path = path;
+    Ok(())
+  }
+
+  // the highest scoring path to a leaf that isn't completed and can be entered
+  fn best_path<FnCanEnter>(&self, completed: &[StepId], can_enter: &mut FnCanEnter, step_store: &ObjectStore<Step, StepId>, state_data: &StateData)
+      -> Result<Option<Vec<StepId>>, Error>
+      where FnCanEnter: FnMut(&StepId) -> Result<(), Error>
+  {
+    let pending = leaf_paths(&self.root, step_store)
+      .into_iter()
+      .filter(|path| !completed.contains(path.last().unwrap()))
+      .collect::<Vec<_>>();
+    let pending_steps = pending.iter()
+      .filter_map(|path| step_store.get(path.last().unwrap()))
+      .collect::<Vec<_>>();
+
+    // a step waits for the steps it comes after until none of their leaves are pending
+    let waiting = |path: &Vec<StepId>| {
+      self.order.iter().any(|(before, after)| path.contains(after) && pending.iter().any(|other| other.contains(before)))
+    };
+
+    // score everything we can enter
+    let mut first_err = None;
+    let mut best: Option<(i64, &Vec<StepId>)> = None;
+    for path in pending.iter() {
+      if waiting(path) {
+        continue;
+      }
+      if let Err(e) = path.iter().try_for_each(&mut *can_enter) {
+        first_err.get_or_insert(e);
+        continue;
+      }
+      let step = step_store.get(path.last().unwrap()).ok_or_else(|| Error::StepId(IdError::IdMissing(*path.last().unwrap())))?;
+      let score = self.score.score(step, &pending_steps, state_data);
+      if best.map(|(best_score, _)| score > best_score).unwrap_or(true) {
+        best = Some((score, path));
+      }
+    }
+
+    match (best, first_err, pending.first()) {
+      (Some((_, path)), _, _) => Ok(Some(path.clone())),
+      (None, Some(e), _) => Err(e),
+      // every step left is waiting on another one
+      (None, None, Some(path)) => Err(Error::StepOrder(*path.last().unwrap())),
+      (None, None, None) => Ok(None),
+    }
+  }
+
+  /// The step [`next`](PriorityTraversal::next) would enter if every step could be entered and exited
+  pub fn peek_next(&self, step_store: &ObjectStore<Step, StepId>, state_data: &StateData) -> Option<StepId> {
+    if self.path.is_empty() {
+      return None;
+    }
+    let mut completed = self.completed.clone();
+    if self.leaf_entered {
+      completed.extend(self.path.last());
+    }
+    self.best_path(&completed, &mut |_| Ok(()), step_store, state_data)
+      .ok()
+      .flatten()
+      .and_then(|path| path.last().cloned())
+  }
+
+  pub fn next<FnCanEnter, FnCanExit>(&mut self, mut can_enter: FnCanEnter, mut can_exit: FnCanExit, step_store: &ObjectStore<Step, StepId>, state_data: &StateData)
+      -> Result<Option<StepId>, Error>
+      where FnCanEnter: FnMut(&StepId) -> Result<(), Error>,
+            FnCanExit: FnMut(&StepId) -> Result<(), Error>
+  {
+    // finished everything already
+    if self.path.is_empty() {
+      return Ok(None);
+    }
+
+    if self.leaf_entered {
+      let current = *self.path.last().unwrap();
+      can_exit(&current)?;
+      self.completed.push(current);
+      self.leaf_entered = false;
+    }
+
+    let best = self.best_path(&self.completed, &mut can_enter, step_store, state_data)?;
+    if let Some(path) = best {
+      self.path = path;
+      self.leaf_entered = true;
+      return Ok(self.path.last().cloned());
+    }
+
+    // all the leaves are done, the parents need to be done too
+    for step_id in super::dfs::preorder(&self.root, step_store).iter().rev() {
+      can_exit(step_id)?;
+    }
+    self.path.clear();
+    Ok(None)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use stepflow_base::ObjectStore;
+  use stepflow_data::StateData;
+  use stepflow_step::{Step, StepId};
+  use stepflow_test_util::test_id;
+  use stepflow_data::var::VarId;
+  use super::{PriorityTraversal, StepScore, Error};
+
+  fn next_all(traversal: &mut PriorityTraversal, step_store: &ObjectStore<Step, StepId>) -> Vec<StepId> {
+    let state_data = StateData::new();
+    let mut result = Vec::new();
+    while let Some(step_id) = traversal.next(|_| Ok(()), |_| Ok(()), step_store, &state_data).unwrap() {
+      result.push(step_id);
+    }
+    result
+  }
+
+  #[test]
+  fn by_metadata() {
+    let mut step_store: ObjectStore<Step, StepId> = ObjectStore::new();
+    let root = step_store.insert_new(|id| Ok(Step::new(id, None, vec![]))).unwrap();
+    let group = step_store.insert_new(|id| Ok(Step::new(id, None, vec![]))).unwrap();
+    let low = step_store.insert_new(|id| Ok(Step::new(id, None, vec![]))).unwrap();
+    let high = step_store.insert_new(|id| Ok(Step::new(id, None, vec![]))).unwrap();
+    let unscored = step_store.insert_new(|id| Ok(Step::new(id, None, vec![]))).unwrap();
+    step_store.get_mut(&low).unwrap().set_metadata("priority", "1");
+    step_store.get_mut(&high).unwrap().set_metadata("priority", "5");
+    step_store.get_mut(&root).unwrap().push_substep(unscored);
+    step_store.get_mut(&root).unwrap().push_substep(group);
+    step_store.get_mut(&group).unwrap().push_substep(low);
+    step_store.get_mut(&group).unwrap().push_substep(high);
+
+    let mut traversal = PriorityTraversal::new(root, StepScore::Metadata("priority".to_owned()));
+    assert_eq!(next_all(&mut traversal, &step_store), vec![high, low, unscored]);
+    assert_eq!(traversal.completed(&step_store), vec![high, low, unscored, root, group]);
+
+    // go back to the group
+    traversal.rewind_to(&group, &step_store).unwrap();
+    assert_eq!(traversal.current(), Some(&group));
+    assert_eq!(traversal.completed(&step_store), vec![unscored]);
+    assert_eq!(next_all(&mut traversal, &step_store), vec![high, low]);
+  }
+
+  #[test]
+  fn unblocks_most() {
+    let mut step_store: ObjectStore<Step, StepId> = ObjectStore::new();
+    let var_a = test_id!(VarId);
+    let var_b = test_id!(VarId);
+    let root = step_store.insert_new(|id| Ok(Step::new(id, None, vec![]))).unwrap();
+    let needs_a = step_store.insert_new(|id| Ok(Step::new(id, Some(vec![var_a]), vec![]))).unwrap();
+    let outputs_b = step_store.insert_new(|id| Ok(Step::new(id, None, vec![var_b]))).unwrap();
+    let outputs_a = step_store.insert_new(|id| Ok(Step::new(id, None, vec![var_a]))).unwrap();
+    for step_id in [needs_a, outputs_b, outputs_a] {
+      step_store.get_mut(&root).unwrap().push_substep(step_id);
+    }
+
+    // can't enter needs_a until outputs_a is done
+    let state_data = StateData::new();
+    let mut done = Vec::new();
+    let mut traversal = PriorityTraversal::new(root, StepScore::UnblocksMost);
+    let mut order = Vec::new();
+    loop {
+      let can_enter = |step_id: &StepId| if *step_id == needs_a && !done.contains(&outputs_a) { Err(Error::NoStateToEval) } else { Ok(()) };
+      match traversal.next(can_enter, |_| Ok(()), &step_store, &state_data).unwrap() {
+        Some(step_id) => {
+          order.push(step_id);
+          done.push(step_id);
+        }
+        None => break,
+      }
+    }
+    assert_eq!(order, vec![outputs_a, needs_a, outputs_b]);
+  }
+
+  #[test]
+  fn order() {
+    let mut step_store: ObjectStore<Step, StepId> = ObjectStore::new();
+    let root = step_store.insert_new(|id| Ok(Step::new(id, None, vec![]))).unwrap();
+    let group = step_store.insert_new(|id| Ok(Step::new(id, None, vec![]))).unwrap();
+    let [a, b, c, d] = [(); 4].map(|_| step_store.insert_new(|id| Ok(Step::new(id, None, vec![]))).unwrap());
+    step_store.get_mut(&root).unwrap().push_substep(a);
+    step_store.get_mut(&root).unwrap().push_substep(group);
+    step_store.get_mut(&group).unwrap().push_substep(b);
+    step_store.get_mut(&group).unwrap().push_substep(c);
+    step_store.get_mut(&root).unwrap().push_substep(d);
+
+    // a waits on the whole group and c waits on d even though they'd go first
+    let mut traversal = PriorityTraversal::new(root, StepScore::UnblocksMost)
+      .with_order(group, a)
+      .with_order(d, c);
+    assert_eq!(traversal.peek_next(&step_store, &StateData::new()), Some(b));
+    assert_eq!(next_all(&mut traversal, &step_store), vec![b, d, c, a]);
+
+    // steps waiting on each other can't go at all
+    let state_data = StateData::new();
+    let mut traversal = PriorityTraversal::new(root, StepScore::UnblocksMost)
+      .with_order(a, d)
+      .with_order(d, a);
+    assert_eq!(traversal.next(|_| Ok(()), |_| Ok(()), &step_store, &state_data), Ok(Some(b)));
+    assert_eq!(traversal.next(|_| Ok(()), |_| Ok(()), &step_store, &state_data), Ok(Some(c)));
+    assert_eq!(traversal.next(|_| Ok(()), |_| Ok(()), &step_store, &state_data), Err(Error::StepOrder(a)));
+  }
+
+  #[test]
+  fn blocked() {
+    let mut step_store: ObjectStore<Step, StepId> = ObjectStore::new();
+    let root = step_store.insert_new(|id| Ok(Step::new(id, None, vec![]))).unwrap();
+    let leaf = step_store.insert_new(|id| Ok(Step::new(id, None, vec![]))).unwrap();
+    step_store.get_mut(&root).unwrap().push_substep(leaf);
+
+    let state_data = StateData::new();
+    let mut traversal = PriorityTraversal::new(root, StepScore::UnblocksMost);
+    assert_eq!(traversal.next(|_| Err(Error::NoStateToEval), |_| Ok(()), &step_store, &state_data), Err(Error::NoStateToEval));
+    assert_eq!(traversal.next(|_| Ok(()), |_| Ok(()), &step_store, &state_data), Ok(Some(leaf)));
+    assert_eq!(traversal.next(|_| Ok(()), |_| Err(Error::NoStateToEval), &step_store, &state_data), Err(Error::NoStateToEval));
+    assert_eq!(traversal.current(), Some(&leaf));
+    assert_eq!(traversal.next(|_| Ok(()), |_| Ok(()), &step_store, &state_data), Ok(None));
+    assert_eq!(traversal.current(), None);
+  }
+}
@@ -0,0 +1,113 @@
+use stepflow_base::ObjectStore;
+use stepflow_data::StateData;
+use stepflow_step::{Step, StepId};
+use super::{Error, dfs::DepthFirstSearch, priority::PriorityTraversal};
+
+/// Check run before entering or exiting a [`Step`]. Usually the step's `can_enter` and `can_exit`.
+pub type StepCheck<'a> = &'a mut dyn FnMut(&StepId) -> Result<(), Error>;
+
+/// Picks the next [`Step`] for a [`Session`](crate::Session)
+///
+/// [`DepthFirstSearch`] is the default. Implement this for other kinds of navigation such as tabs in a wizard.
+pub trait FlowScheduler: std::fmt::Debug {
+  /// The step being worked on. `None` when the flow is finished.
+  fn current(&self) -> Option<&StepId>;
+
+  /// Check if the step is the current step or contains it
+  fn is_entered(&self, step_id: &StepId) -> bool;
+
+  /// The current step and the steps containing it, outermost first
+  fn entered(&self) -> Vec<StepId> {
+    self.current().into_iter().copied().collect()
+  }
+
+  /// Steps that have been exited
+  fn completed(&self, step_store: &ObjectStore<Step, StepId>) -> Vec<StepId>;
+
+  /// Go back to the start of the flow
+  fn restart(&mut self);
+
+  /// Move back to `step_id` as if it was just entered
+  fn rewind_to(&mut self, step_id: &StepId, step_store: &ObjectStore<Step, StepId>) -> Result<(), Error>;
+
+  /// The step [`next`](FlowScheduler::next) would enter if every step could be entered and exited. `None` if it would finish.
+  fn peek_next(&self, step_store: &ObjectStore<Step, StepId>, state_data: &StateData) -> Option<StepId>;
+
+  /// Exit the current step and enter the next one. Returns `None` when the flow is finished.
+  ///
+  /// Steps must pass `can_enter` before being entered and `can_exit` before being exited.
+  fn next(&mut self, can_enter: StepCheck, can_exit: StepCheck, step_store: &ObjectStore<Step, StepId>, state_data: &StateData)
+    -> Result<Option<StepId>, Error>;
+}
+
+impl FlowScheduler for DepthFirstSearch {
+  fn current(&self) -> Option<&StepId> {
+    DepthFirstSearch::current(self)
+  }
+
+  fn is_entered(&self, step_id: &StepId) -> bool {
+    DepthFirstSearch::is_entered(self, step_id)
+  }
+
+  fn entered(&self) -> Vec<StepId> {
+    DepthFirstSearch::entered(self).to_vec()
+  }
+
+  fn completed(&self, step_store: &ObjectStore<Step, StepId>) -> Vec<StepId> {
+    DepthFirstSearch::completed(self, step_store)
+  }
+
+  fn restart(&mut self) {
+    DepthFirstSearch::restart(self)
+  }
+
+  fn rewind_to(&mut self, step_id: &StepId, step_store: &ObjectStore<Step, StepId>) -> Result<(), Error> {
+    DepthFirstSearch::rewind_to(self, step_id, step_store)
+  }
+
+  fn peek_next(&self, step_store: &ObjectStore<Step, StepId>, _state_data: &StateData) -> Option<StepId> {
+    DepthFirstSearch::peek_next(self, step_store)
+  }
+
+  fn next(&mut self, can_enter: StepCheck, can_exit: StepCheck, step_store: &ObjectStore<Step, StepId>, _state_data: &StateData)
+    -> Result<Option<StepId>, Error>
+  {
+    DepthFirstSearch::next(self, can_enter, can_exit, step_store)
+  }
+}
+
+impl FlowScheduler for PriorityTraversal {
+  fn current(&self) -> Option<&StepId> {
+    PriorityTraversal::current(self)
+  }
+
+  fn is_entered(&self, step_id: &StepId) -> bool {
+    PriorityTraversal::is_entered(self, step_id)
+  }
+
+  fn entered(&self) -> Vec<StepId> {
+    PriorityTraversal::entered(self).to_vec()
+  }
+
+  fn completed(&self, step_store: &ObjectStore<Step, StepId>) -> Vec<StepId> {
+    PriorityTraversal::completed(self, step_store)
+  }
+
+  fn restart(&mut self) {
+    PriorityTraversal::restart(self)
+  }
+
+  fn rewind_to(&mut self, step_id: &StepId, step_store: &ObjectStore<Step, StepId>) -> Result<(), Error> {
+    PriorityTraversal::rewind_to(self, step_id, step_store)
+  }
+
+  fn peek_next(&self, step_store: &ObjectStore<Step, StepId>, state_data: &StateData) -> Option<StepId> {
+    PriorityTraversal::peek_next(self, step_store, state_data)
+  }
+
+  fn next(&mut self, can_enter: StepCheck, can_exit: StepCheck, step_store: &ObjectStore<Step, StepId>, state_data: &StateData)
+    -> Result<Option<StepId>, Error>
+  {
+    PriorityTraversal::next(self, can_enter, can_exit, step_store, state_data)
+  }
+}
@@ -1,13 +1,24 @@
+use std::cell::{Cell, RefCell};
 use std::collections::{HashMap, HashSet};
+use std::time::{Duration, SystemTime};
+use std::sync::{Arc, Mutex, PoisonError};
 use stepflow_base::{ObjectStore, ObjectStoreContent, ObjectStoreFiltered, IdError, generate_id_type};
-use stepflow_data::{StateData, StateDataFiltered, var::{Var, VarId}, value::Value};
-use stepflow_step::{Step, StepId};
-use stepflow_action::{Action, ActionResult, ActionId};
-use super::{Error, dfs};
+use stepflow_data::{StateData, StateDataFiltered, VarDependencies, VarAccess, Condition, InvalidValue, InvalidVars, var::{Var, VarId}, value::{Value, StringInterner}};
+use stepflow_step::{Step, StepId, StepMatcher, VisitPolicy};
+use stepflow_action::{Action, ActionResult, ActionId, ApprovalAction, FlowEnvironment, SecretsProvider};
+use super::{ApprovalStep, Error, AdvanceError, DefinitionDrift, ResourceLimits, QuotaExceeded, LoadShedding, SessionEvent, SessionEventKind, ActionOutcome, AttemptId, TraceEntry, StepStatus, FlowScheduler, DepthFirstSearch, TenantQuotas, dfs, graph};
+use super::limits::AdvanceRate;
+use super::history::Checkpoint;
 
 
 generate_id_type!(SessionId);
 
+/// Separates a step group from the name of the step within the group. i.e. `kyc.address`
+const STEP_GROUP_SEPARATOR: char = '.';
+
+/// Who approved a step that was [auto-approved](Escalation::AutoApprove)
+pub const ESCALATION_ACTOR: &str = "escalation";
+
 
 /// Sessions both define a flow and execute them.
 ///
@@ -36,24 +47,67 @@ generate_id_type!(SessionId);
 /// 
 /// // Start the session!
 /// let advance_result = session.advance(None);
-/// assert!(matches!(advance_result, Ok(AdvanceBlockedOn::ActionStartWith(_, _html))));
+/// assert!(matches!(advance_result, Ok(AdvanceBlockedOn::ActionStartWith(_, _html, _))));
 ///
 /// // From here, typically you'd display the form and call session.advance() with the form results
 /// ```
-#[derive(Debug)]
+///
+/// The `Debug` output is a summary of the session. Data values are left out since they may be sensitive.
 pub struct Session {
   id: SessionId,
   state_data: StateData,
-  actions: HashMap<StepId, ActionId>,
+  var_dependencies: VarDependencies,
+  var_access: VarAccess,
+  var_descriptions: HashMap<VarId, String>,
+  action_descriptions: HashMap<ActionId, String>,
+  environment: FlowEnvironment,
+  pub(crate) actions: HashMap<StepId, ActionId>,
+  pub(crate) action_bindings: Vec<(StepMatcher, ActionId)>,
+  pub(crate) action_candidates: HashMap<StepId, Vec<(Condition, ActionId)>>,
+  pub(crate) fallback_chains: HashMap<StepId, Vec<FallbackAction>>,
+  #[cfg(feature = "serde-support")]
+  pub(crate) action_definitions: HashMap<ActionId, crate::ActionDefinition>,
 
   step_store: ObjectStore<Step, StepId>,
   action_store: ObjectStore<Box<dyn Action + Sync + Send>, ActionId>,
   var_store: ObjectStore<Box<dyn Var + Send + Sync>, VarId>,
 
-  step_id_all: StepId,
-  step_id_root: StepId,
-
-  step_id_dfs: dfs::DepthFirstSearch,
+  pub(crate) step_id_all: StepId,
+  pub(crate) step_id_root: StepId,
+
+  scheduler: Box<dyn FlowScheduler + Sync + Send>,
+  visited: HashSet<StepId>,
+  finish_actions: Vec<FinishAction>,
+  completion_keys: HashMap<String, StateData>,
+  compensation_actions: HashMap<StepId, ActionId>,
+  to_compensate: Vec<StepId>,
+  assignees: HashMap<StepId, String>,
+  blocked_observers: Vec<BlockedObserver>,
+  transition_observers: Vec<TransitionObserver>,
+  merged_since_transition: StateData,
+  notify_actions: Vec<ActionId>,
+  last_blocked: Option<BlockedNotice>,
+  blocked_since: Option<SystemTime>,
+  deadline_policies: HashMap<StepId, DeadlinePolicy>,
+  follow_ups: Vec<FollowUp>,
+  loop_passes: HashMap<StepId, u32>,
+
+  limits: ResourceLimits,
+  advance_rate: AdvanceRate,
+  definition_changed: bool,
+  tenant: Option<(String, Arc<Mutex<TenantQuotas>>)>,
+  preview_expires_at: Option<SystemTime>,
+  finished_at: Option<SystemTime>,
+  load_shedding: LoadShedding,
+  string_interner: Option<StringInterner>,
+  history: Vec<SessionEvent>,
+  history_limit: Option<usize>,
+  checkpoints: Option<Vec<Checkpoint>>,
+  next_event_seq: u64,
+  current_attempt: Option<(AttemptId, ActionId)>,
+  rejected_vars: Option<InvalidVars>,
+  actor: Option<String>,
+  trace: Option<Vec<TraceEntry>>,
 }
 
 impl ObjectStoreContent for Session {
@@ -68,6 +122,30 @@ impl ObjectStoreContent for Session {
     }
 }
 
+impl std::fmt::Debug for Session {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let name_or_id = |name: Option<&str>, id: &dyn std::fmt::Display| {
+      name.map(|name| name.to_owned()).unwrap_or_else(|| id.to_string())
+    };
+    let current_step = self.scheduler.current()
+      .map(|step_id| name_or_id(self.step_store.name_from_id(step_id), step_id));
+    let mut data_vars = self.state_data.iter_val()
+      .map(|(var_id, _)| name_or_id(self.var_store.name_from_id(var_id), var_id))
+      .collect::<Vec<_>>();
+    data_vars.sort();
+
+    f.debug_struct("Session")
+      .field("id", &self.id)
+      .field("current_step", &current_step)
+      .field("steps", &self.step_store.len())
+      .field("vars", &self.var_store.len())
+      .field("actions", &self.action_store.len())
+      .field("data_vars", &data_vars)
+      .field("preview", &self.is_preview())
+      .finish()
+  }
+}
+
 impl Session {
   /// Create a new `Session`
   pub fn new(id: SessionId) -> Self {
@@ -93,13 +171,54 @@ impl Session {
     Session {
       id,
       state_data: StateData::new(),
+      var_dependencies: VarDependencies::new(),
+      var_access: VarAccess::new(),
+      var_descriptions: HashMap::new(),
+      action_descriptions: HashMap::new(),
+      environment: FlowEnvironment::new(),
       actions: HashMap::new(),
+      action_bindings: Vec::new(),
+      action_candidates: HashMap::new(),
+      fallback_chains: HashMap::new(),
+      #[cfg(feature = "serde-support")]
+      action_definitions: HashMap::new(),
       step_store,
       action_store: ObjectStore::with_capacity(action_capacity),
       var_store: ObjectStore::with_capacity(var_capacity),
       step_id_all: step_id_all,
       step_id_root: step_id_root,
-      step_id_dfs: dfs::DepthFirstSearch::new(step_id_root),
+      scheduler: Box::new(DepthFirstSearch::new(step_id_root)),
+      visited: HashSet::new(),
+      finish_actions: Vec::new(),
+      completion_keys: HashMap::new(),
+      compensation_actions: HashMap::new(),
+      to_compensate: Vec::new(),
+      assignees: HashMap::new(),
+      blocked_observers: Vec::new(),
+      transition_observers: Vec::new(),
+      merged_since_transition: StateData::new(),
+      notify_actions: Vec::new(),
+      last_blocked: None,
+      blocked_since: None,
+      deadline_policies: HashMap::new(),
+      follow_ups: Vec::new(),
+      loop_passes: HashMap::new(),
+      limits: ResourceLimits::default(),
+      advance_rate: AdvanceRate::default(),
+      definition_changed: true,
+      tenant: None,
+      preview_expires_at: None,
+      finished_at: None,
+      load_shedding: LoadShedding::new(),
+      string_interner: None,
+      history: Vec::new(),
+      history_limit: None,
+      checkpoints: None,
+      next_event_seq: 0,
+      current_attempt: None,
+      rejected_vars: None,
+      actor: None,
+      trace: None,
     }
   }
 
@@ -114,7 +233,126 @@ impl Session {
   }
 
   pub fn current_step(&self) -> Result<&StepId, Error> {
-    self.step_id_dfs.current().ok_or_else(|| Error::NoStateToEval)
+    self.scheduler.current().ok_or_else(|| Error::NoStateToEval)
+  }
+
+  /// Get the status of every step under the root, in the order they're visited
+  pub fn step_statuses(&self) -> Vec<(StepId, StepStatus)> {
+    let completed = self.scheduler.completed(&self.step_store);
+    let current = self.scheduler.current();
+    dfs::preorder(&self.step_id_root, &self.step_store)
+      .into_iter()
+      .map(|step_id| {
+        let step = self.step_store.get(&step_id);
+        let status = if completed.contains(&step_id) {
+          let all_outputs = step.map(|step| step.get_output_vars().iter().all(|var_id| self.state_data.contains(var_id))).unwrap_or(true);
+          if all_outputs { StepStatus::Completed } else { StepStatus::Skipped }
+        } else if Some(&step_id) == current {
+          let can_exit = step.map(|step| step.can_exit(&self.state_data).is_ok()).unwrap_or(false);
+          if can_exit { StepStatus::Current } else { StepStatus::Blocked }
+        } else if self.scheduler.is_entered(&step_id) {
+          StepStatus::Entered
+        } else {
+          StepStatus::Pending
+        };
+        (step_id, status)
+      })
+      .collect()
+  }
+
+  /// Export the flow as a [DOT](https://graphviz.org/doc/info/lang.html) graph with each step colored by its [`StepStatus`]
+  ///
+  /// Steps with an [action set](Session::set_action_for_step) show the [action's description](Session::set_action_description) next to them.
+  pub fn to_dot(&self) -> String {
+    let action_description = |step_id: &StepId| self.actions.get(step_id).and_then(|action_id| self.action_description(action_id));
+    graph::to_dot(&self.step_statuses(), &self.step_store, action_description)
+  }
+
+  /// Change how the next step is picked. `new_scheduler` is called with the root step.
+  /// The flow starts over, keeping the data.
+  /// ```
+  /// # use stepflow_session::{Session, SessionId, PriorityTraversal, StepScore};
+  /// let mut session = Session::new(SessionId::new(0));
+  /// session.set_scheduler(|root| Box::new(PriorityTraversal::new(root, StepScore::UnblocksMost)));
+  /// ```
+  pub fn set_scheduler<F>(&mut self, new_scheduler: F)
+      where F: FnOnce(StepId) -> Box<dyn FlowScheduler + Sync + Send>
+  {
+    self.scheduler = new_scheduler(self.step_id_root);
+  }
+
+  /// Go back to the state before the first [`advance`](Session::advance)
+  ///
+  /// If `keep_data` is false, all the session data is cleared as well.
+  /// Actions keep any internal state they have. Finish actions and follow-ups will run again when the flow finishes and fallback chains start over.
+  pub fn reset(&mut self, keep_data: bool) {
+    self.scheduler.restart();
+    self.visited.clear();
+    self.last_blocked = None;
+    self.blocked_since = None;
+    self.finished_at = None;
+    self.current_attempt = None;
+    self.rejected_vars = None;
+    self.loop_passes.clear();
+    self.completion_keys.clear();
+    self.to_compensate.clear();
+    for finish_action in self.finish_actions.iter_mut() {
+      finish_action.attempts = 0;
+      finish_action.status = FinishActionStatus::Pending;
+    }
+    for follow_up in self.follow_ups.iter_mut() {
+      follow_up.started = false;
+    }
+    for fallback in self.fallback_chains.values_mut().flatten() {
+      fallback.attempts = 0;
+    }
+    if !keep_data {
+      self.state_data = StateData::new();
+    }
+    if let Some(interner) = self.string_interner.as_mut() {
+      interner.purge_unused();
+    }
+  }
+
+  /// Go back to `step_id` as if it was just entered
+  ///
+  /// The outputs of the step and its substeps are removed along with any data that depends on them.
+  pub fn reset_to(&mut self, step_id: &StepId) -> Result<(), Error> {
+    self.scheduler.rewind_to(step_id, &self.step_store)?;
+    self.finished_at = None;
+    self.clear_outputs(step_id, true)
+  }
+
+  /// Move to `step_id` as if it was just entered, keeping the data. i.e. to restore a session that was saved on the step.
+  ///
+  /// The steps before it in a [`DepthFirstSearch`] are completed. Other [schedulers](Session::set_scheduler) may visit them again.
+  pub fn resume_at(&mut self, step_id: &StepId) -> Result<(), Error> {
+    self.scheduler.rewind_to(step_id, &self.step_store)?;
+    self.finished_at = None;
+    Ok(())
+  }
+
+  // remove the outputs of everything under the step, and the step itself if `include_step`
+  fn clear_outputs(&mut self, step_id: &StepId, include_step: bool) -> Result<(), Error> {
+    let mut output_var_ids = HashSet::new();
+    let mut to_visit = vec![*step_id];
+    while let Some(visit_id) = to_visit.pop() {
+      let step = self.step_store.get(&visit_id).ok_or(Error::StepId(IdError::IdMissing(visit_id)))?;
+      if include_step || visit_id != *step_id {
+        output_var_ids.extend(step.get_output_vars().iter().cloned());
+      }
+      if let Some(substeps) = step.get_substeps() {
+        to_visit.extend(substeps.iter().cloned());
+      }
+    }
+
+    for var_id in output_var_ids.iter() {
+      self.state_data.remove(var_id);
+      for dependent in self.var_dependencies.dependents_of(var_id) {
+        self.state_data.remove(&dependent);
+      }
+    }
+    Ok(())
   }
 
   /// Store for [`Step`]s
@@ -124,21 +362,41 @@ impl Session {
 
   /// Mutable store for [`Step`]s
   pub fn step_store_mut(&mut self) -> &mut ObjectStore<Step, StepId> {
+    self.definition_changed = true;
     &mut self.step_store
   }
 
   /// Add a registered [`Step`] to the end of the root step
   pub fn push_root_substep(&mut self, step_id: StepId) {
+    self.definition_changed = true;
     let root_step = self.step_store.get_mut(&self.step_id_root).unwrap();
     root_step.push_substep(step_id);
   }
 
+  /// Insert a [`Step`] named `<group>.<name>` so it can be managed with the rest of the group
+  pub fn insert_group_step<CB>(&mut self, group: &str, name: &str, cb: CB) -> Result<StepId, Error>
+      where CB: FnOnce(StepId) -> Result<Step, IdError<StepId>>
+  {
+    let full_name = format!("{}{}{}", group, STEP_GROUP_SEPARATOR, name);
+    self.step_store.insert_new_named(full_name, cb).map_err(Error::from)
+  }
+
+  /// Get all the [`Step`]s in a group, i.e. named `<group>.<name>`, ordered by [`StepId`].
+  ///
+  /// IDs the step store generates increase so that's the order they were added, but not for steps
+  /// [registered](ObjectStore::register_named) with IDs from elsewhere.
+  pub fn step_group(&self, group: &str) -> Vec<StepId> {
+    let prefix = format!("{}{}", group, STEP_GROUP_SEPARATOR);
+    self.step_store.ids_with_name_prefix(&prefix)
+  }
+
   /// Store for [`Action`](stepflow_action::Action)s
   pub fn action_store(&self) -> &ObjectStore<Box<dyn Action + Sync + Send>, ActionId> {
     &self.action_store
   }
 
   pub fn action_store_mut(&mut self) -> &mut ObjectStore<Box<dyn Action + Sync + Send>, ActionId> {
+    self.definition_changed = true;
     &mut self.action_store
   }
 
@@ -149,319 +407,2942 @@ impl Session {
 
   /// Mutable store for [`Var`]s
   pub fn var_store_mut(&mut self) -> &mut ObjectStore<Box<dyn Var + Sync + Send>, VarId> {
+    self.definition_changed = true;
     &mut self.var_store
   }
 
-  /// Set the [`Action`](stepflow_action::Action) for a [`Step`]
-  ///
-  /// If `step_id` is None, it's registered as the general action for all steps.
-  /// Actions are generally executed with the specific step first (if it exists)
-  /// and the general step after (if the specific step cannot fulfill).
-  pub fn set_action_for_step(&mut self, action_id: ActionId, step_id:Option<&StepId>) 
-  -> Result<(), Error> {
-    let step_id_use = step_id.or(Some(&self.step_id_all)).unwrap();
-    if self.actions.contains_key(step_id_use) {
-      return Err(Error::StepId(IdError::IdAlreadyExists(step_id_use.clone())));
-    }
-    self.actions.insert(step_id_use.clone(), action_id);
-    Ok(())
+  /// Get the ID of a [`Var`] by its name
+  pub fn var_id_from_name(&self, name: &str) -> Result<VarId, Error> {
+    self.var_store
+      .id_from_name(name)
+      .copied()
+      .ok_or_else(|| Error::VarId(IdError::NoSuchName(name.to_owned())))
   }
 
-
-  /// see if next step will accept with current inputs
-  /// if so, advance there (checking for nested states) and return current step
-  /// if not, reject and stay on current step (how relay error msg?)
-  fn try_enter_next_step(&mut self, step_output: Option<(&StepId, StateData)>)
-    -> Result<Option<StepId>, Error>
+  /// Get the ID of a [`Var`] by its name, checking it takes `T` values
+  ///
+  /// Fails with [`Error::InvalidVars`] if the var takes another type of value or doesn't say which it takes.
+  pub fn var_id_for_type<T>(&self, name: &str) -> Result<VarId, Error>
+      where T: Value + std::any::Any
   {
-    if let Some(output) = step_output {
-      // make sure we're updating the right state
-      if self.current_step()? != output.0 {
-        return Err(Error::StepId(IdError::IdUnexpected(output.0.clone())))
-      }
-
-      // merge the new inputs in first. best to not lose this even if the rest fails
-      self.state_data.merge_from(output.1)
+    let var_id = self.var_id_from_name(name)?;
+    let var = self.var_store.get(&var_id).ok_or(Error::VarId(IdError::IdMissing(var_id)))?;
+    if var.value_type() != Some(std::any::TypeId::of::<T>()) {
+      return Err(Error::InvalidVars(InvalidVars::new(vec![(var_id, InvalidValue::WrongType)].into_iter().collect())));
     }
+    Ok(var_id)
+  }
 
-    let state_data = &self.state_data;
-    let step_store = &self.step_store;
-    self.step_id_dfs.next(
-      |step_id| {
-        let step = step_store.get(step_id).ok_or_else(|| Error::StepId(IdError::IdMissing(step_id.clone())))?;
-        step.can_enter(&state_data).map_err(|e| Error::VarId(e))
-      },
-      |step_id| {
-        let step = step_store.get(step_id).ok_or_else(|| Error::StepId(IdError::IdMissing(step_id.clone())))?;
-        step.can_exit(&state_data).map_err(|e| Error::VarId(e))
-      },
-      &self.step_store)
+  /// Limits on the resources the session can use
+  pub fn limits(&self) -> &ResourceLimits {
+    &self.limits
   }
 
-  fn call_action(&mut self, action_id: &ActionId, step_id: &StepId) -> Result<ActionResult, Error> {
-    // setup params
-    fn get_step_input_output_vars(step: &Step) -> HashSet<VarId> {
-      step.get_input_vars()
-        .clone()      
-        .unwrap_or_else(|| vec![])
-        .iter()
-        .chain(step.get_output_vars().iter())
-        .map(|id_ref| id_ref.clone())
-        .collect::<HashSet<VarId>>()
-    }
-  
-    let step = self.step_store.get(step_id).ok_or_else(|| Error::StepId(IdError::IdMissing(step_id.clone())))?;
-    let step_name = self.step_store.name_from_id(&step_id);
-    let step_data: StateDataFiltered = StateDataFiltered::new(&self.state_data, get_step_input_output_vars(&step));
-    let vars = ObjectStoreFiltered::new(&self.var_store, get_step_input_output_vars(&step));
+  /// Set the limits on the resources the session can use. They're enforced when advancing.
+  ///
+  /// [`max_advances_per_minute`](ResourceLimits::max_advances_per_minute) counts this session's calls to [`advance`](Session::advance).
+  /// A [tenant](Session::set_tenant)'s limit applies on top of it.
+  pub fn set_limits(&mut self, limits: ResourceLimits) {
+    self.definition_changed = true;
+    self.limits = limits;
+  }
 
-    // call it
-    let action = self.action_store.get_mut(action_id).ok_or_else(|| Error::ActionId(IdError::IdMissing(action_id.clone())))?;
-    let action_result = action.start(&step, step_name, &step_data, &vars).map_err(|e| Error::from(e))?;
-    match &action_result {
-        ActionResult::Finished(state_data) => {
-          if !state_data.contains_only(&step.output_vars.iter().collect::<HashSet<_>>()) {
-            return Err(Error::InvalidStateDataError);
-          }
-        }
-        ActionResult::StartWith(_) |
-        ActionResult::CannotFulfill => ()
-    }
-    Ok(action_result)
-  }  
+  /// The tenant that owns the session
+  pub fn tenant(&self) -> Option<&str> {
+    self.tenant.as_ref().map(|(tenant, _quotas)| &tenant[..])
+  }
 
-  /// Main function for advancing the flow to the next step.
-  ///
-  /// `step_output` is what the current step generated and is merged with the internal current `state_data`
-  /// before trying to advance to the next step.
-  ///
-  /// Advancing works in a loop that tries to advance as far as possible until it hits a blocking condition
-  /// The loop is roughly:
-  /// - Try to enter the next step. Note: the process continues irregardless of failure
-  /// - Execute the specific action of the current step
-  /// - If there is no specific action or it [`CannotFulfill`](ActionResult::CannotFulfill), execute the general action
-  /// - If the action is not [`Finished`](ActionResult::Finished), then we're blocked and exit the loop
-  pub fn advance(&mut self, step_output: Option<(&StepId, StateData)>) 
-      -> Result<AdvanceBlockedOn, Error>
+  /// Count the session's calls to [`advance`](Session::advance) against `tenant` in `quotas`, shared by all of the tenant's sessions.
+  /// Advancing fails once the tenant is over its [`max_advances_per_minute`](ResourceLimits::max_advances_per_minute).
+  pub fn set_tenant<STR>(&mut self, tenant: STR, quotas: Arc<Mutex<TenantQuotas>>)
+      where STR: Into<String>
   {
-    #[derive(Clone, Debug)]
-    enum States {
-      AdvanceStep,
-      GetSpecificAction(StepId, Option<Error>),  // current step id, step-id-advance error
-      GetGenericAction(StepId, Option<Error>),      // step-id-advance error
-      StartSpecific(ActionId, StepId, Option<Error>), // action id, step-id-advance error
-      StartGeneric(ActionId, StepId, Option<Error>),  // action id, step-id-advance error
-      Done(Result<AdvanceBlockedOn, Error>)
-    }
-
-    // generally we're trying to advance as much as possible without user interaction:
-    // loop until we get to a blocking state (StartWith or No-more-states-left or can't-start)
-    //   advance step
-    //   succeed or fail:
-    //     start specific action
-    //     if doesn't exist or succeed, start generic action
-    // return (step-advance-result, action-result)
-    let mut step_output = step_output;
-    let mut state = States::AdvanceStep;
-    loop {
-      state = match state.clone() {
-        States::Done(result) => return result,
-        States::AdvanceStep => {
-          let advance_result = self.try_enter_next_step(step_output);
-          step_output = None;
-          match &advance_result {
-            Ok(step_id_opt) => {
-              match step_id_opt {
-                Some(step_id) => States::GetSpecificAction(step_id.clone(), None),
-                None => States::Done(Ok(AdvanceBlockedOn::FinishedAdvancing)), // no more steps left to advance
-              }
-            }
-            Err(err) => {
-              let step_id = self.current_step()?.clone();
-              States::GetSpecificAction(step_id, Some(err.clone())) // error advancing but we can try the action to see if that fixes it
-            }
-          }
-        },
-        States::GetSpecificAction(step_id, error) => {
-          match self.actions.get(&step_id) {
-            Some(action_id) => States::StartSpecific(action_id.clone(), step_id, error),
-            None => States::GetGenericAction(step_id, error),
-          }
-        },
-        States::GetGenericAction(step_id, error) => {
-          match self.actions.get(&self.step_id_all) {
-            Some(action_id) => States::StartGeneric(action_id.clone(), step_id, error),
-            None => {
-              match error {
-                None => States::AdvanceStep,  // did we advance? if so, try advancing again
-                Some(err) => return Err(err),   // couldn't advance and no action? then we're stuck
-              }
-            }
-          }
-        },
-        States::StartSpecific(action_id, step_id, error_opt) |
-        States::StartGeneric(action_id, step_id, error_opt) => {
-          let action_result = self.call_action(&action_id, &step_id)?;
-          match action_result {
-              ActionResult::StartWith(val) => {
-                States::Done(Ok(AdvanceBlockedOn::ActionStartWith(action_id, val)))
-              }
-              ActionResult::Finished(state_data) => {
-                // merge the new data and see if we can keep advancing
-                self.state_data.merge_from(state_data.clone());
-                States::AdvanceStep
-              }
-              ActionResult::CannotFulfill => {
-                if matches!(state, States::StartSpecific(_,_,_)) {
-                  // couldn't fulfill specific action, try generic one
-                  States::GetGenericAction(step_id, error_opt)
-                } else {
-                  // couldn't fulfill generic one (and must've already failed specific) -- nothing else we can do
-                  States::Done(Ok(AdvanceBlockedOn::ActionCannotFulfill))
-                }
-              }
-          }
-        }
-      }
-    }
+    self.tenant = Some((tenant.into(), quotas));
   }
 
-  #[cfg(test)]
-  pub fn test_new() -> (Session, StepId) {
-    let mut session = Session::new(stepflow_test_util::test_id!(SessionId));
-    let root_step_id = session.step_store_mut().insert_new_named("root_step", |id| Ok(Step::new(id, None, vec![]))).unwrap();
-    session.push_root_substep(root_step_id.clone());
-    (session, root_step_id)
+  /// Make this a throwaway preview that expires at `expires_at`, i.e. to try a flow from a marketing page
+  ///
+  /// Previews only keep state in memory. They don't notify [blocked](Session::add_blocked_observer) or [transition](Session::on_transition) observers,
+  /// escalate [deadlines](Session::enforce_deadline), run [finish actions](Session::add_finish_action) or start
+  /// [follow-ups](Session::pending_follow_ups). Once expired, advancing fails with [`Error::PreviewExpired`].
+  pub fn set_preview(&mut self, expires_at: SystemTime) {
+    self.preview_expires_at = Some(expires_at);
   }
 
-  #[cfg(test)]
-  pub fn test_new_stringvar(&mut self) -> VarId {
-    let var_id = stepflow_test_util::test_id!(VarId);
-    let var = stepflow_data::var::StringVar::new(var_id);
-    let var_id = self.var_store.register( var.boxed()).unwrap();
-    var_id
+  /// Whether this is a [preview](Session::set_preview)
+  pub fn is_preview(&self) -> bool {
+    self.preview_expires_at.is_some()
   }
-}
 
-/// What [`Session::advance`] has blocked on
-#[derive(Debug, Clone)]
-pub enum AdvanceBlockedOn {
-  /// Same as [`ActionResult::StartWith`] but with the additional identifier of which [`Action`](stepflow_action::Action) blocked.
-  ActionStartWith(ActionId, Box<dyn Value>),
+  /// When the [preview](Session::set_preview) expires
+  pub fn preview_expires_at(&self) -> Option<SystemTime> {
+    self.preview_expires_at
+  }
 
-  /// Same as [`ActionResult::CannotFulfill`]
-  ActionCannotFulfill,
+  /// Whether this is a [preview](Session::set_preview) that expired by `now`
+  pub fn is_expired(&self, now: SystemTime) -> bool {
+    self.preview_expires_at.map(|expires_at| now >= expires_at).unwrap_or(false)
+  }
 
-  /// [`Session`] has finished advancing to the end of the flow
-  FinishedAdvancing,
-}
+  /// When [`advance`](Session::advance) first returned [`AdvanceBlockedOn::FinishedAdvancing`]. Cleared by a [reset](Session::reset).
+  pub fn finished_at(&self) -> Option<SystemTime> {
+    self.finished_at
+  }
 
-impl PartialEq for AdvanceBlockedOn {
-  fn eq(&self, other: &Self) -> bool {
-    match (self, other) {
-      (AdvanceBlockedOn::ActionStartWith(action_id, val),AdvanceBlockedOn::ActionStartWith(action_id_other, val_other)) => {
-        action_id == action_id_other && val == val_other
-      }
-      (AdvanceBlockedOn::ActionCannotFulfill, AdvanceBlockedOn::ActionCannotFulfill) |
-      (AdvanceBlockedOn::FinishedAdvancing, AdvanceBlockedOn::FinishedAdvancing) => {
-        true
-      }
-      _ => false
-    }
+  /// The switch for shedding optional work under high load
+  pub fn load_shedding(&self) -> &LoadShedding {
+    &self.load_shedding
   }
-}
 
+  /// Share `load_shedding` with the session so [blocked observers](Session::add_blocked_observer), [transition observers](Session::on_transition)
+  /// and the [history](Session::history) only get a sample. Blocked notices with an assignee or a deadline are always sent.
+  /// [Notify actions](Session::add_notify_action) still run since they're part of the flow.
+  pub fn set_load_shedding(&mut self, load_shedding: LoadShedding) {
+    self.load_shedding = load_shedding;
+  }
 
-#[cfg(test)]
-mod tests {
-  use core::panic;
-  use stepflow_base::{ObjectStore, IdError};
-  use stepflow_data::{StateData, var::VarId, value::{BoolValue, StringValue}};
-  use stepflow_step::{Step, StepId};
-  use stepflow_test_util::test_id;
-  use stepflow_action::{SetDataAction, ActionId};
-  use crate::test::TestAction;
-  use super::super::{Error};
-  use super::{Session, SessionId, AdvanceBlockedOn};
+  /// Share identical strings across all the data merged into the session, i.e. for flows that copy the same value into many vars
+  ///
+  /// Strings no longer used by the session's data are dropped from the pool when data is merged and on [`reset`](Session::reset).
+  pub fn set_string_interning(&mut self, enabled: bool) {
+    if enabled {
+      let mut interner = self.string_interner.take().unwrap_or_default();
+      self.state_data.intern_strings(&mut interner);
+      self.string_interner = Some(interner);
+    } else {
+      self.string_interner = None;
+    }
+  }
 
+  /// The shared strings when [interning](Session::set_string_interning) is on
+  pub fn string_interner(&self) -> Option<&StringInterner> {
+    self.string_interner.as_ref()
+  }
 
+  /// Every step entered and exited and every action started, oldest first
+  ///
+  /// Only a sample is kept while [load shedding](Session::set_load_shedding) is on.
+  pub fn history(&self) -> &[SessionEvent] {
+    &self.history
+  }
 
-  fn new_simple_step(id: StepId) -> Result<Step, IdError<StepId>> {
-    Ok(Step::new(id, None, vec![]))
+  /// Only keep the newest `limit` events in the [history](Session::history). `None` keeps them all.
+  pub fn set_history_limit(&mut self, limit: Option<usize>) {
+    self.history_limit = limit;
+    self.trim_history();
   }
 
-  fn add_new_simple_substep(parent_id: &StepId, step_store: &mut ObjectStore<Step, StepId>) -> StepId {
-    let substep_id = step_store.insert_new(new_simple_step).unwrap();
-    push_substep(parent_id, substep_id, step_store)
+  // drop the oldest events over the history limit
+  fn trim_history(&mut self) {
+    if let Some(limit) = self.history_limit {
+      let excess = self.history.len().saturating_sub(limit);
+      self.history.drain(..excess);
+    }
+    if let Some(checkpoints) = self.checkpoints.as_mut() {
+      let oldest_seq = self.history.first().map(|event| event.seq).unwrap_or(self.next_event_seq);
+      checkpoints.retain(|checkpoint| checkpoint.seq >= oldest_seq);
+    }
   }
 
-  fn push_substep(parent_id: &StepId, step_id: StepId, step_store: &mut ObjectStore<Step, StepId>) -> StepId {
-    let parent = step_store.get_mut(parent_id).unwrap();
-    parent.push_substep(step_id.clone());
-    step_id
+  /// Remove the history, i.e. after writing it to an audit log
+  pub fn take_history(&mut self) -> Vec<SessionEvent> {
+    if let Some(checkpoints) = self.checkpoints.as_mut() {
+      checkpoints.clear();
+    }
+    std::mem::take(&mut self.history)
   }
 
-  fn step_str_output(session: &Session, var_id: &VarId, val: &'static str) -> (StepId, StateData) {
-    let mut state_data = StateData::new();
-    let var = session.var_store().get(var_id).unwrap();
-    state_data.insert(var, StringValue::try_new(val).unwrap().boxed()).unwrap();
-    (session.current_step().unwrap().clone(), state_data)
+  /// Keep the data and current step along with each event in the [history](Session::history) to look up with [`state_at`](Session::state_at)
+  ///
+  /// Data that's the same as for the previous event is shared but it still adds up for long flows, so it's meant for debugging and support.
+  pub fn set_checkpoints(&mut self, enabled: bool) {
+    self.checkpoints = if enabled { Some(Vec::new()) } else { None };
   }
 
-  #[test]
-  fn empty_session_advance() {
-    let mut session = Session::new(test_id!(SessionId));
-    let advance_result = session.advance(None);
-    assert_eq!(advance_result, Ok(AdvanceBlockedOn::FinishedAdvancing));
+  /// The data and current step when the event at `history_index` in the [history](Session::history) was recorded
+  ///
+  /// `None` if the index is past the end or [checkpoints](Session::set_checkpoints) weren't on when the event was recorded.
+  pub fn state_at(&self, history_index: usize) -> Option<(&StateData, Option<&StepId>)> {
+    let seq = self.history.get(history_index)?.seq;
+    let checkpoints = self.checkpoints.as_ref()?;
+    let checkpoint = checkpoints
+      .binary_search_by_key(&seq, |checkpoint| checkpoint.seq)
+      .ok()
+      .and_then(|pos| checkpoints.get(pos))?;
+    Some((&checkpoint.state_data, checkpoint.current_step.as_ref()))
+  }
+
+  /// Verify the definition of the flow is within the [`ResourceLimits`]
+  ///
+  /// [`advance`](Session::advance) runs this and [`check_definition_drift`](Session::check_definition_drift)
+  /// when the stores or limits were changed since the checks last passed.
+  pub fn check_definition_limits(&self) -> Result<(), Error> {
+    // don't count our internal steps
+    let num_steps = self.step_store.iter()
+      .filter(|(step_id, _)| **step_id != self.step_id_all && **step_id != self.step_id_root)
+      .count();
+    if let Some(max_steps) = self.limits.max_steps {
+      if num_steps > max_steps {
+        return Err(Error::QuotaExceeded(QuotaExceeded::Steps(max_steps)));
+      }
+    }
+    if let Some(max_vars) = self.limits.max_vars {
+      if self.var_store.len() > max_vars {
+        return Err(Error::QuotaExceeded(QuotaExceeded::Vars(max_vars)));
+      }
+    }
+    if let Some(max_substeps) = self.limits.max_substeps {
+      let too_many = self.step_store.iter()
+        .any(|(_, step)| step.get_substeps().map(|substeps| substeps.len() > max_substeps).unwrap_or(false));
+      if too_many {
+        return Err(Error::QuotaExceeded(QuotaExceeded::Substeps(max_substeps)));
+      }
+    }
+    if let Some(max_depth) = self.limits.max_depth {
+      // walk without recursion so a deep or cyclic definition stops at the limit
+      let mut to_visit = vec![(self.step_id_root, 0)];
+      while let Some((step_id, depth)) = to_visit.pop() {
+        if depth > max_depth {
+          return Err(Error::QuotaExceeded(QuotaExceeded::Depth(max_depth)));
+        }
+        let substeps = self.step_store.get(&step_id).and_then(|step| step.get_substeps());
+        to_visit.extend(substeps.into_iter().flatten().map(|substep_id| (*substep_id, depth + 1)));
+      }
+    }
+    Ok(())
+  }
+
+  /// Verify the session only uses steps, vars and actions that are still in the definition
+  ///
+  /// Checks the entered steps, every step's substeps and vars, the data and every action the session was given.
+  /// Fails with [`Error::DefinitionDrift`] listing what's missing.
+  pub fn check_definition_drift(&self) -> Result<(), Error> {
+    let mut steps = self.scheduler.entered();
+    let mut vars = self.state_data.iter_val().map(|(var_id, _)| *var_id).collect::<Vec<_>>();
+    for (_step_id, step) in self.step_store.iter() {
+      steps.extend(step.get_substeps().into_iter().flatten().copied());
+      vars.extend(step.get_input_vars().iter().flatten().copied());
+      vars.extend(step.get_output_vars().iter().copied());
+      vars.extend(step.get_condition_vars());
+    }
+    let mut actions = self.actions.values().copied().collect::<Vec<_>>();
+    actions.extend(self.action_bindings.iter().map(|(_matcher, action_id)| *action_id));
+    actions.extend(self.action_candidates.values().flatten().map(|(_condition, action_id)| *action_id));
+    actions.extend(self.fallback_chains.values().flatten().map(|fallback| fallback.action_id));
+    actions.extend(self.finish_actions.iter().map(|finish_action| finish_action.action_id));
+    actions.extend(self.compensation_actions.values().copied());
+    actions.extend(self.notify_actions.iter().copied());
+
+    fn missing<T: Ord>(mut ids: Vec<T>, exists: impl Fn(&T) -> bool) -> Vec<T> {
+      ids.retain(|id| !exists(id));
+      ids.sort();
+      ids.dedup();
+      ids
+    }
+    let drift = DefinitionDrift {
+      steps: missing(steps, |step_id| self.step_store.get(step_id).is_some()),
+      vars: missing(vars, |var_id| self.var_store.get(var_id).is_some()),
+      actions: missing(actions, |action_id| self.action_store.get(action_id).is_some()),
+    };
+    if drift.is_empty() {
+      Ok(())
+    } else {
+      Err(Error::DefinitionDrift(drift))
+    }
+  }
+
+  /// Dependencies between [`Var`]s
+  pub fn var_dependencies(&self) -> &VarDependencies {
+    &self.var_dependencies
+  }
+
+  /// Mutable dependencies between [`Var`]s.
+  ///
+  /// When a var's value changes, the vars depending on it are removed and the earliest completed [`Step`] that output them is re-opened.
+  pub fn var_dependencies_mut(&mut self) -> &mut VarDependencies {
+    &mut self.var_dependencies
+  }
+
+  /// Which roles can read and write each var. Roles are the same as the actors in [`advance_as`](Session::advance_as).
+  pub fn var_access(&self) -> &VarAccess {
+    &self.var_access
+  }
+
+  /// Mutable access to the var roles, i.e. to only let `"admin"` write `approved`
+  pub fn var_access_mut(&mut self) -> &mut VarAccess {
+    &mut self.var_access
+  }
+
+  /// Set the help text explaining a var, i.e. for generated forms. Steps have their own [`Step::set_description`].
+  pub fn set_var_description<D: Into<String>>(&mut self, var_id: &VarId, description: D) -> Result<(), Error> {
+    if self.var_store.get(var_id).is_none() {
+      return Err(Error::VarId(IdError::IdMissing(*var_id)));
+    }
+    self.var_descriptions.insert(*var_id, description.into());
+    Ok(())
+  }
+
+  /// The help text for a var
+  pub fn var_description(&self, var_id: &VarId) -> Option<&str> {
+    self.var_descriptions.get(var_id).map(|description| &description[..])
+  }
+
+  /// Set the help text explaining what an action does, i.e. for admin tools
+  ///
+  /// It's shown in the [DOT export](Session::to_dot) and for the [action the session is blocked on](Session::current_action).
+  pub fn set_action_description<D: Into<String>>(&mut self, action_id: &ActionId, description: D) -> Result<(), Error> {
+    if self.action_store.get(action_id).is_none() {
+      return Err(Error::ActionId(IdError::IdMissing(*action_id)));
+    }
+    self.action_descriptions.insert(*action_id, description.into());
+    Ok(())
+  }
+
+  /// The help text for an action
+  pub fn action_description(&self, action_id: &ActionId) -> Option<&str> {
+    self.action_descriptions.get(action_id).map(|description| &description[..])
+  }
+
+  /// Values for the environment the flow runs in. Actions get them with [`Action::set_environment`].
+  pub fn environment(&self) -> &FlowEnvironment {
+    &self.environment
+  }
+
+  /// Set the environment, i.e. the API base URL and keys for production or staging
+  ///
+  /// The [secrets provider](Session::set_secrets_provider) is kept unless `environment` has its own.
+  pub fn set_environment(&mut self, mut environment: FlowEnvironment) {
+    if environment.secrets_provider().is_none() {
+      if let Some(secrets_provider) = self.environment.secrets_provider() {
+        environment.set_secrets_provider(secrets_provider.clone());
+      }
+    }
+    self.environment = environment;
+  }
+
+  /// Let actions fetch credentials from `secrets_provider` through the [`environment`](Session::environment).
+  pub fn set_secrets_provider(&mut self, secrets_provider: Arc<dyn SecretsProvider>) {
+    self.environment.set_secrets_provider(secrets_provider);
+  }
+
+  /// The session data `role` can read
+  pub fn state_data_for(&self, role: &str) -> StateDataFiltered<'_> {
+    let var_ids = self.var_access.readable(self.state_data.iter_val().map(|(var_id, _)| var_id), role);
+    StateDataFiltered::new(&self.state_data, var_ids)
+  }
+
+  /// The vars `role` can read
+  pub fn var_store_for(&self, role: &str) -> ObjectStoreFiltered<'_, Box<dyn Var + Send + Sync>, VarId> {
+    let var_ids = self.var_access.readable(self.var_store.iter().map(|(var_id, _)| var_id), role);
+    ObjectStoreFiltered::new(&self.var_store, var_ids)
+  }
+
+  /// Find the vars that actions require but their steps don't declare as inputs or outputs
+  ///
+  /// The vars are still visible to the action but the step can be entered before they're set.
+  pub fn undeclared_action_vars(&self) -> Vec<UndeclaredActionVar> {
+    let mut result = Vec::new();
+    for step_id in dfs::preorder(&self.step_id_root, &self.step_store) {
+      let step = match self.step_store.get(&step_id) {
+        Some(step) => step,
+        None => continue,
+      };
+      let action_ids = self.specific_action_for(&step_id, &[]).into_iter().chain(self.actions.get(&self.step_id_all).copied());
+      for action_id in action_ids {
+        let action = match self.action_store.get(&action_id) {
+          Some(action) => action,
+          None => continue,
+        };
+        let undeclared = action.required_vars()
+          .iter()
+          .filter(|var_id| !step.get_input_vars().iter().flatten().any(|input| input == *var_id))
+          .filter(|var_id| !step.get_output_vars().contains(var_id));
+        for var_id in undeclared {
+          result.push(UndeclaredActionVar { step_id, action_id, var_id: *var_id });
+        }
+      }
+    }
+    result
+  }
+
+  /// Set the [`Action`](stepflow_action::Action) for a [`Step`]
+  ///
+  /// If `step_id` is None, it's registered as the general action for all steps.
+  /// Actions are generally executed with the specific step first (if it exists)
+  /// and the general step after (if the specific step cannot fulfill).
+  pub fn set_action_for_step(&mut self, action_id: ActionId, step_id:Option<&StepId>) 
+  -> Result<(), Error> {
+    let step_id_use = step_id.or(Some(&self.step_id_all)).unwrap();
+    if self.actions.contains_key(step_id_use) {
+      return Err(Error::StepId(IdError::IdAlreadyExists(step_id_use.clone())));
+    }
+    self.check_produces(&action_id)?;
+    self.actions.insert(step_id_use.clone(), action_id);
+    Ok(())
+  }
+
+  /// Set the [`Action`](stepflow_action::Action) for a [`Step`] even if it already has one, i.e. to swap a test stub for the real action
+  ///
+  /// Same as [`set_action_for_step`](Session::set_action_for_step) otherwise. Returns the action it replaced.
+  pub fn replace_action_for_step(&mut self, action_id: ActionId, step_id: Option<&StepId>) -> Result<Option<ActionId>, Error> {
+    let step_id_use = step_id.unwrap_or(&self.step_id_all);
+    self.check_produces(&action_id)?;
+    Ok(self.actions.insert(*step_id_use, action_id))
+  }
+
+  /// Remove the [`Action`](stepflow_action::Action) set for a [`Step`], or the general action if `step_id` is None. Returns the action it removed.
+  pub fn clear_action_for_step(&mut self, step_id: Option<&StepId>) -> Option<ActionId> {
+    let step_id_use = step_id.unwrap_or(&self.step_id_all);
+    self.actions.remove(step_id_use)
+  }
+
+  /// Check that every action [produces](stepflow_action::Action::produces) values that fit its vars, i.e. after loading a definition
+  ///
+  /// Fails with [`InvalidValue::WrongType`] for each var that doesn't fit. Setting an action on steps checks it too.
+  pub fn check_action_types(&self) -> Result<(), Error> {
+    for (action_id, _) in self.action_store.iter() {
+      self.check_produces(action_id)?;
+    }
+    Ok(())
+  }
+
+  // make sure the action doesn't set values a var can't hold
+  fn check_produces(&self, action_id: &ActionId) -> Result<(), Error> {
+    let action = match self.action_store.get(action_id) {
+      Some(action) => action,
+      None => return Ok(()),
+    };
+    let mut invalid = HashMap::new();
+    for (var_id, val) in action.produces() {
+      let var = self.var_store.get(&var_id).ok_or(Error::VarId(IdError::IdMissing(var_id)))?;
+      if let Err(InvalidValue::WrongType) = var.validate_val_type(&val) {
+        invalid.insert(var_id, InvalidValue::WrongType);
+      }
+    }
+    if invalid.is_empty() {
+      Ok(())
+    } else {
+      Err(Error::InvalidVars(InvalidVars::new(invalid)))
+    }
+  }
+
+  /// Bind an [`Action`](stepflow_action::Action) to every [`Step`] selected by `matcher`
+  ///
+  /// Bindings are checked in the order they were added, after the action set for the specific step
+  /// and before the general action for all steps.
+  pub fn bind_action(&mut self, action_id: ActionId, matcher: StepMatcher) -> Result<(), Error> {
+    self.check_produces(&action_id)?;
+    self.action_bindings.push((matcher, action_id));
+    Ok(())
+  }
+
+  /// Add an [`Action`](stepflow_action::Action) for a [`Step`] that's only used when `when` holds, i.e. to send an SMS code if there's a phone number
+  ///
+  /// Candidates are checked in the order they were added, after the action set for the specific step
+  /// and before [bindings](Session::bind_action). The first one whose condition holds is used.
+  pub fn add_action_candidate(&mut self, step_id: &StepId, action_id: ActionId, when: Condition) -> Result<(), Error> {
+    self.check_produces(&action_id)?;
+    self.action_candidates.entry(*step_id).or_default().push((when, action_id));
+    Ok(())
+  }
+
+  /// Try the actions for a [`Step`] in order, each up to its number of attempts, i.e. a primary verification provider then a backup
+  ///
+  /// Each time an action [can't fulfill](ActionResult::CannotFulfill) counts as an attempt and the next action is tried right away.
+  /// Starting a form again or failing doesn't use up attempts. The attempts start over when the step is exited.
+  /// The chain is used after the action set for the specific step and before [candidates](Session::add_action_candidate).
+  /// Use [`FallbackAction::UNLIMITED`] for an action that's tried on every advance.
+  pub fn set_fallback_chain(&mut self, step_id: StepId, chain: Vec<(ActionId, u32)>) -> Result<(), Error> {
+    if self.step_store.get(&step_id).is_none() {
+      return Err(Error::StepId(IdError::IdMissing(step_id)));
+    }
+    if let Some((action_id, _)) = chain.iter().find(|(action_id, _)| self.action_store.get(action_id).is_none()) {
+      return Err(Error::ActionId(IdError::IdMissing(*action_id)));
+    }
+    for (action_id, _) in chain.iter() {
+      self.check_produces(action_id)?;
+    }
+    let chain = chain.into_iter()
+      .map(|(action_id, max_attempts)| FallbackAction { action_id, max_attempts, attempts: 0 })
+      .collect();
+    self.fallback_chains.insert(step_id, chain);
+    Ok(())
+  }
+
+  /// The fallback chain for `step_id` with how many times each action has been tried
+  pub fn fallback_chain(&self, step_id: &StepId) -> &[FallbackAction] {
+    self.fallback_chains.get(step_id).map(|chain| &chain[..]).unwrap_or(&[])
+  }
+
+  /// Add an [`Action`](stepflow_action::Action) with [unlimited attempts](FallbackAction::UNLIMITED) to the end of a [`Step`]'s
+  /// [fallback chain](Session::set_fallback_chain), i.e. a cache lookup, then an HTTP lookup, then a form
+  ///
+  /// Each advance tries the chain in order and moves on to the next action when one [can't fulfill](ActionResult::CannotFulfill).
+  pub fn push_action_for_step(&mut self, step_id: &StepId, action_id: ActionId) -> Result<(), Error> {
+    if self.step_store.get(step_id).is_none() {
+      return Err(Error::StepId(IdError::IdMissing(*step_id)));
+    }
+    if self.action_store.get(&action_id).is_none() {
+      return Err(Error::ActionId(IdError::IdMissing(action_id)));
+    }
+    self.check_produces(&action_id)?;
+    self.fallback_chains.entry(*step_id).or_default().push(FallbackAction { action_id, max_attempts: FallbackAction::UNLIMITED, attempts: 0 });
+    Ok(())
+  }
+
+  // the first action in the step's fallback chain with attempts left that wasn't `tried` in this advance
+  fn next_fallback(&self, step_id: &StepId, tried: &[ActionId]) -> Option<usize> {
+    self.fallback_chains.get(step_id)?
+      .iter()
+      .position(|fallback| fallback.has_attempts_left() && !tried.contains(&fallback.action_id))
+  }
+
+  // the action set for the step, the next in its fallback chain,
+  // the first candidate for the data or else the first binding that matches it
+  fn specific_action_for(&self, step_id: &StepId, tried: &[ActionId]) -> Option<ActionId> {
+    if let Some(action_id) = self.actions.get(step_id) {
+      return Some(*action_id);
+    }
+    if let Some(index) = self.next_fallback(step_id, tried) {
+      return Some(self.fallback_chains[step_id][index].action_id);
+    }
+    let candidate = self.action_candidates.get(step_id)
+      .and_then(|candidates| candidates.iter().find(|(when, _)| when.eval(&self.state_data)));
+    if let Some((_, action_id)) = candidate {
+      return Some(*action_id);
+    }
+    let step = self.step_store.get(step_id)?;
+    let step_name = self.step_store.name_from_id(step_id);
+    self.action_bindings
+      .iter()
+      .find(|(matcher, _)| matcher.matches(step, step_name))
+      .map(|(_, action_id)| *action_id)
+  }
+
+  /// Set the [`Action`](stepflow_action::Action) for every [`Step`] in a group
+  ///
+  /// Nothing is set if any step in the group already has an action.
+  pub fn set_action_for_step_group(&mut self, action_id: ActionId, group: &str) -> Result<Vec<StepId>, Error> {
+    let step_ids = self.step_group(group);
+    if let Some(step_id) = step_ids.iter().find(|step_id| self.actions.contains_key(step_id)) {
+      return Err(Error::StepId(IdError::IdAlreadyExists(*step_id)));
+    }
+    self.check_produces(&action_id)?;
+    for step_id in step_ids.iter() {
+      self.actions.insert(*step_id, action_id);
+    }
+    Ok(step_ids)
+  }
+
+  /// Which step [`advance`](Session::advance) would enter next and the vars it's waiting on, without advancing
+  pub fn peek_next(&self) -> NextStepPreview {
+    let step_id = self.scheduler.peek_next(&self.step_store, &self.state_data);
+    let mut missing_vars = Vec::new();
+    for requirement in self.requirements_to(step_id.as_ref()) {
+      if !missing_vars.contains(&requirement.var_id) {
+        missing_vars.push(requirement.var_id);
+      }
+    }
+    NextStepPreview { step_id, missing_vars }
+  }
+
+  /// Every var that's keeping the session from exiting the current step or entering the next one
+  pub fn blocking_requirements(&self) -> Vec<BlockingRequirement> {
+    let next = self.scheduler.peek_next(&self.step_store, &self.state_data);
+    self.requirements_to(next.as_ref())
+  }
+
+  /// Check the flow can finish when it starts with `seed`, i.e. before a definition is deployed
+  ///
+  /// Walks the steps in order and returns the first var nothing sets before it's needed: an input that isn't seeded
+  /// or an output of an earlier step, or a required output of a step without an action to ask for it or set it.
+  /// Outputs of a step with substeps have to come from the substeps.
+  pub fn completion_blocker(&self, seed: Option<&StateData>) -> Option<BlockingRequirement> {
+    let mut available = seed
+      .map(|seed| seed.iter_val().map(|(var_id, _)| *var_id).collect::<HashSet<_>>())
+      .unwrap_or_default();
+    let mut visited = HashSet::new();
+
+    // each step is entered then exited after its substeps
+    let mut to_visit = vec![(self.step_id_root, TransitionCheck::Enter)];
+    while let Some((step_id, check)) = to_visit.pop() {
+      let step = match self.step_store.get(&step_id) {
+        Some(step) => step,
+        None => continue,
+      };
+      match check {
+        TransitionCheck::Enter => {
+          if !visited.insert(step_id) {
+            continue;
+          }
+          if let Some(var_id) = step.get_input_vars().iter().flatten().find(|var_id| !available.contains(var_id)) {
+            return Some(self.blocking_requirement(&step_id, *var_id, TransitionCheck::Enter));
+          }
+          to_visit.push((step_id, TransitionCheck::Exit));
+          to_visit.extend(step.get_substeps().into_iter().flatten().rev().map(|substep_id| (*substep_id, TransitionCheck::Enter)));
+        }
+        TransitionCheck::Exit => {
+          let action_ids = self.possible_actions_for(&step_id);
+          let can_ask = step.first_substep().is_none() && !action_ids.is_empty();
+          let missing = step.get_output_vars().iter()
+            .find(|var_id| !available.contains(var_id) && step.get_output_condition(var_id).is_none());
+          if let (Some(var_id), false) = (missing, can_ask) {
+            return Some(self.blocking_requirement(&step_id, *var_id, TransitionCheck::Exit));
+          }
+          available.extend(step.get_output_vars().iter().copied());
+          for action in action_ids.iter().filter_map(|action_id| self.action_store.get(action_id)) {
+            available.extend(action.produces().into_iter().map(|(var_id, _val)| var_id));
+          }
+        }
+      }
+    }
+    None
+  }
+
+  // every action that could run for the step, whatever the data is
+  fn possible_actions_for(&self, step_id: &StepId) -> Vec<ActionId> {
+    let mut action_ids = Vec::new();
+    action_ids.extend(self.actions.get(step_id));
+    action_ids.extend(self.fallback_chains.get(step_id).into_iter().flatten().map(|fallback| fallback.action_id));
+    action_ids.extend(self.action_candidates.get(step_id).into_iter().flatten().map(|(_when, action_id)| *action_id));
+    if let Some(step) = self.step_store.get(step_id) {
+      let step_name = self.step_store.name_from_id(step_id);
+      action_ids.extend(self.action_bindings.iter().filter(|(matcher, _)| matcher.matches(step, step_name)).map(|(_, action_id)| *action_id));
+    }
+    action_ids.extend(self.actions.get(&self.step_id_all));
+    action_ids
+  }
+
+  fn blocking_requirement(&self, step_id: &StepId, var_id: VarId, check: TransitionCheck) -> BlockingRequirement {
+    BlockingRequirement {
+      step_id: *step_id,
+      var_id,
+      var_name: self.var_store.name_from_id(&var_id).map(|name| name.to_owned()),
+      check,
+    }
+  }
+
+  // missing outputs of the current leaf step, then the missing inputs of `next`
+  fn requirements_to(&self, next: Option<&StepId>) -> Vec<BlockingRequirement> {
+    let requirement = |step_id: &StepId, var_id: VarId, check: TransitionCheck| self.blocking_requirement(step_id, var_id, check);
+
+    let mut result = Vec::new();
+    if let Some((step_id, step)) = self.scheduler.current().and_then(|step_id| Some((step_id, self.step_store.get(step_id)?))) {
+      if step.first_substep().is_none() {
+        result.extend(step.missing_outputs(&self.state_data).into_iter().map(|var_id| requirement(step_id, var_id, TransitionCheck::Exit)));
+      }
+    }
+    if let Some((step_id, step)) = next.and_then(|step_id| Some((step_id, self.step_store.get(step_id)?))) {
+      result.extend(step.missing_inputs(&self.state_data).into_iter().map(|var_id| requirement(step_id, var_id, TransitionCheck::Enter)));
+    }
+    result
+  }
+
+  /// Run `action_id` once when the flow finishes, i.e. to post the results somewhere
+  ///
+  /// The action sees all of the session data. If it fails or can't fulfill, it's tried again on the next
+  /// [`advance`](Session::advance) until it has been tried `max_attempts` times.
+  pub fn add_finish_action(&mut self, action_id: ActionId, max_attempts: u32) -> Result<(), Error> {
+    if self.action_store.get(&action_id).is_none() {
+      return Err(Error::ActionId(IdError::IdMissing(action_id)));
+    }
+    self.finish_actions.push(FinishAction {
+      action_id,
+      max_attempts,
+      attempts: 0,
+      status: FinishActionStatus::Pending,
+    });
+    Ok(())
+  }
+
+  /// The finish actions in the order they were added, with how they went
+  pub fn finish_actions(&self) -> &[FinishAction] {
+    &self.finish_actions
+  }
+
+  /// Start the `flow` once this one finishes and `when` holds, i.e. onboarding after signup. See [`pending_follow_ups`](Session::pending_follow_ups).
+  ///
+  /// The new flow is seeded with the values of `vars`, matched up by name. Fails if a var doesn't have a name.
+  pub fn add_follow_up(&mut self, flow: String, vars: Vec<VarId>, when: Condition) -> Result<(), Error> {
+    for var_id in vars.iter() {
+      if self.var_store.get(var_id).is_none() {
+        return Err(Error::VarId(IdError::IdMissing(*var_id)));
+      }
+      if self.var_store.name_from_id(var_id).is_none() {
+        return Err(Error::VarId(IdError::IdHasNoName(*var_id)));
+      }
+    }
+    self.follow_ups.push(FollowUp { flow, vars, when, started: false });
+    Ok(())
+  }
+
+  /// The follow-up flows in the order they were added
+  pub fn follow_ups(&self) -> &[FollowUp] {
+    &self.follow_ups
+  }
+
+  /// Get the follow-up flows to start now that the flow is finished.
+  ///
+  /// They're returned until they're [marked as started](Session::set_follow_up_started) so a flow that failed to start can be retried.
+  pub fn pending_follow_ups(&self) -> Vec<FollowUpStart> {
+    if self.scheduler.current().is_some() || self.is_preview() {
+      return Vec::new();
+    }
+    self.follow_ups.iter()
+      .enumerate()
+      .filter(|(_, follow_up)| !follow_up.started && follow_up.when.eval(&self.state_data))
+      .map(|(index, follow_up)| {
+        let seed = follow_up.vars.iter()
+          .filter_map(|var_id| Some((self.var_store.name_from_id(var_id)?.to_owned(), self.state_data.get(var_id)?.get_val().clone())))
+          .collect();
+        FollowUpStart { index, flow: follow_up.flow.clone(), seed }
+      })
+      .collect()
+  }
+
+  /// Mark the follow-up at `index` as started so it's no longer [pending](Session::pending_follow_ups)
+  pub fn set_follow_up_started(&mut self, index: usize) -> Result<(), Error> {
+    let follow_up = self.follow_ups.get_mut(index).ok_or(Error::FollowUpIndex(index))?;
+    follow_up.started = true;
+    Ok(())
+  }
+
+  /// Add data without a step, i.e. to seed a flow with data carried over from another one
+  pub fn seed_data(&mut self, state_data: StateData) -> Result<(), Error> {
+    self.merge_state_data(state_data)
+  }
+
+  /// Undo the effects of `step_id` with `action_id` if the flow is cancelled or fails. See [`compensate`](Session::compensate).
+  pub fn set_compensation_action(&mut self, step_id: StepId, action_id: ActionId) -> Result<(), Error> {
+    if self.step_store.get(&step_id).is_none() {
+      return Err(Error::StepId(IdError::IdMissing(step_id)));
+    }
+    if self.action_store.get(&action_id).is_none() {
+      return Err(Error::ActionId(IdError::IdMissing(action_id)));
+    }
+    self.compensation_actions.insert(step_id, action_id);
+    Ok(())
+  }
+
+  /// Run the compensation actions of the steps that ran and were exited, latest step first
+  ///
+  /// Call this when cancelling the flow or when it fails for good.
+  /// Each step is compensated once. Steps whose action errors or can't fulfill are tried again on the next call.
+  pub fn compensate(&mut self) -> Vec<Compensation> {
+    let mut result = Vec::new();
+    let mut remaining = Vec::new();
+    for step_id in std::mem::take(&mut self.to_compensate).into_iter().rev() {
+      let action_id = match self.compensation_actions.get(&step_id) {
+        Some(action_id) => *action_id,
+        None => continue,
+      };
+      let action_result = self.call_action(&action_id, &step_id);
+      if matches!(action_result, Err(_) | Ok(ActionResult::CannotFulfill)) {
+        remaining.push(step_id);
+      }
+      result.push(Compensation { step_id, action_id, result: action_result });
+    }
+    remaining.reverse();
+    self.to_compensate = remaining;
+    result
+  }
+
+  /// Only `actor` can advance the session while it's on `step_id`, i.e. `"reviewer"`. See [`advance_as`](Session::advance_as).
+  pub fn set_step_assignee(&mut self, step_id: StepId, actor: String) -> Result<(), Error> {
+    if self.step_store.get(&step_id).is_none() {
+      return Err(Error::StepId(IdError::IdMissing(step_id)));
+    }
+    self.assignees.insert(step_id, actor);
+    Ok(())
+  }
+
+  /// Who the step is assigned to, if anyone
+  pub fn step_assignee(&self, step_id: &StepId) -> Option<&str> {
+    self.assignees.get(step_id).map(|actor| &actor[..])
+  }
+
+  /// Steps that are waiting on `actor`. This is the current step when it's assigned to them.
+  pub fn steps_waiting_on(&self, actor: &str) -> Vec<StepId> {
+    self.scheduler.current()
+      .filter(|step_id| self.step_assignee(step_id) == Some(actor))
+      .map(|step_id| vec![*step_id])
+      .unwrap_or_default()
+  }
+
+  /// Hand the current step over to `actor`, i.e. when the assignee is away. Returns who it was assigned to.
+  ///
+  /// Only the step the session is blocked on can be reassigned, otherwise it fails with [`IdError::IdUnexpected`].
+  /// An [`ApprovalAction`] on the step asks the new actor from then on.
+  pub fn reassign_step(&mut self, step_id: &StepId, actor: String) -> Result<Option<String>, Error> {
+    if self.scheduler.current() != Some(step_id) {
+      return Err(Error::StepId(IdError::IdUnexpected(*step_id)));
+    }
+    if let Some(action_id) = self.specific_action_for(step_id, &[]) {
+      if let Some(approval) = self.action_store.get_mut(&action_id).and_then(|action| action.downcast_mut::<ApprovalAction>()) {
+        approval.set_approver(actor.clone());
+      }
+    }
+    let previous = self.assignees.insert(*step_id, actor);
+    if let Some(mut notice) = self.last_blocked.clone().filter(|notice| notice.step_id == *step_id) {
+      notice.overdue = false;
+      self.notify_blocked(notice);
+    }
+    Ok(previous)
+  }
+
+  /// Call `observer` when the session blocks on a step, i.e. to tell the assignee there's something waiting on them
+  ///
+  /// It's called once each time the session blocks on a different step and again when the step is [reassigned](Session::reassign_step).
+  pub fn add_blocked_observer<F>(&mut self, observer: F)
+      where F: FnMut(&BlockedNotice) + Send + Sync + 'static
+  {
+    self.blocked_observers.push(Box::new(observer));
+  }
+
+  /// Call `observer` when the session moves from one step to another, i.e. for logging, metrics or side effects
+  ///
+  /// The [`Transition`] has the data merged since the previous transition. Observers are called before the action of the new step is started.
+  pub fn on_transition<F>(&mut self, observer: F)
+      where F: FnMut(&Transition) + Send + Sync + 'static
+  {
+    self.transition_observers.push(Box::new(observer));
+  }
+
+  /// Start `action_id` on the blocked step whenever the [blocked observers](Session::add_blocked_observer) are called, i.e. to send an email or call a webhook
+  ///
+  /// What the action returns doesn't affect the flow.
+  pub fn add_notify_action(&mut self, action_id: ActionId) -> Result<(), Error> {
+    if self.action_store.get(&action_id).is_none() {
+      return Err(Error::ActionId(IdError::IdMissing(action_id)));
+    }
+    self.notify_actions.push(action_id);
+    Ok(())
+  }
+
+  // fill in who the step is waiting on and tell everyone that's listening
+  fn notify_blocked(&mut self, mut notice: BlockedNotice) {
+    if self.is_preview() {
+      return;
+    }
+    notice.assignee = self.assignees.get(&notice.step_id).cloned();
+    notice.deadline = self.step_store.get(&notice.step_id)
+      .and_then(|step| step.get_metadata("deadline"))
+      .map(|deadline| deadline.to_owned());
+    // someone has to act on assigned or overdue steps so those notices are never shed
+    let has_obligation = notice.assignee.is_some() || notice.deadline.is_some() || notice.overdue;
+    if has_obligation || self.load_shedding.should_emit() {
+      for observer in self.blocked_observers.iter_mut() {
+        observer(&notice);
+      }
+    }
+    for action_id in self.notify_actions.clone() {
+      let _ = self.call_action(&action_id, &notice.step_id);
+    }
+    if !notice.overdue {
+      self.blocked_since = Some(SystemTime::now());
+    }
+    self.last_blocked = Some(notice);
+  }
+
+  /// Escalate when the session is blocked on `step_id` for longer than the policy allows. See [`enforce_deadline`](Session::enforce_deadline).
+  pub fn set_deadline_policy(&mut self, step_id: StepId, policy: DeadlinePolicy) -> Result<(), Error> {
+    if self.step_store.get(&step_id).is_none() {
+      return Err(Error::StepId(IdError::IdMissing(step_id)));
+    }
+    if let Escalation::Reroute(reroute_id) = &policy.escalation {
+      if self.step_store.get(reroute_id).is_none() {
+        return Err(Error::StepId(IdError::IdMissing(*reroute_id)));
+      }
+    }
+    self.deadline_policies.insert(step_id, policy);
+    Ok(())
+  }
+
+  /// Escalate the blocked step if it's past its [deadline](Session::set_deadline_policy). Returns the step if it was escalated.
+  ///
+  /// Call this periodically from a background task. The deadline starts when the session blocks on the step or it's
+  /// [reassigned](Session::reassign_step), and each time it's passed the step is escalated once.
+  pub fn enforce_deadline(&mut self, now: SystemTime) -> Result<Option<StepId>, Error> {
+    if self.is_preview() {
+      return Ok(None);
+    }
+    let notice = match self.last_blocked.clone().filter(|notice| !notice.overdue) {
+      Some(notice) => notice,
+      None => return Ok(None),
+    };
+    let policy = match self.deadline_policies.get(&notice.step_id) {
+      Some(policy) => policy.clone(),
+      None => return Ok(None),
+    };
+    let overdue = self.blocked_since
+      .and_then(|blocked_since| now.duration_since(blocked_since).ok())
+      .map(|blocked_for| blocked_for >= policy.within)
+      .unwrap_or(false);
+    if !overdue {
+      return Ok(None);
+    }
+
+    let step_id = notice.step_id;
+    self.notify_blocked(BlockedNotice { overdue: true, ..notice });
+    match policy.escalation {
+      Escalation::Notify => (),
+      Escalation::Complete(state_data) => {
+        self.advance(Some((&step_id, state_data)))?;
+      }
+      Escalation::AutoApprove(approval) => {
+        let state_data = self.approval_data(ESCALATION_ACTOR, &approval, true, None)?;
+        self.advance(Some((&approval.step_id, state_data)))?;
+      }
+      Escalation::Reroute(reroute_id) => {
+        self.reset_to(&reroute_id)?;
+        self.advance(None)?;
+      }
+    }
+    Ok(Some(step_id))
+  }
+
+  // notify when the advance blocked on a different step than before
+  fn check_blocked(&mut self, advance_result: &Result<AdvanceBlockedOn, Error>) {
+    let blocked = match advance_result {
+      Ok(AdvanceBlockedOn::ActionStartWith(action_id, _, _)) => {
+        self.scheduler.current().map(|step_id| (*step_id, *action_id))
+      }
+      Ok(AdvanceBlockedOn::ActionCannotFulfill(details)) => {
+        details.attempted_actions.last().map(|action_id| (details.step_id, *action_id))
+      }
+      Ok(AdvanceBlockedOn::FinishedAdvancing) => {
+        self.last_blocked = None;
+        self.finished_at.get_or_insert_with(SystemTime::now);
+        None
+      }
+      Err(_) => None,
+    };
+    if let Some((step_id, action_id)) = blocked {
+      if self.last_blocked.as_ref().map(|notice| notice.step_id) != Some(step_id) {
+        self.notify_blocked(BlockedNotice { step_id, action_id, assignee: None, deadline: None, overdue: false });
+      }
+    }
+  }
+
+  /// Same as [`advance`](Session::advance) but checks that `actor` is allowed to
+  ///
+  /// Fails with [`Error::NotAssignee`] if the current step is assigned to someone other than `actor`
+  /// and [`Error::WriteDenied`] if `step_output` has vars `actor` can't [write](VarAccess::can_write).
+  /// Actions started along the way only see the vars `actor` can [read](VarAccess::can_read), i.e. so a form doesn't show them.
+  pub fn advance_as(&mut self, actor: &str, step_output: Option<(&StepId, StateData)>)
+      -> Result<AdvanceBlockedOn, Error>
+  {
+    if let Some(step_id) = self.scheduler.current() {
+      if matches!(self.step_assignee(step_id), Some(assignee) if assignee != actor) {
+        return Err(Error::NotAssignee(*step_id));
+      }
+    }
+    if let Some((_, state_data)) = &step_output {
+      let mut denied = state_data.iter_val()
+        .map(|(var_id, _)| *var_id)
+        .filter(|var_id| !self.var_access.can_write(var_id, actor))
+        .collect::<Vec<_>>();
+      if !denied.is_empty() {
+        denied.sort();
+        return Err(Error::WriteDenied(denied));
+      }
+    }
+    self.actor = Some(actor.to_owned());
+    let advance_result = self.advance(step_output);
+    self.actor = None;
+    advance_result
+  }
+
+  /// Keys of the side effects that are done with the outputs the action finished with. See [`Action::completion_key`].
+  ///
+  /// Save these with the session data so restoring them after a crash doesn't run the side effects again.
+  /// They're cleared by [`reset`](Session::reset).
+  pub fn completion_keys(&self) -> &HashMap<String, StateData> {
+    &self.completion_keys
+  }
+
+  /// Restore a saved key and its outputs from [`completion_keys`](Session::completion_keys)
+  pub fn add_completion_key(&mut self, key: String, outputs: StateData) {
+    self.completion_keys.insert(key, outputs);
+  }
+
+  // start the action unless its side effect is already done, in which case it finishes with the outputs it had
+  fn start_once(action: &mut Box<dyn Action + Sync + Send>, completion_keys: &mut HashMap<String, StateData>, environment: &FlowEnvironment,
+      step: &Step, step_name: Option<&str>, step_data: &StateDataFiltered, vars: &ObjectStoreFiltered<Box<dyn Var + Send + Sync>, VarId>)
+      -> Result<ActionResult, Error>
+  {
+    let completion_key = action.completion_key(step, step_data);
+    if let Some(outputs) = completion_key.as_ref().and_then(|key| completion_keys.get(key)) {
+      // finishing without the step's outputs would just start the action again
+      let fulfilled = step.get_output_vars().iter().all(|var_id| outputs.contains(var_id) || step_data.contains(var_id));
+      if !fulfilled {
+        return Ok(ActionResult::CannotFulfill);
+      }
+      return Ok(ActionResult::Finished(outputs.clone()));
+    }
+    action.set_environment(environment);
+    let action_result = action.start(step, step_name, step_data, vars)?;
+    if let (Some(key), ActionResult::Finished(outputs)) = (completion_key, &action_result) {
+      completion_keys.insert(key, outputs.clone());
+    }
+    Ok(action_result)
+  }
+
+  // run the finish actions that haven't succeeded and have attempts left
+  fn run_finish_actions(&mut self) -> Result<(), Error> {
+    if self.is_preview() {
+      return Ok(());
+    }
+    for index in 0..self.finish_actions.len() {
+      let finish_action = &self.finish_actions[index];
+      if finish_action.status == FinishActionStatus::Succeeded || finish_action.attempts >= finish_action.max_attempts {
+        continue;
+      }
+      let action_id = finish_action.action_id;
+
+      let var_ids = self.state_data.iter_val().map(|(var_id, _)| *var_id).collect::<HashSet<_>>();
+      let data = StateDataFiltered::new(&self.state_data, var_ids.clone());
+      let vars = ObjectStoreFiltered::new(&self.var_store, var_ids);
+      let step = self.step_store.get(&self.step_id_root).ok_or(Error::StepId(IdError::IdMissing(self.step_id_root)))?;
+      let step_name = self.step_store.name_from_id(&self.step_id_root);
+      let action = self.action_store.get_mut(&action_id).ok_or(Error::ActionId(IdError::IdMissing(action_id)))?;
+      let action_result = Self::start_once(action, &mut self.completion_keys, &self.environment, step, step_name, &data, &vars)
+        .and_then(|action_result| {
+          if let ActionResult::Finished(state_data) = &action_result {
+            self.validate_finish_output(state_data)?;
+          }
+          Ok(action_result)
+        });
+      self.record_event(SessionEventKind::FinishActionRan { action_id, outcome: ActionOutcome::from(&action_result) });
+      let status = match action_result {
+        Ok(ActionResult::Finished(state_data)) => match self.merge_state_data(state_data) {
+          Ok(()) => FinishActionStatus::Succeeded,
+          Err(err) => FinishActionStatus::Failed(Some(err)),
+        },
+        // there's no one left to wait on
+        Ok(ActionResult::StartWith(_)) | Ok(ActionResult::CannotFulfill) => FinishActionStatus::Failed(None),
+        Err(err) => FinishActionStatus::Failed(Some(err)),
+      };
+
+      let finish_action = &mut self.finish_actions[index];
+      finish_action.attempts += 1;
+      finish_action.status = status;
+    }
+    Ok(())
+  }
+
+  // finish actions can set any var but the values have to fit
+  fn validate_finish_output(&self, state_data: &StateData) -> Result<(), Error> {
+    let invalid = state_data.iter_val()
+      .filter_map(|(var_id, val)| match self.var_store.get(var_id) {
+        Some(var) => var.validate_val_type(val).err().map(|err| (*var_id, err)),
+        None => Some((*var_id, InvalidValue::Unexpected)),
+      })
+      .collect::<HashMap<_, _>>();
+    if invalid.is_empty() {
+      Ok(())
+    } else {
+      Err(Error::InvalidVars(InvalidVars::new(invalid)))
+    }
+  }
+
+  /// see if next step will accept with current inputs
+  /// if so, advance there (checking for nested states) and return current step
+  /// if not, reject and stay on current step (how relay error msg?)
+  fn try_enter_next_step(&mut self, step_output: Option<(&StepId, StateData)>)
+    -> Result<Option<StepId>, Error>
+  {
+    if let Some(output) = step_output {
+      // make sure we're updating the right state
+      if self.current_step()? != output.0 {
+        return Err(Error::StepId(IdError::IdUnexpected(output.0.clone())))
+      }
+
+      // a rejected output leaves everything as it was so the step can be submitted again
+      self.validate_output(output.0, &output.1)?;
+
+      // merge the new inputs in first. best to not lose this even if the rest fails
+      self.merge_state_data(output.1)?;
+    }
+    self.enter_next_step()
+  }
+
+  // run the step's validators on the data it would have with the output, the same as exiting the step would
+  fn validate_output(&mut self, step_id: &StepId, output: &StateData) -> Result<(), Error> {
+    let step = self.step_store.get(step_id).ok_or(Error::StepId(IdError::IdMissing(*step_id)))?;
+    if !step.has_validators() {
+      return Ok(());
+    }
+    let mut merged = self.state_data.clone();
+    merged.merge_with_dependencies(output.clone(), &self.var_dependencies);
+    if step.is_skipped(&merged) || step.can_exit_all(&merged).is_err() {
+      return Ok(());
+    }
+    if let Err(invalid) = step.validate(&merged) {
+      self.trace(TraceEntry::ValidationFailed { step_id: *step_id, invalid: invalid.clone() });
+      return Err(Error::InvalidVars(invalid));
+    }
+    Ok(())
+  }
+
+  fn enter_next_step(&mut self) -> Result<Option<StepId>, Error> {
+    loop {
+      // loops that were entered start counting again and a finished pass of a loop is repeated instead of exited
+      let entered_loops = RefCell::new(Vec::new());
+      let repeat_step = Cell::new(None);
+      let tracing = self.trace.is_some();
+      let traced = RefCell::new(Vec::new());
+      let state_data = &self.state_data;
+      let step_store = &self.step_store;
+      let var_store = &self.var_store;
+      let loop_passes = &self.loop_passes;
+      let missing_vars = |step_id: &StepId, check: TransitionCheck, missing: Vec<VarId>| {
+        if tracing {
+          traced.borrow_mut().push(TraceEntry::GuardFailed { step_id: *step_id, check, missing: missing.clone() });
+        }
+        Error::MissingVars(AdvanceError {
+          step_id: *step_id,
+          step_name: step_store.name_from_id(step_id).map(|name| name.to_owned()),
+          check,
+          missing_vars: missing.into_iter().map(|var_id| (var_id, var_store.name_from_id(&var_id).map(|name| name.to_owned()))).collect(),
+        })
+      };
+      let next_result = self.scheduler.next(
+        &mut |step_id| {
+          let step = step_store.get(step_id).ok_or_else(|| Error::StepId(IdError::IdMissing(step_id.clone())))?;
+          if step.is_skipped(state_data) {
+            return Ok(());
+          }
+          if tracing {
+            traced.borrow_mut().push(TraceEntry::StepTried(*step_id));
+          }
+          step.can_enter_all(state_data).map_err(|missing| missing_vars(step_id, TransitionCheck::Enter, missing))?;
+          if step.repeat().is_some() {
+            entered_loops.borrow_mut().push(*step_id);
+          }
+          Ok(())
+        },
+        &mut |step_id| {
+          let step = step_store.get(step_id).ok_or_else(|| Error::StepId(IdError::IdMissing(step_id.clone())))?;
+          if step.is_skipped(state_data) {
+            return Ok(());
+          }
+          step.can_exit_all(state_data).map_err(|missing| missing_vars(step_id, TransitionCheck::Exit, missing))?;
+          step.validate(state_data).map_err(|invalid| {
+            if tracing {
+              traced.borrow_mut().push(TraceEntry::ValidationFailed { step_id: *step_id, invalid: invalid.clone() });
+            }
+            Error::InvalidVars(invalid)
+          })?;
+          if let Some(repeat) = step.repeat() {
+            let passes = loop_passes.get(step_id).copied().unwrap_or(0) + 1;
+            if repeat.should_repeat(passes, state_data) {
+              // stop the scheduler here. the outputs are cleared for the next pass so it can't be exited
+              // and the error is dropped once the loop is rewound below
+              repeat_step.set(Some(*step_id));
+              return Err(Error::MissingVars(AdvanceError {
+                step_id: *step_id,
+                step_name: step_store.name_from_id(step_id).map(|name| name.to_owned()),
+                check: TransitionCheck::Exit,
+                missing_vars: step.get_output_vars().iter().map(|var_id| (*var_id, var_store.name_from_id(var_id).map(|name| name.to_owned()))).collect(),
+              }));
+            }
+            if repeat.limit_reached(passes, state_data) {
+              return Err(Error::RepeatLimit(*step_id));
+            }
+          }
+          Ok(())
+        },
+        &self.step_store,
+        &self.state_data);
+
+      for step_id in entered_loops.into_inner() {
+        self.loop_passes.remove(&step_id);
+      }
+      for entry in traced.into_inner() {
+        self.trace(entry);
+      }
+      match repeat_step.get() {
+        Some(step_id) => {
+          self.trace(TraceEntry::LoopRepeated(step_id));
+          *self.loop_passes.entry(step_id).or_insert(0) += 1;
+          self.scheduler.rewind_to(&step_id, &self.step_store)?;
+          self.clear_outputs(&step_id, false)?;
+        }
+        None => return next_result,
+      }
+    }
+  }
+
+  /// merge new data, removing stale dependent data and re-opening the steps that output it
+  fn merge_state_data(&mut self, mut state_data: StateData) -> Result<(), Error> {
+    if let Some(interner) = self.string_interner.as_mut() {
+      state_data.intern_strings(interner);
+    }
+    if !self.transition_observers.is_empty() {
+      self.merged_since_transition.merge_from(state_data.clone());
+    }
+    let removed = self.state_data.merge_with_dependencies(state_data, &self.var_dependencies);
+    if let Some(interner) = self.string_interner.as_mut() {
+      interner.purge_unused();
+    }
+    if removed.is_empty() {
+      return Ok(());
+    }
+
+    // prefer the earliest leaf step so we don't redo more than necessary
+    let step_store = &self.step_store;
+    let reopen = self.scheduler.completed(step_store)
+      .into_iter()
+      .filter(|step_id| {
+        step_store.get(step_id)
+          .map(|step| step.get_output_vars().iter().any(|var_id| removed.contains(var_id)))
+          .unwrap_or(false)
+      })
+      .min_by_key(|step_id| step_store.get(step_id).and_then(|step| step.first_substep()).is_some());
+    if let Some(step_id) = reopen {
+      self.scheduler.rewind_to(&step_id, step_store)?;
+    }
+    Ok(())
+  }
+
+  fn call_action(&mut self, action_id: &ActionId, step_id: &StepId) -> Result<ActionResult, Error> {
+    // setup params
+    fn get_step_input_output_vars(step: &Step) -> HashSet<VarId> {
+      step.get_input_vars()
+        .clone()      
+        .unwrap_or_else(|| vec![])
+        .iter()
+        .chain(step.get_output_vars().iter())
+        .map(|id_ref| id_ref.clone())
+        .collect::<HashSet<VarId>>()
+    }
+  
+    let step = self.step_store.get(step_id).ok_or_else(|| Error::StepId(IdError::IdMissing(step_id.clone())))?;
+    let step_name = self.step_store.name_from_id(&step_id);
+    let required_vars = self.action_store.get(action_id)
+      .ok_or_else(|| Error::ActionId(IdError::IdMissing(action_id.clone())))?
+      .required_vars()
+      .to_vec();
+    let mut action_vars = get_step_input_output_vars(&step);
+    action_vars.extend(required_vars);
+    if let Some(actor) = &self.actor {
+      action_vars = self.var_access.readable(action_vars.iter(), actor);
+    }
+    let mut step_data_vars = action_vars.clone();
+    step_data_vars.extend(step.get_condition_vars());
+    if let Some(actor) = &self.actor {
+      step_data_vars = self.var_access.readable(step_data_vars.iter(), actor);
+    }
+    let step_data: StateDataFiltered = StateDataFiltered::new(&self.state_data, step_data_vars);
+    let vars = ObjectStoreFiltered::new(&self.var_store, action_vars);
+
+    // call it
+    let action = self.action_store.get_mut(action_id).ok_or_else(|| Error::ActionId(IdError::IdMissing(action_id.clone())))?;
+    let action_result = Self::start_once(action, &mut self.completion_keys, &self.environment, step, step_name, &step_data, &vars)?;
+    match &action_result {
+        ActionResult::Finished(state_data) => {
+          // collect everything wrong with the data so the action's author can see it all at once
+          let invalid = state_data.iter_val()
+            .filter_map(|(var_id, val)| {
+              if !step.output_vars.contains(var_id) {
+                return Some((*var_id, InvalidValue::Unexpected));
+              }
+              let var = self.var_store.get(var_id)?;
+              var.validate_val_type(val).err().map(|err| (*var_id, err))
+            })
+            .collect::<HashMap<_, _>>();
+          if !invalid.is_empty() {
+            return Err(Error::InvalidVars(InvalidVars::new(invalid)));
+          }
+        }
+        ActionResult::StartWith(_) |
+        ActionResult::CannotFulfill => ()
+    }
+    Ok(action_result)
+  }  
+
+  // add to the trace when advancing with `advance_traced`
+  fn trace(&mut self, entry: TraceEntry) {
+    if let Some(trace) = self.trace.as_mut() {
+      trace.push(entry);
+    }
+  }
+
+  // add to the history with the next sequence number unless the history is being shed
+  fn record_event(&mut self, kind: SessionEventKind) -> SessionEvent {
+    let event = SessionEvent::now(self.next_event_seq, kind);
+    self.next_event_seq += 1;
+    if self.load_shedding.should_emit() {
+      self.history.push(event.clone());
+      self.record_checkpoint(event.seq);
+      self.trim_history();
+    }
+    event
+  }
+
+  fn record_checkpoint(&mut self, seq: u64) {
+    let current_step = self.scheduler.current().copied();
+    let checkpoints = match self.checkpoints.as_mut() {
+      Some(checkpoints) => checkpoints,
+      None => return,
+    };
+    let state_data = match checkpoints.last() {
+      Some(last) if *last.state_data == self.state_data => last.state_data.clone(),
+      _ => Arc::new(self.state_data.clone()),
+    };
+    checkpoints.push(Checkpoint { seq, state_data, current_step });
+  }
+
+  fn record_transition(&mut self, previous_step: Option<StepId>, next_step: Option<StepId>) {
+    if previous_step == next_step {
+      return;
+    }
+    let mut recorded = None;
+    if let Some(step_id) = previous_step {
+      recorded = Some(self.record_event(SessionEventKind::StepExited(step_id)));
+    }
+    if let Some(step_id) = next_step {
+      recorded = Some(self.record_event(SessionEventKind::StepEntered(step_id)));
+    }
+    // at least one was recorded since they differ
+    let (seq, at) = recorded.map(|event| (event.seq, event.at)).unwrap();
+
+    let merged = std::mem::replace(&mut self.merged_since_transition, StateData::new());
+    if self.is_preview() || !self.load_shedding.should_emit() {
+      return;
+    }
+    let transition = Transition { seq, at, from: previous_step, to: next_step, merged };
+    for observer in self.transition_observers.iter_mut() {
+      observer(&transition);
+    }
+  }
+
+  // remember the steps that ran and were exited so compensating only undoes work that happened
+  fn record_exited(&mut self, entered_before: Vec<StepId>, skipped_steps: &HashSet<StepId>) {
+    let entered = self.scheduler.entered();
+    for step_id in entered_before.into_iter().rev() {
+      let exited = !entered.contains(&step_id) && step_id != self.step_id_root;
+      if !exited {
+        continue;
+      }
+      // the fallback chain starts over the next time the step is entered
+      for fallback in self.fallback_chains.get_mut(&step_id).into_iter().flatten() {
+        fallback.attempts = 0;
+      }
+      if !skipped_steps.contains(&step_id) && !self.to_compensate.contains(&step_id) {
+        self.to_compensate.push(step_id);
+      }
+    }
+  }
+
+  // see if the step's visit policy says to move past it without running its action
+  fn skip_visit(&self, step_id: &StepId) -> bool {
+    let step = match self.step_store.get(step_id) {
+      Some(step) => step,
+      None => return false,
+    };
+    if step.is_skipped(&self.state_data) {
+      return true;
+    }
+    match step.visit_policy() {
+      VisitPolicy::Always => false,
+      VisitPolicy::Once => self.visited.contains(step_id),
+      VisitPolicy::UntilValid => step.can_exit(&self.state_data).is_ok(),
+    }
+  }
+
+  /// Main function for advancing the flow to the next step.
+  ///
+  /// This is the privileged entry point for the system itself: it doesn't check [assignees](Session::set_step_assignee) or [var access](Session::var_access_mut).
+  /// Use [`advance_as`](Session::advance_as) for anything a user submits.
+  ///
+  /// `step_output` is what the current step generated and is merged with the internal current `state_data`
+  /// before trying to advance to the next step.
+  ///
+  /// Advancing works in a loop that tries to advance as far as possible until it hits a blocking condition
+  /// The loop is roughly:
+  /// - Try to enter the next step. Note: the process continues irregardless of failure
+  /// - Skip the step if its [`VisitPolicy`] says it doesn't need to run again
+  /// - Execute the specific action of the current step
+  /// - If it's from the step's [fallback chain](Session::set_fallback_chain) and [`CannotFulfill`](ActionResult::CannotFulfill), execute the next one in the chain
+  /// - If there is no specific action or it [`CannotFulfill`](ActionResult::CannotFulfill), execute the general action
+  /// - If the action is not [`Finished`](ActionResult::Finished), then we're blocked and exit the loop
+  /// - Once there are no steps left, run the [finish actions](Session::add_finish_action) that still need to run
+  ///
+  /// Fails with [`Error::QuotaExceeded`] if the session is over its [`ResourceLimits`].
+  /// If a step's [validators](Step::add_validator) reject its data, the data isn't kept and the step's action is started again
+  /// so it can show the [rejected vars](Session::rejected_vars). Without an action it fails with [`Error::InvalidVars`].
+  ///
+  /// When it blocks on a step, the [blocked observers](Session::add_blocked_observer) are called.
+  pub fn advance(&mut self, step_output: Option<(&StepId, StateData)>) 
+      -> Result<AdvanceBlockedOn, Error>
+  {
+    self.rejected_vars = None;
+    let advance_result = self.advance_steps(step_output);
+    self.check_blocked(&advance_result);
+    match &advance_result {
+      Ok(AdvanceBlockedOn::ActionStartWith(action_id, _, attempt_id)) => self.current_attempt = Some((*attempt_id, *action_id)),
+      Ok(_) => self.current_attempt = None,
+      Err(_) => (),
+    }
+    advance_result
+  }
+
+  /// The vars a step's [validators](Step::add_validator) rejected on the last advance, i.e. to show the errors on the form that's shown again
+  ///
+  /// Rejected data isn't kept so the step can be submitted again.
+  pub fn rejected_vars(&self) -> Option<&InvalidVars> {
+    self.rejected_vars.as_ref()
+  }
+
+  /// The attempt the session is blocked on. `None` if it isn't blocked on an action's [`StartWith`](ActionResult::StartWith).
+  pub fn current_attempt(&self) -> Option<AttemptId> {
+    self.current_attempt.map(|(attempt_id, _action_id)| attempt_id)
+  }
+
+  /// The action of the [current attempt](Session::current_attempt), i.e. to show its [description](Session::action_description)
+  pub fn current_action(&self) -> Option<ActionId> {
+    self.current_attempt.map(|(_attempt_id, action_id)| action_id)
+  }
+
+  /// Same as [`advance`](Session::advance) but only with the submission for `attempt_id`, i.e. so a form posted twice only advances once
+  ///
+  /// Fails with [`Error::AttemptId`] if the session isn't blocked on the attempt.
+  pub fn advance_attempt(&mut self, attempt_id: AttemptId, step_output: Option<(&StepId, StateData)>)
+      -> Result<AdvanceBlockedOn, Error>
+  {
+    if self.current_attempt() != Some(attempt_id) {
+      return Err(Error::AttemptId(IdError::IdUnexpected(attempt_id)));
+    }
+    self.advance(step_output)
+  }
+
+  /// Same as [`advance`](Session::advance) but also returns what it did, i.e. to see why a session is stuck
+  ///
+  /// The trace has the steps tried, the vars missing when a step couldn't be entered or exited and the actions started.
+  pub fn advance_traced(&mut self, step_output: Option<(&StepId, StateData)>)
+      -> (Result<AdvanceBlockedOn, Error>, Vec<TraceEntry>)
+  {
+    self.trace = Some(Vec::new());
+    let advance_result = self.advance(step_output);
+    (advance_result, self.trace.take().unwrap_or_default())
+  }
+
+  fn advance_steps(&mut self, step_output: Option<(&StepId, StateData)>)
+      -> Result<AdvanceBlockedOn, Error>
+  {
+    // both walk the whole definition so only run them after it changes
+    if self.definition_changed {
+      self.check_definition_limits()?;
+      self.check_definition_drift()?;
+      self.definition_changed = false;
+    }
+    if self.is_expired(SystemTime::now()) {
+      return Err(Error::PreviewExpired);
+    }
+    // only count the call once both limits let it through
+    let now = std::time::Instant::now();
+    self.advance_rate.check(self.limits.max_advances_per_minute, now)?;
+    if let Some((tenant, quotas)) = &self.tenant {
+      quotas.lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .record_advance(tenant, now)?;
+    }
+    self.advance_rate.try_record(self.limits.max_advances_per_minute, now)?;
+
+    #[derive(Clone, Debug)]
+    enum States {
+      AdvanceStep,
+      GetSpecificAction(StepId, Option<Error>),  // current step id, step-id-advance error
+      GetGenericAction(StepId, Option<Error>),      // step-id-advance error
+      StartSpecific(ActionId, StepId, Option<Error>), // action id, step-id-advance error
+      StartGeneric(ActionId, StepId, Option<Error>),  // action id, step-id-advance error
+      Done(Result<AdvanceBlockedOn, Error>)
+    }
+
+    // generally we're trying to advance as much as possible without user interaction:
+    // loop until we get to a blocking state (StartWith or No-more-states-left or can't-start)
+    //   advance step
+    //   succeed or fail:
+    //     start specific action
+    //     if doesn't exist or succeed, start generic action
+    // return (step-advance-result, action-result)
+    let mut step_output = step_output;
+    let mut state = States::AdvanceStep;
+    let mut attempted_actions = Vec::new();
+    let mut skipped_steps = HashSet::new();
+    loop {
+      state = match state.clone() {
+        States::Done(result) => return result,
+        States::AdvanceStep => {
+          attempted_actions.clear();
+          let previous_step = self.current_step().ok().copied().filter(|step_id| *step_id != self.step_id_root);
+          let entered_before = self.scheduler.entered();
+          let advance_result = self.try_enter_next_step(step_output);
+          step_output = None;
+          if let Ok(next_step) = &advance_result {
+            self.record_transition(previous_step, *next_step);
+            self.record_exited(entered_before, &skipped_steps);
+          }
+          match &advance_result {
+            Ok(step_id_opt) => {
+              match step_id_opt {
+                Some(step_id) => {
+                  let skip = self.skip_visit(step_id);
+                  self.trace(if skip { TraceEntry::StepSkipped(*step_id) } else { TraceEntry::StepEntered(*step_id) });
+                  self.visited.insert(*step_id);
+                  if skip {
+                    skipped_steps.insert(*step_id);
+                    States::AdvanceStep
+                  } else {
+                    States::GetSpecificAction(step_id.clone(), None)
+                  }
+                }
+                None => {
+                  // no more steps left to advance
+                  self.trace(TraceEntry::Finished);
+                  self.run_finish_actions()?;
+                  States::Done(Ok(AdvanceBlockedOn::FinishedAdvancing))
+                }
+              }
+            }
+            // the loop can't go on, the action can't help
+            Err(err @ Error::RepeatLimit(_)) => return Err(err.clone()),
+            Err(err) => {
+              if let Error::InvalidVars(invalid) = err {
+                self.rejected_vars = Some(invalid.clone());
+              }
+              let step_id = self.current_step()?.clone();
+              States::GetSpecificAction(step_id, Some(err.clone())) // error advancing but we can try the action to see if that fixes it
+            }
+          }
+        },
+        States::GetSpecificAction(step_id, error) => {
+          match self.specific_action_for(&step_id, &attempted_actions) {
+            Some(action_id) => States::StartSpecific(action_id, step_id, error),
+            None => States::GetGenericAction(step_id, error),
+          }
+        },
+        States::GetGenericAction(step_id, error) => {
+          match self.actions.get(&self.step_id_all) {
+            Some(action_id) => States::StartGeneric(action_id.clone(), step_id, error),
+            None => {
+              match error {
+                None => States::AdvanceStep,  // did we advance? if so, try advancing again
+                Some(err) => return Err(err),   // couldn't advance and no action? then we're stuck
+              }
+            }
+          }
+        },
+        States::StartSpecific(action_id, step_id, error_opt) |
+        States::StartGeneric(action_id, step_id, error_opt) => {
+          let fallback_index = match state {
+            States::StartSpecific(_, _, _) if !self.actions.contains_key(&step_id) => self.next_fallback(&step_id, &attempted_actions),
+            _ => None,
+          };
+          attempted_actions.push(action_id);
+
+          let action_result = self.call_action(&action_id, &step_id);
+          let outcome = ActionOutcome::from(&action_result);
+          self.trace(TraceEntry::ActionStarted { step_id, action_id, outcome: outcome.clone() });
+          let attempt_id = match (&action_result, self.current_attempt) {
+            // showing the same action again when nothing else happened since isn't a new attempt
+            (Ok(ActionResult::StartWith(_)), Some((attempt_id, attempt_action_id)))
+              if attempt_action_id == action_id && attempt_id.val() + 1 == self.next_event_seq => attempt_id,
+            _ => AttemptId::new(self.record_event(SessionEventKind::ActionStarted { step_id, action_id, outcome }).seq),
+          };
+          match action_result? {
+              ActionResult::StartWith(val) => {
+                States::Done(Ok(AdvanceBlockedOn::ActionStartWith(action_id, val, attempt_id)))
+              }
+              ActionResult::Finished(state_data) => {
+                // merge the new data and see if we can keep advancing
+                self.merge_state_data(state_data)?;
+                States::AdvanceStep
+              }
+              ActionResult::CannotFulfill => {
+                if let Some(index) = fallback_index {
+                  // count the attempt and move on to the next action in the chain
+                  let fallback = &mut self.fallback_chains.get_mut(&step_id).unwrap()[index];
+                  fallback.attempts = fallback.attempts.saturating_add(1);
+                  States::GetSpecificAction(step_id, error_opt)
+                } else if matches!(state, States::StartSpecific(_,_,_)) {
+                  // couldn't fulfill specific action, try generic one
+                  States::GetGenericAction(step_id, error_opt)
+                } else {
+                  // couldn't fulfill generic one (and must've already failed specific) -- nothing else we can do
+                  States::Done(Ok(AdvanceBlockedOn::ActionCannotFulfill(CannotFulfillDetails {
+                    step_id,
+                    attempted_actions: attempted_actions.clone(),
+                    advance_error: error_opt,
+                  })))
+                }
+              }
+          }
+        }
+      }
+    }
+  }
+
+  #[cfg(test)]
+  pub fn test_new() -> (Session, StepId) {
+    let mut session = Session::new(stepflow_test_util::test_id!(SessionId));
+    let root_step_id = session.step_store_mut().insert_new_named("root_step", |id| Ok(Step::new(id, None, vec![]))).unwrap();
+    session.push_root_substep(root_step_id.clone());
+    (session, root_step_id)
+  }
+
+  #[cfg(test)]
+  pub fn test_new_stringvar(&mut self) -> VarId {
+    let var_id = stepflow_test_util::test_id!(VarId);
+    let var = stepflow_data::var::StringVar::new(var_id);
+    let var_id = self.var_store.register( var.boxed()).unwrap();
+    var_id
+  }
+}
+
+/// What [`Session::advance`] has blocked on
+#[derive(Debug, Clone)]
+pub enum AdvanceBlockedOn {
+  /// Same as [`ActionResult::StartWith`] but with the additional identifier of which [`Action`](stepflow_action::Action) blocked
+  /// and the [`AttemptId`] to submit with.
+  ActionStartWith(ActionId, Box<dyn Value>, AttemptId),
+
+  /// Same as [`ActionResult::CannotFulfill`] with what was tried
+  ActionCannotFulfill(CannotFulfillDetails),
+
+  /// [`Session`] has finished advancing to the end of the flow
+  FinishedAdvancing,
+}
+
+/// An action run when the flow finishes. See [`Session::add_finish_action`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct FinishAction {
+  pub action_id: ActionId,
+
+  /// How many times to try the action before giving up
+  pub max_attempts: u32,
+
+  /// How many times the action has been tried
+  pub attempts: u32,
+
+  pub status: FinishActionStatus,
+}
+
+/// An action in a step's fallback chain. See [`Session::set_fallback_chain`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct FallbackAction {
+  pub action_id: ActionId,
+
+  /// How many times the action can fail to fulfill before it's no longer tried
+  pub max_attempts: u32,
+
+  /// How many times the action couldn't fulfill since the step was entered
+  pub attempts: u32,
+}
+
+impl FallbackAction {
+  /// `max_attempts` for an action that's never used up
+  pub const UNLIMITED: u32 = u32::MAX;
+
+  /// Whether the action can still be started
+  pub fn has_attempts_left(&self) -> bool {
+    self.max_attempts == FallbackAction::UNLIMITED || self.attempts < self.max_attempts
+  }
+}
+
+/// How a [`FinishAction`] went
+#[derive(Debug, Clone, PartialEq)]
+pub enum FinishActionStatus {
+  /// Hasn't been tried yet
+  Pending,
+
+  /// Ran successfully and won't run again
+  Succeeded,
+
+  /// The last attempt failed with the error, or `None` if the action couldn't fulfill or waited on the user since no one is left to answer
+  Failed(Option<Error>),
+}
+
+/// What's next for a [`Session`]. See [`Session::peek_next`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct NextStepPreview {
+  /// The step that would be entered next. `None` if the flow would finish.
+  pub step_id: Option<StepId>,
+
+  /// Vars that need values to get there: the current step's missing outputs, then the next step's missing inputs
+  pub missing_vars: Vec<VarId>,
+}
+
+/// A var missing a value that blocks the session from moving on. See [`Session::blocking_requirements`] and [`Session::completion_blocker`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockingRequirement {
+  pub step_id: StepId,
+  pub var_id: VarId,
+  pub var_name: Option<String>,
+  pub check: TransitionCheck,
+}
+
+/// Whether a [`BlockingRequirement`] is for leaving or entering its step
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde-support", derive(serde::Serialize))]
+pub enum TransitionCheck {
+  /// An output of the current step
+  Exit,
+
+  /// An input of the next step
+  Enter,
+}
+
+/// Called with the step a session is blocked on. See [`Session::add_blocked_observer`].
+pub type BlockedObserver = Box<dyn FnMut(&BlockedNotice) + Send + Sync>;
+
+/// Called when the session moves between steps. See [`Session::on_transition`].
+pub type TransitionObserver = Box<dyn FnMut(&Transition) + Send + Sync>;
+
+/// A move from one step to another. See [`Session::on_transition`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transition {
+  /// The [`seq`](SessionEvent::seq) of the transition's last event in the history
+  pub seq: u64,
+
+  /// When it happened by the wall clock
+  pub at: SystemTime,
+
+  /// The step the session was on. `None` when the flow starts.
+  pub from: Option<StepId>,
+
+  /// The step the session is on now. `None` when there are no steps left.
+  pub to: Option<StepId>,
+
+  /// The data merged since the previous transition, i.e. the outputs of `from`
+  pub merged: StateData,
+}
+
+/// A step the session is blocked on. See [`Session::add_blocked_observer`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockedNotice {
+  pub step_id: StepId,
+
+  /// The action that's waiting, i.e. on a user or an external system
+  pub action_id: ActionId,
+
+  /// Who the step is [assigned](Session::set_step_assignee) to
+  pub assignee: Option<String>,
+
+  /// The step's `deadline` [metadata](Step::get_metadata)
+  pub deadline: Option<String>,
+
+  /// The step is past its [deadline policy](Session::set_deadline_policy) and is being escalated
+  pub overdue: bool,
+}
+
+/// How long a step can be blocked and what happens after. See [`Session::set_deadline_policy`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeadlinePolicy {
+  pub within: Duration,
+  pub escalation: Escalation,
+}
+
+/// What to do when a step is past its [`DeadlinePolicy`]
+///
+/// The [blocked observers](Session::add_blocked_observer) are always told with [`BlockedNotice::overdue`] set.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Escalation {
+  /// Only notify
+  Notify,
+
+  /// Complete the step with the data and advance, i.e. to auto-approve
+  Complete(StateData),
+
+  /// Approve the [approval step](Session::insert_approval_step) as [`ESCALATION_ACTOR`]
+  AutoApprove(ApprovalStep),
+
+  /// Go to another step instead, i.e. to have a manager review it
+  Reroute(StepId),
+}
+
+/// A flow to start when this one finishes. See [`Session::add_follow_up`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct FollowUp {
+  /// Name of the flow to start
+  pub flow: String,
+
+  /// Vars to seed the flow with
+  pub vars: Vec<VarId>,
+
+  /// Only start the flow when this holds
+  pub when: Condition,
+
+  /// Whether it was [started](Session::set_follow_up_started)
+  pub started: bool,
+}
+
+/// A follow-up flow to start with the data to seed it with. See [`Session::pending_follow_ups`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct FollowUpStart {
+  /// Position in [`Session::follow_ups`]
+  pub index: usize,
+
+  pub flow: String,
+
+  /// Var names and their values
+  pub seed: Vec<(String, Box<dyn Value>)>,
+}
+
+/// The outcome of a step's compensation action. See [`Session::compensate`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Compensation {
+  pub step_id: StepId,
+  pub action_id: ActionId,
+  pub result: Result<ActionResult, Error>,
+}
+
+/// A var an [`Action`] requires that its [`Step`] doesn't declare. See [`Session::undeclared_action_vars`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UndeclaredActionVar {
+  pub step_id: StepId,
+  pub action_id: ActionId,
+  pub var_id: VarId,
+}
+
+/// Diagnostics for [`AdvanceBlockedOn::ActionCannotFulfill`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CannotFulfillDetails {
+  /// The step that couldn't be fulfilled
+  pub step_id: StepId,
+
+  /// The actions that were tried, in order
+  pub attempted_actions: Vec<ActionId>,
+
+  /// Why the session couldn't move past the step, i.e. a missing output
+  pub advance_error: Option<Error>,
+}
+
+impl PartialEq for AdvanceBlockedOn {
+  fn eq(&self, other: &Self) -> bool {
+    match (self, other) {
+      (AdvanceBlockedOn::ActionStartWith(action_id, val, attempt_id),AdvanceBlockedOn::ActionStartWith(action_id_other, val_other, attempt_id_other)) => {
+        action_id == action_id_other && val == val_other && attempt_id == attempt_id_other
+      }
+      (AdvanceBlockedOn::ActionCannotFulfill(details), AdvanceBlockedOn::ActionCannotFulfill(details_other)) => {
+        details == details_other
+      }
+      (AdvanceBlockedOn::FinishedAdvancing, AdvanceBlockedOn::FinishedAdvancing) => {
+        true
+      }
+      _ => false
+    }
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use core::panic;
+  use std::collections::HashMap;
+  use std::sync::{Arc, Mutex};
+  use std::time::{Duration, SystemTime};
+  use stepflow_base::{ObjectStore, IdError};
+  use stepflow_data::{StateData, StateDataFiltered, Condition, InvalidValue, InvalidVars, var::{VarId, StringVar, EmailVar, BoolVar}, value::{BoolValue, StringValue}};
+  use stepflow_step::{Step, StepId, StepMatcher, VisitPolicy, Repeat};
+  use stepflow_test_util::test_id;
+  use stepflow_action::{Action, SetDataAction, ActionId, ActionResult, FlowEnvironment, SecretValue, StringTemplateAction, UriEscapedString, EscapedString};
+  use stepflow_action::testing::MockAction;
+  use super::super::{Error, AdvanceError, ResourceLimits, QuotaExceeded, TenantQuotas, LoadShedding, StepStatus, StepScore, PriorityTraversal};
+  use crate::{SessionEventKind, ActionOutcome, AttemptId, TraceEntry};
+  use super::{Session, SessionId, AdvanceBlockedOn, CannotFulfillDetails, UndeclaredActionVar, FinishActionStatus, NextStepPreview, TransitionCheck, BlockedNotice, DeadlinePolicy, Escalation, FollowUpStart};
+
+
+
+  fn new_simple_step(id: StepId) -> Result<Step, IdError<StepId>> {
+    Ok(Step::new(id, None, vec![]))
+  }
+
+  fn add_new_simple_substep(parent_id: &StepId, step_store: &mut ObjectStore<Step, StepId>) -> StepId {
+    let substep_id = step_store.insert_new(new_simple_step).unwrap();
+    push_substep(parent_id, substep_id, step_store)
+  }
+
+  fn push_substep(parent_id: &StepId, step_id: StepId, step_store: &mut ObjectStore<Step, StepId>) -> StepId {
+    let parent = step_store.get_mut(parent_id).unwrap();
+    parent.push_substep(step_id.clone());
+    step_id
+  }
+
+  fn step_str_output(session: &Session, var_id: &VarId, val: &'static str) -> (StepId, StateData) {
+    let mut state_data = StateData::new();
+    let var = session.var_store().get(var_id).unwrap();
+    state_data.insert(var, StringValue::try_new(val).unwrap().boxed()).unwrap();
+    (session.current_step().unwrap().clone(), state_data)
+  }
+
+  fn missing_var_ids<T>(result: Result<T, Error>) -> Result<T, Vec<VarId>> {
+    result.map_err(|err| match err {
+      Error::MissingVars(advance_error) => advance_error.var_ids(),
+      err => panic!("Not missing vars: {:?}", err),
+    })
+  }
+
+  #[test]
+  fn empty_session_advance() {
+    let mut session = Session::new(test_id!(SessionId));
+    let advance_result = session.advance(None);
+    assert_eq!(advance_result, Ok(AdvanceBlockedOn::FinishedAdvancing));
+  }
+
+  #[test]
+  fn progress_session_inputs_outputs() {
+    let mut session = Session::new(test_id!(SessionId));
+
+    let var_output1_id = session.test_new_stringvar();
+    let var_input2_id = session.test_new_stringvar();
+    let var_output2_id = session.test_new_stringvar();
+
+    let root_step_id = session.step_store.insert_new_named(
+      "root_step", |id| {
+        Ok(Step::new(
+          id,
+          Some(vec![var_input2_id.clone()]),
+          vec![var_output1_id.clone(), var_output2_id.clone()]))
+      })
+      .unwrap();
+    session.push_root_substep(root_step_id);
+    
+    let substep1_id = session.step_store_mut().insert_new_named("SubStep 1",
+      |id| Ok(Step::new(id, None, vec![var_output1_id.clone()])))
+      .unwrap();
+    let substep2_id = session.step_store_mut().insert_new_named("SubStep 2",
+      |id| Ok(Step::new(id, Some(vec![var_input2_id.clone()]), vec![var_output2_id.clone()])))
+      .unwrap();
+
+    let root_step = session.step_store_mut().get_mut(&root_step_id).unwrap();
+    root_step.push_substep(substep1_id.clone());
+    root_step.push_substep(substep2_id.clone());
+    
+    assert_eq!(
+      session.try_enter_next_step(None),
+      Err(Error::MissingVars(AdvanceError {
+        step_id: root_step_id,
+        step_name: Some("root_step".to_owned()),
+        check: TransitionCheck::Enter,
+        missing_vars: vec![(var_input2_id, None)],
+      })));    // start without proper input
+
+    // go to substep1
+    let output1 = step_str_output(&session, &var_input2_id, "input2");
+    assert_eq!(session.try_enter_next_step(Some((&output1.0, output1.1))), Ok(Some(substep1_id.clone())));  // start without proper input
+
+    // go to substep2
+    assert_eq!(missing_var_ids(session.try_enter_next_step(None)), Err(vec![var_output1_id]));  // didn't add output
+    let output2 = step_str_output(&session, &var_output1_id, "output1");
+    assert_eq!(session.try_enter_next_step(Some((&output2.0, output2.1))), Ok(Some(substep2_id.clone())));
+
+    // done with states but can't leave root without the output from substep 2
+    assert_eq!(missing_var_ids(session.try_enter_next_step(None)), Err(vec![var_output2_id]));
+    let output3 = step_str_output(&session, &var_output2_id, "output2");
+    assert_eq!(session.try_enter_next_step(Some((&output3.0, output3.1))), Ok(None));
+    
+    // try it again to check we're still done advancing
+    assert_eq!(session.try_enter_next_step(None), Ok(None));
+  }
+
+  #[test]
+  fn dependent_var_reopens_step() {
+    let (mut session, root_step_id) = Session::test_new();
+    let var_email_id = session.test_new_stringvar();
+    let var_validated_id = session.test_new_stringvar();
+    session.var_dependencies_mut().add(var_validated_id, var_email_id);
+
+    let substep1 = session.step_store_mut().insert_new(|id| Ok(Step::new(id, None, vec![var_email_id]))).unwrap();
+    let substep2 = session.step_store_mut().insert_new(|id| Ok(Step::new(id, Some(vec![var_email_id]), vec![var_validated_id]))).unwrap();
+    let substep3 = session.step_store_mut().insert_new(|id| Ok(Step::new(id, None, vec![var_email_id]))).unwrap();
+    push_substep(&root_step_id, substep1, session.step_store_mut());
+    push_substep(&root_step_id, substep2, session.step_store_mut());
+    push_substep(&root_step_id, substep3, session.step_store_mut());
+
+    assert_eq!(session.try_enter_next_step(None), Ok(Some(substep1)));
+    let output = step_str_output(&session, &var_email_id, "a@a.com");
+    assert_eq!(session.try_enter_next_step(Some((&output.0, output.1))), Ok(Some(substep2)));
+    let output = step_str_output(&session, &var_validated_id, "validated");
+    assert_eq!(session.try_enter_next_step(Some((&output.0, output.1))), Ok(Some(substep3)));
+
+    // changing the email removes the validation and goes back to the step that set it
+    let output = step_str_output(&session, &var_email_id, "b@b.com");
+    assert_eq!(missing_var_ids(session.try_enter_next_step(Some((&output.0, output.1)))), Err(vec![var_validated_id]));
+    assert_eq!(*session.current_step().unwrap(), substep2);
+    assert!(!session.state_data().contains(&var_validated_id));
+
+    let output = step_str_output(&session, &var_validated_id, "validated");
+    assert_eq!(session.try_enter_next_step(Some((&output.0, output.1))), Ok(Some(substep3)));
+
+    // same value doesn't change anything
+    let output = step_str_output(&session, &var_email_id, "b@b.com");
+    assert_eq!(session.try_enter_next_step(Some((&output.0, output.1))), Ok(None));
+    assert!(session.state_data().contains(&var_validated_id));
+  }
+
+  #[test]
+  fn step_groups() {
+    let (mut session, root_step_id) = Session::test_new();
+    let kyc1 = session.insert_group_step("kyc", "name", new_simple_step).unwrap();
+    let other = session.insert_group_step("other", "name", new_simple_step).unwrap();
+    let kyc2 = session.insert_group_step("kyc", "address", new_simple_step).unwrap();
+    for step_id in session.step_group("kyc") {
+      push_substep(&root_step_id, step_id, session.step_store_mut());
+    }
+
+    assert_eq!(session.step_store().id_from_name("kyc.name"), Some(&kyc1));
+    assert_eq!(session.step_group("kyc"), vec![kyc1, kyc2]);
+    assert_eq!(session.step_group("ky"), vec![]);
+    assert!(matches!(session.insert_group_step("kyc", "name", new_simple_step), Err(Error::StepId(IdError::NameAlreadyExists(_)))));
+
+    // bind one action to the group
+    let test_action_id = session.action_store_mut().insert_new(|id| Ok(MockAction::new(id).boxed())).unwrap();
+    assert_eq!(session.set_action_for_step_group(test_action_id, "kyc"), Ok(vec![kyc1, kyc2]));
+    assert_eq!(session.set_action_for_step_group(test_action_id, "kyc"), Err(Error::StepId(IdError::IdAlreadyExists(kyc1))));
+    assert_eq!(session.set_action_for_step(test_action_id, Some(&other)), Ok(()));
+
+    assert!(matches!(session.advance(None), Ok(AdvanceBlockedOn::ActionStartWith(action_id, _, _)) if action_id == test_action_id));
+    assert_eq!(session.current_step(), Ok(&kyc1));
+  }
+
+  #[test]
+  fn bound_actions() {
+    let (mut session, root_step_id) = Session::test_new();
+    let form_step = add_new_simple_substep(&root_step_id, session.step_store_mut());
+    let redirect_step = add_new_simple_substep(&root_step_id, session.step_store_mut());
+    let named_step = session.step_store_mut().insert_new_named("kyc.name", new_simple_step).unwrap();
+    push_substep(&root_step_id, named_step, session.step_store_mut());
+    let exact_step = add_new_simple_substep(&root_step_id, session.step_store_mut());
+    session.step_store_mut().get_mut(&form_step).unwrap().add_tag("form");
+    session.step_store_mut().get_mut(&redirect_step).unwrap().set_metadata("kind", "redirect");
+    session.step_store_mut().get_mut(&exact_step).unwrap().add_tag("form");
+
+    let mut new_action = || session.action_store_mut().insert_new(|id| Ok(MockAction::new(id).boxed())).unwrap();
+    let form_action = new_action();
+    let redirect_action = new_action();
+    let name_action = new_action();
+    let exact_action = new_action();
+    session.bind_action(form_action, StepMatcher::Tag("form".to_owned())).unwrap();
+    session.bind_action(redirect_action, StepMatcher::Metadata("kind".to_owned(), "redirect".to_owned())).unwrap();
+    session.bind_action(name_action, StepMatcher::NamePattern("kyc.*".to_owned())).unwrap();
+    session.set_action_for_step(exact_action, Some(&exact_step)).unwrap();
+
+    for expected in [form_action, redirect_action, name_action, exact_action] {
+      match session.advance(None) {
+        Ok(AdvanceBlockedOn::ActionStartWith(action_id, _, _)) => assert_eq!(action_id, expected),
+        result => panic!("Unexpected advance result: {:?}", result),
+      }
+    }
+  }
+
+  #[test]
+  fn limits() {
+    let (mut session, root_step_id) = Session::test_new();
+    let substep1 = add_new_simple_substep(&root_step_id, session.step_store_mut());
+    let test_action_id = session.action_store_mut().insert_new(|id| Ok(MockAction::new(id).boxed())).unwrap();
+    session.set_action_for_step(test_action_id, None).unwrap();
+
+    session.set_limits(ResourceLimits { max_steps: Some(1), ..Default::default() });
+    assert_eq!(session.advance(None), Err(Error::QuotaExceeded(QuotaExceeded::Steps(1))));
+
+    session.set_limits(ResourceLimits { max_vars: Some(0), ..Default::default() });
+    session.test_new_stringvar();
+    assert_eq!(session.advance(None), Err(Error::QuotaExceeded(QuotaExceeded::Vars(0))));
+
+    // root_step -> substep1 -> nested
+    let nested = add_new_simple_substep(&substep1, session.step_store_mut());
+    session.set_limits(ResourceLimits { max_depth: Some(2), ..Default::default() });
+    assert_eq!(session.check_definition_limits(), Err(Error::QuotaExceeded(QuotaExceeded::Depth(2))));
+    session.set_limits(ResourceLimits { max_depth: Some(3), ..Default::default() });
+    assert_eq!(session.check_definition_limits(), Ok(()));
+
+    add_new_simple_substep(&root_step_id, session.step_store_mut());
+    session.set_limits(ResourceLimits { max_substeps: Some(1), ..Default::default() });
+    assert_eq!(session.advance(None), Err(Error::QuotaExceeded(QuotaExceeded::Substeps(1))));
+
+    session.set_limits(ResourceLimits { max_steps: Some(4), ..Default::default() });
+    assert!(session.advance(None).is_ok());
+
+    // only checked again once the definition changes
+    assert!(!session.definition_changed);
+    add_new_simple_substep(&root_step_id, session.step_store_mut());
+    assert!(session.definition_changed);
+    assert_eq!(session.advance(None), Err(Error::QuotaExceeded(QuotaExceeded::Steps(4))));
+
+    // a cycle stops at the limit
+    push_substep(&nested, root_step_id, session.step_store_mut());
+    session.set_limits(ResourceLimits { max_depth: Some(3), ..Default::default() });
+    assert_eq!(session.check_definition_limits(), Err(Error::QuotaExceeded(QuotaExceeded::Depth(3))));
+  }
+
+  #[test]
+  fn session_advance_limit() {
+    let new_session = || {
+      let (mut session, root_step_id) = Session::test_new();
+      add_new_simple_substep(&root_step_id, session.step_store_mut());
+      let test_action_id = session.action_store_mut().insert_new(|id| Ok(MockAction::new(id).boxed())).unwrap();
+      session.set_action_for_step(test_action_id, None).unwrap();
+      session.set_limits(ResourceLimits { max_advances_per_minute: Some(1), ..Default::default() });
+      session
+    };
+
+    // counted per session without a tenant
+    let mut session1 = new_session();
+    assert_eq!(session1.tenant(), None);
+    assert!(session1.advance(None).is_ok());
+    assert_eq!(session1.advance(None), Err(Error::QuotaExceeded(QuotaExceeded::AdvancesPerMinute(1))));
+    assert!(new_session().advance(None).is_ok());
+
+    // a call the tenant turns away doesn't count against the session
+    let quotas = Arc::new(Mutex::new(TenantQuotas::new(ResourceLimits { max_advances_per_minute: Some(0), ..Default::default() })));
+    let mut session2 = new_session();
+    session2.set_tenant("acme", quotas.clone());
+    assert_eq!(session2.advance(None), Err(Error::QuotaExceeded(QuotaExceeded::AdvancesPerMinute(0))));
+    quotas.lock().unwrap().set_tenant_limits("acme", ResourceLimits::default());
+    assert!(session2.advance(None).is_ok());
+  }
+
+  #[test]
+  fn tenant_advance_limit() {
+    let quotas = Arc::new(Mutex::new(TenantQuotas::new(ResourceLimits { max_advances_per_minute: Some(1), ..Default::default() })));
+    let new_session = |tenant: &str| {
+      let (mut session, root_step_id) = Session::test_new();
+      add_new_simple_substep(&root_step_id, session.step_store_mut());
+      let test_action_id = session.action_store_mut().insert_new(|id| Ok(MockAction::new(id).boxed())).unwrap();
+      session.set_action_for_step(test_action_id, None).unwrap();
+      session.set_tenant(tenant, quotas.clone());
+      session
+    };
+
+    // opening another session doesn't get around the limit
+    let mut session1 = new_session("acme");
+    let mut session2 = new_session("acme");
+    assert_eq!(session1.tenant(), Some("acme"));
+    assert!(session1.advance(None).is_ok());
+    assert_eq!(session2.advance(None), Err(Error::QuotaExceeded(QuotaExceeded::AdvancesPerMinute(1))));
+    assert!(new_session("other").advance(None).is_ok());
+  }
+
+  #[test]
+  fn reset() {
+    let (mut session, root_step_id) = Session::test_new();
+    let var_email_id = session.test_new_stringvar();
+    let var_validated_id = session.test_new_stringvar();
+    let var_other_id = session.test_new_stringvar();
+    session.var_dependencies_mut().add(var_validated_id, var_email_id);
+
+    let substep1 = session.step_store_mut().insert_new(|id| Ok(Step::new(id, None, vec![var_email_id]))).unwrap();
+    let substep2 = session.step_store_mut().insert_new(|id| Ok(Step::new(id, None, vec![var_other_id]))).unwrap();
+    push_substep(&root_step_id, substep1, session.step_store_mut());
+    push_substep(&root_step_id, substep2, session.step_store_mut());
+
+    let mut output = StateData::new();
+    for var_id in [var_email_id, var_validated_id, var_other_id].iter() {
+      let var = session.var_store().get(var_id).unwrap();
+      output.insert(var, StringValue::try_new("val").unwrap().boxed()).unwrap();
+    }
+    assert_eq!(session.try_enter_next_step(None), Ok(Some(substep1)));
+    assert_eq!(session.try_enter_next_step(Some((&substep1, output))), Ok(Some(substep2)));
+    assert_eq!(session.try_enter_next_step(None), Ok(None));
+
+    // back to the first step, its output and dependents are gone but not the rest
+    session.reset_to(&substep1).unwrap();
+    assert_eq!(session.current_step(), Ok(&substep1));
+    assert!(!session.state_data().contains(&var_email_id));
+    assert!(!session.state_data().contains(&var_validated_id));
+    assert!(session.state_data().contains(&var_other_id));
+    assert_eq!(missing_var_ids(session.try_enter_next_step(None)), Err(vec![var_email_id]));
+
+    // all the way back, keeping the data
+    session.reset(true);
+    assert_eq!(session.try_enter_next_step(None), Ok(Some(substep1)));
+    assert!(session.state_data().contains(&var_other_id));
+
+    // all the way back, clearing the data
+    session.reset(false);
+    assert!(session.state_data().iter_val().next().is_none());
+    assert_eq!(session.try_enter_next_step(None), Ok(Some(substep1)));
+  }
+
+  #[test]
+  fn step_statuses() {
+    let (mut session, root_step_id) = Session::test_new();
+    let var_name_id = session.test_new_stringvar();
+    let var_company_id = session.test_new_stringvar();
+    let var_email_id = session.test_new_stringvar();
+    let var_other_id = session.test_new_stringvar();
+
+    let substep1 = session.step_store_mut().insert_new(|id| Ok(Step::new(id, None, vec![var_name_id]))).unwrap();
+    session.step_store_mut().get_mut(&substep1).unwrap().set_output_required_when(var_company_id, Condition::IsSet(var_other_id));
+    let substep2 = session.step_store_mut().insert_new(|id| Ok(Step::new(id, None, vec![var_email_id]))).unwrap();
+    push_substep(&root_step_id, substep1, session.step_store_mut());
+    push_substep(&root_step_id, substep2, session.step_store_mut());
+    let substep3 = add_new_simple_substep(&root_step_id, session.step_store_mut());
+
+    assert_eq!(session.try_enter_next_step(None), Ok(Some(substep1)));
+    let output = step_str_output(&session, &var_name_id, "name");
+    assert_eq!(session.try_enter_next_step(Some((&output.0, output.1))), Ok(Some(substep2)));
+
+    let statuses = session.step_statuses().into_iter().skip(1).collect::<Vec<_>>(); // skip SESSION_ROOT
+    assert_eq!(statuses, vec![
+      (root_step_id, StepStatus::Entered),
+      (substep1, StepStatus::Skipped),
+      (substep2, StepStatus::Blocked),
+      (substep3, StepStatus::Pending),
+    ]);
+    assert!(session.to_dot().contains(&format!("\"{}\" -> \"{}\"", root_step_id, substep2)));
+  }
+
+  #[test]
+  fn priority_traversal() {
+    let (mut session, root_step_id) = Session::test_new();
+    let substep1 = add_new_simple_substep(&root_step_id, session.step_store_mut());
+    let substep2 = add_new_simple_substep(&root_step_id, session.step_store_mut());
+    session.step_store_mut().get_mut(&substep2).unwrap().set_metadata("priority", "10");
+    session.set_scheduler(|root| Box::new(PriorityTraversal::new(root, StepScore::Metadata("priority".to_owned()))));
+
+    assert_eq!(session.try_enter_next_step(None), Ok(Some(substep2)));
+    assert_eq!(session.step_statuses().last(), Some(&(substep2, StepStatus::Current)));
+    assert_eq!(session.try_enter_next_step(None), Ok(Some(substep1)));
+    assert_eq!(session.try_enter_next_step(None), Ok(None));
+
+    // reset keeps the scheduler
+    session.reset(true);
+    assert_eq!(session.try_enter_next_step(None), Ok(Some(substep2)));
   }
 
   #[test]
-  fn progress_session_inputs_outputs() {
-    let mut session = Session::new(test_id!(SessionId));
+  fn visit_policies() {
+    let (mut session, root_step_id) = Session::test_new();
+    let var1_id = session.test_new_stringvar();
+    let var2_id = session.test_new_stringvar();
+    let substep1 = session.step_store_mut().insert_new(|id| Ok(Step::new(id, None, vec![var1_id]))).unwrap();
+    let substep2 = session.step_store_mut().insert_new(|id| Ok(Step::new(id, None, vec![var2_id]))).unwrap();
+    push_substep(&root_step_id, substep1, session.step_store_mut());
+    push_substep(&root_step_id, substep2, session.step_store_mut());
+    let test_action_id = session.action_store_mut().insert_new(|id| Ok(MockAction::new(id).boxed())).unwrap();
+    session.set_action_for_step(test_action_id, None).unwrap();
 
-    let var_output1_id = session.test_new_stringvar();
-    let var_input2_id = session.test_new_stringvar();
-    let var_output2_id = session.test_new_stringvar();
+    let mut both_outputs = StateData::new();
+    for var_id in [var1_id, var2_id].iter() {
+      both_outputs.insert(session.var_store().get(var_id).unwrap(), StringValue::try_new("val").unwrap().boxed()).unwrap();
+    }
 
-    let root_step_id = session.step_store.insert_new_named(
-      "root_step", |id| {
-        Ok(Step::new(
-          id,
-          Some(vec![var_input2_id.clone()]),
-          vec![var_output1_id.clone(), var_output2_id.clone()]))
-      })
-      .unwrap();
-    session.push_root_substep(root_step_id);
-    
-    let substep1_id = session.step_store_mut().insert_new_named("SubStep 1",
-      |id| Ok(Step::new(id, None, vec![var_output1_id.clone()])))
+    // always: runs even though the outputs are there
+    assert!(matches!(session.advance(None), Ok(AdvanceBlockedOn::ActionStartWith(_, _, _))));
+    assert!(matches!(session.advance(Some((&substep1, both_outputs.clone()))), Ok(AdvanceBlockedOn::ActionStartWith(_, _, _))));
+    assert_eq!(session.current_step(), Ok(&substep2));
+
+    // until valid: skipped when the outputs are there
+    session.step_store_mut().get_mut(&substep2).unwrap().set_visit_policy(VisitPolicy::UntilValid);
+    session.reset(false);
+    assert!(matches!(session.advance(None), Ok(AdvanceBlockedOn::ActionStartWith(_, _, _))));
+    assert_eq!(session.advance(Some((&substep1, both_outputs))), Ok(AdvanceBlockedOn::FinishedAdvancing));
+
+    // once: skipped after the first visit when the flow goes back
+    session.step_store_mut().get_mut(&substep2).unwrap().set_visit_policy(VisitPolicy::Once);
+    session.reset_to(&substep1).unwrap();
+    let output = step_str_output(&session, &var1_id, "new val");
+    assert_eq!(session.advance(Some((&output.0, output.1))), Ok(AdvanceBlockedOn::FinishedAdvancing));
+
+    // but not after a reset
+    session.reset(true);
+    let output = step_str_output(&session, &var1_id, "val");
+    assert!(matches!(session.advance(None), Ok(AdvanceBlockedOn::ActionStartWith(_, _, _))));
+    assert!(matches!(session.advance(Some((&substep1, output.1))), Ok(AdvanceBlockedOn::ActionStartWith(_, _, _))));
+    assert_eq!(session.current_step(), Ok(&substep2));
+  }
+
+  #[test]
+  fn cannot_fulfill_details() {
+    let (mut session, root_step_id) = Session::test_new();
+    let var_id = session.test_new_stringvar();
+    let substep = session.step_store_mut().insert_new(|id| Ok(Step::new(id, None, vec![var_id]))).unwrap();
+    push_substep(&root_step_id, substep, session.step_store_mut());
+
+    let mut new_delayed_action = || session.action_store_mut().insert_new(|id| Ok(SetDataAction::new(id, StateData::new(), 5).boxed())).unwrap();
+    let specific_action = new_delayed_action();
+    let generic_action = new_delayed_action();
+    session.set_action_for_step(specific_action, Some(&substep)).unwrap();
+    session.set_action_for_step(generic_action, None).unwrap();
+
+    assert_eq!(
+      session.advance(None),
+      Ok(AdvanceBlockedOn::ActionCannotFulfill(CannotFulfillDetails {
+        step_id: substep,
+        attempted_actions: vec![specific_action, generic_action],
+        advance_error: None,
+      })));
+
+    // next time it can't get past the step because of the missing output
+    assert_eq!(
+      session.advance(None),
+      Ok(AdvanceBlockedOn::ActionCannotFulfill(CannotFulfillDetails {
+        step_id: substep,
+        attempted_actions: vec![specific_action, generic_action],
+        advance_error: Some(Error::MissingVars(AdvanceError {
+          step_id: substep,
+          step_name: None,
+          check: TransitionCheck::Exit,
+          missing_vars: vec![(var_id, None)],
+        })),
+      })));
+  }
+
+  #[test]
+  fn action_required_vars() {
+    let (mut session, root_step_id) = Session::test_new();
+    let var_name_id = session.test_new_stringvar();
+    let var_greeting_id = session.test_new_stringvar();
+    let substep1 = session.step_store_mut().insert_new(|id| Ok(Step::new(id, None, vec![var_name_id]))).unwrap();
+    let substep2 = session.step_store_mut().insert_new(|id| Ok(Step::new(id, None, vec![var_greeting_id]))).unwrap();
+    push_substep(&root_step_id, substep1, session.step_store_mut());
+    push_substep(&root_step_id, substep2, session.step_store_mut());
+
+    let greet_action = session.action_store_mut().insert_new(|id| Ok(MockAction::new(id).with_required_vars(vec![var_name_id]).boxed())).unwrap();
+    let form_action = session.action_store_mut().insert_new(|id| Ok(MockAction::new(id).boxed())).unwrap();
+    session.set_action_for_step(greet_action, Some(&substep2)).unwrap();
+    session.set_action_for_step(form_action, None).unwrap();
+    assert_eq!(session.undeclared_action_vars(), vec![UndeclaredActionVar { step_id: substep2, action_id: greet_action, var_id: var_name_id }]);
+
+    // the action can see the var even though the step doesn't declare it
+    assert!(matches!(session.advance(None), Ok(AdvanceBlockedOn::ActionStartWith(action_id, _, _)) if action_id == form_action));
+    let output = step_str_output(&session, &var_name_id, "Jane");
+    assert!(matches!(session.advance(Some((&output.0, output.1))), Ok(AdvanceBlockedOn::ActionStartWith(action_id, _, _)) if action_id == greet_action));
+    let greet_call = &session.action_store().get(&greet_action).unwrap().downcast::<MockAction>().unwrap().calls()[0];
+    assert_eq!(greet_call.step_data.get(&var_name_id).unwrap().get_val(), &StringValue::try_new("Jane").unwrap().boxed());
+  }
+
+  #[test]
+  fn finish_actions() {
+    let (mut session, root_step_id) = Session::test_new();
+    let var_id = session.test_new_stringvar();
+    let step_id = session.step_store_mut().insert_new(|id| Ok(Step::new(id, None, vec![var_id]))).unwrap();
+    push_substep(&root_step_id, step_id, session.step_store_mut());
+    let webhook = session.action_store_mut().insert_new(|id| Ok(MockAction::new_finished(id).with_failures(1).boxed())).unwrap();
+    let flaky = session.action_store_mut().insert_new(|id| Ok(MockAction::new_finished(id).with_failures(5).boxed())).unwrap();
+    session.add_finish_action(webhook, 3).unwrap();
+    session.add_finish_action(flaky, 2).unwrap();
+    assert!(session.add_finish_action(test_id!(ActionId), 1).is_err());
+
+    // nothing runs until the flow is finished
+    assert!(session.advance(None).is_err());
+    assert!(session.finish_actions().iter().all(|finish_action| finish_action.attempts == 0));
+
+    let output = step_str_output(&session, &var_id, "done");
+    assert_eq!(session.advance(Some((&output.0, output.1))), Ok(AdvanceBlockedOn::FinishedAdvancing));
+    let statuses = session.finish_actions().iter().map(|finish_action| (finish_action.attempts, finish_action.status.clone())).collect::<Vec<_>>();
+    assert_eq!(statuses, vec![(1, FinishActionStatus::Failed(Some(Error::Other))), (1, FinishActionStatus::Failed(Some(Error::Other)))]);
+
+    // retried until they succeed or run out of attempts
+    for _ in 0..3 {
+      assert_eq!(session.advance(None), Ok(AdvanceBlockedOn::FinishedAdvancing));
+    }
+    let statuses = session.finish_actions().iter().map(|finish_action| (finish_action.attempts, finish_action.status.clone())).collect::<Vec<_>>();
+    assert_eq!(statuses, vec![(2, FinishActionStatus::Succeeded), (2, FinishActionStatus::Failed(Some(Error::Other)))]);
+
+    // every run is in the history
+    let runs = session.history().iter().filter(|event| matches!(event.kind, SessionEventKind::FinishActionRan { .. })).count();
+    assert_eq!(runs, 4);
+
+    // reset lets them run again
+    session.reset(true);
+    assert_eq!(session.finish_actions()[0].status, FinishActionStatus::Pending);
+  }
+
+  #[test]
+  fn finish_action_results() {
+    let (mut session, _root_step_id) = Session::test_new();
+    let flag = session.var_store_mut().insert_new(|id| Ok(BoolVar::new(id).boxed())).unwrap();
+    let mut wrong_type = StateData::new();
+    let flag_as_string: Box<dyn stepflow_data::var::Var + Send + Sync> = StringVar::new(flag).boxed();
+    wrong_type.insert(&flag_as_string, StringValue::try_new("yes").unwrap().boxed()).unwrap();
+    let waits = session.action_store_mut().insert_new(|id| Ok(MockAction::new(id).boxed())).unwrap();
+    let bad_output = session.action_store_mut().insert_new(|id| Ok(MockAction::new(id).with_default(ActionResult::Finished(wrong_type.clone())).boxed())).unwrap();
+    session.add_finish_action(waits, 1).unwrap();
+    session.add_finish_action(bad_output, 1).unwrap();
+
+    // waiting on the user can't succeed and bad values aren't merged
+    assert_eq!(session.advance(None), Ok(AdvanceBlockedOn::FinishedAdvancing));
+    assert_eq!(session.finish_actions()[0].status, FinishActionStatus::Failed(None));
+    assert_eq!(
+      session.finish_actions()[1].status,
+      FinishActionStatus::Failed(Some(Error::InvalidVars(InvalidVars::new(vec![(flag, InvalidValue::WrongType)].into_iter().collect())))));
+    assert!(!session.state_data().contains(&flag));
+  }
+
+  #[test]
+  fn completion_keys() {
+    fn session_with_email_action() -> (Session, ActionId) {
+      let (mut session, root_step_id) = Session::test_new();
+      add_new_simple_substep(&root_step_id, session.step_store_mut());
+      add_new_simple_substep(&root_step_id, session.step_store_mut());
+      let action_id = session.action_store_mut().insert_new(|id| Ok(MockAction::new_finished(id).with_completion_key("email-sent").boxed())).unwrap();
+      session.set_action_for_step(action_id, None).unwrap();
+      (session, action_id)
+    }
+    fn starts(session: &Session, action_id: &ActionId) -> usize {
+      session.action_store().get(action_id).unwrap().downcast::<MockAction>().unwrap().calls().len()
+    }
+
+    // the second step has the same key so it doesn't send again
+    let (mut session, action_id) = session_with_email_action();
+    assert_eq!(session.advance(None), Ok(AdvanceBlockedOn::FinishedAdvancing));
+    assert_eq!(starts(&session, &action_id), 1);
+
+    // restored keys are already done
+    let keys = session.completion_keys().clone();
+    let (mut restored, restored_action_id) = session_with_email_action();
+    keys.into_iter().for_each(|(key, outputs)| restored.add_completion_key(key, outputs));
+    assert_eq!(restored.advance(None), Ok(AdvanceBlockedOn::FinishedAdvancing));
+    assert_eq!(starts(&restored, &restored_action_id), 0);
+
+    // a reset starts over
+    session.reset(true);
+    assert!(session.completion_keys().is_empty());
+    assert_eq!(session.advance(None), Ok(AdvanceBlockedOn::FinishedAdvancing));
+    assert_eq!(starts(&session, &action_id), 2);
+  }
+
+  #[test]
+  fn completion_key_outputs() {
+    let (mut session, root_step_id) = Session::test_new();
+    let var_id = session.test_new_stringvar();
+    let substep = session.step_store_mut().insert_new(|id| Ok(Step::new(id, None, vec![var_id]))).unwrap();
+    push_substep(&root_step_id, substep, session.step_store_mut());
+    let mut outputs = StateData::new();
+    outputs.insert(session.var_store().get(&var_id).unwrap(), StringValue::try_new("sent").unwrap().boxed()).unwrap();
+    let action_id = session.action_store_mut()
+      .insert_new(|id| Ok(MockAction::new(id).with_default(ActionResult::Finished(outputs.clone())).with_completion_key("email-sent").boxed()))
       .unwrap();
-    let substep2_id = session.step_store_mut().insert_new_named("SubStep 2",
-      |id| Ok(Step::new(id, Some(vec![var_input2_id.clone()]), vec![var_output2_id.clone()])))
+    session.set_action_for_step(action_id, Some(&substep)).unwrap();
+
+    // done keys finish with the outputs they had
+    session.add_completion_key("email-sent".to_owned(), outputs.clone());
+    assert_eq!(session.advance(None), Ok(AdvanceBlockedOn::FinishedAdvancing));
+    assert_eq!(session.state_data().get(&var_id).map(|val| val.get_val().clone()), outputs.get(&var_id).map(|val| val.get_val().clone()));
+
+    // and can't fulfill without them instead of finishing over and over, leaving the step blocked
+    let (mut session, root_step_id) = Session::test_new();
+    let var_id = session.test_new_stringvar();
+    let substep = session.step_store_mut().insert_new(|id| Ok(Step::new(id, None, vec![var_id]))).unwrap();
+    push_substep(&root_step_id, substep, session.step_store_mut());
+    let action_id = session.action_store_mut().insert_new(|id| Ok(MockAction::new_finished(id).with_completion_key("email-sent").boxed())).unwrap();
+    session.set_action_for_step(action_id, Some(&substep)).unwrap();
+    session.add_completion_key("email-sent".to_owned(), StateData::new());
+    assert!(matches!(session.advance(None), Err(Error::MissingVars(_))));
+  }
+
+  #[test]
+  fn action_candidates() {
+    let (mut session, root_step_id) = Session::test_new();
+    let var_phone = session.test_new_stringvar();
+    let var_code = session.test_new_stringvar();
+    let substep = session.step_store_mut().insert_new(|id| Ok(Step::new(id, None, vec![var_code]))).unwrap();
+    push_substep(&root_step_id, substep, session.step_store_mut());
+
+    let sms_action = session.action_store_mut().insert_new(|id| Ok(MockAction::new(id).boxed())).unwrap();
+    let email_action = session.action_store_mut().insert_new(|id| Ok(MockAction::new(id).boxed())).unwrap();
+    session.add_action_candidate(&substep, sms_action, Condition::IsSet(var_phone)).unwrap();
+    session.add_action_candidate(&substep, email_action, Condition::All(vec![])).unwrap();
+
+    // no phone so email
+    assert!(matches!(session.advance(None), Ok(AdvanceBlockedOn::ActionStartWith(action_id, _, _)) if action_id == email_action));
+
+    // phone so sms
+    let mut state_data = StateData::new();
+    state_data.insert(session.var_store().get(&var_phone).unwrap(), StringValue::try_new("555-1234").unwrap().boxed()).unwrap();
+    session.seed_data(state_data).unwrap();
+    assert!(matches!(session.advance(None), Ok(AdvanceBlockedOn::ActionStartWith(action_id, _, _)) if action_id == sms_action));
+  }
+
+  #[test]
+  fn fallback_chain() {
+    let (mut session, root_step_id) = Session::test_new();
+    let var_code = session.test_new_stringvar();
+    let substep = session.step_store_mut().insert_new(|id| Ok(Step::new(id, None, vec![var_code]))).unwrap();
+    push_substep(&root_step_id, substep, session.step_store_mut());
+
+    let primary = session.action_store_mut()
+      .insert_new(|id| Ok(MockAction::new(id).with_failures(1).with_result(Ok(ActionResult::CannotFulfill)).with_result(Ok(ActionResult::CannotFulfill)).boxed()))
       .unwrap();
+    let backup = session.action_store_mut().insert_new(|id| Ok(MockAction::new(id).boxed())).unwrap();
+    let missing = test_id!(ActionId);
+    assert_eq!(session.set_fallback_chain(substep, vec![(primary, 2), (missing, 1)]), Err(Error::ActionId(IdError::IdMissing(missing))));
+    session.set_fallback_chain(substep, vec![(primary, 2), (backup, 1)]).unwrap();
+    let attempts = |session: &Session| session.fallback_chain(&substep).iter().map(|fallback| fallback.attempts).collect::<Vec<_>>();
+
+    // failures don't count as attempts
+    assert!(session.advance(None).is_err());
+    assert_eq!(attempts(&session), vec![0, 0]);
+
+    // each time it can't fulfill counts and the next one is tried right away
+    assert!(matches!(session.advance(None), Ok(AdvanceBlockedOn::ActionStartWith(action_id, _, _)) if action_id == backup));
+    assert!(matches!(session.advance(None), Ok(AdvanceBlockedOn::ActionStartWith(action_id, _, _)) if action_id == backup));
+    assert_eq!(attempts(&session), vec![2, 0]);
+
+    // showing the backup's form again doesn't use it up
+    assert!(matches!(session.advance(None), Ok(AdvanceBlockedOn::ActionStartWith(action_id, _, _)) if action_id == backup));
+    assert_eq!(attempts(&session), vec![2, 0]);
+
+    // attempts start over once the step is exited
+    let output = step_str_output(&session, &var_code, "123456");
+    assert_eq!(session.advance(Some((&output.0, output.1))), Ok(AdvanceBlockedOn::FinishedAdvancing));
+    assert_eq!(attempts(&session), vec![0, 0]);
+
+    session.reset(true);
+    assert!(matches!(session.advance(None), Ok(AdvanceBlockedOn::ActionStartWith(action_id, _, _)) if action_id == primary));
+  }
 
-    let root_step = session.step_store_mut().get_mut(&root_step_id).unwrap();
-    root_step.push_substep(substep1_id.clone());
-    root_step.push_substep(substep2_id.clone());
-    
-    assert_eq!(session.try_enter_next_step(None), Err(Error::VarId(IdError::IdMissing(var_input2_id.clone()))));    // start without proper input
+  #[test]
+  fn action_chain() {
+    let (mut session, root_step_id) = Session::test_new();
+    let var_code = session.test_new_stringvar();
+    let substep = session.step_store_mut().insert_new(|id| Ok(Step::new(id, None, vec![var_code]))).unwrap();
+    push_substep(&root_step_id, substep, session.step_store_mut());
+
+    let cache = session.action_store_mut().insert_new(|id| Ok(MockAction::new(id).with_default(ActionResult::CannotFulfill).boxed())).unwrap();
+    let lookup = session.action_store_mut().insert_new(|id| Ok(MockAction::new(id).with_default(ActionResult::CannotFulfill).boxed())).unwrap();
+    let form = session.action_store_mut().insert_new(|id| Ok(MockAction::new(id).boxed())).unwrap();
+    let missing = test_id!(ActionId);
+    assert_eq!(session.push_action_for_step(&substep, missing), Err(Error::ActionId(IdError::IdMissing(missing))));
+    for action_id in [cache, lookup, form] {
+      session.push_action_for_step(&substep, action_id).unwrap();
+    }
+    assert_eq!(session.fallback_chain(&substep).iter().map(|fallback| fallback.action_id).collect::<Vec<_>>(), vec![cache, lookup, form]);
+
+    // each advance starts over at the front of the chain
+    let starts = |session: &Session, action_id: &ActionId| {
+      session.action_store().get(action_id).unwrap().downcast::<MockAction>().unwrap().calls().len()
+    };
+    for advances in 1..=2 {
+      assert!(matches!(session.advance(None), Ok(AdvanceBlockedOn::ActionStartWith(action_id, _, _)) if action_id == form));
+      assert_eq!(starts(&session, &cache), advances);
+      assert_eq!(starts(&session, &lookup), advances);
+    }
+  }
 
-    // go to substep1
-    let output1 = step_str_output(&session, &var_input2_id, "input2");
-    assert_eq!(session.try_enter_next_step(Some((&output1.0, output1.1))), Ok(Some(substep1_id.clone())));  // start without proper input
+  #[test]
+  fn replace_action() {
+    let (mut session, root_step_id) = Session::test_new();
+    let var_code = session.test_new_stringvar();
+    let substep = session.step_store_mut().insert_new(|id| Ok(Step::new(id, None, vec![var_code]))).unwrap();
+    push_substep(&root_step_id, substep, session.step_store_mut());
+
+    let stub = session.action_store_mut().insert_new(|id| Ok(MockAction::new(id).boxed())).unwrap();
+    let real = session.action_store_mut().insert_new(|id| Ok(MockAction::new(id).boxed())).unwrap();
+    let general = session.action_store_mut().insert_new(|id| Ok(MockAction::new(id).boxed())).unwrap();
+    session.set_action_for_step(stub, Some(&substep)).unwrap();
+    session.set_action_for_step(general, None).unwrap();
+    assert_eq!(session.set_action_for_step(real, Some(&substep)), Err(Error::StepId(IdError::IdAlreadyExists(substep))));
+
+    assert_eq!(session.replace_action_for_step(real, Some(&substep)), Ok(Some(stub)));
+    assert!(matches!(session.advance(None), Ok(AdvanceBlockedOn::ActionStartWith(action_id, _, _)) if action_id == real));
+
+    // falls back to the general action once cleared
+    assert_eq!(session.clear_action_for_step(Some(&substep)), Some(real));
+    assert_eq!(session.clear_action_for_step(Some(&substep)), None);
+    assert!(matches!(session.advance(None), Ok(AdvanceBlockedOn::ActionStartWith(action_id, _, _)) if action_id == general));
+    assert_eq!(session.clear_action_for_step(None), Some(general));
+    assert_eq!(session.replace_action_for_step(stub, Some(&substep)), Ok(None));
+  }
 
-    // go to substep2
-    assert_eq!(session.try_enter_next_step(None), Err(Error::VarId(IdError::IdMissing(var_output1_id.clone()))));  // didn't add output
-    let output2 = step_str_output(&session, &var_output1_id, "output1");
-    assert_eq!(session.try_enter_next_step(Some((&output2.0, output2.1))), Ok(Some(substep2_id.clone())));
+  #[test]
+  fn descriptions() {
+    let (mut session, _root_step_id) = Session::test_new();
+    let var_id = session.test_new_stringvar();
+    let action_id = session.action_store_mut().insert_new(|id| Ok(MockAction::new(id).boxed())).unwrap();
 
-    // done with states but can't leave root without the output from substep 2
-    assert_eq!(session.try_enter_next_step(None), Err(Error::VarId(IdError::IdMissing(var_output2_id.clone()))));
-    let output3 = step_str_output(&session, &var_output2_id, "output2");
-    assert_eq!(session.try_enter_next_step(Some((&output3.0, output3.1))), Ok(None));
-    
-    // try it again to check we're still done advancing
-    assert_eq!(session.try_enter_next_step(None), Ok(None));
+    session.set_var_description(&var_id, "Where we send your receipt").unwrap();
+    session.set_action_description(&action_id, "Emails a login code").unwrap();
+    assert_eq!(session.var_description(&var_id), Some("Where we send your receipt"));
+    assert_eq!(session.action_description(&action_id), Some("Emails a login code"));
+
+    let missing = test_id!(VarId);
+    assert_eq!(session.set_var_description(&missing, "nope"), Err(Error::VarId(IdError::IdMissing(missing))));
+    assert_eq!(session.var_description(&missing), None);
+  }
+
+  #[test]
+  fn action_types() {
+    let (mut session, root_step_id) = Session::test_new();
+    let var_name = session.test_new_stringvar();
+    let var_agreed = session.var_store_mut().insert_new(|id| Ok(BoolVar::new(id).boxed())).unwrap();
+    let substep = add_new_simple_substep(&root_step_id, session.step_store_mut());
+
+    let mut data = StateData::new();
+    data.insert(session.var_store().get(&var_name).unwrap(), StringValue::try_new("stepflow").unwrap().boxed()).unwrap();
+    let good_action = session.action_store_mut().insert_new(|id| Ok(SetDataAction::new(id, data, 0).boxed())).unwrap();
+    session.set_action_for_step(good_action, Some(&substep)).unwrap();
+
+    // bool var gets a string, i.e. when the data was made for a different definition
+    let mut bad_data = StateData::new();
+    bad_data.insert(&StringVar::new(var_agreed).boxed(), StringValue::try_new("yes").unwrap().boxed()).unwrap();
+    let bad_action = session.action_store_mut().insert_new(|id| Ok(SetDataAction::new(id, bad_data, 0).boxed())).unwrap();
+
+    let mut expected = HashMap::new();
+    expected.insert(var_agreed, InvalidValue::WrongType);
+    let expected = Err(Error::InvalidVars(InvalidVars::new(expected)));
+    assert_eq!(session.bind_action(bad_action, StepMatcher::Tag("any".to_owned())), expected);
+    assert_eq!(session.set_action_for_step(bad_action, None), expected);
+    assert_eq!(session.check_action_types(), expected);
+
+    // email var gets a plain string, which is the same kind of base value
+    let var_email = session.var_store_mut().insert_new(|id| Ok(EmailVar::new(id).boxed())).unwrap();
+    let mut bad_data = StateData::new();
+    bad_data.insert(&StringVar::new(var_email).boxed(), StringValue::try_new("a@b.com").unwrap().boxed()).unwrap();
+    let bad_email_action = session.action_store_mut().insert_new(|id| Ok(SetDataAction::new(id, bad_data, 0).boxed())).unwrap();
+    let expected = Err(Error::InvalidVars(InvalidVars::new(vec![(var_email, InvalidValue::WrongType)].into_iter().collect())));
+    assert_eq!(session.set_action_for_step(bad_email_action, None), expected);
+  }
+
+  #[test]
+  fn environment() {
+    let (mut session, root_step_id) = Session::test_new();
+    let var_id = session.test_new_stringvar();
+    let substep = session.step_store_mut().insert_new(|id| Ok(Step::new(id, None, vec![var_id]))).unwrap();
+    push_substep(&root_step_id, substep, session.step_store_mut());
+    let template = UriEscapedString::already_escaped("{{env.app_base}}/{{step}}".to_owned());
+    let action_id = session.action_store_mut().insert_new(|id| Ok(StringTemplateAction::new(id, template).boxed())).unwrap();
+    session.set_action_for_step(action_id, None).unwrap();
+
+    let mut environment = FlowEnvironment::new();
+    environment.set("app_base", "staging");
+    session.set_environment(environment.clone());
+    assert_eq!(session.environment(), &environment);
+    let result = session.advance(None);
+    assert_eq!(
+      result,
+      Ok(AdvanceBlockedOn::ActionStartWith(action_id, StringValue::try_new(format!("staging/{}", substep)).unwrap().boxed(), session.current_attempt().unwrap())));
+
+    // the action sees changes on its next start
+    environment.set("app_base", "production");
+    session.set_environment(environment.clone());
+    let result = session.advance(None);
+    assert_eq!(
+      result,
+      Ok(AdvanceBlockedOn::ActionStartWith(action_id, StringValue::try_new(format!("production/{}", substep)).unwrap().boxed(), session.current_attempt().unwrap())));
+
+    let mut vault = HashMap::new();
+    vault.insert("api_key".to_owned(), "hunter2".to_owned());
+    session.set_secrets_provider(Arc::new(vault));
+    assert_eq!(session.environment().secret("api_key"), Some(SecretValue::new("hunter2")));
+
+    // changing the environment keeps the provider
+    session.set_environment(environment.clone());
+    assert_eq!(session.environment().get("app_base"), Some("production"));
+    assert_eq!(session.environment().secret("api_key"), Some(SecretValue::new("hunter2")));
+  }
+
+  #[test]
+  fn compensate() {
+    let (mut session, root_step_id) = Session::test_new();
+    let substep1 = add_new_simple_substep(&root_step_id, session.step_store_mut());
+    let substep2 = add_new_simple_substep(&root_step_id, session.step_store_mut());
+    let substep3 = add_new_simple_substep(&root_step_id, session.step_store_mut());
+    let test_action_id = session.action_store_mut().insert_new(|id| Ok(MockAction::new(id).boxed())).unwrap();
+    session.set_action_for_step(test_action_id, None).unwrap();
+
+    session.step_store_mut().get_mut(&substep2).unwrap().set_skip_when(Condition::All(vec![]));
+
+    let undo1 = session.action_store_mut().insert_new(|id| Ok(MockAction::new_finished(id).with_failures(1).boxed())).unwrap();
+    let undo2 = session.action_store_mut().insert_new(|id| Ok(MockAction::new_finished(id).boxed())).unwrap();
+    let undo3 = session.action_store_mut().insert_new(|id| Ok(MockAction::new_finished(id).boxed())).unwrap();
+    session.set_compensation_action(substep1, undo1).unwrap();
+    session.set_compensation_action(substep2, undo2).unwrap();
+    session.set_compensation_action(substep3, undo3).unwrap();
+    assert!(session.set_compensation_action(test_id!(StepId), undo1).is_err());
+
+    // only steps that ran and were exited are compensated, not skipped ones or the current one
+    session.advance(None).unwrap();
+    session.advance(None).unwrap();
+    assert_eq!(session.current_step(), Ok(&substep3));
+    let compensated = session.compensate().into_iter().map(|c| (c.step_id, c.action_id, c.result.is_ok())).collect::<Vec<_>>();
+    assert_eq!(compensated, vec![(substep1, undo1, false)]);
+
+    // failures are retried
+    let compensated = session.compensate().into_iter().map(|c| (c.step_id, c.result)).collect::<Vec<_>>();
+    assert_eq!(compensated, vec![(substep1, Ok(ActionResult::Finished(StateData::new())))]);
+    assert!(session.compensate().is_empty());
+
+    // finishing exits the last step, resetting forgets it
+    session.advance(None).unwrap();
+    assert_eq!(session.to_compensate, vec![substep3, root_step_id]);
+    session.reset(true);
+    assert!(session.compensate().is_empty());
+  }
+
+  #[test]
+  fn peek_next() {
+    let (mut session, root_step_id) = Session::test_new();
+    let email = session.test_new_stringvar();
+    let code = session.test_new_stringvar();
+    let email_step = session.step_store_mut().insert_new(|id| Ok(Step::new(id, None, vec![email]))).unwrap();
+    let verify_step = session.step_store_mut().insert_new(|id| Ok(Step::new(id, Some(vec![email]), vec![code]))).unwrap();
+    push_substep(&root_step_id, email_step, session.step_store_mut());
+    push_substep(&root_step_id, verify_step, session.step_store_mut());
+    let test_action_id = session.action_store_mut().insert_new(|id| Ok(MockAction::new(id).boxed())).unwrap();
+    session.set_action_for_step(test_action_id, None).unwrap();
+
+    assert_eq!(session.peek_next(), NextStepPreview { step_id: Some(email_step), missing_vars: vec![] });
+    session.advance(None).unwrap();
+
+    // peeking doesn't move anything
+    let preview = NextStepPreview { step_id: Some(verify_step), missing_vars: vec![email] };
+    assert_eq!(session.peek_next(), preview);
+    assert_eq!(session.peek_next(), preview);
+    assert_eq!(session.current_step(), Ok(&email_step));
+
+    let output = step_str_output(&session, &email, "a@b.c");
+    session.advance(Some((&output.0, output.1))).unwrap();
+    assert_eq!(session.peek_next(), NextStepPreview { step_id: None, missing_vars: vec![code] });
+
+    // same with another scheduler
+    session.set_scheduler(|root| Box::new(PriorityTraversal::new(root, StepScore::UnblocksMost)));
+    session.reset(false);
+    assert_eq!(session.peek_next(), NextStepPreview { step_id: Some(email_step), missing_vars: vec![] });
+    session.advance(None).unwrap();
+    assert_eq!(session.peek_next(), NextStepPreview { step_id: Some(verify_step), missing_vars: vec![email] });
+  }
+
+  #[test]
+  fn blocking_requirements() {
+    let (mut session, root_step_id) = Session::test_new();
+    let first = session.var_store_mut().insert_new_named("first", |id| Ok(StringVar::new(id).boxed())).unwrap();
+    let last = session.var_store_mut().insert_new_named("last", |id| Ok(StringVar::new(id).boxed())).unwrap();
+    let email = session.var_store_mut().insert_new_named("email", |id| Ok(StringVar::new(id).boxed())).unwrap();
+    let name_step = session.step_store_mut().insert_new(|id| Ok(Step::new(id, None, vec![first, last]))).unwrap();
+    let email_step = session.step_store_mut().insert_new(|id| Ok(Step::new(id, Some(vec![first, email]), vec![]))).unwrap();
+    push_substep(&root_step_id, name_step, session.step_store_mut());
+    push_substep(&root_step_id, email_step, session.step_store_mut());
+    let test_action_id = session.action_store_mut().insert_new(|id| Ok(MockAction::new(id).boxed())).unwrap();
+    session.set_action_for_step(test_action_id, None).unwrap();
+    session.advance(None).unwrap();
+
+    let blocking = session.blocking_requirements().into_iter()
+      .map(|requirement| (requirement.step_id, requirement.var_name.unwrap(), requirement.check))
+      .collect::<Vec<_>>();
+    assert_eq!(blocking, vec![
+      (name_step, "first".to_owned(), TransitionCheck::Exit),
+      (name_step, "last".to_owned(), TransitionCheck::Exit),
+      (email_step, "first".to_owned(), TransitionCheck::Enter),
+      (email_step, "email".to_owned(), TransitionCheck::Enter),
+    ]);
+
+    let output = step_str_output(&session, &first, "Jane");
+    assert!(session.advance(Some((&output.0, output.1))).is_ok());
+    let blocking = session.blocking_requirements().into_iter().map(|requirement| requirement.var_id).collect::<Vec<_>>();
+    assert_eq!(blocking, vec![last, email]);
+  }
+
+  #[test]
+  fn completion_blocker() {
+    let (mut session, root_step_id) = Session::test_new();
+    let first = session.var_store_mut().insert_new_named("first", |id| Ok(StringVar::new(id).boxed())).unwrap();
+    let email = session.var_store_mut().insert_new_named("email", |id| Ok(StringVar::new(id).boxed())).unwrap();
+    let name_step = session.step_store_mut().insert_new(|id| Ok(Step::new(id, None, vec![first]))).unwrap();
+    let email_step = session.step_store_mut().insert_new(|id| Ok(Step::new(id, Some(vec![first, email]), vec![]))).unwrap();
+    push_substep(&root_step_id, name_step, session.step_store_mut());
+    push_substep(&root_step_id, email_step, session.step_store_mut());
+
+    // no action to ask for the name
+    let blocker = session.completion_blocker(None).unwrap();
+    assert_eq!((blocker.step_id, blocker.var_name.unwrap(), blocker.check), (name_step, "first".to_owned(), TransitionCheck::Exit));
+
+    // nothing sets the email
+    let action_id = session.action_store_mut().insert_new(|id| Ok(MockAction::new(id).boxed())).unwrap();
+    session.set_action_for_step(action_id, Some(&name_step)).unwrap();
+    let blocker = session.completion_blocker(None).unwrap();
+    assert_eq!((blocker.step_id, blocker.var_id, blocker.check), (email_step, email, TransitionCheck::Enter));
+
+    // seeded email
+    let mut seed = StateData::new();
+    seed.insert(session.var_store().get(&email).unwrap(), StringValue::try_new("a@b.com").unwrap().boxed()).unwrap();
+    assert_eq!(session.completion_blocker(Some(&seed)), None);
+  }
+
+  #[test]
+  fn advance_traced() {
+    let (mut session, root_step_id) = Session::test_new();
+    let first = session.test_new_stringvar();
+    let email = session.test_new_stringvar();
+    let name_step = session.step_store_mut().insert_new(|id| Ok(Step::new(id, None, vec![first]))).unwrap();
+    let email_step = session.step_store_mut().insert_new(|id| Ok(Step::new(id, Some(vec![first]), vec![email]))).unwrap();
+    push_substep(&root_step_id, name_step, session.step_store_mut());
+    push_substep(&root_step_id, email_step, session.step_store_mut());
+    let action_id = session.action_store_mut().insert_new(|id| Ok(MockAction::new(id).boxed())).unwrap();
+    session.set_action_for_step(action_id, None).unwrap();
+
+    let (result, trace) = session.advance_traced(None);
+    assert!(matches!(result, Ok(AdvanceBlockedOn::ActionStartWith(_, _, _))));
+    assert_eq!(trace, vec![
+      TraceEntry::StepTried(root_step_id),
+      TraceEntry::StepTried(name_step),
+      TraceEntry::StepEntered(name_step),
+      TraceEntry::ActionStarted { step_id: name_step, action_id, outcome: ActionOutcome::StartWith },
+    ]);
+
+    // the name is still missing so the action is asked again
+    let output = step_str_output(&session, &email, "a@b.com");
+    let (_, trace) = session.advance_traced(Some((&output.0, output.1)));
+    assert_eq!(trace[0], TraceEntry::GuardFailed { step_id: name_step, check: TransitionCheck::Exit, missing: vec![first] });
+    assert!(matches!(trace[1], TraceEntry::ActionStarted { step_id, .. } if step_id == name_step));
+
+    let output = step_str_output(&session, &first, "Jane");
+    let (_, trace) = session.advance_traced(Some((&output.0, output.1)));
+    assert_eq!(&trace[..2], &[TraceEntry::StepTried(email_step), TraceEntry::StepEntered(email_step)]);
+
+    // only when asked for
+    session.advance(None).unwrap();
+    assert_eq!(session.trace, None);
+  }
+
+  #[test]
+  fn step_validators() {
+    let (mut session, root_step_id) = Session::test_new();
+    let password = session.test_new_stringvar();
+    let confirm = session.test_new_stringvar();
+    let mut step = Step::new(test_id!(StepId), None, vec![password, confirm]);
+    step.add_validator(move |step_data: &StateDataFiltered| {
+      if step_data.get(&password).map(|val| val.get_val()) == step_data.get(&confirm).map(|val| val.get_val()) {
+        return Ok(());
+      }
+      Err(InvalidVars::new(vec![(confirm, InvalidValue::WrongValue)].into_iter().collect()))
+    });
+    let step_id = session.step_store_mut().register(step).unwrap();
+    push_substep(&root_step_id, step_id, session.step_store_mut());
+    let action_id = session.action_store_mut().insert_new(|id| Ok(MockAction::new(id).boxed())).unwrap();
+    session.set_action_for_step(action_id, None).unwrap();
+    session.advance(None).unwrap();
+
+    let passwords = |session: &Session, confirmed: &'static str| {
+      let (step_id, mut state_data) = step_str_output(session, &password, "hunter2");
+      state_data.insert(session.var_store().get(&confirm).unwrap(), StringValue::try_new(confirmed).unwrap().boxed()).unwrap();
+      (step_id, state_data)
+    };
+    // rejected data isn't kept and the form is shown again with the errors
+    let output = passwords(&session, "hunter3");
+    assert!(matches!(session.advance(Some((&output.0, output.1))), Ok(AdvanceBlockedOn::ActionStartWith(id, _, _)) if id == action_id));
+    assert_eq!(session.rejected_vars(), Some(&InvalidVars::new(vec![(confirm, InvalidValue::WrongValue)].into_iter().collect())));
+    assert_eq!(session.current_step(), Ok(&step_id));
+    assert!(!session.state_data().contains(&password));
+    assert!(matches!(session.advance(None), Ok(AdvanceBlockedOn::ActionStartWith(_, _, _))));
+    assert_eq!(session.rejected_vars(), None);
+
+    let output = passwords(&session, "hunter2");
+    assert_eq!(session.advance(Some((&output.0, output.1))), Ok(AdvanceBlockedOn::FinishedAdvancing));
+  }
+
+  #[test]
+  fn rejected_output_keeps_progress() {
+    let (mut session, root_step_id) = Session::test_new();
+    let email = session.test_new_stringvar();
+    let verified = session.test_new_stringvar();
+    let nickname = session.test_new_stringvar();
+    session.var_dependencies_mut().add(verified, email);
+    let email_step = session.step_store_mut().insert_new(|id| Ok(Step::new(id, None, vec![email, verified]))).unwrap();
+    let mut profile_step = Step::new(test_id!(StepId), Some(vec![email]), vec![email, nickname]);
+    profile_step.add_validator(move |step_data: &StateDataFiltered| {
+      if step_data.get(&nickname).and_then(|val| val.get_val().downcast::<StringValue>()).map(|val| val.val()) == Some("admin") {
+        return Err(InvalidVars::new(vec![(nickname, InvalidValue::WrongValue)].into_iter().collect()));
+      }
+      Ok(())
+    });
+    let profile_step = session.step_store_mut().register(profile_step).unwrap();
+    push_substep(&root_step_id, email_step, session.step_store_mut());
+    push_substep(&root_step_id, profile_step, session.step_store_mut());
+    session.on_transition(|_transition| ());
+    let interner_strings = |session: &Session| session.string_interner().map(|interner| interner.len());
+    session.set_string_interning(true);
+    let action_id = session.action_store_mut().insert_new(|id| Ok(MockAction::new(id).boxed())).unwrap();
+    session.set_action_for_step(action_id, None).unwrap();
+    session.advance(None).unwrap();
+
+    let (step_id, mut output) = step_str_output(&session, &email, "a@b.com");
+    output.insert(session.var_store().get(&verified).unwrap(), StringValue::try_new("yes").unwrap().boxed()).unwrap();
+    session.advance(Some((&step_id, output))).unwrap();
+    assert_eq!(session.current_step(), Ok(&profile_step));
+    let before = (session.state_data().clone(), session.merged_since_transition.clone(), interner_strings(&session));
+
+    // changing the email would have cleared verified and gone back to the completed email step
+    let (step_id, mut output) = step_str_output(&session, &email, "c@d.com");
+    output.insert(session.var_store().get(&nickname).unwrap(), StringValue::try_new("admin").unwrap().boxed()).unwrap();
+    assert!(matches!(session.advance(Some((&step_id, output))), Ok(AdvanceBlockedOn::ActionStartWith(_, _, _))));
+    assert_eq!(session.rejected_vars(), Some(&InvalidVars::new(vec![(nickname, InvalidValue::WrongValue)].into_iter().collect())));
+    assert_eq!(session.current_step(), Ok(&profile_step));
+    assert_eq!((session.state_data().clone(), session.merged_since_transition.clone(), interner_strings(&session)), before);
+  }
+
+  #[test]
+  fn attempts() {
+    let (mut session, root_step_id) = Session::test_new();
+    let name = session.test_new_stringvar();
+    let email = session.test_new_stringvar();
+    let name_step = session.step_store_mut().insert_new(|id| Ok(Step::new(id, None, vec![name]))).unwrap();
+    let email_step = session.step_store_mut().insert_new(|id| Ok(Step::new(id, None, vec![email]))).unwrap();
+    push_substep(&root_step_id, name_step, session.step_store_mut());
+    push_substep(&root_step_id, email_step, session.step_store_mut());
+    let action_id = session.action_store_mut().insert_new(|id| Ok(MockAction::new(id).boxed())).unwrap();
+    session.set_action_for_step(action_id, None).unwrap();
+    assert_eq!(session.current_attempt(), None);
+
+    // same as the history
+    let name_attempt = match session.advance(None) {
+      Ok(AdvanceBlockedOn::ActionStartWith(_, _, attempt_id)) => attempt_id,
+      result => panic!("Not blocked on an action: {:?}", result),
+    };
+    assert_eq!(session.current_attempt(), Some(name_attempt));
+    assert_eq!(session.current_action(), Some(action_id));
+    let event = session.history().iter().find(|event| event.seq == name_attempt.val()).unwrap();
+    assert!(matches!(event.kind, SessionEventKind::ActionStarted { step_id, .. } if step_id == name_step));
+
+    // showing the form again is the same attempt
+    let history_len = session.history().len();
+    assert!(matches!(session.advance(None), Ok(AdvanceBlockedOn::ActionStartWith(_, _, attempt_id)) if attempt_id == name_attempt));
+    assert_eq!(session.history().len(), history_len);
+
+    // the same submission twice only advances once
+    let output = step_str_output(&session, &name, "Jane");
+    assert!(session.advance_attempt(name_attempt, Some((&output.0, output.1.clone()))).is_ok());
+    let email_attempt = session.current_attempt().unwrap();
+    assert_ne!(email_attempt, name_attempt);
+    assert_eq!(session.advance_attempt(name_attempt, Some((&output.0, output.1))), Err(Error::AttemptId(IdError::IdUnexpected(name_attempt))));
+
+    let output = step_str_output(&session, &email, "a@b.com");
+    assert_eq!(session.advance_attempt(email_attempt, Some((&output.0, output.1))), Ok(AdvanceBlockedOn::FinishedAdvancing));
+    assert_eq!(session.current_attempt(), None);
+    assert_eq!(email_attempt.to_string().parse::<AttemptId>(), Ok(email_attempt));
+  }
+
+  #[test]
+  fn invalid_action_data() {
+    let (mut session, root_step_id) = Session::test_new();
+    let name_id = session.test_new_stringvar();
+    let other_id = session.test_new_stringvar();
+    let step_id = session.step_store_mut().insert_new(|id| Ok(Step::new(id, None, vec![name_id]))).unwrap();
+    push_substep(&root_step_id, step_id, session.step_store_mut());
+
+    // a bool for the string var and a var that isn't an output
+    let mut data = StateData::new();
+    data.insert(&BoolVar::new(name_id).boxed(), BoolValue::new(true).boxed()).unwrap();
+    data.insert(session.var_store().get(&other_id).unwrap(), StringValue::try_new("other").unwrap().boxed()).unwrap();
+    let action_id = session.action_store_mut().insert_new(|id| Ok(SetDataAction::new(id, data, 0).boxed())).unwrap();
+
+    // the wrong type is caught when the action is set
+    let expected = vec![(name_id, InvalidValue::WrongType)].into_iter().collect();
+    assert_eq!(session.set_action_for_step(action_id, None), Err(Error::InvalidVars(InvalidVars::new(expected))));
+
+    // the output is only known once it runs
+    let mut data = StateData::new();
+    data.insert(session.var_store().get(&other_id).unwrap(), StringValue::try_new("other").unwrap().boxed()).unwrap();
+    let action_id = session.action_store_mut().insert_new(|id| Ok(SetDataAction::new(id, data, 0).boxed())).unwrap();
+    session.set_action_for_step(action_id, None).unwrap();
+
+    let expected = vec![(other_id, InvalidValue::Unexpected)].into_iter().collect();
+    assert_eq!(session.advance(None), Err(Error::InvalidVars(InvalidVars::new(expected))));
   }
 
   #[test]
@@ -473,7 +3354,7 @@ mod tests {
     let substep3 = add_new_simple_substep(&root_step_id, session.step_store_mut());
 
     let test_action_id = session.action_store_mut().insert_new(
-      |id| Ok(TestAction::new_with_id(id, true).boxed()))
+      |id| Ok(MockAction::new(id).boxed()))
       .unwrap();
     session.set_action_for_step(test_action_id, None).unwrap();
 
@@ -482,7 +3363,7 @@ mod tests {
       match session.advance(None) {
         Ok(advance_result) => {
           match advance_result {
-            AdvanceBlockedOn::ActionStartWith(_, _) => (),
+            AdvanceBlockedOn::ActionStartWith(_, _, _) => (),
             AdvanceBlockedOn::FinishedAdvancing => break,
             _ => panic!("Unexpected advance result: {:?}", advance_result),
           }
@@ -499,6 +3380,152 @@ mod tests {
   }
 
 
+  #[test]
+  fn history() {
+    let (mut session, root_step_id) = Session::test_new();
+    let substep1 = add_new_simple_substep(&root_step_id, session.step_store_mut());
+    let substep2 = add_new_simple_substep(&root_step_id, session.step_store_mut());
+    let test_action_id = session.action_store_mut().insert_new(|id| Ok(MockAction::new(id).boxed())).unwrap();
+    session.set_action_for_step(test_action_id, None).unwrap();
+
+    let started = |step_id| SessionEventKind::ActionStarted { step_id, action_id: test_action_id, outcome: ActionOutcome::StartWith };
+    let before = SystemTime::now();
+    session.advance(None).unwrap();
+    session.advance(None).unwrap();
+    session.advance(None).unwrap();
+    let kinds = session.history().iter().map(|event| event.kind.clone()).collect::<Vec<_>>();
+    assert_eq!(kinds, vec![
+      SessionEventKind::StepEntered(substep1),
+      started(substep1),
+      SessionEventKind::StepExited(substep1),
+      SessionEventKind::StepEntered(substep2),
+      started(substep2),
+      SessionEventKind::StepExited(substep2),
+    ]);
+    assert!(session.history().windows(2).all(|events| events[0].at <= events[1].at));
+    assert!(session.history()[0].at >= before);
+    assert_eq!(session.history().iter().map(|event| event.seq).collect::<Vec<_>>(), (0..6).collect::<Vec<_>>());
+
+    // the sequence keeps counting after the history is taken
+    assert_eq!(session.take_history().len(), 6);
+    assert!(session.history().is_empty());
+    session.reset(true);
+    session.advance(None).unwrap();
+    assert_eq!(session.history()[0].seq, 6);
+
+    // only the newest events are kept
+    session.set_history_limit(Some(1));
+    assert_eq!(session.history().iter().map(|event| event.seq).collect::<Vec<_>>(), vec![7]);
+    session.advance(None).unwrap();
+    assert_eq!(session.history().iter().map(|event| event.seq).collect::<Vec<_>>(), vec![10]);
+  }
+
+  #[test]
+  fn state_at() {
+    let (mut session, root_step_id) = Session::test_new();
+    let name = session.test_new_stringvar();
+    let email = session.test_new_stringvar();
+    let name_step = session.step_store_mut().insert_new(|id| Ok(Step::new(id, None, vec![name]))).unwrap();
+    let email_step = session.step_store_mut().insert_new(|id| Ok(Step::new(id, None, vec![email]))).unwrap();
+    push_substep(&root_step_id, name_step, session.step_store_mut());
+    push_substep(&root_step_id, email_step, session.step_store_mut());
+    let action_id = session.action_store_mut().insert_new(|id| Ok(MockAction::new(id).boxed())).unwrap();
+    session.set_action_for_step(action_id, None).unwrap();
+
+    // not kept until turned on
+    session.advance(None).unwrap();
+    assert_eq!(session.state_at(0), None);
+    session.set_checkpoints(true);
+    let (step_id, output) = step_str_output(&session, &name, "Jane");
+    session.advance(Some((&step_id, output))).unwrap();
+
+    let kinds = session.history().iter().map(|event| event.kind.clone()).collect::<Vec<_>>();
+    assert_eq!(kinds[2..], [
+      SessionEventKind::StepExited(name_step),
+      SessionEventKind::StepEntered(email_step),
+      SessionEventKind::ActionStarted { step_id: email_step, action_id, outcome: ActionOutcome::StartWith },
+    ]);
+    assert_eq!(session.state_at(1), None);
+    let (state_data, current_step) = session.state_at(2).unwrap();
+    assert_eq!(state_data.get(&name).unwrap().get_val().downcast::<StringValue>().unwrap().val(), "Jane");
+    assert_eq!(current_step, Some(&email_step));
+
+    // later changes don't show up in the earlier checkpoints
+    let (step_id, output) = step_str_output(&session, &email, "jane@example.com");
+    session.advance(Some((&step_id, output))).unwrap();
+    assert!(!session.state_at(4).unwrap().0.contains(&email));
+    assert!(session.state_at(session.history().len() - 1).unwrap().0.contains(&email));
+    assert_eq!(session.state_at(session.history().len()), None);
+
+    // trimmed along with the history
+    session.set_history_limit(Some(1));
+    assert_eq!(session.checkpoints.as_ref().map(|checkpoints| checkpoints.len()), Some(1));
+    assert!(session.state_at(0).is_some());
+    session.take_history();
+    assert_eq!(session.checkpoints.as_ref().map(|checkpoints| checkpoints.len()), Some(0));
+  }
+
+  #[test]
+  fn resume_at() {
+    let (mut session, root_step_id) = Session::test_new();
+    let name = session.test_new_stringvar();
+    let email = session.test_new_stringvar();
+    let name_step = session.step_store_mut().insert_new(|id| Ok(Step::new(id, None, vec![name]))).unwrap();
+    let email_step = session.step_store_mut().insert_new(|id| Ok(Step::new(id, None, vec![email]))).unwrap();
+    push_substep(&root_step_id, name_step, session.step_store_mut());
+    push_substep(&root_step_id, email_step, session.step_store_mut());
+    let action_id = session.action_store_mut().insert_new(|id| Ok(MockAction::new(id).boxed())).unwrap();
+    session.set_action_for_step(action_id, None).unwrap();
+
+    let missing_id = test_id!(StepId);
+    assert_eq!(session.resume_at(&missing_id), Err(Error::StepId(IdError::IdMissing(missing_id))));
+    session.resume_at(&email_step).unwrap();
+    assert_eq!(session.current_step(), Ok(&email_step));
+    assert!(session.history().is_empty());
+    assert_eq!(session.finished_at(), None);
+    let (step_id, output) = step_str_output(&session, &email, "jane@example.com");
+    assert_eq!(session.advance(Some((&step_id, output))), Ok(AdvanceBlockedOn::FinishedAdvancing));
+
+    // finishing again keeps the first time
+    let finished_at = session.finished_at().unwrap();
+    assert_eq!(session.advance(None), Ok(AdvanceBlockedOn::FinishedAdvancing));
+    assert_eq!(session.finished_at(), Some(finished_at));
+    session.resume_at(&email_step).unwrap();
+    assert_eq!(session.finished_at(), None);
+  }
+
+  #[test]
+  fn transition_observers() {
+    let (mut session, root_step_id) = Session::test_new();
+    let var_id = session.test_new_stringvar();
+    let substep1 = session.step_store_mut().insert_new(|id| Ok(Step::new(id, None, vec![var_id]))).unwrap();
+    push_substep(&root_step_id, substep1, session.step_store_mut());
+    let substep2 = add_new_simple_substep(&root_step_id, session.step_store_mut());
+    let test_action_id = session.action_store_mut().insert_new(|id| Ok(MockAction::new(id).boxed())).unwrap();
+    session.set_action_for_step(test_action_id, None).unwrap();
+
+    let observed = Arc::new(Mutex::new(Vec::new()));
+    let observer = observed.clone();
+    session.on_transition(move |transition| observer.lock().unwrap().push(transition.clone()));
+
+    session.advance(None).unwrap();
+    let output = step_str_output(&session, &var_id, "hi");
+    let expected_merged = output.1.clone();
+    session.advance(Some((&output.0, output.1))).unwrap();
+    session.advance(None).unwrap();
+
+    let observed = observed.lock().unwrap();
+    assert_eq!(observed.len(), 3);
+    assert_eq!((observed[0].from, observed[0].to), (None, Some(substep1)));
+    assert_eq!((observed[1].from, observed[1].to), (Some(substep1), Some(substep2)));
+    assert_eq!(observed[1].merged, expected_merged);
+    assert_eq!((observed[2].from, observed[2].to), (Some(substep2), None));
+    assert_eq!(observed[2].merged, StateData::new());
+    assert_eq!(observed.iter().map(|transition| transition.seq).collect::<Vec<_>>(), vec![0, 3, 5]);
+    assert_eq!(session.history()[3].kind, SessionEventKind::StepEntered(substep2));
+    assert_eq!(session.history()[3].at, observed[1].at);
+  }
+
   #[test]
   fn specific_generic_actions() {
 
@@ -528,7 +3555,7 @@ mod tests {
     }).unwrap();
 
     let test_action_id = session.action_store_mut().insert_new(|id| {
-        Ok(TestAction::new_with_id(id, true).boxed())
+        Ok(MockAction::new(id).boxed())
       })
       .unwrap();
 
@@ -537,21 +3564,21 @@ mod tests {
     session.set_action_for_step(test_action_id, None).unwrap();
 
     // 1. advance to substep 1, fail to execute specific setval, succeed generic test_action
-    if let AdvanceBlockedOn::ActionStartWith(_, _) = session.advance(None).unwrap() {
+    if let AdvanceBlockedOn::ActionStartWith(_, _, _) = session.advance(None).unwrap() {
       assert_eq!(*session.current_step().unwrap(), substep1.clone()); // advanced to substep1
     } else {
       panic!("did not advance");
     }
 
     // 2. fail advance to substep2 (setval::count=1 now but min is 2), succeed setval::count=2
-    if let AdvanceBlockedOn::ActionStartWith(_, _) = session.advance(None).unwrap() {
+    if let AdvanceBlockedOn::ActionStartWith(_, _, _) = session.advance(None).unwrap() {
       assert!(!session.state_data.contains(&var_id)); // setval still hasn't worked
     } else {
       panic!("did not advance");
     }
 
     // 3. succeed advance to substep2 (setval executed, then advanced step), succeed generic test_action
-    if let AdvanceBlockedOn::ActionStartWith(_, _) = session.advance(None).unwrap() {
+    if let AdvanceBlockedOn::ActionStartWith(_, _, _) = session.advance(None).unwrap() {
       assert_eq!(*session.current_step().unwrap(), substep2.clone()); // advanced to substep2
       assert!(session.state_data.contains(&var_id)); // setval worked
     } else {
@@ -568,7 +3595,7 @@ mod tests {
   fn auto_advance() {
     let (mut session, root_step_id) = Session::test_new();
     let test_action_id = session.action_store_mut().insert_new(|id| {
-        Ok(TestAction::new_with_id(id, false).boxed())
+        Ok(MockAction::new_finished(id).boxed())
       })
       .unwrap();
 
@@ -588,16 +3615,421 @@ mod tests {
     let abo_finish = AdvanceBlockedOn::FinishedAdvancing;
     assert_eq!(abo_finish, abo_finish);
 
-    let abo_cannot_fulfill = AdvanceBlockedOn::ActionCannotFulfill;
+    let abo_cannot_fulfill = AdvanceBlockedOn::ActionCannotFulfill(CannotFulfillDetails {
+      step_id: test_id!(StepId),
+      attempted_actions: vec![test_id!(ActionId)],
+      advance_error: None,
+    });
+    assert_eq!(abo_cannot_fulfill, abo_cannot_fulfill);
     assert_ne!(abo_finish, abo_cannot_fulfill);
 
     let action_id = test_id!(ActionId);
-    let abo_start_true = AdvanceBlockedOn::ActionStartWith(action_id.clone(), BoolValue::new(true).boxed());
-    let abo_start_false = AdvanceBlockedOn::ActionStartWith(action_id, BoolValue::new(false).boxed());
+    let abo_start_true = AdvanceBlockedOn::ActionStartWith(action_id.clone(), BoolValue::new(true).boxed(), AttemptId::new(0));
+    let abo_start_false = AdvanceBlockedOn::ActionStartWith(action_id, BoolValue::new(false).boxed(), AttemptId::new(0));
     assert_eq!(abo_start_false, abo_start_false);
     assert_ne!(abo_start_true, abo_start_false);
+    assert_ne!(abo_start_true, AdvanceBlockedOn::ActionStartWith(action_id, BoolValue::new(true).boxed(), AttemptId::new(1)));
     assert_ne!(abo_start_false, abo_finish);
   }
 
-}
 
+  #[test]
+  fn debug_summary() {
+    let (mut session, root_step_id) = Session::test_new();
+    let var_id = session.var_store_mut().insert_new_named("password", |id| Ok(StringVar::new(id).boxed())).unwrap();
+    let step_id = session.step_store_mut().insert_new_named("login", |id| Ok(Step::new(id, None, vec![var_id]))).unwrap();
+    push_substep(&root_step_id, step_id, session.step_store_mut());
+    let test_action_id = session.action_store_mut().insert_new(|id| Ok(MockAction::new(id).boxed())).unwrap();
+    session.set_action_for_step(test_action_id, None).unwrap();
+    session.advance(None).unwrap();
+
+    let mut data = StateData::new();
+    data.insert(session.var_store().get(&var_id).unwrap(), StringValue::try_new("hunter2").unwrap().boxed()).unwrap();
+    session.advance(Some((&step_id, data))).unwrap();
+
+    let debug = format!("{:?}", session);
+    assert!(debug.contains("data_vars: [\"password\"]"));
+    assert!(!debug.contains("hunter2"));
+  }
+
+  #[test]
+  fn assignees() {
+    let (mut session, root_step_id) = Session::test_new();
+    let var_id = session.test_new_stringvar();
+    let apply_step = session.step_store_mut().insert_new(|id| Ok(Step::new(id, None, vec![var_id]))).unwrap();
+    let review_step = session.step_store_mut().insert_new(|id| Ok(Step::new(id, None, vec![]))).unwrap();
+    push_substep(&root_step_id, apply_step, session.step_store_mut());
+    push_substep(&root_step_id, review_step, session.step_store_mut());
+    let test_action_id = session.action_store_mut().insert_new(|id| Ok(MockAction::new(id).boxed())).unwrap();
+    session.set_action_for_step(test_action_id, None).unwrap();
+    session.set_step_assignee(apply_step, "applicant".to_owned()).unwrap();
+    session.set_step_assignee(review_step, "reviewer".to_owned()).unwrap();
+    assert!(matches!(session.set_step_assignee(test_id!(StepId), "reviewer".to_owned()), Err(Error::StepId(IdError::IdMissing(_)))));
+    assert_eq!(session.step_assignee(&review_step), Some("reviewer"));
+
+    session.advance_as("applicant", None).unwrap();
+    assert_eq!(session.steps_waiting_on("applicant"), vec![apply_step]);
+    assert!(session.steps_waiting_on("reviewer").is_empty());
+
+    // the reviewer can't fill in the application
+    let (step_id, output) = step_str_output(&session, &var_id, "hi");
+    assert_eq!(session.advance_as("reviewer", Some((&step_id, output.clone()))), Err(Error::NotAssignee(apply_step)));
+    session.advance_as("applicant", Some((&step_id, output))).unwrap();
+    assert_eq!(*session.current_step().unwrap(), review_step);
+    assert_eq!(session.steps_waiting_on("reviewer"), vec![review_step]);
+
+    assert_eq!(session.advance_as("applicant", None), Err(Error::NotAssignee(review_step)));
+    assert_eq!(session.advance_as("reviewer", None), Ok(AdvanceBlockedOn::FinishedAdvancing));
+  }
+
+  #[test]
+  fn blocked_notices() {
+    let (mut session, root_step_id) = Session::test_new();
+    let var_id = session.test_new_stringvar();
+    let apply_step = session.step_store_mut().insert_new(|id| Ok(Step::new(id, None, vec![var_id]))).unwrap();
+    let review_step = session.step_store_mut().insert_new(|id| Ok(Step::new(id, None, vec![]))).unwrap();
+    push_substep(&root_step_id, apply_step, session.step_store_mut());
+    push_substep(&root_step_id, review_step, session.step_store_mut());
+    session.step_store_mut().get_mut(&review_step).unwrap().set_metadata("deadline", "2030-01-01");
+    let test_action_id = session.action_store_mut().insert_new(|id| Ok(MockAction::new(id).boxed())).unwrap();
+    session.set_action_for_step(test_action_id, None).unwrap();
+    session.set_step_assignee(review_step, "reviewer".to_owned()).unwrap();
+    let notify_action_id = session.action_store_mut().insert_new(|id| Ok(MockAction::new_finished(id).boxed())).unwrap();
+    session.add_notify_action(notify_action_id).unwrap();
+    assert!(session.add_notify_action(test_id!(ActionId)).is_err());
+
+    let notices = Arc::new(Mutex::new(Vec::new()));
+    let observed = notices.clone();
+    session.add_blocked_observer(move |notice| observed.lock().unwrap().push(notice.clone()));
+
+    session.advance(None).unwrap();
+    session.advance(None).unwrap(); // still blocked on the same step
+    let (step_id, output) = step_str_output(&session, &var_id, "hi");
+    session.advance(Some((&step_id, output))).unwrap();
+    session.reassign_step(&review_step, "manager".to_owned()).unwrap();
+    assert_eq!(*notices.lock().unwrap(), vec![
+      BlockedNotice { step_id: apply_step, action_id: test_action_id, assignee: None, deadline: None, overdue: false },
+      BlockedNotice { step_id: review_step, action_id: test_action_id, assignee: Some("reviewer".to_owned()), deadline: Some("2030-01-01".to_owned()), overdue: false },
+      BlockedNotice { step_id: review_step, action_id: test_action_id, assignee: Some("manager".to_owned()), deadline: Some("2030-01-01".to_owned()), overdue: false },
+    ]);
+    let notify_action = session.action_store().get(&notify_action_id).unwrap().downcast::<MockAction>().unwrap();
+    assert_eq!(notify_action.calls().len(), 3);
+
+    assert_eq!(session.advance_as("manager", None), Ok(AdvanceBlockedOn::FinishedAdvancing));
+    assert_eq!(notices.lock().unwrap().len(), 3);
+  }
+
+  #[test]
+  fn string_interning() {
+    let (mut session, root_step_id) = Session::test_new();
+    let var1 = session.test_new_stringvar();
+    let var2 = session.test_new_stringvar();
+    let step1 = session.step_store_mut().insert_new(|id| Ok(Step::new(id, None, vec![var1]))).unwrap();
+    let step2 = session.step_store_mut().insert_new(|id| Ok(Step::new(id, None, vec![var2]))).unwrap();
+    push_substep(&root_step_id, step1, session.step_store_mut());
+    push_substep(&root_step_id, step2, session.step_store_mut());
+    let test_action_id = session.action_store_mut().insert_new(|id| Ok(MockAction::new(id).boxed())).unwrap();
+    session.set_action_for_step(test_action_id, None).unwrap();
+
+    // data already in the session is interned when it's turned on
+    session.advance(None).unwrap();
+    let output = step_str_output(&session, &var1, "same");
+    session.advance(Some((&step1, output.1))).unwrap();
+    assert!(session.string_interner().is_none());
+    session.set_string_interning(true);
+
+    let output = step_str_output(&session, &var2, "same");
+    session.advance(Some((&step2, output.1))).unwrap();
+    let shared = |var_id| session.state_data().get(var_id).unwrap().get_val().downcast::<StringValue>().unwrap().shared().clone();
+    assert!(Arc::ptr_eq(&shared(&var1), &shared(&var2)));
+    assert_eq!(session.string_interner().unwrap().len(), 1);
+
+    // replaced strings are dropped from the pool
+    let other = |session: &Session, var_id: &VarId| {
+      let mut state_data = StateData::new();
+      state_data.insert(session.var_store().get(var_id).unwrap(), StringValue::try_new("other").unwrap().boxed()).unwrap();
+      state_data
+    };
+    session.seed_data(other(&session, &var2)).unwrap();
+    assert_eq!(session.string_interner().unwrap().len(), 2);
+    session.seed_data(other(&session, &var1)).unwrap();
+    assert_eq!(session.string_interner().unwrap().len(), 1);
+
+    session.reset(false);
+    assert!(session.string_interner().unwrap().is_empty());
+  }
+
+  #[test]
+  fn load_shedding() {
+    let (mut session, root_step_id) = Session::test_new();
+    let var_id = session.test_new_stringvar();
+    let first_step = session.step_store_mut().insert_new(|id| Ok(Step::new(id, None, vec![var_id]))).unwrap();
+    let second_step = session.step_store_mut().insert_new(|id| Ok(Step::new(id, None, vec![var_id]))).unwrap();
+    push_substep(&root_step_id, first_step, session.step_store_mut());
+    push_substep(&root_step_id, second_step, session.step_store_mut());
+    let test_action_id = session.action_store_mut().insert_new(|id| Ok(MockAction::new(id).boxed())).unwrap();
+    session.set_action_for_step(test_action_id, None).unwrap();
+    let notify_action_id = session.action_store_mut().insert_new(|id| Ok(MockAction::new(id).boxed())).unwrap();
+    session.add_notify_action(notify_action_id).unwrap();
+    let notices = Arc::new(Mutex::new(0));
+    let observed = notices.clone();
+    session.add_blocked_observer(move |_notice| *observed.lock().unwrap() += 1);
+    let transitions = Arc::new(Mutex::new(0));
+    let transitioned = transitions.clone();
+    session.on_transition(move |_transition| *transitioned.lock().unwrap() += 1);
+
+    // shed everything from a shared switch
+    let shedding = LoadShedding::new();
+    session.set_load_shedding(shedding.clone());
+    shedding.set_sample_percent(0);
+    session.advance(None).unwrap();
+    assert_eq!(*notices.lock().unwrap(), 0);
+    assert_eq!(*transitions.lock().unwrap(), 0);
+    assert!(session.history().is_empty());
+
+    // notify actions still run
+    let notify_action = session.action_store().get(&notify_action_id).unwrap().downcast::<MockAction>().unwrap();
+    assert_eq!(notify_action.calls().len(), 1);
+
+    // someone has to act on an assigned step
+    session.set_step_assignee(second_step, "ops".to_owned()).unwrap();
+    let output = step_str_output(&session, &var_id, "done");
+    session.advance(Some((&first_step, output.1))).unwrap();
+    assert_eq!(*session.current_step().unwrap(), second_step);
+    assert_eq!(*notices.lock().unwrap(), 1);
+    assert_eq!(*transitions.lock().unwrap(), 0);
+
+    shedding.set_sample_percent(100);
+    let output = step_str_output(&session, &var_id, "done again");
+    session.advance(Some((&second_step, output.1))).unwrap();
+    assert_eq!(*transitions.lock().unwrap(), 1);
+    assert!(!session.history().is_empty());
+  }
+
+  #[test]
+  fn deadlines() {
+    let (mut session, root_step_id) = Session::test_new();
+    let var_id = session.test_new_stringvar();
+    let review_step = session.step_store_mut().insert_new(|id| Ok(Step::new(id, None, vec![var_id]))).unwrap();
+    let manager_step = session.step_store_mut().insert_new(|id| Ok(Step::new(id, None, vec![var_id]))).unwrap();
+    push_substep(&root_step_id, review_step, session.step_store_mut());
+    push_substep(&root_step_id, manager_step, session.step_store_mut());
+    let test_action_id = session.action_store_mut().insert_new(|id| Ok(MockAction::new(id).boxed())).unwrap();
+    session.set_action_for_step(test_action_id, None).unwrap();
+
+    let hour = Duration::from_secs(60 * 60);
+    session.set_deadline_policy(review_step, DeadlinePolicy { within: hour, escalation: Escalation::Reroute(manager_step) }).unwrap();
+    session.set_deadline_policy(manager_step, DeadlinePolicy { within: hour, escalation: Escalation::Notify }).unwrap();
+    assert!(session.set_deadline_policy(test_id!(StepId), DeadlinePolicy { within: hour, escalation: Escalation::Notify }).is_err());
+    assert!(session.set_deadline_policy(review_step, DeadlinePolicy { within: hour, escalation: Escalation::Reroute(test_id!(StepId)) }).is_err());
+
+    let notices = Arc::new(Mutex::new(Vec::new()));
+    let observed = notices.clone();
+    session.add_blocked_observer(move |notice| observed.lock().unwrap().push((notice.step_id, notice.overdue)));
+
+    let now = SystemTime::now();
+    assert_eq!(session.enforce_deadline(now), Ok(None)); // not blocked yet
+    session.advance(None).unwrap();
+    assert_eq!(session.enforce_deadline(now), Ok(None));
+
+    // rerouted to the manager, who only gets a reminder
+    assert_eq!(session.enforce_deadline(now + 2 * hour), Ok(Some(review_step)));
+    assert_eq!(*session.current_step().unwrap(), manager_step);
+    assert_eq!(session.enforce_deadline(now + 2 * hour), Ok(Some(manager_step)));
+    assert_eq!(session.enforce_deadline(now + 3 * hour), Ok(None));
+    assert_eq!(*session.current_step().unwrap(), manager_step);
+    assert_eq!(*notices.lock().unwrap(), vec![
+      (review_step, false),
+      (review_step, true),
+      (manager_step, false),
+      (manager_step, true),
+    ]);
+  }
+
+  #[test]
+  fn follow_ups() {
+    let (mut session, root_step_id) = Session::test_new();
+    let email = session.var_store_mut().insert_new_named("email", |id| Ok(StringVar::new(id).boxed())).unwrap();
+    let is_business = session.var_store_mut().insert_new_named("is_business", |id| Ok(BoolVar::new(id).boxed())).unwrap();
+    let unnamed = session.test_new_stringvar();
+    let step_id = session.step_store_mut().insert_new(|id| Ok(Step::new(id, None, vec![email]))).unwrap();
+    push_substep(&root_step_id, step_id, session.step_store_mut());
+    let test_action_id = session.action_store_mut().insert_new(|id| Ok(MockAction::new(id).boxed())).unwrap();
+    session.set_action_for_step(test_action_id, None).unwrap();
+
+    session.add_follow_up("onboarding".to_owned(), vec![email], Condition::All(vec![])).unwrap();
+    session.add_follow_up("kyb".to_owned(), vec![email], Condition::IsSet(is_business)).unwrap();
+    assert_eq!(
+      session.add_follow_up("kyc".to_owned(), vec![unnamed], Condition::All(vec![])),
+      Err(Error::VarId(IdError::IdHasNoName(unnamed))));
+
+    session.advance(None).unwrap();
+    assert_eq!(session.pending_follow_ups(), vec![]); // not finished yet
+
+    let mut data = StateData::new();
+    data.insert(session.var_store().get(&email).unwrap(), StringValue::try_new("a@b.c").unwrap().boxed()).unwrap();
+    session.advance(Some((&step_id, data))).unwrap();
+    let pending = vec![FollowUpStart {
+      index: 0,
+      flow: "onboarding".to_owned(),
+      seed: vec![("email".to_owned(), StringValue::try_new("a@b.c").unwrap().boxed())],
+    }];
+    assert_eq!(session.pending_follow_ups(), pending);
+    assert_eq!(session.pending_follow_ups(), pending); // until it's started
+    session.set_follow_up_started(0).unwrap();
+    assert!(session.follow_ups()[0].started);
+    assert_eq!(session.pending_follow_ups(), vec![]);
+    assert_eq!(session.set_follow_up_started(2), Err(Error::FollowUpIndex(2)));
+
+    // seeding is like any other data
+    let (mut follow_up, _) = Session::test_new();
+    let follow_up_email = follow_up.var_store_mut().insert_new_named("email", |id| Ok(StringVar::new(id).boxed())).unwrap();
+    let mut seed = StateData::new();
+    seed.insert(follow_up.var_store().get(&follow_up_email).unwrap(), StringValue::try_new("a@b.c").unwrap().boxed()).unwrap();
+    follow_up.seed_data(seed).unwrap();
+    assert!(follow_up.state_data().contains(&follow_up_email));
+  }
+
+  #[test]
+  fn repeat() {
+    let (mut session, root_step_id) = Session::test_new();
+    let draft = session.test_new_stringvar();
+    let approved = session.var_store_mut().insert_new(|id| Ok(BoolVar::new(id).boxed())).unwrap();
+    let review_step = session.step_store_mut().insert_new(|id| Ok(Step::new(id, None, vec![]))).unwrap();
+    let draft_step = session.step_store_mut().insert_new(|id| Ok(Step::new(id, None, vec![draft]))).unwrap();
+    let approve_step = session.step_store_mut().insert_new(|id| Ok(Step::new(id, None, vec![approved]))).unwrap();
+    push_substep(&root_step_id, review_step, session.step_store_mut());
+    push_substep(&review_step, draft_step, session.step_store_mut());
+    push_substep(&review_step, approve_step, session.step_store_mut());
+    let test_action_id = session.action_store_mut().insert_new(|id| Ok(MockAction::new(id).boxed())).unwrap();
+    session.set_action_for_step(test_action_id, None).unwrap();
+    let repeat = Repeat::Until { condition: Condition::Equals(approved, BoolValue::new(true).boxed()), max_times: 3 };
+    session.step_store_mut().get_mut(&review_step).unwrap().set_repeat(repeat);
+
+    let approve = |session: &mut Session, is_approved: bool| {
+      let mut state_data = StateData::new();
+      state_data.insert(session.var_store().get(&approved).unwrap(), BoolValue::new(is_approved).boxed()).unwrap();
+      session.advance(Some((&approve_step, state_data)))
+    };
+
+    // rejected drafts go around again with the draft cleared
+    session.advance(None).unwrap();
+    for _ in 0..2 {
+      let output = step_str_output(&session, &draft, "draft");
+      session.advance(Some((&output.0, output.1))).unwrap();
+      assert_eq!(*session.current_step().unwrap(), approve_step);
+      assert!(matches!(approve(&mut session, false), Ok(AdvanceBlockedOn::ActionStartWith(_, _, _))));
+      assert_eq!(*session.current_step().unwrap(), draft_step);
+      assert!(!session.state_data().contains(&draft));
+    }
+    let output = step_str_output(&session, &draft, "draft");
+    session.advance(Some((&output.0, output.1))).unwrap();
+    assert_eq!(approve(&mut session, true), Ok(AdvanceBlockedOn::FinishedAdvancing));
+
+    // a loop that never gets approved fails once it hits the limit
+    session.reset(false);
+    session.advance(None).unwrap();
+    for pass in 1..=3 {
+      let output = step_str_output(&session, &draft, "draft");
+      session.advance(Some((&output.0, output.1))).unwrap();
+      let result = approve(&mut session, false);
+      if pass < 3 {
+        assert!(matches!(result, Ok(AdvanceBlockedOn::ActionStartWith(_, _, _))));
+      } else {
+        assert_eq!(result, Err(Error::RepeatLimit(review_step)));
+      }
+    }
+    assert_eq!(session.advance(None), Err(Error::RepeatLimit(review_step)));
+    assert_eq!(*session.current_step().unwrap(), review_step);
+
+    // a fixed number of passes
+    session.step_store_mut().get_mut(&review_step).unwrap().set_repeat(Repeat::Times(2));
+    session.reset(false);
+    session.advance(None).unwrap();
+    for _ in 0..2 {
+      let output = step_str_output(&session, &draft, "draft");
+      session.advance(Some((&output.0, output.1))).unwrap();
+      assert_eq!(*session.current_step().unwrap(), approve_step);
+      approve(&mut session, true).unwrap();
+    }
+    assert_eq!(session.current_step(), Err(Error::NoStateToEval));
+  }
+
+  #[test]
+  fn preview() {
+    let (mut session, root_step_id) = Session::test_new();
+    let var_id = session.test_new_stringvar();
+    let step_id = session.step_store_mut().insert_new(|id| Ok(Step::new(id, None, vec![var_id]))).unwrap();
+    push_substep(&root_step_id, step_id, session.step_store_mut());
+    let test_action_id = session.action_store_mut().insert_new(|id| Ok(MockAction::new(id).boxed())).unwrap();
+    session.set_action_for_step(test_action_id, None).unwrap();
+    let webhook = session.action_store_mut().insert_new(|id| Ok(MockAction::new_finished(id).boxed())).unwrap();
+    session.add_finish_action(webhook, 1).unwrap();
+    session.add_follow_up("onboarding".to_owned(), vec![], Condition::All(vec![])).unwrap();
+    session.set_deadline_policy(step_id, DeadlinePolicy { within: Duration::from_secs(60), escalation: Escalation::Notify }).unwrap();
+    let observed = Arc::new(Mutex::new(0));
+    let observer_count = observed.clone();
+    session.add_blocked_observer(move |_notice| *observer_count.lock().unwrap() += 1);
+
+    let now = SystemTime::now();
+    session.set_preview(now + Duration::from_secs(600));
+    assert!(session.is_preview());
+    assert!(!session.is_expired(now));
+    assert!(session.is_expired(now + Duration::from_secs(600)));
+
+    // nothing leaves the session
+    assert!(matches!(session.advance(None), Ok(AdvanceBlockedOn::ActionStartWith(_, _, _))));
+    assert_eq!(*observed.lock().unwrap(), 0);
+    assert_eq!(session.enforce_deadline(now + Duration::from_secs(120)), Ok(None));
+    let output = step_str_output(&session, &var_id, "done");
+    assert_eq!(session.advance(Some((&output.0, output.1))), Ok(AdvanceBlockedOn::FinishedAdvancing));
+    assert_eq!(session.finish_actions()[0].attempts, 0);
+    assert_eq!(session.pending_follow_ups(), vec![]);
+
+    // expired previews can't advance
+    session.set_preview(now);
+    assert_eq!(session.advance(None), Err(Error::PreviewExpired));
+  }
+
+  #[test]
+  fn var_access() {
+    let (mut session, root_step_id) = Session::test_new();
+    let email = session.test_new_stringvar();
+    let ssn = session.test_new_stringvar();
+    let approved = session.var_store_mut().insert_new(|id| Ok(BoolVar::new(id).boxed())).unwrap();
+    let step_id = session.step_store_mut().insert_new(|id| Ok(Step::new(id, None, vec![email, ssn, approved]))).unwrap();
+    push_substep(&root_step_id, step_id, session.step_store_mut());
+    let test_action_id = session.action_store_mut().insert_new(|id| Ok(MockAction::new(id).boxed())).unwrap();
+    session.set_action_for_step(test_action_id, None).unwrap();
+    session.var_access_mut().allow_read(ssn, "applicant");
+    session.var_access_mut().allow_write(approved, "admin");
+    session.advance(None).unwrap();
+
+    let mut data = StateData::new();
+    data.insert(session.var_store().get(&email).unwrap(), StringValue::try_new("a@b.c").unwrap().boxed()).unwrap();
+    data.insert(session.var_store().get(&ssn).unwrap(), StringValue::try_new("123").unwrap().boxed()).unwrap();
+    let mut approval = data.clone();
+    approval.insert(session.var_store().get(&approved).unwrap(), BoolValue::new(true).boxed()).unwrap();
+    assert_eq!(session.advance_as("applicant", Some((&step_id, approval))), Err(Error::WriteDenied(vec![approved])));
+    session.advance_as("applicant", Some((&step_id, data))).unwrap();
+
+    // reviewer can see the email but not the ssn
+    let reviewer_data = session.state_data_for("reviewer");
+    assert!(reviewer_data.contains(&email));
+    assert!(!reviewer_data.contains(&ssn));
+    assert!(session.state_data_for("applicant").contains(&ssn));
+    let reviewer_vars = session.var_store_for("reviewer");
+    assert!(reviewer_vars.get(&approved).is_some());
+    assert!(reviewer_vars.get(&ssn).is_none());
+
+    // actions started for the reviewer can't see it either
+    let last_call = |session: &Session| {
+      session.action_store().get(&test_action_id).unwrap().downcast::<MockAction>().unwrap().calls().last().unwrap().step_data.clone()
+    };
+    session.advance_as("reviewer", None).unwrap();
+    assert!(last_call(&session).contains(&email));
+    assert!(!last_call(&session).contains(&ssn));
+    session.advance_as("applicant", None).unwrap();
+    assert!(last_call(&session).contains(&ssn));
+  }
+}
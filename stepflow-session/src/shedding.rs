@@ -0,0 +1,86 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU8, AtomicU64, Ordering};
+
+/// Switch to shed optional work under high load, shared by all the sessions it's given to
+///
+/// Only a sample of the [blocked observer](crate::Session::add_blocked_observer) and [transition observer](crate::Session::on_transition) calls,
+/// [history](crate::Session::history) events and events are emitted.
+/// Blocked notices with an assignee or a deadline are never shed since someone has to act on them.
+/// Clones share the same switch, so it can be turned from anywhere while the sessions are running.
+/// ```
+/// # use stepflow_session::LoadShedding;
+/// let shedding = LoadShedding::new();
+/// let switch = shedding.clone();
+/// switch.set_sample_percent(25);
+/// assert_eq!((0..100).filter(|_| shedding.should_emit()).count(), 25);
+/// ```
+#[derive(Debug, Clone)]
+pub struct LoadShedding {
+  sample_percent: Arc<AtomicU8>,
+  calls: Arc<AtomicU64>,
+}
+
+impl Default for LoadShedding {
+  fn default() -> Self {
+    Self {
+      sample_percent: Arc::new(AtomicU8::new(100)),
+      calls: Arc::new(AtomicU64::new(0)),
+    }
+  }
+}
+
+impl PartialEq for LoadShedding {
+  fn eq(&self, other: &Self) -> bool {
+    Arc::ptr_eq(&self.sample_percent, &other.sample_percent)
+  }
+}
+
+impl LoadShedding {
+  /// Create a switch that emits everything
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Percent of the observer calls and events to emit. Over 100 is the same as 100.
+  pub fn sample_percent(&self) -> u8 {
+    self.sample_percent.load(Ordering::Relaxed)
+  }
+
+  /// Emit `sample_percent` of the observer calls and events, i.e. 0 to skip them all while the load is high
+  pub fn set_sample_percent(&self, sample_percent: u8) {
+    self.sample_percent.store(sample_percent.min(100), Ordering::Relaxed);
+  }
+
+  /// Whether to emit this call. Spreads the emitted calls evenly over each 100 calls.
+  pub fn should_emit(&self) -> bool {
+    let sample_percent = self.sample_percent() as u64;
+    if sample_percent >= 100 {
+      return true;
+    }
+    let call = self.calls.fetch_add(1, Ordering::Relaxed) % 100;
+    // emit when the running total of samples ticks over
+    (call + 1) * sample_percent / 100 > call * sample_percent / 100
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::LoadShedding;
+
+  #[test]
+  fn sampling() {
+    let shedding = LoadShedding::new();
+    assert!((0..100).all(|_| shedding.should_emit()));
+
+    shedding.clone().set_sample_percent(0);
+    assert!(!(0..100).any(|_| shedding.should_emit()));
+
+    shedding.set_sample_percent(50);
+    let emitted = (0..10).map(|_| shedding.should_emit()).collect::<Vec<_>>();
+    assert_eq!(emitted, vec![false, true, false, true, false, true, false, true, false, true]);
+
+    shedding.set_sample_percent(200);
+    assert_eq!(shedding.sample_percent(), 100);
+    assert_ne!(shedding, LoadShedding::new());
+  }
+}
@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+use stepflow_base::{ObjectStore, ObjectStoreFiltered, ObjectStoreContent, IdError};
+use stepflow_data::{StateData, StateDataFiltered, InvalidValue, InvalidVars, var::{Var, VarId}};
+use stepflow_step::Step;
+use stepflow_action::{Action, ActionId, ActionResult, ActionError};
+use crate::{Session, AdvanceBlockedOn, Error};
+
+/// An [`Action`] that runs a whole flow as one step of another flow
+///
+/// Vars are matched up by name unless they're [mapped](SubflowAction::map_var) to another name. The first start seeds the child [`Session`] with the parent step's inputs.
+/// Each start advances the child. Once it finishes, the action finishes with the child's values for the parent step's outputs.
+/// Data for the child's own steps goes straight to the child with [`session_mut`](SubflowAction::session_mut).
+///
+/// This lives here rather than with the other actions since it needs a [`Session`].
+#[derive(Debug)]
+pub struct SubflowAction {
+  id: ActionId,
+  session: Session,
+  seeded: bool,
+  var_mapping: HashMap<String, String>,
+}
+
+impl SubflowAction {
+  pub fn new(id: ActionId, session: Session) -> Self {
+    SubflowAction { id, session, seeded: false, var_mapping: HashMap::new() }
+  }
+
+  /// Pass the parent's `parent_name` var to and from the child's `child_name` var instead of the child var with the same name.
+  ///
+  /// Both vars have to exist and hold the same kind of value. A mismatch is [`InvalidValue::WrongType`] for the parent var.
+  pub fn map_var(&mut self, parent_vars: &ObjectStore<Box<dyn Var + Send + Sync>, VarId>, parent_name: &str, child_name: &str) -> Result<(), Error> {
+    let parent_var = parent_vars.get_by_name(parent_name).ok_or_else(|| Error::VarId(IdError::NoSuchName(parent_name.to_owned())))?;
+    let child_var = self.session.var_store().get_by_name(child_name).ok_or_else(|| Error::VarId(IdError::NoSuchName(child_name.to_owned())))?;
+    if let (Some(parent_kind), Some(child_kind)) = (parent_var.base_value_kind(), child_var.base_value_kind()) {
+      if parent_kind != child_kind {
+        return Err(Error::InvalidVars(InvalidVars::new(vec![(*parent_var.id(), InvalidValue::WrongType)].into_iter().collect())));
+      }
+    }
+    self.var_mapping.insert(parent_name.to_owned(), child_name.to_owned());
+    Ok(())
+  }
+
+  /// Parent var names and the child var names they're mapped to
+  pub fn var_mapping(&self) -> &HashMap<String, String> {
+    &self.var_mapping
+  }
+
+  // the child var for a parent var
+  fn child_var_id(&self, parent_vars: &ObjectStoreFiltered<Box<dyn Var + Send + Sync>, VarId>, parent_var_id: &VarId) -> Option<&VarId> {
+    let parent_name = parent_vars.name_from_id(parent_var_id)?;
+    let child_name = self.var_mapping.get(parent_name).map(|name| name.as_str()).unwrap_or(parent_name);
+    self.session.var_store().id_from_name(child_name)
+  }
+
+  /// The child session
+  pub fn session(&self) -> &Session {
+    &self.session
+  }
+
+  /// The child session, i.e. to advance it with data for its steps
+  pub fn session_mut(&mut self) -> &mut Session {
+    &mut self.session
+  }
+
+  // copy the values of `var_ids` to the child, skipping vars the child doesn't have
+  fn copy_to_child<'a>(&self, var_ids: impl Iterator<Item = &'a VarId>, from: &StateDataFiltered, from_vars: &ObjectStoreFiltered<Box<dyn Var + Send + Sync>, VarId>)
+      -> Result<StateData, ActionError>
+  {
+    let mut state_data = StateData::new();
+    for var_id in var_ids {
+      let valid_val = match from.get(var_id) {
+        Some(valid_val) => valid_val,
+        None => continue,
+      };
+      let to_var = self.child_var_id(from_vars, var_id)
+        .and_then(|to_var_id| self.session.var_store().get(to_var_id));
+      if let Some(to_var) = to_var {
+        state_data.insert(to_var, valid_val.get_val().clone())
+          .map_err(|invalid| ActionError::InvalidVars(stepflow_data::InvalidVars::new(vec![(*to_var.id(), invalid)].into_iter().collect())))?;
+      }
+    }
+    Ok(state_data)
+  }
+}
+
+impl Action for SubflowAction {
+  fn id(&self) -> &ActionId {
+    &self.id
+  }
+
+  fn start(&mut self, step: &Step, _step_name: Option<&str>, step_data: &StateDataFiltered, vars: &ObjectStoreFiltered<Box<dyn Var + Send + Sync>, VarId>)
+      -> Result<ActionResult, ActionError>
+  {
+    if !self.seeded {
+      let input_vars = step.get_input_vars().clone().unwrap_or_default();
+      let seed = self.copy_to_child(input_vars.iter(), step_data, vars)?;
+      self.session.seed_data(seed).map_err(to_action_error)?;
+      self.seeded = true;
+    }
+
+    match self.session.advance(None).map_err(to_action_error)? {
+      AdvanceBlockedOn::ActionStartWith(_, val, _) => Ok(ActionResult::StartWith(val)),
+      AdvanceBlockedOn::ActionCannotFulfill(_) => Ok(ActionResult::CannotFulfill),
+      AdvanceBlockedOn::FinishedAdvancing => {
+        // map the child's outputs back to the parent's vars
+        let mut outputs = StateData::new();
+        for var_id in step.get_output_vars().iter() {
+          let child_val = self.child_var_id(vars, var_id)
+            .and_then(|child_var_id| self.session.state_data().get(child_var_id));
+          if let (Some(child_val), Some(var)) = (child_val, vars.get(var_id)) {
+            outputs.insert(var, child_val.get_val().clone())
+              .map_err(|invalid| ActionError::InvalidVars(stepflow_data::InvalidVars::new(vec![(*var_id, invalid)].into_iter().collect())))?;
+          }
+        }
+        Ok(ActionResult::Finished(outputs))
+      }
+    }
+  }
+}
+
+fn to_action_error(error: Error) -> ActionError {
+  match error {
+    Error::InvalidVars(invalid) => ActionError::InvalidVars(invalid),
+    error => ActionError::PluginFailed(error.to_string()),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use stepflow_base::IdError;
+  use stepflow_data::{StateData, InvalidValue, InvalidVars, var::{StringVar, BoolVar}, value::StringValue};
+  use stepflow_step::Step;
+  use stepflow_action::{Action, SetDataAction};
+  use crate::{Session, SessionId, AdvanceBlockedOn, Error};
+  use stepflow_action::testing::MockAction;
+  use super::SubflowAction;
+
+  // child flow that asks for a code then sets the address from the name it was given
+  fn child_session() -> Session {
+    let mut child = Session::new(SessionId::new(100));
+    let name = child.var_store_mut().insert_new_named("name", |id| Ok(StringVar::new(id).boxed())).unwrap();
+    let code = child.var_store_mut().insert_new_named("code", |id| Ok(StringVar::new(id).boxed())).unwrap();
+    let address = child.var_store_mut().insert_new_named("address", |id| Ok(StringVar::new(id).boxed())).unwrap();
+    let code_step = child.step_store_mut().insert_new_named("code", |id| Ok(Step::new(id, Some(vec![name]), vec![code]))).unwrap();
+    let address_step = child.step_store_mut().insert_new_named("address", |id| Ok(Step::new(id, Some(vec![name]), vec![address]))).unwrap();
+    child.push_root_substep(code_step);
+    child.push_root_substep(address_step);
+
+    let ask_action = child.action_store_mut().insert_new(|id| Ok(MockAction::new(id).boxed())).unwrap();
+    child.set_action_for_step(ask_action, Some(&code_step)).unwrap();
+    let mut address_data = StateData::new();
+    address_data.insert(child.var_store().get(&address).unwrap(), StringValue::try_new("1 Main St").unwrap().boxed()).unwrap();
+    let address_action = child.action_store_mut().insert_new(|id| Ok(SetDataAction::new(id, address_data, 0).boxed())).unwrap();
+    child.set_action_for_step(address_action, Some(&address_step)).unwrap();
+    child
+  }
+
+  #[test]
+  fn subflow() {
+    let mut session = Session::new(SessionId::new(0));
+    let name = session.var_store_mut().insert_new_named("name", |id| Ok(StringVar::new(id).boxed())).unwrap();
+    let address = session.var_store_mut().insert_new_named("address", |id| Ok(StringVar::new(id).boxed())).unwrap();
+    let name_step = session.step_store_mut().insert_new(|id| Ok(Step::new(id, None, vec![name]))).unwrap();
+    let address_step = session.step_store_mut().insert_new(|id| Ok(Step::new(id, Some(vec![name]), vec![address]))).unwrap();
+    session.push_root_substep(name_step);
+    session.push_root_substep(address_step);
+    let subflow_id = session.action_store_mut().insert_new(|id| Ok(SubflowAction::new(id, child_session()).boxed())).unwrap();
+    session.set_action_for_step(subflow_id, Some(&address_step)).unwrap();
+
+    let mut name_data = StateData::new();
+    name_data.insert(session.var_store().get(&name).unwrap(), StringValue::try_new("Jane").unwrap().boxed()).unwrap();
+
+    // enter the name step then block on the child's code step with the name passed along
+    assert!(session.advance(None).is_err());
+    assert!(matches!(session.advance(Some((&name_step, name_data))), Ok(AdvanceBlockedOn::ActionStartWith(action_id, _, _)) if action_id == subflow_id));
+    let subflow = session.action_store_mut().get_mut(&subflow_id).unwrap().downcast_mut::<SubflowAction>().unwrap();
+    let child = subflow.session_mut();
+    let child_name = *child.var_store().id_from_name("name").unwrap();
+    assert_eq!(child.state_data().get(&child_name).unwrap().get_val(), &StringValue::try_new("Jane").unwrap().boxed());
+
+    // finish the child's step and the parent gets the address
+    let child_code = *child.var_store().id_from_name("code").unwrap();
+    let child_step = *child.current_step().unwrap();
+    let mut code_data = StateData::new();
+    code_data.insert(child.var_store().get(&child_code).unwrap(), StringValue::try_new("1234").unwrap().boxed()).unwrap();
+    assert!(child.advance(Some((&child_step, code_data))).is_ok());
+
+    assert_eq!(session.advance(None), Ok(AdvanceBlockedOn::FinishedAdvancing));
+    assert_eq!(session.state_data().get(&address).unwrap().get_val(), &StringValue::try_new("1 Main St").unwrap().boxed());
+  }
+
+  #[test]
+  fn var_mapping() {
+    let mut session = Session::new(SessionId::new(0));
+    let full_name = session.var_store_mut().insert_new_named("full_name", |id| Ok(StringVar::new(id).boxed())).unwrap();
+    let home = session.var_store_mut().insert_new_named("home", |id| Ok(StringVar::new(id).boxed())).unwrap();
+    let agreed = session.var_store_mut().insert_new_named("agreed", |id| Ok(BoolVar::new(id).boxed())).unwrap();
+    let home_step = session.step_store_mut().insert_new(|id| Ok(Step::new(id, Some(vec![full_name]), vec![home]))).unwrap();
+    session.push_root_substep(home_step);
+
+    let subflow_id = session.action_store_mut().reserve_id().unwrap();
+    let mut subflow = SubflowAction::new(subflow_id, child_session());
+    subflow.map_var(session.var_store(), "full_name", "name").unwrap();
+    subflow.map_var(session.var_store(), "home", "address").unwrap();
+    assert_eq!(subflow.map_var(session.var_store(), "agreed", "code"), Err(Error::InvalidVars(InvalidVars::new(vec![(agreed, InvalidValue::WrongType)].into_iter().collect()))));
+    assert_eq!(subflow.map_var(session.var_store(), "nope", "code"), Err(Error::VarId(IdError::NoSuchName("nope".to_owned()))));
+    assert_eq!(subflow.map_var(session.var_store(), "home", "nope"), Err(Error::VarId(IdError::NoSuchName("nope".to_owned()))));
+    assert_eq!(subflow.var_mapping().len(), 2);
+    session.action_store_mut().register(subflow.boxed()).unwrap();
+    session.set_action_for_step(subflow_id, Some(&home_step)).unwrap();
+
+    let mut name_data = StateData::new();
+    name_data.insert(session.var_store().get(&full_name).unwrap(), StringValue::try_new("Jane").unwrap().boxed()).unwrap();
+    session.seed_data(name_data).unwrap();
+
+    // the child gets the full name as its name
+    assert!(matches!(session.advance(None), Ok(AdvanceBlockedOn::ActionStartWith(action_id, _, _)) if action_id == subflow_id));
+    let child = session.action_store_mut().get_mut(&subflow_id).unwrap().downcast_mut::<SubflowAction>().unwrap().session_mut();
+    let child_name = *child.var_store().id_from_name("name").unwrap();
+    assert_eq!(child.state_data().get(&child_name).unwrap().get_val(), &StringValue::try_new("Jane").unwrap().boxed());
+
+    // and the parent gets the child's address as its home
+    let child_code = *child.var_store().id_from_name("code").unwrap();
+    let child_step = *child.current_step().unwrap();
+    let mut code_data = StateData::new();
+    code_data.insert(child.var_store().get(&child_code).unwrap(), StringValue::try_new("1234").unwrap().boxed()).unwrap();
+    child.advance(Some((&child_step, code_data))).unwrap();
+    assert_eq!(session.advance(None), Ok(AdvanceBlockedOn::FinishedAdvancing));
+    assert_eq!(session.state_data().get(&home).unwrap().get_val(), &StringValue::try_new("1 Main St").unwrap().boxed());
+  }
+}
@@ -1,4 +0,0 @@
-#[cfg(test)]
-mod action_test;
-#[cfg(test)]
-pub use action_test::TestAction;
\ No newline at end of file
@@ -0,0 +1,44 @@
+use stepflow_data::{InvalidVars, var::VarId};
+use stepflow_step::StepId;
+use stepflow_action::ActionId;
+use crate::{ActionOutcome, TransitionCheck};
+
+/// One thing [`Session::advance_traced`](crate::Session::advance_traced) did while advancing, in the order they happened
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde-support", derive(serde::Serialize))]
+pub enum TraceEntry {
+  /// The session checked whether it could enter the step
+  StepTried(StepId),
+
+  /// The step couldn't be entered or exited without the vars
+  GuardFailed {
+    step_id: StepId,
+    check: TransitionCheck,
+    missing: Vec<VarId>,
+  },
+
+  /// The step's [validators](stepflow_step::Step::add_validator) rejected its data
+  ValidationFailed {
+    step_id: StepId,
+    invalid: InvalidVars,
+  },
+
+  /// A loop step finished a pass and starts again
+  LoopRepeated(StepId),
+
+  /// The session moved to the step
+  StepEntered(StepId),
+
+  /// The session moved past the step without running its action, i.e. because of its [`VisitPolicy`](stepflow_step::VisitPolicy)
+  StepSkipped(StepId),
+
+  /// An action was started for the step
+  ActionStarted {
+    step_id: StepId,
+    action_id: ActionId,
+    outcome: ActionOutcome,
+  },
+
+  /// There are no steps left
+  Finished,
+}
@@ -3,4 +3,10 @@
 //! Allows a flow to be broken down into [`Step`]s that are easier for the user to work with.
 
 mod step;
-pub use step::{ Step, StepId };
+pub use step::{ Step, StepId, VisitPolicy, Repeat };
+
+mod validator;
+pub use validator::Validator;
+
+mod step_matcher;
+pub use step_matcher::StepMatcher;
@@ -1,8 +1,54 @@
+use std::collections::{HashMap, HashSet};
 use stepflow_base::{generate_id_type, IdError, ObjectStoreContent};
-use stepflow_data::{StateData, var::VarId};
+use stepflow_data::{StateData, StateDataFiltered, Condition, InvalidVars, var::VarId};
+use super::Validator;
 
 generate_id_type!(StepId);
 
+/// Whether a [`Step`]'s action runs again when the flow passes by it again, i.e. after going back to an earlier step
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VisitPolicy {
+  /// Run every time the step is entered
+  #[default]
+  Always,
+
+  /// Only run the first time the step is entered, even if it was skipped. Later visits move on if the step can be exited.
+  Once,
+
+  /// Only run while the step can't be exited, such as when its outputs are missing
+  UntilValid,
+}
+
+/// How a [`Step`] repeats its substeps. See [`Step::set_repeat`].
+///
+/// The substeps always run at least once.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Repeat {
+  /// Run the substeps this many times
+  Times(u32),
+
+  /// Run the substeps again until `condition` holds, at most `max_times` so the flow can't loop forever
+  Until { condition: Condition, max_times: u32 },
+}
+
+impl Repeat {
+  /// Whether to run the substeps again after `passes` runs
+  pub fn should_repeat(&self, passes: u32, state_data: &StateData) -> bool {
+    match self {
+      Repeat::Times(times) => passes < *times,
+      Repeat::Until { condition, max_times } => passes < *max_times && !condition.eval(state_data),
+    }
+  }
+
+  /// Whether an [`Until`](Repeat::Until) ran `max_times` after `passes` runs without its condition holding
+  pub fn limit_reached(&self, passes: u32, state_data: &StateData) -> bool {
+    match self {
+      Repeat::Times(_) => false,
+      Repeat::Until { condition, max_times } => passes >= *max_times && !condition.eval(state_data),
+    }
+  }
+}
+
 #[derive(Debug)]
 /// A single step in a flow
 ///
@@ -14,6 +60,15 @@ pub struct Step {
   pub output_vars: Vec<VarId>,
 
   substep_step_ids: Option<Vec<StepId>>,
+  output_conditions: HashMap<VarId, Condition>,
+  tags: HashSet<String>,
+  metadata: HashMap<String, String>,
+  display_names: HashMap<String, String>,
+  description: Option<String>,
+  visit_policy: VisitPolicy,
+  skip_condition: Option<Condition>,
+  repeat: Option<Repeat>,
+  validators: Vec<Box<dyn Validator + Send + Sync>>,
 }
 
 impl ObjectStoreContent for Step {
@@ -38,6 +93,15 @@ impl Step {
       input_vars,
       output_vars,
       substep_step_ids: None,
+      output_conditions: HashMap::new(),
+      tags: HashSet::new(),
+      metadata: HashMap::new(),
+      display_names: HashMap::new(),
+      description: None,
+      visit_policy: VisitPolicy::default(),
+      skip_condition: None,
+      repeat: None,
+      validators: Vec::new(),
     }
   }
 
@@ -54,6 +118,128 @@ impl Step {
     &self.output_vars
   }
 
+  /// Only require the output `var_id` when `condition` holds.
+  ///
+  /// The var is added to the outputs if it's not already there.
+  pub fn set_output_required_when(&mut self, var_id: VarId, condition: Condition) {
+    if !self.output_vars.contains(&var_id) {
+      self.output_vars.push(var_id);
+    }
+    self.output_conditions.insert(var_id, condition);
+  }
+
+  /// Get the condition for when the output `var_id` is required
+  pub fn get_output_condition(&self, var_id: &VarId) -> Option<&Condition> {
+    self.output_conditions.get(var_id)
+  }
+
+  /// Check if the output `var_id` is currently required
+  pub fn is_output_required(&self, var_id: &VarId, state_data: &StateData) -> bool {
+    match self.output_conditions.get(var_id) {
+      Some(condition) => condition.eval(state_data),
+      None => self.output_vars.contains(var_id),
+    }
+  }
+
+  /// Get all vars used by the output conditions
+  pub fn get_condition_vars(&self) -> HashSet<VarId> {
+    self.output_conditions
+      .values()
+      .chain(self.skip_condition.iter())
+      .chain(self.repeat.iter().filter_map(|repeat| match repeat {
+        Repeat::Until { condition, .. } => Some(condition),
+        Repeat::Times(_) => None,
+      }))
+      .flat_map(|condition| condition.var_ids())
+      .collect()
+  }
+
+  /// Add a tag to the step. Tags are free-form labels such as `form`.
+  pub fn add_tag<STR>(&mut self, tag: STR)
+      where STR: Into<String>
+  {
+    self.tags.insert(tag.into());
+  }
+
+  pub fn has_tag(&self, tag: &str) -> bool {
+    self.tags.contains(tag)
+  }
+
+  pub fn tags(&self) -> &HashSet<String> {
+    &self.tags
+  }
+
+  /// Set a free-form metadata value on the step
+  pub fn set_metadata<K, V>(&mut self, key: K, value: V)
+      where K: Into<String>, V: Into<String>
+  {
+    self.metadata.insert(key.into(), value.into());
+  }
+
+  pub fn get_metadata(&self, key: &str) -> Option<&str> {
+    self.metadata.get(key).map(|value| &value[..])
+  }
+
+  /// Set the help text explaining the step, i.e. for generated UIs and admin tools
+  pub fn set_description<D: Into<String>>(&mut self, description: D) {
+    self.description = Some(description.into());
+  }
+
+  /// The help text set with [`set_description`](Step::set_description)
+  pub fn description(&self) -> Option<&str> {
+    self.description.as_deref()
+  }
+
+  /// Set the name shown to users for `locale`, i.e. `en` or `fr-CA`
+  ///
+  /// The step's name in the [`ObjectStore`](stepflow_base::ObjectStore) stays the same for routing.
+  pub fn set_display_name<L, N>(&mut self, locale: L, name: N)
+      where L: Into<String>, N: Into<String>
+  {
+    self.display_names.insert(locale.into(), name.into());
+  }
+
+  /// The name shown to users for `locale`, falling back to its language (`fr` for `fr-CA`)
+  pub fn display_name(&self, locale: &str) -> Option<&str> {
+    self.display_names.get(locale)
+      .or_else(|| {
+        let language = locale.split('-').next()?;
+        self.display_names.get(language)
+      })
+      .map(|name| &name[..])
+  }
+
+  /// Set whether the step runs again when it's revisited
+  pub fn set_visit_policy(&mut self, visit_policy: VisitPolicy) {
+    self.visit_policy = visit_policy;
+  }
+
+  pub fn visit_policy(&self) -> VisitPolicy {
+    self.visit_policy
+  }
+
+  /// Skip the step when `condition` holds, i.e. to branch on an earlier decision.
+  /// Skipped steps don't need their inputs or outputs. This only applies to the step itself and not its substeps.
+  pub fn set_skip_when(&mut self, condition: Condition) {
+    self.skip_condition = Some(condition);
+  }
+
+  /// Check if the step should be skipped. See [`set_skip_when`](Step::set_skip_when).
+  pub fn is_skipped(&self, state_data: &StateData) -> bool {
+    self.skip_condition.as_ref().map(|condition| condition.eval(state_data)).unwrap_or(false)
+  }
+
+  /// Run the substeps again when they're done, i.e. "repeat until `approved` is true"
+  ///
+  /// The outputs of the substeps are cleared before each pass.
+  pub fn set_repeat(&mut self, repeat: Repeat) {
+    self.repeat = Some(repeat);
+  }
+
+  pub fn repeat(&self) -> Option<&Repeat> {
+    self.repeat.as_ref()
+  }
+
   /// Push a substep to the end of the current sub-steps
   pub fn push_substep(&mut self, substep_step_id: StepId) {
     match &mut self.substep_step_ids {
@@ -81,10 +267,32 @@ impl Step {
     iter.next()
   }
 
+  /// Get all the sub-steps in order
+  pub fn get_substeps(&self) -> Option<&Vec<StepId>> {
+    self.substep_step_ids.as_ref()
+  }
+
   pub fn first_substep(&self) -> Option<&StepId> {
     self.substep_step_ids.as_ref()?.first()
   }
 
+  /// Inputs that are missing from `state_data`
+  pub fn missing_inputs(&self, state_data: &StateData) -> Vec<VarId> {
+    self.input_vars.iter()
+      .flatten()
+      .filter(|input_var_id| !state_data.contains(input_var_id))
+      .cloned()
+      .collect()
+  }
+
+  /// Required outputs that are missing from `state_data`
+  pub fn missing_outputs(&self, state_data: &StateData) -> Vec<VarId> {
+    self.output_vars.iter()
+      .filter(|output_var_id| !state_data.contains(output_var_id) && self.is_output_required(output_var_id, state_data))
+      .cloned()
+      .collect()
+  }
+
   /// Verifies that `inputs` fulfills the required inputs to enter the step
   pub fn can_enter(&self, inputs: &StateData) -> Result<(), IdError<VarId>> {
     // see if we're missing any inputs
@@ -104,19 +312,64 @@ impl Step {
     self.can_enter(state_data)?;
 
     // see if we're missing any outputs
-    let first_missing_output = &self.output_vars.iter().find(|output_var_id| !state_data.contains(output_var_id));
+    let first_missing_output = &self.output_vars
+      .iter()
+      .find(|output_var_id| !state_data.contains(output_var_id) && self.is_output_required(output_var_id, state_data));
     if first_missing_output.is_some() {
       return Err(IdError::IdMissing(first_missing_output.unwrap().clone()))
     }
 
     Ok(())
   }
+
+  /// Same as [`can_enter`](Step::can_enter) but with every missing input
+  pub fn can_enter_all(&self, inputs: &StateData) -> Result<(), Vec<VarId>> {
+    let missing = self.missing_inputs(inputs);
+    if missing.is_empty() { Ok(()) } else { Err(missing) }
+  }
+
+  /// Same as [`can_exit`](Step::can_exit) but with every missing input and required output
+  pub fn can_exit_all(&self, state_data: &StateData) -> Result<(), Vec<VarId>> {
+    let mut missing = self.missing_inputs(state_data);
+    missing.extend(self.missing_outputs(state_data));
+    if missing.is_empty() { Ok(()) } else { Err(missing) }
+  }
+
+  /// Add a check across the step's vars that has to pass to exit the step
+  pub fn add_validator<V>(&mut self, validator: V)
+      where V: Validator + Send + Sync + 'static
+  {
+    self.validators.push(Box::new(validator));
+  }
+
+  /// Whether the step has any validators
+  pub fn has_validators(&self) -> bool {
+    !self.validators.is_empty()
+  }
+
+  /// Run the validators on the step's inputs and outputs, returning the errors of all of them
+  pub fn validate(&self, state_data: &StateData) -> Result<(), InvalidVars> {
+    if self.validators.is_empty() {
+      return Ok(());
+    }
+    let var_ids = self.input_vars.iter().flatten().chain(self.output_vars.iter()).copied().collect();
+    let step_data = StateDataFiltered::new(state_data, var_ids);
+    let mut invalid = InvalidVars::new(HashMap::new());
+    for validator in self.validators.iter() {
+      if let Err(InvalidVars(vars)) = validator.validate(&step_data) {
+        invalid.0.extend(vars);
+      }
+    }
+    if invalid.0.is_empty() { Ok(()) } else { Err(invalid) }
+  }
 }
 
 #[cfg(test)]
 mod tests {
-  use stepflow_base::ObjectStoreContent;
-  use super::{ Step };
+  use stepflow_base::{ObjectStoreContent, IdError};
+  use stepflow_data::{StateData, StateDataFiltered, Condition, InvalidValue, InvalidVars, var::{VarId, BoolVar, StringVar}, value::{BoolValue, StringValue}};
+  use stepflow_test_util::test_id;
+  use super::{ Step, StepId, Repeat };
 
   #[test]
   fn test_add_get_substep() {
@@ -136,5 +389,121 @@ mod tests {
     assert_eq!(step.first_substep().unwrap(), substep1.id());
     assert_eq!(step.next_substep(substep1.id()).unwrap(), substep2.id());
     assert_eq!(step.next_substep(&substep2.id()), None);
+    assert_eq!(step.get_substeps().unwrap(), &vec![*substep1.id(), *substep2.id()]);
   }
-}
\ No newline at end of file
+
+  #[test]
+  fn display_names() {
+    let mut step = Step::test_new();
+    step.set_display_name("en", "Email");
+    step.set_display_name("fr", "Courriel");
+    step.set_display_name("fr-CA", "Adresse courriel");
+    assert_eq!(step.display_name("en-US"), Some("Email"));
+    assert_eq!(step.display_name("fr-FR"), Some("Courriel"));
+    assert_eq!(step.display_name("fr-CA"), Some("Adresse courriel"));
+    assert_eq!(step.display_name("de"), None);
+  }
+
+  #[test]
+  fn all_missing() {
+    let input1 = StringVar::new(test_id!(VarId)).boxed();
+    let input2 = StringVar::new(test_id!(VarId)).boxed();
+    let output = StringVar::new(test_id!(VarId)).boxed();
+    let step = Step::new(test_id!(StepId), Some(vec![*input1.id(), *input2.id()]), vec![*output.id()]);
+
+    let mut state_data = StateData::new();
+    assert_eq!(step.can_enter_all(&state_data), Err(vec![*input1.id(), *input2.id()]));
+    assert_eq!(step.can_exit_all(&state_data), Err(vec![*input1.id(), *input2.id(), *output.id()]));
+
+    state_data.insert(&input1, StringValue::try_new("1").unwrap().boxed()).unwrap();
+    state_data.insert(&input2, StringValue::try_new("2").unwrap().boxed()).unwrap();
+    assert_eq!(step.can_enter_all(&state_data), Ok(()));
+    assert_eq!(step.can_exit_all(&state_data), Err(vec![*output.id()]));
+  }
+
+  #[test]
+  fn conditional_output() {
+    let is_business = BoolVar::new(test_id!(VarId)).boxed();
+    let company_name = StringVar::new(test_id!(VarId)).boxed();
+
+    let mut step = Step::new(test_id!(StepId), None, vec![*is_business.id()]);
+    step.set_output_required_when(*company_name.id(), Condition::Equals(*is_business.id(), BoolValue::new(true).boxed()));
+    assert_eq!(step.get_output_vars(), &vec![*is_business.id(), *company_name.id()]);
+    assert_eq!(step.get_condition_vars().len(), 1);
+
+    // not a business, company not needed
+    let mut state_data = StateData::new();
+    state_data.insert(&is_business, BoolValue::new(false).boxed()).unwrap();
+    assert_eq!(step.can_exit(&state_data), Ok(()));
+
+    assert_eq!(step.missing_outputs(&state_data), vec![]);
+
+    // business needs the company
+    state_data.insert(&is_business, BoolValue::new(true).boxed()).unwrap();
+    assert_eq!(step.can_exit(&state_data), Err(IdError::IdMissing(*company_name.id())));
+    assert_eq!(step.missing_outputs(&state_data), vec![*company_name.id()]);
+    assert_eq!(step.can_exit_all(&state_data), Err(vec![*company_name.id()]));
+    state_data.insert(&company_name, StringValue::try_new("stepflow").unwrap().boxed()).unwrap();
+    assert_eq!(step.can_exit(&state_data), Ok(()));
+  }
+
+  #[test]
+  fn validators() {
+    let password = StringVar::new(test_id!(VarId)).boxed();
+    let confirm = StringVar::new(test_id!(VarId)).boxed();
+    let (password_id, confirm_id) = (*password.id(), *confirm.id());
+    let mut step = Step::new(test_id!(StepId), None, vec![password_id, confirm_id]);
+    step.add_validator(move |step_data: &StateDataFiltered| {
+      if step_data.get(&password_id).map(|val| val.get_val()) == step_data.get(&confirm_id).map(|val| val.get_val()) {
+        return Ok(());
+      }
+      Err(InvalidVars::new(vec![(confirm_id, InvalidValue::WrongValue)].into_iter().collect()))
+    });
+    step.add_validator(|_step_data: &StateDataFiltered| Ok(()));
+
+    let mut state_data = StateData::new();
+    state_data.insert(&password, StringValue::try_new("hunter2").unwrap().boxed()).unwrap();
+    state_data.insert(&confirm, StringValue::try_new("hunter3").unwrap().boxed()).unwrap();
+    let invalid = step.validate(&state_data).unwrap_err();
+    assert_eq!(invalid.0.into_iter().collect::<Vec<_>>(), vec![(confirm_id, InvalidValue::WrongValue)]);
+
+    state_data.insert(&confirm, StringValue::try_new("hunter2").unwrap().boxed()).unwrap();
+    assert_eq!(step.validate(&state_data), Ok(()));
+  }
+
+  #[test]
+  fn skip_when() {
+    let approved = BoolVar::new(test_id!(VarId)).boxed();
+    let mut step = Step::new(test_id!(StepId), None, vec![]);
+    let mut state_data = StateData::new();
+    assert!(!step.is_skipped(&state_data));
+
+    step.set_skip_when(Condition::Equals(*approved.id(), BoolValue::new(false).boxed()));
+    assert!(step.get_condition_vars().contains(approved.id()));
+    assert!(!step.is_skipped(&state_data));
+    state_data.insert(&approved, BoolValue::new(false).boxed()).unwrap();
+    assert!(step.is_skipped(&state_data));
+  }
+
+  #[test]
+  fn repeat() {
+    let approved = BoolVar::new(test_id!(VarId)).boxed();
+    let mut state_data = StateData::new();
+    assert!(Repeat::Times(2).should_repeat(1, &state_data));
+    assert!(!Repeat::Times(2).should_repeat(2, &state_data));
+
+    let until = Repeat::Until { condition: Condition::Equals(*approved.id(), BoolValue::new(true).boxed()), max_times: 3 };
+    assert!(until.should_repeat(1, &state_data));
+    assert!(!until.should_repeat(3, &state_data)); // gives up
+    assert!(until.limit_reached(3, &state_data));
+    assert!(!Repeat::Times(2).limit_reached(2, &state_data));
+    state_data.insert(&approved, BoolValue::new(true).boxed()).unwrap();
+    assert!(!until.should_repeat(1, &state_data));
+    assert!(!until.limit_reached(3, &state_data));
+
+    let mut step = Step::new(test_id!(StepId), None, vec![]);
+    step.set_repeat(until.clone());
+    assert_eq!(step.repeat(), Some(&until));
+    assert!(step.get_condition_vars().contains(approved.id()));
+  }
+}
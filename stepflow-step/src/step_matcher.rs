@@ -0,0 +1,94 @@
+use super::Step;
+
+/// Selects [`Step`]s by something other than their [`StepId`](crate::StepId)
+///
+/// ```
+/// # use stepflow_step::{Step, StepId, StepMatcher};
+/// let mut step = Step::new(StepId::new(0), None, vec![]);
+/// step.add_tag("form");
+///
+/// assert!(StepMatcher::Tag("form".to_owned()).matches(&step, None));
+/// assert!(StepMatcher::NamePattern("kyc.*".to_owned()).matches(&step, Some("kyc.address")));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum StepMatcher {
+  /// Name matches the pattern where `*` matches any run of characters. Unnamed steps never match.
+  NamePattern(String),
+
+  /// Step has the tag
+  Tag(String),
+
+  /// Step has the metadata key set to the value
+  Metadata(String, String),
+}
+
+impl StepMatcher {
+  /// Check if the `step` with `step_name` is selected
+  pub fn matches(&self, step: &Step, step_name: Option<&str>) -> bool {
+    match self {
+      StepMatcher::NamePattern(pattern) => step_name.map(|name| wildcard_match(pattern, name)).unwrap_or(false),
+      StepMatcher::Tag(tag) => step.has_tag(tag),
+      StepMatcher::Metadata(key, value) => step.get_metadata(key) == Some(&value[..]),
+    }
+  }
+}
+
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+  let mut parts = pattern.split('*');
+
+  // without a wildcard it must be exact
+  let first = parts.next().unwrap_or("");
+  if !text.starts_with(first) {
+    return false;
+  }
+  let mut rest = &text[first.len()..];
+  let mut parts = parts.collect::<Vec<_>>();
+  let last = match parts.pop() {
+    Some(last) => last,
+    None => return rest.is_empty(),
+  };
+
+  // middle parts can be found anywhere in order, the last must be at the end
+  for part in parts {
+    match rest.find(part) {
+      Some(pos) => rest = &rest[pos + part.len()..],
+      None => return false,
+    }
+  }
+  rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+  use stepflow_test_util::test_id;
+  use super::{StepMatcher, wildcard_match};
+  use crate::{Step, StepId};
+
+  #[test]
+  fn wildcard() {
+    assert!(wildcard_match("kyc", "kyc"));
+    assert!(!wildcard_match("kyc", "kyc.name"));
+    assert!(wildcard_match("kyc.*", "kyc.name"));
+    assert!(wildcard_match("kyc.*", "kyc."));
+    assert!(!wildcard_match("kyc.*", "other.name"));
+    assert!(wildcard_match("*.name", "kyc.name"));
+    assert!(wildcard_match("*", ""));
+    assert!(wildcard_match("a*b*c", "a-b-b-c"));
+    assert!(!wildcard_match("a*b*c", "a-c-b"));
+    assert!(!wildcard_match("ab*ba", "aba"));
+  }
+
+  #[test]
+  fn matches() {
+    let mut step = Step::new(test_id!(StepId), None, vec![]);
+    step.add_tag("form");
+    step.set_metadata("kind", "redirect");
+
+    assert!(StepMatcher::Tag("form".to_owned()).matches(&step, None));
+    assert!(!StepMatcher::Tag("redirect".to_owned()).matches(&step, None));
+    assert!(StepMatcher::Metadata("kind".to_owned(), "redirect".to_owned()).matches(&step, None));
+    assert!(!StepMatcher::Metadata("kind".to_owned(), "form".to_owned()).matches(&step, None));
+    assert!(!StepMatcher::NamePattern("*".to_owned()).matches(&step, None));
+    assert!(StepMatcher::NamePattern("*".to_owned()).matches(&step, Some("name")));
+  }
+}
@@ -0,0 +1,36 @@
+use stepflow_data::{StateDataFiltered, InvalidVars};
+
+/// A check across a [`Step`](crate::Step)'s vars, i.e. a password and its confirmation match. See [`Step::add_validator`](crate::Step::add_validator).
+///
+/// Closures taking the step's data are validators too.
+/// ```
+/// # use std::collections::HashMap;
+/// # use stepflow_data::{InvalidValue, InvalidVars, StateDataFiltered, var::VarId};
+/// # use stepflow_step::{Step, StepId};
+/// let (password, confirm) = (VarId::new(0), VarId::new(1));
+/// let mut step = Step::new(StepId::new(0), None, vec![password, confirm]);
+/// step.add_validator(move |step_data: &StateDataFiltered| {
+///   if step_data.get(&password).map(|val| val.get_val()) == step_data.get(&confirm).map(|val| val.get_val()) {
+///     return Ok(());
+///   }
+///   Err(InvalidVars::new(vec![(confirm, InvalidValue::WrongValue)].into_iter().collect::<HashMap<_, _>>()))
+/// });
+/// ```
+pub trait Validator {
+  /// Check the step's inputs and outputs, returning every var that's wrong
+  fn validate(&self, step_data: &StateDataFiltered) -> Result<(), InvalidVars>;
+}
+
+impl<F> Validator for F
+    where F: Fn(&StateDataFiltered) -> Result<(), InvalidVars>
+{
+  fn validate(&self, step_data: &StateDataFiltered) -> Result<(), InvalidVars> {
+    self(step_data)
+  }
+}
+
+impl std::fmt::Debug for dyn Validator + Send + Sync {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "Validator")
+  }
+}
@@ -0,0 +1,214 @@
+//! Per-session event streams so UIs can react to a session without polling
+//!
+//! Advance with [`SessionEvents::advance_session`] and the events are sent to everyone who [subscribed](SessionEvents::subscribe) to the session.
+//! [`SessionEvent::to_sse`] formats an event for server-sent events. The same text works as a websocket message.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use stepflow_data::StateData;
+use stepflow_session::{Session, SessionId, AdvanceBlockedOn, LoadShedding, Error};
+use stepflow_step::StepId;
+use crate::{SessionStore, stored_session, field_errors};
+
+/// Something that happened in a session
+#[derive(Debug, Clone, PartialEq)]
+pub enum SessionEvent {
+  /// The session moved to another step
+  StepChanged { step: String },
+
+  /// Submitted fields weren't valid. Each field has what was wrong with it.
+  ValidationFailed { fields: Vec<(String, String)> },
+
+  /// The flow is finished
+  Finished,
+}
+
+fn escape_json(s: &str) -> String {
+  s.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+impl SessionEvent {
+  /// Name of the event, i.e. `step_changed`
+  pub fn name(&self) -> &'static str {
+    match self {
+      SessionEvent::StepChanged { .. } => "step_changed",
+      SessionEvent::ValidationFailed { .. } => "validation_failed",
+      SessionEvent::Finished => "finished",
+    }
+  }
+
+  /// The event's data as JSON
+  pub fn data_json(&self) -> String {
+    match self {
+      SessionEvent::StepChanged { step } => format!("{{\"step\":\"{}\"}}", escape_json(step)),
+      SessionEvent::ValidationFailed { fields } => {
+        let fields = fields.iter()
+          .map(|(name, error)| format!("\"{}\":\"{}\"", escape_json(name), escape_json(error)))
+          .collect::<Vec<_>>();
+        format!("{{\"fields\":{{{}}}}}", fields.join(","))
+      }
+      SessionEvent::Finished => "{}".to_owned(),
+    }
+  }
+
+  /// Format as a server-sent event
+  pub fn to_sse(&self) -> String {
+    format!("event: {}\ndata: {}\n\n", self.name(), self.data_json())
+  }
+}
+
+// name of the current step or `None` when finished
+fn current_step_name(session: &Session) -> Option<String> {
+  let step_id = session.current_step().ok()?;
+  Some(session.step_store().name_from_id(step_id).map(|name| name.to_owned()).unwrap_or_else(|| step_id.to_string()))
+}
+
+/// Subscribers for each session's events
+#[derive(Debug, Default)]
+pub struct SessionEvents {
+  subscribers: Mutex<HashMap<SessionId, Vec<Sender<SessionEvent>>>>,
+  load_shedding: LoadShedding,
+}
+
+impl SessionEvents {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Only publish a sample of the events while `load_shedding` says so. See [`LoadShedding`].
+  pub fn set_load_shedding(&mut self, load_shedding: LoadShedding) {
+    self.load_shedding = load_shedding;
+  }
+
+  /// Get the events for the session. Dropping the receiver unsubscribes.
+  pub fn subscribe(&self, session_id: SessionId) -> Receiver<SessionEvent> {
+    let (sender, receiver) = channel();
+    self.subscribers.lock().unwrap().entry(session_id).or_default().push(sender);
+    receiver
+  }
+
+  /// Send the event to the session's subscribers
+  pub fn publish(&self, session_id: &SessionId, event: SessionEvent) {
+    if !self.load_shedding.should_emit() {
+      return;
+    }
+    let mut subscribers = self.subscribers.lock().unwrap();
+    if let Some(senders) = subscribers.get_mut(session_id) {
+      senders.retain(|sender| sender.send(event.clone()).is_ok());
+      if senders.is_empty() {
+        subscribers.remove(session_id);
+      }
+    }
+  }
+
+  /// Same as [`advance_session`](crate::advance_session) but publishes what happened
+  pub fn advance_session(&self, session_store: &SessionStore, session_id: &SessionId, step_output: Option<(&StepId, StateData)>)
+      -> Result<AdvanceBlockedOn, Error>
+  {
+    let stored = stored_session(session_store, session_id)?;
+    let mut session = stored.lock()?;
+    let step_before = current_step_name(&session);
+
+    let advance_result = session.advance(step_output);
+
+    let event = match &advance_result {
+      Ok(AdvanceBlockedOn::FinishedAdvancing) => Some(SessionEvent::Finished),
+      Ok(_) => current_step_name(&session)
+        .filter(|step| Some(step) != step_before.as_ref())
+        .map(|step| SessionEvent::StepChanged { step }),
+      Err(error) => {
+        let mut fields = field_errors(&session, error).into_iter()
+          .map(|(name, invalid)| (name, invalid.to_string()))
+          .collect::<Vec<_>>();
+        fields.sort();
+        if fields.is_empty() { None } else { Some(SessionEvent::ValidationFailed { fields }) }
+      }
+    };
+    drop(session);
+    if let Some(event) = event {
+      self.publish(session_id, event);
+    }
+    advance_result
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use stepflow_action::{Action, ActionId, ActionResult, ActionError};
+  use stepflow_base::ObjectStoreFiltered;
+  use stepflow_data::{StateData, StateDataFiltered, InvalidValue, var::{Var, VarId, StringVar}, value::{TrueValue, StringValue}};
+  use stepflow_session::{Session, SessionId, LoadShedding};
+  use stepflow_step::Step;
+  use crate::{StoredSession, new_session_store, stored_session};
+  use super::{SessionEvents, SessionEvent};
+
+  // waits for the user on every step
+  #[derive(Debug)]
+  struct WaitAction(ActionId);
+  impl Action for WaitAction {
+    fn id(&self) -> &ActionId { &self.0 }
+    fn start(&mut self, _step: &Step, _step_name: Option<&str>, _step_data: &StateDataFiltered, _vars: &ObjectStoreFiltered<Box<dyn Var + Send + Sync>, VarId>)
+        -> Result<ActionResult, ActionError>
+    {
+      Ok(ActionResult::StartWith(TrueValue::new().boxed()))
+    }
+  }
+
+  #[test]
+  fn events() {
+    let session_store = new_session_store();
+    let (session_id, step_id, var_id) = {
+      let session_id = session_store.write().unwrap().insert_new(|id| Ok(StoredSession::new(Session::new(id)))).unwrap();
+      let stored = stored_session(&session_store, &session_id).unwrap();
+      let mut session = stored.lock().unwrap();
+      let var_id = session.var_store_mut().insert_new_named("name", |id| Ok(StringVar::new(id).boxed())).unwrap();
+      let step_id = session.step_store_mut().insert_new_named("name \"step\"", |id| Ok(Step::new(id, None, vec![var_id]))).unwrap();
+      session.push_root_substep(step_id);
+      let action_id = session.action_store_mut().insert_new(|id| Ok(WaitAction(id).boxed())).unwrap();
+      session.set_action_for_step(action_id, None).unwrap();
+      (session_id, step_id, var_id)
+    };
+
+    let events = SessionEvents::new();
+    let receiver = events.subscribe(session_id);
+    events.advance_session(&session_store, &session_id, None).unwrap();
+    let event = receiver.try_recv().unwrap();
+    assert_eq!(event, SessionEvent::StepChanged { step: "name \"step\"".to_owned() });
+    assert_eq!(event.to_sse(), "event: step_changed\ndata: {\"step\":\"name \\\"step\\\"\"}\n\n");
+
+    // staying on the same step isn't a change
+    events.advance_session(&session_store, &session_id, None).unwrap();
+    assert!(receiver.try_recv().is_err());
+
+    events.publish(&session_id, SessionEvent::ValidationFailed { fields: vec![("name".to_owned(), InvalidValue::Empty.to_string())] });
+    assert_eq!(receiver.try_recv().unwrap().data_json(), "{\"fields\":{\"name\":\"Empty\"}}");
+
+    let mut data = StateData::new();
+    data.insert(&StringVar::new(var_id).boxed(), StringValue::try_new("Jane").unwrap().boxed()).unwrap();
+    events.advance_session(&session_store, &session_id, Some((&step_id, data))).unwrap();
+    assert_eq!(receiver.try_recv().unwrap(), SessionEvent::Finished);
+
+    // unsubscribed by dropping the receiver
+    drop(receiver);
+    events.publish(&session_id, SessionEvent::Finished);
+    assert!(events.subscribers.lock().unwrap().is_empty());
+  }
+
+  #[test]
+  fn load_shedding() {
+    let session_id = SessionId::new(0);
+    let shedding = LoadShedding::new();
+    let mut events = SessionEvents::new();
+    events.set_load_shedding(shedding.clone());
+    let receiver = events.subscribe(session_id);
+
+    shedding.set_sample_percent(0);
+    events.publish(&session_id, SessionEvent::Finished);
+    assert!(receiver.try_recv().is_err());
+
+    shedding.set_sample_percent(100);
+    events.publish(&session_id, SessionEvent::Finished);
+    assert_eq!(receiver.try_recv(), Ok(SessionEvent::Finished));
+  }
+}
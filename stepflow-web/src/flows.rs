@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use stepflow_base::{ObjectStore, IdError};
+use stepflow_data::{StateData, value::Value};
+use stepflow_session::{Session, SessionId, Error};
+use crate::{SessionStore, StoredSession};
+
+/// Defines a new session's flow
+pub type SessionFactory = Arc<dyn Fn(SessionId) -> Result<Session, Error> + Send + Sync>;
+
+/// A follow-up flow and the session it started or why it couldn't. See [`FlowRegistry::start_follow_ups`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct StartedFollowUp {
+  pub flow: String,
+  pub result: Result<SessionId, Error>,
+}
+
+/// Named flows so sessions can start [follow-up flows](Session::add_follow_up) when they finish
+#[derive(Default, Clone)]
+pub struct FlowRegistry {
+  flows: HashMap<String, SessionFactory>,
+}
+
+impl std::fmt::Debug for FlowRegistry {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("FlowRegistry")
+      .field("flows", &self.flows.keys().collect::<Vec<_>>())
+      .finish()
+  }
+}
+
+impl FlowRegistry {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Add a flow. Replaces any flow with the same name.
+  pub fn register(&mut self, flow: String, session_factory: SessionFactory) {
+    self.flows.insert(flow, session_factory);
+  }
+
+  /// Create a session for `flow` in the store with the `seed` data, matching up vars by name
+  ///
+  /// Vars the flow doesn't have are left out.
+  pub fn start(&self, session_store: &SessionStore, flow: &str, seed: &[(String, Box<dyn Value>)]) -> Result<SessionId, Error> {
+    self.start_with(session_store, flow, seed, |_session| ())
+  }
+
+  /// Create a [preview](Session::set_preview) of `flow` that expires after `lifetime`, i.e. to try the flow from a marketing page
+  ///
+  /// Keep previews in their own store so they don't mix with real sessions, and clean it up with [`remove_expired_previews`](crate::remove_expired_previews).
+  pub fn start_preview(&self, preview_store: &SessionStore, flow: &str, lifetime: Duration) -> Result<SessionId, Error> {
+    self.start_with(preview_store, flow, &[], |session| session.set_preview(SystemTime::now() + lifetime))
+  }
+
+  fn start_with<F>(&self, session_store: &SessionStore, flow: &str, seed: &[(String, Box<dyn Value>)], setup: F) -> Result<SessionId, Error>
+      where F: FnOnce(&mut Session)
+  {
+    let mut session_store = session_store.write().map_err(|_| Error::Other)?;
+    self.start_in(&mut session_store, flow, seed, setup)
+  }
+
+  fn start_in<F>(&self, session_store: &mut ObjectStore<StoredSession, SessionId>, flow: &str, seed: &[(String, Box<dyn Value>)], setup: F) -> Result<SessionId, Error>
+      where F: FnOnce(&mut Session)
+  {
+    let session_factory = self.flows.get(flow).ok_or_else(|| Error::SessionId(IdError::NoSuchName(flow.to_owned())))?;
+    let session_id = session_store.reserve_id()?;
+    let mut session = session_factory(session_id)?;
+    setup(&mut session);
+
+    let mut state_data = StateData::new();
+    for (name, val) in seed {
+      if let Some(var) = session.var_store().get_by_name(name) {
+        state_data.insert(var, val.clone())?;
+      }
+    }
+    session.seed_data(state_data)?;
+    Ok(session_store.register(StoredSession::new(session))?)
+  }
+
+  /// Start the session's [follow-up flows](Session::pending_follow_ups) if it's finished. Call this after advancing.
+  ///
+  /// Flows that failed to start are tried again on the next call.
+  pub fn start_follow_ups(&self, session_store: &SessionStore, session_id: &SessionId) -> Result<Vec<StartedFollowUp>, Error> {
+    // lock the store before the session so follow-ups are only started once
+    let mut session_store = session_store.write().map_err(|_| Error::Other)?;
+    let stored = session_store.get(session_id).cloned().ok_or(Error::SessionId(IdError::IdMissing(*session_id)))?;
+    let mut session = stored.lock()?;
+    let follow_ups = session.pending_follow_ups();
+
+    let mut started = Vec::new();
+    for follow_up in follow_ups {
+      let result = self.start_in(&mut session_store, &follow_up.flow, &follow_up.seed, |_session| ());
+      if result.is_ok() {
+        session.set_follow_up_started(follow_up.index)?;
+      }
+      started.push(StartedFollowUp { flow: follow_up.flow, result });
+    }
+    Ok(started)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::Arc;
+  use std::time::{Duration, SystemTime};
+  use stepflow_action::{Action, EscapedString, StringTemplateAction, UriEscapedString};
+  use stepflow_base::IdError;
+  use stepflow_data::{Condition, var::StringVar, value::StringValue};
+  use stepflow_session::{Session, Error};
+  use stepflow_step::Step;
+  use crate::{new_session_store, stored_session, advance_session, remove_expired_previews, form_to_state_data};
+  use super::{FlowRegistry, SessionFactory, StartedFollowUp};
+
+  fn email_flow(follow_up: Option<&'static str>) -> SessionFactory {
+    Arc::new(move |session_id| {
+      let mut session = Session::new(session_id);
+      let email = session.var_store_mut().insert_new_named("email", |id| Ok(StringVar::new(id).boxed()))?;
+      let step_id = session.step_store_mut().insert_new_named("email", |id| Ok(Step::new(id, None, vec![email])))?;
+      session.push_root_substep(step_id);
+      let action_id = session.action_store_mut().insert_new(|id| {
+        Ok(StringTemplateAction::new(id, UriEscapedString::already_escaped("/{{step}}".to_owned())).boxed())
+      })?;
+      session.set_action_for_step(action_id, None)?;
+      if let Some(flow) = follow_up {
+        session.add_follow_up(flow.to_owned(), vec![email], Condition::All(vec![]))?;
+      }
+      Ok(session)
+    })
+  }
+
+  #[test]
+  fn follow_ups() {
+    let mut registry = FlowRegistry::new();
+    registry.register("signup".to_owned(), email_flow(Some("onboarding")));
+    let session_store = new_session_store();
+    assert_eq!(registry.start(&session_store, "missing", &[]), Err(Error::SessionId(IdError::NoSuchName("missing".to_owned()))));
+
+    let signup_id = registry.start(&session_store, "signup", &[]).unwrap();
+    advance_session(&session_store, &signup_id, None).unwrap();
+    assert_eq!(registry.start_follow_ups(&session_store, &signup_id), Ok(vec![]));
+
+    let (step_id, state_data) = {
+      let stored = stored_session(&session_store, &signup_id).unwrap();
+      let session = stored.lock().unwrap();
+      let step_id = *session.current_step().unwrap();
+      (step_id, form_to_state_data(&session, vec![("email".to_owned(), "a@b.c".to_owned())]).unwrap())
+    };
+    advance_session(&session_store, &signup_id, Some((&step_id, state_data))).unwrap();
+
+    // a flow that fails to start is reported and tried again
+    assert_eq!(
+      registry.start_follow_ups(&session_store, &signup_id),
+      Ok(vec![StartedFollowUp { flow: "onboarding".to_owned(), result: Err(Error::SessionId(IdError::NoSuchName("onboarding".to_owned()))) }]));
+    registry.register("onboarding".to_owned(), email_flow(None));
+    let started = registry.start_follow_ups(&session_store, &signup_id).unwrap();
+    assert_eq!(started.len(), 1);
+    assert_eq!(started[0].flow, "onboarding");
+    let onboarding_id = started[0].result.clone().unwrap();
+    assert_eq!(registry.start_follow_ups(&session_store, &signup_id), Ok(vec![]));
+
+    // onboarding already has the email
+    let stored = stored_session(&session_store, &onboarding_id).unwrap();
+    let onboarding = stored.lock().unwrap();
+    let email = onboarding.var_id_from_name("email").unwrap();
+    assert_eq!(onboarding.state_data().get(&email).unwrap().get_val(), &StringValue::try_new("a@b.c").unwrap().boxed());
+  }
+
+  #[test]
+  fn previews() {
+    let mut registry = FlowRegistry::new();
+    registry.register("signup".to_owned(), email_flow(Some("onboarding")));
+    registry.register("onboarding".to_owned(), email_flow(None));
+    let preview_store = new_session_store();
+    let preview_id = registry.start_preview(&preview_store, "signup", Duration::from_secs(600)).unwrap();
+    assert!(stored_session(&preview_store, &preview_id).unwrap().lock().unwrap().is_preview());
+
+    // finishing a preview doesn't start anything
+    advance_session(&preview_store, &preview_id, None).unwrap();
+    let (step_id, state_data) = {
+      let stored = stored_session(&preview_store, &preview_id).unwrap();
+      let session = stored.lock().unwrap();
+      (*session.current_step().unwrap(), form_to_state_data(&session, vec![("email".to_owned(), "a@b.c".to_owned())]).unwrap())
+    };
+    advance_session(&preview_store, &preview_id, Some((&step_id, state_data))).unwrap();
+    assert_eq!(registry.start_follow_ups(&preview_store, &preview_id), Ok(vec![]));
+
+    assert_eq!(remove_expired_previews(&preview_store, SystemTime::now()), Ok(vec![]));
+    assert_eq!(remove_expired_previews(&preview_store, SystemTime::now() + Duration::from_secs(600)), Ok(vec![preview_id]));
+    assert!(preview_store.read().unwrap().is_empty());
+  }
+}
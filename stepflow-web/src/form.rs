@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+use stepflow_data::{StateData, InvalidValue, InvalidVars, var::VarId, value::UnansweredValue};
+#[cfg(any(feature = "graphql", feature = "json-api"))]
+use stepflow_data::var::{Var, BoolVar, EmailVar, StringVar, TrueVar};
+use stepflow_session::{Session, Error};
+
+/// Convert posted form fields to [`StateData`] using the session's vars. Fields without a var are ignored.
+///
+/// A field named with the [skip prefix](UnansweredValue::SKIP_FIELD_PREFIX) and a var's name, i.e. `skip:newsletter`, submits the var
+/// as [unanswered](UnansweredValue) unless its value is `false`. The var's own field is ignored then.
+/// Fails with every field that couldn't be converted. Skipping a var that doesn't [allow it](stepflow_data::var::Var::allows_unanswered) is [`InvalidValue::WrongType`].
+pub fn form_to_state_data<I>(session: &Session, form: I) -> Result<StateData, InvalidVars>
+    where I: IntoIterator<Item = (String, String)>
+{
+  FormSubmission::new(session, form).into_result()
+}
+
+/// A posted form converted to the session's vars, kept so a resubmission only revalidates the fields that changed
+///
+/// ```
+/// # use stepflow_session::{Session, SessionId};
+/// # use stepflow_data::var::{StringVar, EmailVar};
+/// # use stepflow_web::FormSubmission;
+/// # let mut session = Session::new(SessionId::new(0));
+/// # session.var_store_mut().insert_new_named("name", |id| Ok(StringVar::new(id).boxed())).unwrap();
+/// # session.var_store_mut().insert_new_named("email", |id| Ok(EmailVar::new(id).boxed())).unwrap();
+/// let form = vec![("name".to_owned(), "Jane".to_owned()), ("email".to_owned(), "jane".to_owned())];
+/// let submission = FormSubmission::new(&session, form);
+/// assert!(submission.result().is_err());
+///
+/// // only the email is checked again
+/// let form = vec![("name".to_owned(), "Jane".to_owned()), ("email".to_owned(), "jane@example.com".to_owned())];
+/// assert!(submission.resubmit(&session, form).result().is_ok());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormSubmission {
+  fields: HashMap<String, String>,
+  state_data: StateData,
+  invalid: HashMap<VarId, InvalidValue>,
+}
+
+impl FormSubmission {
+  /// Convert every field. Fields without a var are ignored.
+  pub fn new<I>(session: &Session, form: I) -> Self
+      where I: IntoIterator<Item = (String, String)>
+  {
+    let empty = FormSubmission {
+      fields: HashMap::new(),
+      state_data: StateData::new(),
+      invalid: HashMap::new(),
+    };
+    empty.resubmit(session, form)
+  }
+
+  /// Convert the fields that differ from this submission and keep the results of the rest
+  ///
+  /// Unchanged fields keep their previously valid value or their previous error. Fields that aren't posted again are dropped.
+  pub fn resubmit<I>(&self, session: &Session, form: I) -> Self
+      where I: IntoIterator<Item = (String, String)>
+  {
+    let fields: HashMap<String, String> = form.into_iter().collect();
+    let mut state_data = StateData::new();
+    let mut invalid = HashMap::new();
+    let skipped = fields.iter()
+      .filter(|(_, field_val)| *field_val != "false")
+      .filter_map(|(field_name, _)| field_name.strip_prefix(UnansweredValue::SKIP_FIELD_PREFIX))
+      .collect::<Vec<_>>();
+    for var_name in skipped.iter() {
+      if let Some(var) = session.var_store().get_by_name(var_name) {
+        if let Err(e) = state_data.insert(var, UnansweredValue::new().boxed()) {
+          invalid.insert(*var.id(), e);
+        }
+      }
+    }
+    for (field_name, field_val) in fields.iter().filter(|(field_name, _)| !skipped.contains(&&field_name[..])) {
+      let var = match session.var_store().get_by_name(field_name) {
+        Some(var) => var,
+        None => continue,
+      };
+      let var_id = var.id();
+      let unchanged = self.fields.get(field_name) == Some(field_val);
+      let result = match (unchanged, self.state_data.get(var_id), self.invalid.get(var_id)) {
+        (true, Some(valid_val), _) => state_data.insert(var, valid_val.get_val().clone()),
+        (true, None, Some(e)) => Err(*e),
+        _ => var.value_from_str(field_val).and_then(|val| state_data.insert(var, val)),
+      };
+      if let Err(e) = result {
+        invalid.insert(*var_id, e);
+      }
+    }
+    FormSubmission { fields, state_data, invalid }
+  }
+
+  /// The posted fields
+  pub fn fields(&self) -> &HashMap<String, String> {
+    &self.fields
+  }
+
+  /// The valid values, even if other fields are invalid
+  pub fn state_data(&self) -> &StateData {
+    &self.state_data
+  }
+
+  /// The values if every field is valid, otherwise every field that isn't
+  pub fn result(&self) -> Result<StateData, InvalidVars> {
+    self.clone().into_result()
+  }
+
+  /// Same as [`result`](FormSubmission::result) without cloning the values
+  pub fn into_result(self) -> Result<StateData, InvalidVars> {
+    if !self.invalid.is_empty() {
+      return Err(InvalidVars::new(self.invalid));
+    }
+    Ok(self.state_data)
+  }
+}
+
+// type names for the built-in vars
+#[cfg(any(feature = "graphql", feature = "json-api"))]
+pub(crate) fn var_type_name(var: &(dyn Var + Send + Sync + 'static)) -> &'static str {
+  if var.is::<StringVar>() {
+    "String"
+  } else if var.is::<EmailVar>() {
+    "Email"
+  } else if var.is::<BoolVar>() {
+    "Bool"
+  } else if var.is::<TrueVar>() {
+    "True"
+  } else {
+    "Custom"
+  }
+}
+
+/// Field names with what's wrong with them so a form can show the errors. Missing vars are [`InvalidValue::Empty`].
+pub fn field_errors(session: &Session, error: &Error) -> HashMap<String, InvalidValue> {
+  let var_errors: Vec<_> = match error {
+    Error::InvalidVars(invalid) => invalid.0.iter().map(|(var_id, invalid)| (*var_id, *invalid)).collect(),
+    Error::MissingVars(advance_error) => advance_error.var_ids().into_iter().map(|var_id| (var_id, InvalidValue::Empty)).collect(),
+    _ => Vec::new(),
+  };
+  var_errors.into_iter()
+    .filter_map(|(var_id, invalid)| {
+      let name = session.var_store().name_from_id(&var_id)?;
+      Some((name.to_owned(), invalid))
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use stepflow_data::{InvalidValue, InvalidVars, var::{StringVar, EmailVar, BoolVar}};
+  use stepflow_session::{Session, SessionId, Error, AdvanceError, TransitionCheck};
+  use stepflow_step::StepId;
+  use stepflow_test_util::test_id;
+  use super::{form_to_state_data, field_errors, FormSubmission};
+
+  #[test]
+  fn form() {
+    let mut session = Session::new(test_id!(SessionId));
+    let name_id = session.var_store_mut().insert_new_named("name", |id| Ok(StringVar::new(id).boxed())).unwrap();
+    let email_id = session.var_store_mut().insert_new_named("email", |id| Ok(EmailVar::new(id).boxed())).unwrap();
+
+    let form = vec![("name".to_owned(), "Jane".to_owned()), ("unknown".to_owned(), "?".to_owned())];
+    let state_data = form_to_state_data(&session, form).unwrap();
+    assert!(state_data.contains(&name_id));
+    assert!(!state_data.contains(&email_id));
+
+    // bad email
+    let form = vec![("name".to_owned(), "Jane".to_owned()), ("email".to_owned(), "not an email".to_owned())];
+    let error = Error::InvalidVars(form_to_state_data(&session, form).unwrap_err());
+    assert_eq!(field_errors(&session, &error), vec![("email".to_owned(), InvalidValue::BadFormat)].into_iter().collect());
+
+    // skipped vars are unanswered and their field is ignored
+    let newsletter_id = session.var_store_mut().insert_new_named("newsletter", |id| Ok(BoolVar::new(id).allow_unanswered().boxed())).unwrap();
+    let form = vec![("skip:newsletter".to_owned(), "true".to_owned()), ("newsletter".to_owned(), "".to_owned())];
+    assert!(form_to_state_data(&session, form).unwrap().is_unanswered(&newsletter_id));
+    let form = vec![("skip:newsletter".to_owned(), "false".to_owned()), ("newsletter".to_owned(), "true".to_owned())];
+    assert!(!form_to_state_data(&session, form).unwrap().is_unanswered(&newsletter_id));
+    let form = vec![("skip:name".to_owned(), "true".to_owned())];
+    assert_eq!(form_to_state_data(&session, form), Err(InvalidVars::new(vec![(name_id, InvalidValue::WrongType)].into_iter().collect())));
+
+    // missing data
+    let error = Error::MissingVars(AdvanceError {
+      step_id: StepId::new(0),
+      step_name: None,
+      check: TransitionCheck::Exit,
+      missing_vars: vec![(name_id, Some("name".to_owned()))],
+    });
+    assert_eq!(field_errors(&session, &error), vec![("name".to_owned(), InvalidValue::Empty)].into_iter().collect());
+  }
+
+  #[test]
+  fn resubmit() {
+    let mut session = Session::new(test_id!(SessionId));
+    let name_id = session.var_store_mut().insert_new_named("name", |id| Ok(StringVar::new(id).boxed())).unwrap();
+    let email_id = session.var_store_mut().insert_new_named("email", |id| Ok(EmailVar::new(id).boxed())).unwrap();
+    let to_form = |fields: &[(&str, &str)]| fields.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect::<Vec<_>>();
+
+    let first = FormSubmission::new(&session, to_form(&[("name", "Jane"), ("email", "jane")]));
+    assert!(first.state_data().contains(&name_id));
+    assert_eq!(first.result(), Err(InvalidVars::new(vec![(email_id, InvalidValue::BadFormat)].into_iter().collect())));
+
+    // unchanged errors stay, valid values are kept when another field errors
+    let second = first.resubmit(&session, to_form(&[("name", "Jane"), ("email", "jane")]));
+    assert_eq!(second, first);
+    let third = second.resubmit(&session, to_form(&[("name", ""), ("email", "jane@example.com")]));
+    assert!(third.state_data().contains(&email_id));
+    assert_eq!(third.result(), Err(InvalidVars::new(vec![(name_id, InvalidValue::Empty)].into_iter().collect())));
+
+    // fixed, combined with the previously valid email
+    let fourth = third.resubmit(&session, to_form(&[("name", "Jo"), ("email", "jane@example.com")]));
+    let state_data = fourth.into_result().unwrap();
+    assert!(state_data.contains(&name_id) && state_data.contains(&email_id));
+
+    // fields not posted again are dropped
+    let fifth = first.resubmit(&session, to_form(&[("name", "Jane")]));
+    assert!(!fifth.state_data().contains(&email_id));
+    assert!(fifth.result().is_ok());
+  }
+}
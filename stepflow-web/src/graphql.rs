@@ -0,0 +1,292 @@
+//! GraphQL API for sessions with [async-graphql](https://docs.rs/async-graphql)
+//!
+//! Queries return a session's state with the fields its current step needs. Mutations create sessions and submit step data.
+//! The fields come from the flow's definition so there's nothing to keep in sync by hand.
+
+use async_graphql::{Context, EmptySubscription, InputObject, Object, Schema, SimpleObject};
+use stepflow_base::IdError;
+use stepflow_data::{BaseValue, value::UnansweredValue};
+use stepflow_session::{Session, SessionId, Error};
+use crate::{SessionStore, StoredSession, SessionFactory, AdvanceReply, stored_session, advance_session, form_to_state_data, field_errors, reply_from_advance};
+use crate::form::var_type_name;
+
+/// The schema from [`build_schema`]
+pub type FlowSchema = Schema<QueryRoot, MutationRoot, EmptySubscription>;
+
+/// Create the schema for the sessions in `session_store`
+pub fn build_schema(session_store: SessionStore, session_factory: SessionFactory) -> FlowSchema {
+  Schema::build(QueryRoot, MutationRoot, EmptySubscription)
+    .data(session_store)
+    .data(session_factory)
+    .finish()
+}
+
+fn gql_error(error: Error) -> async_graphql::Error {
+  async_graphql::Error::new(error.to_string())
+}
+
+/// A field the current step needs
+#[derive(SimpleObject)]
+pub struct FieldInfo {
+  pub name: String,
+  pub var_type: String,
+  pub required: bool,
+
+  /// The field can be skipped
+  pub can_skip: bool,
+
+  pub choices: Option<Vec<String>>,
+  pub description: Option<String>,
+}
+
+/// A var with a value
+#[derive(SimpleObject)]
+pub struct FieldValue {
+  pub name: String,
+  pub value: String,
+}
+
+/// What's wrong with a submitted field
+#[derive(SimpleObject)]
+pub struct FieldError {
+  pub name: String,
+  pub error: String,
+}
+
+/// Submitted value for a field
+#[derive(InputObject)]
+pub struct FieldInput {
+  pub name: String,
+  pub value: String,
+}
+
+/// State of a session
+#[derive(SimpleObject)]
+pub struct SessionState {
+  pub id: u16,
+
+  /// `None` once the flow is finished
+  pub current_step: Option<String>,
+
+  /// Help text for the current step
+  pub step_description: Option<String>,
+
+  /// Help text for the action the current step is waiting on
+  pub action_description: Option<String>,
+
+  /// Outputs of the current step
+  pub fields: Vec<FieldInfo>,
+
+  /// Named vars with a value
+  pub data: Vec<FieldValue>,
+}
+
+impl SessionState {
+  fn new(session: &Session) -> Self {
+    let current_step = session.current_step().ok().and_then(|step_id| session.step_store().get(step_id));
+    let fields = current_step
+      .map(|step| {
+        step.get_output_vars().iter()
+          .filter_map(|var_id| {
+            let name = session.var_store().name_from_id(var_id)?;
+            let var = session.var_store().get(var_id)?;
+            Some(FieldInfo {
+              name: name.to_owned(),
+              var_type: var_type_name(var.as_ref()).to_owned(),
+              required: step.is_output_required(var_id, session.state_data()),
+              can_skip: var.allows_unanswered(),
+              choices: var.choices().map(|choices| choices.to_vec()),
+              description: session.var_description(var_id).map(|description| description.to_owned()),
+            })
+          })
+          .collect()
+      })
+      .unwrap_or_default();
+
+    let mut data = session.state_data().iter_val()
+      .filter_map(|(var_id, val)| {
+        let name = session.var_store().name_from_id(var_id)?;
+        let value = match val.get_baseval() {
+          BaseValue::String(s) => s,
+          BaseValue::Boolean(b) => b.to_string(),
+          BaseValue::Int(i) => i.to_string(),
+          BaseValue::Float(f) => f.to_string(),
+          list @ BaseValue::List(_) => list.to_string(),
+          BaseValue::Null => String::new(),
+        };
+        Some(FieldValue { name: name.to_owned(), value })
+      })
+      .collect::<Vec<_>>();
+    data.sort_by(|a, b| a.name.cmp(&b.name));
+
+    SessionState {
+      id: session.id().val(),
+      current_step: current_step.map(|step| {
+        session.step_store().name_from_id(&step.id).map(|name| name.to_owned()).unwrap_or_else(|| step.id.to_string())
+      }),
+      step_description: current_step.and_then(|step| step.description()).map(|description| description.to_owned()),
+      action_description: session.current_action()
+        .and_then(|action_id| session.action_description(&action_id))
+        .map(|description| description.to_owned()),
+      fields,
+      data,
+    }
+  }
+}
+
+/// Result of the `submitStep` mutation
+#[derive(SimpleObject)]
+pub struct SubmitResult {
+  pub session: SessionState,
+
+  /// Where to go next if the action gave a URI
+  pub redirect: Option<String>,
+
+  /// Problems with the submitted fields. The session doesn't advance if there are any.
+  pub field_errors: Vec<FieldError>,
+}
+
+/// GraphQL queries
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+  /// Get a session's state
+  async fn session(&self, ctx: &Context<'_>, id: u16) -> async_graphql::Result<Option<SessionState>> {
+    let stored = ctx.data::<SessionStore>()?.read().map_err(|_| gql_error(Error::Other))?.get(&SessionId::new(id)).cloned();
+    match stored {
+      Some(stored) => Ok(Some(SessionState::new(&*stored.lock().map_err(gql_error)?))),
+      None => Ok(None),
+    }
+  }
+}
+
+/// GraphQL mutations
+pub struct MutationRoot;
+
+#[Object]
+impl MutationRoot {
+  /// Create a session and advance it to its first step
+  async fn create_session(&self, ctx: &Context<'_>) -> async_graphql::Result<SessionState> {
+    let store = ctx.data::<SessionStore>()?;
+    let session_factory = ctx.data::<SessionFactory>()?;
+    let session_id = {
+      let mut session_store = store.write().map_err(|_| gql_error(Error::Other))?;
+      let session_id = session_store.reserve_id().map_err(|e| gql_error(Error::from(e)))?;
+      let session = session_factory(session_id).map_err(gql_error)?;
+      session_store.register(StoredSession::new(session)).map_err(|e| gql_error(Error::from(e)))?
+    };
+    advance_session(store, &session_id, None).map_err(gql_error)?;
+
+    let stored = stored_session(store, &session_id).map_err(gql_error)?;
+    let session = stored.lock().map_err(gql_error)?;
+    Ok(SessionState::new(&session))
+  }
+
+  /// Submit the fields for a step and advance the session. `skipped` has the fields the user chose not to answer.
+  async fn submit_step(&self, ctx: &Context<'_>, session_id: u16, step: String, fields: Vec<FieldInput>, skipped: Option<Vec<String>>) -> async_graphql::Result<SubmitResult> {
+    let store = ctx.data::<SessionStore>()?;
+    let session_id = SessionId::new(session_id);
+    let to_result = |session: &Session, redirect, error: Option<&Error>| SubmitResult {
+      session: SessionState::new(session),
+      redirect,
+      field_errors: error
+        .map(|error| field_errors(session, error).into_iter().map(|(name, invalid)| FieldError { name, error: invalid.to_string() }).collect())
+        .unwrap_or_default(),
+    };
+
+    let stored = stored_session(store, &session_id).map_err(gql_error)?;
+    let mut session = stored.lock().map_err(gql_error)?;
+    let step_id = *session.step_store().id_from_name(&step).ok_or_else(|| gql_error(Error::StepId(IdError::NoSuchName(step.clone()))))?;
+    let skipped = skipped.unwrap_or_default().into_iter().map(|name| (format!("{}{}", UnansweredValue::SKIP_FIELD_PREFIX, name), "true".to_owned()));
+    let state_data = match form_to_state_data(&session, fields.into_iter().map(|field| (field.name, field.value)).chain(skipped)) {
+      Ok(state_data) => state_data,
+      Err(invalid) => return Ok(to_result(&session, None, Some(&Error::InvalidVars(invalid)))),
+    };
+
+    let advance_result = session.advance(Some((&step_id, state_data)));
+    let session = &*session;
+    match advance_result.and_then(|advance_result| reply_from_advance(&advance_result)) {
+      Ok(AdvanceReply::Redirect(uri)) => {
+        // the form is shown again if the step's validators rejected the fields
+        let rejected = session.rejected_vars().map(|invalid| Error::InvalidVars(invalid.clone()));
+        Ok(to_result(session, Some(uri), rejected.as_ref()))
+      },
+      Ok(AdvanceReply::Finished) => Ok(to_result(session, None, None)),
+      Err(error) if !field_errors(session, &error).is_empty() => Ok(to_result(session, None, Some(&error))),
+      Err(error) => Err(gql_error(error)),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::Arc;
+  use stepflow_action::{Action, EscapedString, StringTemplateAction, UriEscapedString};
+  use stepflow_data::var::{StringVar, EmailVar};
+  use stepflow_session::Session;
+  use stepflow_step::Step;
+  use crate::new_session_store;
+  use crate::SessionFactory;
+  use super::build_schema;
+
+  fn new_flow() -> SessionFactory {
+    Arc::new(|session_id| {
+      let mut session = Session::new(session_id);
+      let name = session.var_store_mut().insert_new_named("name", |id| Ok(StringVar::new(id).boxed()))?;
+      let email = session.var_store_mut().insert_new_named("email", |id| Ok(EmailVar::new(id).allow_unanswered().boxed()))?;
+      let name_step = session.step_store_mut().insert_new_named("name", |id| Ok(Step::new(id, None, vec![name])))?;
+      let email_step = session.step_store_mut().insert_new_named("email", |id| Ok(Step::new(id, None, vec![email])))?;
+      session.step_store_mut().get_mut(&name_step).unwrap().set_description("Tell us who you are");
+      session.set_var_description(&name, "Your full name")?;
+      session.push_root_substep(name_step);
+      session.push_root_substep(email_step);
+      let action_id = session.action_store_mut().insert_new(|id| {
+        Ok(StringTemplateAction::new(id, UriEscapedString::already_escaped("/flow/{{step}}".to_owned())).boxed())
+      })?;
+      session.set_action_for_step(action_id, None)?;
+      session.set_action_description(&action_id, "Shows the step's page")?;
+      Ok(session)
+    })
+  }
+
+  fn execute(schema: &super::FlowSchema, query: &str) -> serde_json::Value {
+    let response = futures::executor::block_on(schema.execute(query));
+    assert!(response.errors.is_empty(), "{:?}", response.errors);
+    response.data.into_json().unwrap()
+  }
+
+  #[test]
+  fn flow() {
+    let schema = build_schema(new_session_store(), new_flow());
+
+    let created = execute(&schema, "mutation { createSession { id currentStep stepDescription actionDescription fields { name varType required description } } }");
+    assert_eq!(created, serde_json::json!({ "createSession": {
+      "id": 0, "currentStep": "name", "stepDescription": "Tell us who you are", "actionDescription": "Shows the step's page",
+      "fields": [{ "name": "name", "varType": "String", "required": true, "description": "Your full name" }]
+    }}));
+
+    // bad email stays on the step
+    execute(&schema, r#"mutation { submitStep(sessionId: 0, step: "name", fields: [{ name: "name", value: "Jane" }]) { redirect } }"#);
+    let submitted = execute(&schema, r#"mutation { submitStep(sessionId: 0, step: "email", fields: [{ name: "email", value: "nope" }]) { redirect fieldErrors { name error } } }"#);
+    assert_eq!(submitted, serde_json::json!({ "submitStep": { "redirect": null, "fieldErrors": [{ "name": "email", "error": "BadFormat" }] } }));
+
+    let submitted = execute(&schema, r#"mutation { submitStep(sessionId: 0, step: "email", fields: [{ name: "email", value: "jane@example.com" }]) { session { currentStep } } }"#);
+    assert_eq!(submitted, serde_json::json!({ "submitStep": { "session": { "currentStep": null } } }));
+
+    let queried = execute(&schema, "{ session(id: 0) { data { name value } } }");
+    assert_eq!(queried, serde_json::json!({ "session": { "data": [
+      { "name": "email", "value": "jane@example.com" },
+      { "name": "name", "value": "Jane" },
+    ]}}));
+
+    // the email can be skipped but the name can't
+    execute(&schema, "mutation { createSession { id } }");
+    let submitted = execute(&schema, r#"mutation { submitStep(sessionId: 1, step: "name", fields: [], skipped: ["name"]) { fieldErrors { name error } } }"#);
+    assert_eq!(submitted, serde_json::json!({ "submitStep": { "fieldErrors": [{ "name": "name", "error": "WrongType" }] } }));
+    let submitted = execute(&schema, r#"mutation { submitStep(sessionId: 1, step: "name", fields: [{ name: "name", value: "Jo" }]) { session { fields { name canSkip } } } }"#);
+    assert_eq!(submitted, serde_json::json!({ "submitStep": { "session": { "fields": [{ "name": "email", "canSkip": true }] } } }));
+    let submitted = execute(&schema, r#"mutation { submitStep(sessionId: 1, step: "email", fields: [], skipped: ["email"]) { session { currentStep } } }"#);
+    assert_eq!(submitted, serde_json::json!({ "submitStep": { "session": { "currentStep": null } } }));
+  }
+}
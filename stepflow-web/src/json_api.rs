@@ -0,0 +1,349 @@
+//! A minimal JSON protocol for clients that don't use HTML forms, i.e. mobile apps
+//!
+//! - Create a session with [`create_session`] and get the step it's on with [`get_step`]. The [`StepResponse`] has a [`FieldSpec`] for each field the step needs.
+//! - Post the answers as an [`AnswersRequest`] with [`post_answers`]. It replies with the next step or an [`ApiError`] the client can show.
+//!
+//! The types are `Serialize` and `Deserialize` so the same crate can be used on both sides.
+//! Steps don't need an action since they wait on the client's answers. Steps with an action that starts with a value,
+//! i.e. a `StringTemplateAction` for the HTML pages, work too.
+//! Wiring the functions to routes is left to the web framework, i.e. `GET /sessions/{id}` and `POST /sessions/{id}`.
+//!
+//! ```
+//! # use std::sync::Arc;
+//! # use stepflow_data::var::{StringVar, EmailVar};
+//! # use stepflow_session::Session;
+//! # use stepflow_step::Step;
+//! # use stepflow_web::{new_session_store, SessionFactory};
+//! use stepflow_web::json_api::{create_session, get_step, post_answers, AnswersRequest, ApiError};
+//! # let flow: SessionFactory = Arc::new(|session_id| {
+//! #   let mut session = Session::new(session_id);
+//! #   let email = session.var_store_mut().insert_new_named("email", |id| Ok(EmailVar::new(id).boxed()))?;
+//! #   let email_step = session.step_store_mut().insert_new_named("email", |id| Ok(Step::new(id, None, vec![email])))?;
+//! #   session.push_root_substep(email_step);
+//! #   Ok(session)
+//! # });
+//! let session_store = new_session_store();
+//! let step = create_session(&session_store, &flow).unwrap();
+//! assert_eq!(step.step.as_deref(), Some("email"));
+//! assert_eq!(step.fields[0].name, "email");
+//!
+//! // the client sends JSON
+//! let answers: AnswersRequest = serde_json::from_str(r#"{ "step": "email", "answers": { "email": "nope" } }"#).unwrap();
+//! let error = post_answers(&session_store, step.session_id, &answers).unwrap_err();
+//! assert_eq!(serde_json::to_value(&error).unwrap(), serde_json::json!({
+//!   "error": "invalid_fields",
+//!   "fields": { "email": "BadFormat" },
+//! }));
+//!
+//! let answers = AnswersRequest::new("email", vec![("email", "jane@example.com")]);
+//! let step = post_answers(&session_store, step.session_id, &answers).unwrap();
+//! assert!(step.finished);
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use stepflow_base::IdError;
+use stepflow_data::value::UnansweredValue;
+use stepflow_session::{Session, SessionId, Error};
+use crate::{SessionStore, StoredSession, SessionFactory, stored_session, advance_session, form_to_state_data, field_errors};
+use crate::form::var_type_name;
+
+/// A field the current step needs
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct FieldSpec {
+  pub name: String,
+
+  /// `String`, `Email`, `Bool`, `True` or `Custom`
+  pub var_type: String,
+
+  pub required: bool,
+
+  /// The field can be [skipped](AnswersRequest::skipped)
+  #[serde(default)]
+  pub can_skip: bool,
+
+  /// The only values allowed, if there's a fixed set
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub choices: Option<Vec<String>>,
+
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub description: Option<String>,
+
+  /// Example text to show before a value is entered
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub placeholder: Option<String>,
+}
+
+/// The step a session is on
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct StepResponse {
+  pub session_id: u16,
+
+  /// Name of the step. `None` once the flow is finished.
+  pub step: Option<String>,
+
+  /// Help text for the step
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub description: Option<String>,
+
+  /// Help text for the action the step is waiting on
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub action_description: Option<String>,
+
+  /// The fields to answer to finish the step
+  pub fields: Vec<FieldSpec>,
+
+  /// The flow is finished
+  pub finished: bool,
+}
+
+impl StepResponse {
+  fn new(session: &Session) -> Self {
+    let current_step = session.current_step().ok().and_then(|step_id| session.step_store().get(step_id));
+    let fields = current_step
+      .map(|step| {
+        step.get_output_vars().iter()
+          .filter_map(|var_id| {
+            let name = session.var_store().name_from_id(var_id)?;
+            let var = session.var_store().get(var_id)?;
+            Some(FieldSpec {
+              name: name.to_owned(),
+              var_type: var_type_name(var.as_ref()).to_owned(),
+              required: step.is_output_required(var_id, session.state_data()),
+              can_skip: var.allows_unanswered(),
+              choices: var.choices().map(|choices| choices.to_vec()),
+              description: session.var_description(var_id).map(|description| description.to_owned()),
+              placeholder: var.metadata().placeholder.clone(),
+            })
+          })
+          .collect()
+      })
+      .unwrap_or_default();
+
+    StepResponse {
+      session_id: session.id().val(),
+      step: current_step.map(|step| {
+        session.step_store().name_from_id(&step.id).map(|name| name.to_owned()).unwrap_or_else(|| step.id.to_string())
+      }),
+      description: current_step.and_then(|step| step.description()).map(|description| description.to_owned()),
+      action_description: session.current_action()
+        .and_then(|action_id| session.action_description(&action_id))
+        .map(|description| description.to_owned()),
+      fields,
+      finished: current_step.is_none(),
+    }
+  }
+}
+
+/// Answers to the fields of a step
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct AnswersRequest {
+  /// The step being answered. It must be the step the session is on.
+  pub step: String,
+
+  /// Field names and their values
+  pub answers: HashMap<String, String>,
+
+  /// Fields the user chose not to answer. They're kept as [unanswered](stepflow_data::value::UnansweredValue).
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub skipped: Vec<String>,
+}
+
+impl AnswersRequest {
+  /// Create the answers for `step`, i.e. on the client
+  pub fn new<I, K, V>(step: &str, answers: I) -> Self
+      where I: IntoIterator<Item = (K, V)>,
+            K: Into<String>,
+            V: Into<String>,
+  {
+    AnswersRequest {
+      step: step.to_owned(),
+      answers: answers.into_iter().map(|(name, value)| (name.into(), value.into())).collect(),
+      skipped: Vec::new(),
+    }
+  }
+
+  /// Skip the field named `name`
+  pub fn with_skipped(mut self, name: &str) -> Self {
+    self.skipped.push(name.to_owned());
+    self
+  }
+}
+
+/// Why a request failed
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "error", rename_all = "snake_case")]
+pub enum ApiError {
+  /// There's no session with the ID
+  NoSuchSession { session_id: u16 },
+
+  /// The answers are for another step than the one the session is on
+  WrongStep { current_step: Option<String> },
+
+  /// Fields that are missing or not valid, with what's wrong with each, i.e. `BadFormat`. The session stays on the step.
+  InvalidFields { fields: BTreeMap<String, String> },
+
+  /// Anything else
+  Other { message: String },
+}
+
+impl From<Error> for ApiError {
+  fn from(error: Error) -> Self {
+    match error {
+      Error::SessionId(IdError::IdMissing(session_id)) => ApiError::NoSuchSession { session_id: session_id.val() },
+      error => ApiError::Other { message: error.to_string() },
+    }
+  }
+}
+
+/// Create a session with `session_factory` and advance it to its first step
+pub fn create_session(session_store: &SessionStore, session_factory: &SessionFactory) -> Result<StepResponse, ApiError> {
+  let session_id = {
+    let mut session_store = session_store.write().map_err(|_| Error::Other)?;
+    let session_id = session_store.reserve_id().map_err(Error::from)?;
+    let session = session_factory(session_id)?;
+    session_store.register(StoredSession::new(session)).map_err(Error::from)?
+  };
+  match advance_session(session_store, &session_id, None) {
+    // waiting on the client to answer the first step
+    Ok(_) | Err(Error::MissingVars(_)) => get_step(session_store, session_id.val()),
+    Err(error) => Err(error.into()),
+  }
+}
+
+// the session or `NoSuchSession`
+fn api_session(session_store: &SessionStore, session_id: u16) -> Result<StoredSession, ApiError> {
+  match stored_session(session_store, &SessionId::new(session_id)) {
+    Err(Error::SessionId(_)) => Err(ApiError::NoSuchSession { session_id }),
+    stored => Ok(stored?),
+  }
+}
+
+/// Get the step the session is on
+pub fn get_step(session_store: &SessionStore, session_id: u16) -> Result<StepResponse, ApiError> {
+  let stored = api_session(session_store, session_id)?;
+  let session = stored.lock()?;
+  Ok(StepResponse::new(&session))
+}
+
+/// Answer the fields of the current step and advance the session
+pub fn post_answers(session_store: &SessionStore, session_id: u16, answers: &AnswersRequest) -> Result<StepResponse, ApiError> {
+  let invalid_fields = |session: &Session, error: &Error| {
+    let fields = field_errors(session, error).into_iter()
+      .map(|(name, invalid)| (name, invalid.to_string()))
+      .collect::<BTreeMap<_, _>>();
+    if fields.is_empty() { None } else { Some(ApiError::InvalidFields { fields }) }
+  };
+
+  let stored = api_session(session_store, session_id)?;
+  let mut session = stored.lock()?;
+  let current_step = StepResponse::new(&session).step;
+  let step_id = session.current_step().ok().copied()
+    .filter(|_| current_step.as_ref() == Some(&answers.step))
+    .ok_or(ApiError::WrongStep { current_step })?;
+  let skipped = answers.skipped.iter().map(|name| (format!("{}{}", UnansweredValue::SKIP_FIELD_PREFIX, name), "true".to_owned()));
+  let fields = answers.answers.iter().map(|(name, value)| (name.clone(), value.clone())).chain(skipped);
+  let state_data = match form_to_state_data(&session, fields) {
+    Ok(state_data) => state_data,
+    Err(invalid) => return Err(invalid_fields(&session, &Error::InvalidVars(invalid)).unwrap()),
+  };
+
+  let advance_result = session.advance(Some((&step_id, state_data)));
+  let response = StepResponse::new(&session);
+  match advance_result {
+    // the step's validators rejected the answers and it's waiting on them again
+    Ok(_) => match session.rejected_vars().and_then(|invalid| invalid_fields(&session, &Error::InvalidVars(invalid.clone()))) {
+      Some(api_error) => Err(api_error),
+      None => Ok(response),
+    },
+    // waiting on the client to answer the next step
+    Err(Error::MissingVars(_)) if response.step.as_ref() != Some(&answers.step) => Ok(response),
+    Err(error) => Err(invalid_fields(&session, &error).unwrap_or_else(|| ApiError::from(error))),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::Arc;
+  use stepflow_data::var::{StringVar, EmailVar};
+  use stepflow_session::Session;
+  use stepflow_step::Step;
+  use crate::{new_session_store, SessionFactory};
+  use super::{create_session, get_step, post_answers, AnswersRequest, ApiError, FieldSpec, StepResponse};
+
+  fn new_flow() -> SessionFactory {
+    Arc::new(|session_id| {
+      let mut session = Session::new(session_id);
+      let name = session.var_store_mut().insert_new_named("name", |id| Ok(StringVar::new(id).boxed()))?;
+      let email = session.var_store_mut().insert_new_named("email", |id| Ok(EmailVar::new(id).allow_unanswered().boxed()))?;
+      let name_step = session.step_store_mut().insert_new_named("name", |id| Ok(Step::new(id, None, vec![name])))?;
+      let email_step = session.step_store_mut().insert_new_named("email", |id| Ok(Step::new(id, None, vec![email])))?;
+      session.step_store_mut().get_mut(&name_step).unwrap().set_description("Tell us who you are");
+      session.set_var_description(&name, "Your full name")?;
+      session.push_root_substep(name_step);
+      session.push_root_substep(email_step);
+      Ok(session)
+    })
+  }
+
+  #[test]
+  fn flow() {
+    let session_store = new_session_store();
+    let step = create_session(&session_store, &new_flow()).unwrap();
+    let expected = StepResponse {
+      session_id: 0,
+      step: Some("name".to_owned()),
+      description: Some("Tell us who you are".to_owned()),
+      action_description: None,
+      fields: vec![FieldSpec {
+        name: "name".to_owned(),
+        var_type: "String".to_owned(),
+        required: true,
+        can_skip: false,
+        choices: None,
+        description: Some("Your full name".to_owned()),
+        placeholder: None,
+      }],
+      finished: false,
+    };
+    assert_eq!(step, expected);
+    assert_eq!(serde_json::from_str::<StepResponse>(&serde_json::to_string(&step).unwrap()).unwrap(), expected);
+    assert_eq!(get_step(&session_store, 0), Ok(expected));
+    assert_eq!(get_step(&session_store, 1), Err(ApiError::NoSuchSession { session_id: 1 }));
+
+    // answers for another step
+    let answers = AnswersRequest::new("email", vec![("email", "jane@example.com")]);
+    assert_eq!(post_answers(&session_store, 0, &answers), Err(ApiError::WrongStep { current_step: Some("name".to_owned()) }));
+
+    // missing the field
+    let answers = AnswersRequest::new("name", Vec::<(String, String)>::new());
+    let error = post_answers(&session_store, 0, &answers).unwrap_err();
+    assert_eq!(serde_json::to_value(&error).unwrap(), serde_json::json!({ "error": "invalid_fields", "fields": { "name": "Empty" } }));
+
+    let answers = AnswersRequest::new("name", vec![("name", "Jane")]);
+    let step = post_answers(&session_store, 0, &answers).unwrap();
+    assert_eq!(step.step.as_deref(), Some("email"));
+    assert_eq!(step.fields[0].var_type, "Email");
+    assert!(step.fields[0].can_skip);
+
+    let answers = AnswersRequest::new("email", vec![("email", "jane@example.com")]);
+    let step = post_answers(&session_store, 0, &answers).unwrap();
+    assert!(step.finished && step.fields.is_empty());
+    assert_eq!(post_answers(&session_store, 0, &answers), Err(ApiError::WrongStep { current_step: None }));
+  }
+
+  #[test]
+  fn skipped() {
+    let session_store = new_session_store();
+    create_session(&session_store, &new_flow()).unwrap();
+    let answers: AnswersRequest = serde_json::from_str(r#"{ "step": "name", "answers": {}, "skipped": ["name"] }"#).unwrap();
+    let error = post_answers(&session_store, 0, &answers).unwrap_err();
+    assert_eq!(serde_json::to_value(&error).unwrap(), serde_json::json!({ "error": "invalid_fields", "fields": { "name": "WrongType" } }));
+
+    post_answers(&session_store, 0, &AnswersRequest::new("name", vec![("name", "Jane")])).unwrap();
+    let answers = AnswersRequest::new("email", Vec::<(String, String)>::new()).with_skipped("email");
+    assert!(post_answers(&session_store, 0, &answers).unwrap().finished);
+    let stored = crate::stored_session(&session_store, &stepflow_session::SessionId::new(0)).unwrap();
+    let session = stored.lock().unwrap();
+    assert!(session.state_data().is_unanswered(session.var_store().id_from_name("email").unwrap()));
+  }
+}
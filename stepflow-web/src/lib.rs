@@ -0,0 +1,33 @@
+//! Helpers for serving a [StepFlow](https://stepflow.dev) on the web
+//!
+//! The top level is framework agnostic: a shared [`SessionStore`], turning form posts into [`StateData`](stepflow_data::StateData)
+//! and turning the result of [`Session::advance`](stepflow_session::Session::advance) into a reply.
+//! Adapters for specific frameworks are behind features, i.e. `warp-support` for `warp_filters`.
+//! The `graphql` feature adds a GraphQL API in `graphql`, `json-api` adds a JSON protocol for non-HTML clients in `json_api`
+//! and `events` adds per-session event streams in `events`. `bulk-export` adds [`export_all`] and [`import_all`] to back up or move the sessions in a store.
+
+mod store;
+pub use store::{SessionStore, StoredSession, new_session_store, stored_session, advance_session, advance_sessions, Advanced, steps_waiting_on, reassign_step, enforce_deadlines, remove_expired_previews, Escalated, FinishedCollector, FinishedObserver};
+#[cfg(feature = "bulk-export")]
+pub use store::{export_all, import_all, BulkError};
+
+mod flows;
+pub use flows::{SessionFactory, FlowRegistry, StartedFollowUp};
+
+mod form;
+pub use form::{form_to_state_data, field_errors, FormSubmission};
+
+mod reply;
+pub use reply::{AdvanceReply, reply_from_advance};
+
+#[cfg(feature = "warp-support")]
+pub mod warp_filters;
+
+#[cfg(feature = "graphql")]
+pub mod graphql;
+
+#[cfg(feature = "json-api")]
+pub mod json_api;
+
+#[cfg(feature = "events")]
+pub mod events;
@@ -0,0 +1,46 @@
+use stepflow_data::value::StringValue;
+use stepflow_session::{AdvanceBlockedOn, Error};
+
+/// What to reply with after advancing a session
+#[derive(Debug, Clone, PartialEq)]
+pub enum AdvanceReply {
+  /// Redirect to the URI from the action, i.e. from a `StringTemplateAction`
+  Redirect(String),
+
+  /// The flow is finished
+  Finished,
+}
+
+/// Map the result of [`Session::advance`](stepflow_session::Session::advance) to a reply
+///
+/// Actions must start with a [`StringValue`] URI. Fails with [`Error::Other`] if they don't or can't fulfill.
+pub fn reply_from_advance(advance_result: &AdvanceBlockedOn) -> Result<AdvanceReply, Error> {
+  match advance_result {
+    AdvanceBlockedOn::ActionStartWith(_, val, _) => {
+      val.downcast::<StringValue>()
+        .map(|uri| AdvanceReply::Redirect(uri.val().to_owned()))
+        .ok_or(Error::Other)
+    }
+    AdvanceBlockedOn::ActionCannotFulfill(_) => Err(Error::Other),
+    AdvanceBlockedOn::FinishedAdvancing => Ok(AdvanceReply::Finished),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use stepflow_action::ActionId;
+  use stepflow_data::value::{StringValue, BoolValue};
+  use stepflow_session::{AdvanceBlockedOn, AttemptId, Error};
+  use stepflow_test_util::test_id;
+  use super::{AdvanceReply, reply_from_advance};
+
+  #[test]
+  fn replies() {
+    let uri = AdvanceBlockedOn::ActionStartWith(test_id!(ActionId), StringValue::try_new("/step/name").unwrap().boxed(), AttemptId::new(0));
+    assert_eq!(reply_from_advance(&uri), Ok(AdvanceReply::Redirect("/step/name".to_owned())));
+    assert_eq!(reply_from_advance(&AdvanceBlockedOn::FinishedAdvancing), Ok(AdvanceReply::Finished));
+
+    let not_uri = AdvanceBlockedOn::ActionStartWith(test_id!(ActionId), BoolValue::new(true).boxed(), AttemptId::new(1));
+    assert_eq!(reply_from_advance(&not_uri), Err(Error::Other));
+  }
+}
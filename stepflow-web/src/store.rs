@@ -0,0 +1,644 @@
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex, MutexGuard, RwLock};
+use std::time::{Duration, SystemTime};
+use stepflow_base::{ObjectStore, ObjectStoreContent, IdError};
+use stepflow_data::StateData;
+use stepflow_session::{Session, SessionId, AdvanceBlockedOn, Error};
+use stepflow_step::StepId;
+
+/// A session in a [`SessionStore`]. Each session has its own lock so one can be advanced while the store is used for the others.
+#[derive(Debug, Clone)]
+pub struct StoredSession {
+  id: SessionId,
+  session: Arc<Mutex<Session>>,
+}
+
+impl StoredSession {
+  /// Wrap the session to put it in a [`SessionStore`]
+  pub fn new(session: Session) -> Self {
+    StoredSession {
+      id: *session.id(),
+      session: Arc::new(Mutex::new(session)),
+    }
+  }
+
+  /// Lock the session
+  ///
+  /// Fails with [`Error::Other`] if an action panicked while the session was locked since the session could be half changed.
+  pub fn lock(&self) -> Result<MutexGuard<'_, Session>, Error> {
+    self.session.lock().map_err(|_| Error::Other)
+  }
+}
+
+impl ObjectStoreContent for StoredSession {
+  type IdType = SessionId;
+
+  fn new_id(id_val: u16) -> Self::IdType {
+    SessionId::new(id_val)
+  }
+
+  fn id(&self) -> &Self::IdType {
+    &self.id
+  }
+}
+
+/// Sessions shared between request handlers
+pub type SessionStore = Arc<RwLock<ObjectStore<StoredSession, SessionId>>>;
+
+/// Create an empty [`SessionStore`]
+pub fn new_session_store() -> SessionStore {
+  Arc::new(RwLock::new(ObjectStore::new()))
+}
+
+/// Get the session from the store so it can be [locked](StoredSession::lock) without keeping the store locked
+///
+/// Fails with [`Error::SessionId`] if the session isn't in the store and [`Error::Other`] if the lock is poisoned.
+pub fn stored_session(session_store: &SessionStore, session_id: &SessionId) -> Result<StoredSession, Error> {
+  let session_store = session_store.read().map_err(|_| Error::Other)?;
+  session_store.get(session_id).cloned().ok_or(Error::SessionId(IdError::IdMissing(*session_id)))
+}
+
+/// [`advance`](Session::advance) the session in the store
+///
+/// Fails with [`Error::SessionId`] if the session isn't in the store and [`Error::Other`] if a lock is poisoned.
+pub fn advance_session(session_store: &SessionStore, session_id: &SessionId, step_output: Option<(&StepId, StateData)>)
+    -> Result<AdvanceBlockedOn, Error>
+{
+  let stored = stored_session(session_store, session_id)?;
+  let mut session = stored.lock()?;
+  session.advance(step_output)
+}
+
+/// The result of advancing one session. See [`advance_sessions`].
+pub type Advanced = (SessionId, Result<AdvanceBlockedOn, Error>);
+
+/// [`advance`](Session::advance) many sessions without new data, i.e. once a delay has passed for each of them
+///
+/// The sessions stay in the store and are split between at most `workers` threads. Each is locked on its own while it's advanced
+/// so the store isn't locked while their actions run.
+/// Returns the result for each session, ordered by session. Sessions that aren't in the store fail with [`Error::SessionId`].
+/// Sessions whose action panicked fail with [`Error::Other`], as does locking them afterwards.
+pub fn advance_sessions(session_store: &SessionStore, session_ids: &[SessionId], workers: usize) -> Result<Vec<Advanced>, Error> {
+  let mut session_ids = session_ids.to_vec();
+  session_ids.sort();
+  session_ids.dedup();
+
+  let mut result = Vec::new();
+  let mut sessions = Vec::new();
+  {
+    let session_store = session_store.read().map_err(|_| Error::Other)?;
+    for session_id in session_ids.iter() {
+      match session_store.get(session_id) {
+        Some(stored) => sessions.push((stored.clone(), Err(Error::Other))),
+        None => result.push((*session_id, Err(Error::SessionId(IdError::IdMissing(*session_id))))),
+      }
+    }
+  }
+
+  if !sessions.is_empty() {
+    let chunk_size = sessions.len().div_ceil(workers.max(1));
+    std::thread::scope(|scope| {
+      for chunk in sessions.chunks_mut(chunk_size) {
+        scope.spawn(move || {
+          for (stored, advanced) in chunk.iter_mut() {
+            // a panicking action only fails its own session and poisons its lock
+            let advance = std::panic::AssertUnwindSafe(|| stored.lock().and_then(|mut session| session.advance(None)));
+            *advanced = std::panic::catch_unwind(advance).unwrap_or(Err(Error::Other));
+          }
+        });
+      }
+    });
+  }
+
+  result.extend(sessions.into_iter().map(|(stored, advanced)| (stored.id, advanced)));
+  result.sort_by_key(|(session_id, _)| *session_id);
+  Ok(result)
+}
+
+/// [`reassign`](Session::reassign_step) the step the session is blocked on to `actor`
+pub fn reassign_step(session_store: &SessionStore, session_id: &SessionId, step_id: &StepId, actor: String) -> Result<Option<String>, Error> {
+  let stored = stored_session(session_store, session_id)?;
+  let mut session = stored.lock()?;
+  session.reassign_step(step_id, actor)
+}
+
+/// Steps in all of the sessions that are waiting on `actor`, ordered by session. See [`Session::set_step_assignee`].
+///
+/// Sessions whose lock is poisoned are skipped.
+pub fn steps_waiting_on(session_store: &SessionStore, actor: &str) -> Result<Vec<(SessionId, StepId)>, Error> {
+  let session_store = session_store.read().map_err(|_| Error::Other)?;
+  let mut result = session_store.iter()
+    .filter_map(|(session_id, stored)| stored.lock().ok().map(|session| (*session_id, session)))
+    .filter(|(_session_id, session)| !session.is_preview())
+    .flat_map(|(session_id, session)| session.steps_waiting_on(actor).into_iter().map(move |step_id| (session_id, step_id)))
+    .collect::<Vec<_>>();
+  result.sort();
+  Ok(result)
+}
+
+/// The step a session escalated or why escalating failed. See [`enforce_deadlines`].
+pub type Escalated = (SessionId, Result<StepId, Error>);
+
+/// [Enforce](Session::enforce_deadline) the deadlines of all the sessions. Run this periodically from a background task.
+///
+/// Returns the sessions that escalated a step or failed while escalating, ordered by session.
+/// Sessions whose lock is poisoned fail with [`Error::Other`].
+pub fn enforce_deadlines(session_store: &SessionStore, now: SystemTime) -> Result<Vec<Escalated>, Error> {
+  let session_store = session_store.read().map_err(|_| Error::Other)?;
+  let mut result = session_store.iter()
+    .filter_map(|(session_id, stored)| {
+      let escalated = stored.lock().and_then(|mut session| session.enforce_deadline(now));
+      escalated.transpose().map(|escalated| (*session_id, escalated))
+    })
+    .collect::<Vec<_>>();
+  result.sort_by_key(|(session_id, _)| *session_id);
+  Ok(result)
+}
+
+/// Remove the [previews](Session::set_preview) that expired by `now`. Run this periodically from a background task.
+///
+/// Returns the removed sessions, ordered by session.
+pub fn remove_expired_previews(session_store: &SessionStore, now: SystemTime) -> Result<Vec<SessionId>, Error> {
+  let mut session_store = session_store.write().map_err(|_| Error::Other)?;
+  let mut expired = session_store.iter()
+    .filter(|(_session_id, stored)| stored.lock().map(|session| session.is_expired(now)).unwrap_or(false))
+    .map(|(session_id, _stored)| *session_id)
+    .collect::<Vec<_>>();
+  expired.sort();
+  for session_id in expired.iter() {
+    session_store.remove(session_id);
+  }
+  Ok(expired)
+}
+
+/// Called with a session once it [finished](Session::finished_at), i.e. to read its results. See [`FinishedCollector`].
+pub type FinishedObserver = Box<dyn FnMut(&Session) + Send + Sync>;
+
+/// Removes finished sessions from the store so they don't pile up. Run [`sweep`](FinishedCollector::sweep) periodically from a background task.
+///
+/// Each finished session is passed to the observer on the first sweep that sees it
+/// and removed once it's been finished for the grace period, i.e. so a confirmation page can still show it.
+pub struct FinishedCollector {
+  grace_period: Duration,
+  on_finished: Option<FinishedObserver>,
+  notified: HashSet<SessionId>,
+}
+
+impl std::fmt::Debug for FinishedCollector {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("FinishedCollector")
+      .field("grace_period", &self.grace_period)
+      .field("on_finished", &self.on_finished.is_some())
+      .field("notified", &self.notified)
+      .finish()
+  }
+}
+
+impl FinishedCollector {
+  pub fn new(grace_period: Duration) -> Self {
+    FinishedCollector {
+      grace_period,
+      on_finished: None,
+      notified: HashSet::new(),
+    }
+  }
+
+  /// Call `observer` with each finished session before it's removed
+  ///
+  /// The store and the session are locked while it runs so it can't use the store.
+  pub fn with_on_finished<F>(mut self, observer: F) -> Self
+      where F: FnMut(&Session) + Send + Sync + 'static
+  {
+    self.on_finished = Some(Box::new(observer));
+    self
+  }
+
+  /// Pass the newly finished sessions to the observer and remove the ones finished for longer than the grace period by `now`
+  ///
+  /// Returns the removed sessions, ordered by session. Sessions whose lock is poisoned are left alone.
+  pub fn sweep(&mut self, session_store: &SessionStore, now: SystemTime) -> Result<Vec<SessionId>, Error> {
+    let mut session_store = session_store.write().map_err(|_| Error::Other)?;
+    let mut finished = session_store.iter()
+      .filter_map(|(session_id, stored)| stored.lock().ok().and_then(|session| session.finished_at()).map(|finished_at| (*session_id, finished_at)))
+      .collect::<Vec<_>>();
+    finished.sort();
+
+    let mut removed = Vec::new();
+    for (session_id, finished_at) in finished {
+      if !self.notified.contains(&session_id) {
+        if let (Some(observer), Some(stored)) = (self.on_finished.as_mut(), session_store.get(&session_id)) {
+          if let Ok(session) = stored.lock() {
+            observer(&session);
+          }
+        }
+        self.notified.insert(session_id);
+      }
+      if now.duration_since(finished_at).map(|finished_for| finished_for >= self.grace_period).unwrap_or(false) {
+        session_store.remove(&session_id);
+        removed.push(session_id);
+      }
+    }
+
+    // forget the sessions that are gone so a reused ID is reported again
+    self.notified.retain(|session_id| session_store.get(session_id).is_some());
+    Ok(removed)
+  }
+}
+
+// one line of `export_all`
+#[cfg(feature = "bulk-export")]
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct ExportedSession {
+  session_id: u16,
+  current_step: u16,
+  data: std::collections::HashMap<u16, stepflow_data::value::TaggedValue>,
+}
+
+/// Why [`export_all`] or [`import_all`] failed
+#[cfg(feature = "bulk-export")]
+#[derive(Debug)]
+pub enum BulkError {
+  Io(std::io::Error),
+  /// The store's lock is poisoned
+  Store(Error),
+  /// A line that isn't an exported session, with the line number starting at 1
+  Parse(usize, serde_json::Error),
+  /// A session that couldn't be exported or restored, with the line number starting at 1
+  Session(usize, Error),
+}
+
+#[cfg(feature = "bulk-export")]
+impl From<std::io::Error> for BulkError {
+  fn from(err: std::io::Error) -> Self {
+    BulkError::Io(err)
+  }
+}
+
+/// Write the sessions in the store as newline delimited JSON, i.e. to back them up or move them to another deployment
+///
+/// Each line has a session's ID, current step and data with the type of each value. The flow itself isn't written so
+/// [`import_all`] recreates the sessions with the same [`SessionFactory`](crate::SessionFactory).
+/// Finished sessions are left out since they can't be resumed. Returns how many sessions were written.
+#[cfg(feature = "bulk-export")]
+pub fn export_all<W: std::io::Write>(session_store: &SessionStore, mut writer: W) -> Result<usize, BulkError> {
+  let mut sessions = session_store.read().map_err(|_| BulkError::Store(Error::Other))?
+    .iter()
+    .map(|(session_id, stored)| (*session_id, stored.clone()))
+    .collect::<Vec<_>>();
+  sessions.sort_by_key(|(session_id, _stored)| *session_id);
+
+  let mut written = 0;
+  for (_session_id, stored) in sessions {
+    let line = written + 1;
+    let session = stored.lock().map_err(|err| BulkError::Session(line, err))?;
+    let current_step = match session.current_step() {
+      Ok(step_id) => step_id.val(),
+      Err(_) => continue,
+    };
+    let exported = ExportedSession {
+      session_id: session.id().val(),
+      current_step,
+      data: session.state_data().to_tagged().into_iter().map(|(var_id, tagged)| (var_id.val(), tagged)).collect(),
+    };
+    serde_json::to_writer(&mut writer, &exported).map_err(std::io::Error::from)?;
+    writer.write_all(b"\n")?;
+    written += 1;
+  }
+  writer.flush()?;
+  Ok(written)
+}
+
+/// Add the sessions written by [`export_all`] to the store, creating each one with `session_factory` and its saved ID
+///
+/// The sessions are put back on the step they were on with their data, without running any actions.
+/// Stops at the first line that fails, keeping the sessions before it. Returns the IDs of the sessions added.
+#[cfg(feature = "bulk-export")]
+pub fn import_all<R: std::io::BufRead>(session_store: &SessionStore, reader: R, session_factory: &crate::SessionFactory)
+    -> Result<Vec<SessionId>, BulkError>
+{
+  let registry = stepflow_data::value::ValueRegistry::with_builtins();
+  let mut imported = Vec::new();
+  for (index, line) in reader.lines().enumerate() {
+    let line_number = index + 1;
+    let line = line?;
+    if line.trim().is_empty() {
+      continue;
+    }
+    let exported: ExportedSession = serde_json::from_str(&line).map_err(|err| BulkError::Parse(line_number, err))?;
+    let restore = || -> Result<Session, Error> {
+      let mut session = session_factory(SessionId::new(exported.session_id))?;
+      let tagged = exported.data.iter().map(|(var_id, tagged)| (stepflow_data::var::VarId::new(*var_id), tagged.clone())).collect();
+      let state_data = StateData::from_tagged(&tagged, session.var_store(), &registry).map_err(Error::InvalidVars)?;
+      session.seed_data(state_data)?;
+      session.resume_at(&StepId::new(exported.current_step))?;
+      Ok(session)
+    };
+    let session = restore().map_err(|err| BulkError::Session(line_number, err))?;
+    let mut session_store = session_store.write().map_err(|_| BulkError::Store(Error::Other))?;
+    let session_id = session_store.register(StoredSession::new(session))
+      .map_err(|err| BulkError::Session(line_number, Error::from(err)))?;
+    imported.push(session_id);
+  }
+  Ok(imported)
+}
+
+#[cfg(test)]
+mod tests {
+  use stepflow_action::{Action, ActionId, ActionResult, ActionError, EscapedString, StringTemplateAction, UriEscapedString};
+  use stepflow_base::{IdError, ObjectStoreFiltered};
+  use std::sync::{Mutex, mpsc};
+  use std::time::{Duration, SystemTime};
+  use stepflow_data::{StateDataFiltered, var::{Var, VarId}, value::StringValue};
+  use stepflow_session::{Session, SessionId, AdvanceBlockedOn, Error, DeadlinePolicy, Escalation};
+  use stepflow_step::Step;
+  use stepflow_test_util::test_id;
+  use super::{StoredSession, new_session_store, stored_session, advance_session, advance_sessions, steps_waiting_on, reassign_step, enforce_deadlines};
+
+  #[test]
+  fn advance() {
+    let session_store = new_session_store();
+    let session_id = session_store.write().unwrap().insert_new(|id| Ok(StoredSession::new(Session::new(id)))).unwrap();
+    assert_eq!(advance_session(&session_store, &session_id, None), Ok(AdvanceBlockedOn::FinishedAdvancing));
+
+    let missing_id = test_id!(SessionId);
+    assert_eq!(advance_session(&session_store, &missing_id, None), Err(Error::SessionId(IdError::IdMissing(missing_id))));
+  }
+
+  #[derive(Debug)]
+  struct PanicAction {
+    id: ActionId,
+  }
+
+  impl Action for PanicAction {
+    fn id(&self) -> &ActionId {
+      &self.id
+    }
+
+    fn start(&mut self, _step: &Step, _step_name: Option<&str>, _step_data: &StateDataFiltered, _vars: &ObjectStoreFiltered<Box<dyn Var + Send + Sync>, VarId>)
+      -> Result<ActionResult, ActionError>
+    {
+      panic!("action blew up")
+    }
+  }
+
+  // tells the test it started and waits for it to continue
+  #[derive(Debug)]
+  struct PausedAction {
+    id: ActionId,
+    started: Mutex<mpsc::Sender<()>>,
+    resume: Mutex<mpsc::Receiver<()>>,
+  }
+
+  impl Action for PausedAction {
+    fn id(&self) -> &ActionId {
+      &self.id
+    }
+
+    fn start(&mut self, _step: &Step, _step_name: Option<&str>, _step_data: &StateDataFiltered, _vars: &ObjectStoreFiltered<Box<dyn Var + Send + Sync>, VarId>)
+      -> Result<ActionResult, ActionError>
+    {
+      self.started.lock().unwrap().send(()).unwrap();
+      self.resume.lock().unwrap().recv().unwrap();
+      Ok(ActionResult::StartWith(StringValue::try_new("/paused").unwrap().boxed()))
+    }
+  }
+
+  fn add_session_with_action<F>(session_store: &super::SessionStore, new_action: F) -> SessionId
+      where F: FnOnce(ActionId) -> Box<dyn Action + Sync + Send>
+  {
+    let session_id = session_store.write().unwrap().insert_new(|id| Ok(StoredSession::new(Session::new(id)))).unwrap();
+    let stored = stored_session(session_store, &session_id).unwrap();
+    let mut session = stored.lock().unwrap();
+    let step_id = session.step_store_mut().insert_new(|id| Ok(Step::new(id, None, vec![]))).unwrap();
+    session.push_root_substep(step_id);
+    let action_id = session.action_store_mut().insert_new(|id| Ok(new_action(id))).unwrap();
+    session.set_action_for_step(action_id, None).unwrap();
+    session_id
+  }
+
+  fn add_waiting_session(session_store: &super::SessionStore, panics: bool) -> SessionId {
+    add_session_with_action(session_store, |id| {
+      if panics {
+        PanicAction { id }.boxed()
+      } else {
+        StringTemplateAction::new(id, UriEscapedString::already_escaped("/wait".to_owned())).boxed()
+      }
+    })
+  }
+
+  #[test]
+  fn advance_many() {
+    let session_store = new_session_store();
+    let session_ids = (0..5).map(|_| add_waiting_session(&session_store, false)).collect::<Vec<_>>();
+
+    let missing_id = test_id!(SessionId);
+    let mut requested = session_ids.clone();
+    requested.push(missing_id);
+    requested.push(session_ids[0]);
+    let advanced = advance_sessions(&session_store, &requested, 2).unwrap();
+    assert_eq!(advanced.len(), 6);
+    assert!(advanced.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    for (session_id, result) in advanced {
+      if session_id == missing_id {
+        assert_eq!(result, Err(Error::SessionId(IdError::IdMissing(missing_id))));
+      } else {
+        assert!(matches!(result, Ok(AdvanceBlockedOn::ActionStartWith(_, _, _))));
+      }
+    }
+    assert_eq!(advance_sessions(&session_store, &[], 0), Ok(vec![]));
+    assert_eq!(session_store.read().unwrap().len(), 5);
+  }
+
+  #[test]
+  fn advance_many_panic() {
+    let session_store = new_session_store();
+    let waiting = add_waiting_session(&session_store, false);
+    let panics = add_waiting_session(&session_store, true);
+    let named = add_waiting_session(&session_store, false);
+    session_store.write().unwrap().rename(&named, "named", false).unwrap();
+
+    // one worker for all of them so the panic shares a chunk
+    let advanced = advance_sessions(&session_store, &[waiting, panics, named], 1).unwrap();
+    assert!(matches!(advanced[0], (session_id, Ok(AdvanceBlockedOn::ActionStartWith(_, _, _))) if session_id == waiting));
+    assert_eq!(advanced[1], (panics, Err(Error::Other)));
+    assert!(matches!(advanced[2], (session_id, Ok(AdvanceBlockedOn::ActionStartWith(_, _, _))) if session_id == named));
+
+    // they all stayed in the store, the one that panicked can't be used anymore
+    assert_eq!(session_store.read().unwrap().len(), 3);
+    assert_eq!(session_store.read().unwrap().id_from_name("named"), Some(&named));
+    assert_eq!(advance_session(&session_store, &panics, None), Err(Error::Other));
+    assert!(advance_session(&session_store, &waiting, None).is_ok());
+  }
+
+  #[test]
+  fn advance_many_in_store() {
+    let session_store = new_session_store();
+    let (started_sender, started) = mpsc::channel();
+    let (resume, resume_receiver) = mpsc::channel();
+    let paused = add_session_with_action(&session_store, |id| {
+      PausedAction { id, started: Mutex::new(started_sender), resume: Mutex::new(resume_receiver) }.boxed()
+    });
+    session_store.write().unwrap().rename(&paused, "paused", true).unwrap();
+    session_store.write().unwrap().rename(&paused, "renamed", true).unwrap();
+
+    std::thread::scope(|scope| {
+      let batch = scope.spawn(|| advance_sessions(&session_store, &[paused], 1));
+      started.recv().unwrap();
+
+      // while the batch runs the session is still in the store with its name and alias
+      {
+        let mut session_store = session_store.write().unwrap();
+        assert_eq!(session_store.id_from_name("paused"), Some(&paused));
+        let taken = session_store.insert_new_named("renamed", |id| Ok(StoredSession::new(Session::new(id))));
+        assert_eq!(taken, Err(IdError::NameAlreadyExists("renamed".to_owned())));
+      }
+      assert!(stored_session(&session_store, &paused).is_ok());
+
+      resume.send(()).unwrap();
+      let advanced = batch.join().unwrap().unwrap();
+      assert!(matches!(advanced[0], (session_id, Ok(AdvanceBlockedOn::ActionStartWith(_, _, _))) if session_id == paused));
+    });
+    assert_eq!(session_store.read().unwrap().id_from_name("renamed"), Some(&paused));
+    assert_eq!(session_store.read().unwrap().id_from_name("paused"), Some(&paused));
+  }
+
+  #[test]
+  fn waiting_on() {
+    let session_store = new_session_store();
+    let mut review_steps = Vec::new();
+    for _ in 0..3 {
+      let session_id = session_store.write().unwrap().insert_new(|id| Ok(StoredSession::new(Session::new(id)))).unwrap();
+      let stored = stored_session(&session_store, &session_id).unwrap();
+      let mut session = stored.lock().unwrap();
+      let step_id = session.step_store_mut().insert_new(|id| Ok(Step::new(id, None, vec![]))).unwrap();
+      session.push_root_substep(step_id);
+      session.set_step_assignee(step_id, "reviewer".to_owned()).unwrap();
+      let action_id = session.action_store_mut().insert_new(|id| {
+        Ok(StringTemplateAction::new(id, UriEscapedString::already_escaped("/review".to_owned())).boxed())
+      }).unwrap();
+      session.set_action_for_step(action_id, None).unwrap();
+      review_steps.push((session_id, step_id));
+    }
+    assert_eq!(steps_waiting_on(&session_store, "reviewer"), Ok(vec![]));
+
+    for (session_id, _) in &review_steps[1..] {
+      advance_session(&session_store, session_id, None).unwrap();
+    }
+    assert_eq!(steps_waiting_on(&session_store, "reviewer"), Ok(review_steps[1..].to_vec()));
+    assert_eq!(steps_waiting_on(&session_store, "applicant"), Ok(vec![]));
+
+    let (session_id, step_id) = review_steps[2];
+    assert_eq!(reassign_step(&session_store, &session_id, &step_id, "manager".to_owned()), Ok(Some("reviewer".to_owned())));
+    assert_eq!(steps_waiting_on(&session_store, "reviewer"), Ok(vec![review_steps[1]]));
+    assert_eq!(steps_waiting_on(&session_store, "manager"), Ok(vec![(session_id, step_id)]));
+
+    // only the blocked sessions escalate
+    for (session_id, step_id) in &review_steps {
+      let policy = DeadlinePolicy { within: Duration::from_secs(60), escalation: Escalation::Notify };
+      stored_session(&session_store, session_id).unwrap().lock().unwrap().set_deadline_policy(*step_id, policy).unwrap();
+    }
+    assert_eq!(enforce_deadlines(&session_store, SystemTime::now()), Ok(vec![]));
+    assert_eq!(
+      enforce_deadlines(&session_store, SystemTime::now() + Duration::from_secs(120)),
+      Ok(review_steps[1..].iter().map(|(session_id, step_id)| (*session_id, Ok(*step_id))).collect()));
+  }
+
+  #[test]
+  fn collect_finished() {
+    use std::sync::Arc;
+    use super::FinishedCollector;
+
+    let session_store = new_session_store();
+    let waiting = add_waiting_session(&session_store, false);
+    advance_session(&session_store, &waiting, None).unwrap();
+    let finished = (0..2)
+      .map(|_| session_store.write().unwrap().insert_new(|id| Ok(StoredSession::new(Session::new(id)))).unwrap())
+      .collect::<Vec<_>>();
+    assert_eq!(advance_session(&session_store, &finished[0], None), Ok(AdvanceBlockedOn::FinishedAdvancing));
+
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let seen_clone = seen.clone();
+    let mut collector = FinishedCollector::new(Duration::from_secs(60))
+      .with_on_finished(move |session| seen_clone.lock().unwrap().push(*session.id()));
+
+    // told right away, kept for the grace period
+    let now = SystemTime::now();
+    assert_eq!(collector.sweep(&session_store, now), Ok(vec![]));
+    assert_eq!(collector.sweep(&session_store, now), Ok(vec![]));
+    assert_eq!(*seen.lock().unwrap(), vec![finished[0]]);
+    assert!(stored_session(&session_store, &finished[0]).is_ok());
+
+    assert_eq!(advance_session(&session_store, &finished[1], None), Ok(AdvanceBlockedOn::FinishedAdvancing));
+    assert_eq!(collector.sweep(&session_store, SystemTime::now() + Duration::from_secs(120)), Ok(finished.clone()));
+    assert_eq!(*seen.lock().unwrap(), finished);
+    assert_eq!(session_store.read().unwrap().len(), 1);
+    assert!(stored_session(&session_store, &waiting).is_ok());
+  }
+
+  #[cfg(feature = "bulk-export")]
+  #[test]
+  fn export_import() {
+    use std::sync::Arc;
+    use stepflow_data::var::StringVar;
+    use crate::{SessionFactory, form_to_state_data};
+    use super::{export_all, import_all, BulkError};
+
+    let session_factory: SessionFactory = Arc::new(|session_id| {
+      let mut session = Session::new(session_id);
+      for name in ["name", "email"].iter() {
+        let var_id = session.var_store_mut().insert_new_named(*name, |id| Ok(StringVar::new(id).boxed()))?;
+        let step_id = session.step_store_mut().insert_new_named(*name, |id| Ok(Step::new(id, None, vec![var_id])))?;
+        session.push_root_substep(step_id);
+      }
+      let action_id = session.action_store_mut().insert_new(|id| {
+        Ok(StringTemplateAction::new(id, UriEscapedString::already_escaped("/{{step}}".to_owned())).boxed())
+      })?;
+      session.set_action_for_step(action_id, None)?;
+      Ok(session)
+    });
+    let submit = |session_store: &super::SessionStore, session_id: &SessionId, field: &str| {
+      let stored = stored_session(session_store, session_id).unwrap();
+      let mut session = stored.lock().unwrap();
+      let step_id = *session.current_step().unwrap();
+      let state_data = form_to_state_data(&session, vec![(field.to_owned(), "a@b.c".to_owned())]).unwrap();
+      session.advance(Some((&step_id, state_data))).unwrap()
+    };
+
+    // one on each step and one finished
+    let session_store = new_session_store();
+    let session_ids = (0..3)
+      .map(|_| {
+        let session_id = session_store.write().unwrap().insert_new(|id| Ok(StoredSession::new(session_factory(id).unwrap()))).unwrap();
+        advance_session(&session_store, &session_id, None).unwrap();
+        session_id
+      })
+      .collect::<Vec<_>>();
+    submit(&session_store, &session_ids[1], "name");
+    submit(&session_store, &session_ids[2], "name");
+    assert_eq!(submit(&session_store, &session_ids[2], "email"), AdvanceBlockedOn::FinishedAdvancing);
+
+    let mut exported = Vec::new();
+    assert_eq!(export_all(&session_store, &mut exported).unwrap(), 2);
+    assert_eq!(exported.iter().filter(|byte| **byte == b'\n').count(), 2);
+
+    let imported_store = new_session_store();
+    assert_eq!(import_all(&imported_store, &exported[..], &session_factory).unwrap(), session_ids[..2].to_vec());
+    for (session_id, step_name, has_name) in [(session_ids[0], "name", false), (session_ids[1], "email", true)].iter() {
+      let stored = stored_session(&imported_store, session_id).unwrap();
+      let session = stored.lock().unwrap();
+      let step_id = session.step_store().id_from_name(step_name).unwrap();
+      assert_eq!(session.current_step(), Ok(step_id));
+      let name_id = session.var_store().id_from_name("name").unwrap();
+      assert_eq!(session.state_data().contains(name_id), *has_name);
+    }
+    assert_eq!(submit(&imported_store, &session_ids[1], "email"), AdvanceBlockedOn::FinishedAdvancing);
+
+    // sessions before a bad line stay imported
+    let imported_store = new_session_store();
+    let mut bad = exported[..exported.iter().position(|byte| *byte == b'\n').unwrap() + 1].to_vec();
+    bad.extend_from_slice(b"not json\n");
+    assert!(matches!(import_all(&imported_store, &bad[..], &session_factory), Err(BulkError::Parse(2, _))));
+    assert_eq!(imported_store.read().unwrap().len(), 1);
+    assert!(matches!(import_all(&imported_store, &exported[..], &session_factory), Err(BulkError::Session(1, Error::SessionId(_)))));
+  }
+}
@@ -0,0 +1,38 @@
+//! Adapter for the [warp](https://docs.rs/warp) web framework
+
+use std::convert::Infallible;
+use warp::{Filter, Reply};
+use stepflow_session::Error;
+use crate::{SessionStore, AdvanceReply};
+
+/// Rejection for a StepFlow [`Error`]
+#[derive(Debug)]
+pub struct StepflowRejection(pub Error);
+impl warp::reject::Reject for StepflowRejection {}
+
+/// Reject the request with the error
+pub fn reject(error: Error) -> warp::Rejection {
+  warp::reject::custom(StepflowRejection(error))
+}
+
+/// Pass the [`SessionStore`] to the handler
+pub fn with_session_store(session_store: SessionStore) -> impl Filter<Extract = (SessionStore,), Error = Infallible> + Clone {
+  warp::any().map(move || session_store.clone())
+}
+
+/// Redirect with `303 See Other` so the browser doesn't post the form again
+pub fn redirect_see_other(uri: &str) -> warp::reply::WithHeader<warp::http::StatusCode> {
+  warp::reply::with_header(
+    warp::http::StatusCode::SEE_OTHER,
+    warp::http::header::LOCATION,
+    uri.to_string(),
+  )
+}
+
+/// Redirect for an [`AdvanceReply`], going to `done_uri` when the flow is finished
+pub fn redirect_from_reply(advance_reply: &AdvanceReply, done_uri: &str) -> impl Reply {
+  match advance_reply {
+    AdvanceReply::Redirect(uri) => redirect_see_other(uri),
+    AdvanceReply::Finished => redirect_see_other(done_uri),
+  }
+}
@@ -7,28 +7,47 @@ pub mod prelude {
 }
 
 pub mod object {
-  pub use stepflow_base::ObjectStore;
+  pub use stepflow_base::{ObjectStore, FrozenObjectStore};
   pub use stepflow_base::IdError;
+  pub use stepflow_base::slugify;
 }
 
 pub mod data {
-  pub use stepflow_data::{StateData, StateDataFiltered, BaseValue};
-  pub use stepflow_data::var::{BoolVar, EmailVar, Var, VarId, StringVar, TrueVar};
-  pub use stepflow_data::value::{ValidVal, StringValue, TrueValue, EmailValue, BoolValue};
-  pub use stepflow_data::{InvalidVars, InvalidValue};
+  pub use stepflow_data::{StateData, StateDataFiltered, BaseValue, BaseValueKind};
+  pub use stepflow_data::var::{BoolVar, EmailVar, Var, VarId, VarMetadata, VarGroup, StringVar, TrueVar, ListVar, NumberVar, DateTimeVar, EnumVar};
+  pub use stepflow_data::value::{ValidVal, StringValue, TrueValue, EmailValue, BoolValue, TaggedValue, ValueRegistry, ValueConstructor, StringInterner, ListValue, UnansweredValue, IntValue, FloatValue, DateTimeValue, EnumValue};
+  pub use stepflow_data::{InvalidVars, InvalidValue, InvalidVarReport};
+  pub use stepflow_data::{VarDependencies, VarAccess, Condition};
+  pub use stepflow_data::{ValueGenerator, Normalization};
 }
 
 pub mod step {
-  pub use stepflow_step::{Step, StepId};
+  pub use stepflow_step::{Step, StepId, StepMatcher, VisitPolicy, Repeat, Validator};
 }
 
 pub mod action {
-  pub use stepflow_action::{ActionId, ActionResult};
-  pub use stepflow_action::{HtmlFormAction, HtmlFormConfig, SetDataAction};
+  pub use stepflow_action::{ActionId, ActionResult, FlowEnvironment, SecretsProvider, SecretValue};
+  pub use stepflow_action::{HtmlFormAction, HtmlFormConfig, SetDataAction, ApprovalAction, ImportAction};
+  pub use stepflow_action::{DuplicateCheckAction, DuplicateChecker};
   pub use stepflow_action::{StringTemplateAction, HtmlEscapedString, UriEscapedString};
+  pub use stepflow_action::{SetDataActionConfig, StringTemplateActionConfig, ApprovalActionConfig, ImportActionConfig};
+  #[cfg(feature = "serde-support")]
+  pub use stepflow_action::{ActionRegistry, ActionConstructor};
+  #[cfg(feature = "wasm-actions")]
+  pub use stepflow_action::{WasmAction, WasmActionConfig};
   pub use stepflow_action::ActionError;
 }
 
-pub use stepflow_session::{Session, SessionId};
-pub use stepflow_session::AdvanceBlockedOn;
-pub use stepflow_session::Error;
\ No newline at end of file
+/// Test doubles for unit testing flows
+pub mod testing {
+  pub use stepflow_action::testing::{MockAction, ScriptedAction, ActionCall};
+}
+
+pub use stepflow_session::{Session, SessionId, SubflowAction, VarMigration, DefinitionDrift, SessionEvent, SessionEventKind, ActionOutcome, AttemptId, TraceEntry, Transition};
+#[cfg(feature = "serde-support")]
+pub use stepflow_session::{FlowDefinition, VarDefinition, StepDefinition, ActionDefinition};
+pub use stepflow_session::{AdvanceBlockedOn, CannotFulfillDetails, UndeclaredActionVar, FinishAction, FinishActionStatus, FallbackAction, FollowUp, FollowUpStart, Compensation, BlockedNotice, BlockedObserver, DeadlinePolicy, Escalation, ESCALATION_ACTOR, ApprovalStep, NextStepPreview, BlockingRequirement, TransitionCheck, StepStatus};
+pub use stepflow_session::{FlowScheduler, StepCheck, DepthFirstSearch, PriorityTraversal, StepScore, StepScoreFn};
+pub use stepflow_session::{Error, AdvanceError};
+pub use stepflow_session::{ResourceLimits, QuotaExceeded, TenantQuotas, LoadShedding, LoadTest, LoadTestReport, CsvExport};
+pub use stepflow_session::session_data_accessor;
\ No newline at end of file